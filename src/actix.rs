@@ -0,0 +1,35 @@
+use std::error::Error as StdError;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use actix_tls::connect::Resolve;
+
+use crate::futureresolver::FutureResolver;
+
+/// A `FutureResolver` can be used as the resolver for `actix-tls` connectors - and so for `awc`
+/// clients - in place of the default resolver, which makes blocking lookups on a thread pool.
+///
+/// Names are looked up by `get_host_by_name()`, for both IPv4 and IPv6 addresses.  Since a
+/// `FutureResolver` is cheap to clone, the same channel can be shared by every client.
+///
+/// ```no_run
+/// use actix_tls::connect::{Connector, Resolver};
+/// use c_ares_resolver::FutureResolver;
+///
+/// let resolver = Resolver::custom(FutureResolver::new().unwrap());
+/// let connector = Connector::new(resolver);
+/// ```
+impl Resolve for FutureResolver {
+    fn lookup<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SocketAddr>, Box<dyn StdError>>> + 'a>> {
+        let lookup = self.get_host_by_name(host, c_ares::AddressFamily::UNSPEC);
+        Box::pin(async move {
+            let results = lookup.await?;
+            Ok(results.to_socket_addrs(port))
+        })
+    }
+}
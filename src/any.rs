@@ -0,0 +1,250 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::dnsrec::{DnsRr, T_DNSKEY, T_DS, T_RRSIG, T_SSHFP};
+use crate::dnssec::{DNSKEYResult, DSResult, RRSIGResult};
+use crate::sshfp::SSHFPResult;
+use crate::svcb::HTTPSResult;
+use crate::tlsa::TLSAResult;
+
+use c_ares_sys::{ares_dns_rec_type_t, ares_dns_rr_key_t};
+
+dns_results!(
+    /// The result of a successful ANY lookup: all of the records in the answer, of whatever type.
+    AnyResults,
+    AnyResult
+);
+
+/// A single record returned by an ANY lookup.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnyResult {
+    /// The name that the record is for.
+    pub name: String,
+
+    /// The time-to-live of the record, in seconds.
+    pub ttl: u32,
+
+    /// The record data.
+    pub record: AnyRecord,
+}
+
+/// The data of a record returned by an ANY lookup.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::upper_case_acronyms)]
+pub enum AnyRecord {
+    /// An A record.
+    A(Ipv4Addr),
+
+    /// An AAAA record.
+    AAAA(Ipv6Addr),
+
+    /// A CNAME record, holding the canonical name.
+    CNAME(String),
+
+    /// An NS record, holding the name server.
+    NS(String),
+
+    /// A PTR record, holding the domain name.
+    PTR(String),
+
+    /// An SOA record.
+    SOA {
+        /// The primary name server.
+        mname: String,
+        /// The mailbox of the person responsible for the zone.
+        rname: String,
+        /// The serial number of the zone.
+        serial: u32,
+        /// The refresh interval, in seconds.
+        refresh: u32,
+        /// The retry interval, in seconds.
+        retry: u32,
+        /// The expiry limit, in seconds.
+        expire: u32,
+        /// The minimum time-to-live, in seconds.
+        minimum: u32,
+    },
+
+    /// An HINFO record.
+    HINFO {
+        /// The CPU type.
+        cpu: String,
+        /// The operating system.
+        os: String,
+    },
+
+    /// An MX record.
+    MX {
+        /// The preference - lower is preferred.
+        preference: u16,
+        /// The mail exchange.
+        exchange: String,
+    },
+
+    /// A TXT record, holding the text.
+    TXT(Vec<u8>),
+
+    /// An SRV record.
+    SRV {
+        /// The priority - lower is preferred.
+        priority: u16,
+        /// The weight, for choosing between records of the same priority.
+        weight: u16,
+        /// The port.
+        port: u16,
+        /// The target host.
+        target: String,
+    },
+
+    /// A NAPTR record.
+    NAPTR {
+        /// The order in which records must be processed.
+        order: u16,
+        /// The preference, between records of the same order.
+        preference: u16,
+        /// The flags.
+        flags: String,
+        /// The services.
+        services: String,
+        /// The regular expression.
+        regexp: String,
+        /// The replacement.
+        replacement: String,
+    },
+
+    /// A DS record.
+    DS(DSResult),
+
+    /// An RRSIG record.
+    RRSIG(RRSIGResult),
+
+    /// A DNSKEY record.
+    DNSKEY(DNSKEYResult),
+
+    /// An SSHFP record.
+    SSHFP(SSHFPResult),
+
+    /// A TLSA record.
+    TLSA(TLSAResult),
+
+    /// An SVCB record.
+    SVCB(HTTPSResult),
+
+    /// An HTTPS record.
+    HTTPS(HTTPSResult),
+
+    /// A URI record.
+    URI {
+        /// The priority - lower is preferred.
+        priority: u16,
+        /// The weight, for choosing between records of the same priority.
+        weight: u16,
+        /// The URI.
+        target: String,
+    },
+
+    /// A CAA record.
+    CAA {
+        /// Whether the record is critical.
+        critical: bool,
+        /// The property tag.
+        tag: String,
+        /// The property value.
+        value: Vec<u8>,
+    },
+
+    /// A record of any other type, holding its type and its raw data.
+    ///
+    /// The data is empty for the few types that `c-ares` parses but that have no variant here.
+    Other {
+        /// The record type.
+        rr_type: u16,
+        /// The raw record data.
+        data: Vec<u8>,
+    },
+}
+
+impl AnyResult {
+    fn from_rr(rr: &DnsRr) -> Self {
+        use ares_dns_rr_key_t::*;
+        let rec_type = rr.rec_type();
+        let record = match rec_type {
+            ares_dns_rec_type_t::ARES_REC_TYPE_A => AnyRecord::A(rr.addr(ARES_RR_A_ADDR)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_AAAA => AnyRecord::AAAA(rr.addr6(ARES_RR_AAAA_ADDR)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_CNAME => {
+                AnyRecord::CNAME(rr.str(ARES_RR_CNAME_CNAME))
+            }
+            ares_dns_rec_type_t::ARES_REC_TYPE_NS => AnyRecord::NS(rr.str(ARES_RR_NS_NSDNAME)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_PTR => AnyRecord::PTR(rr.str(ARES_RR_PTR_DNAME)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_SOA => AnyRecord::SOA {
+                mname: rr.str(ARES_RR_SOA_MNAME),
+                rname: rr.str(ARES_RR_SOA_RNAME),
+                serial: rr.u32(ARES_RR_SOA_SERIAL),
+                refresh: rr.u32(ARES_RR_SOA_REFRESH),
+                retry: rr.u32(ARES_RR_SOA_RETRY),
+                expire: rr.u32(ARES_RR_SOA_EXPIRE),
+                minimum: rr.u32(ARES_RR_SOA_MINIMUM),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_HINFO => AnyRecord::HINFO {
+                cpu: rr.str(ARES_RR_HINFO_CPU),
+                os: rr.str(ARES_RR_HINFO_OS),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_MX => AnyRecord::MX {
+                preference: rr.u16(ARES_RR_MX_PREFERENCE),
+                exchange: rr.str(ARES_RR_MX_EXCHANGE),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_TXT => AnyRecord::TXT(rr.bin(ARES_RR_TXT_DATA)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_SRV => AnyRecord::SRV {
+                priority: rr.u16(ARES_RR_SRV_PRIORITY),
+                weight: rr.u16(ARES_RR_SRV_WEIGHT),
+                port: rr.u16(ARES_RR_SRV_PORT),
+                target: rr.str(ARES_RR_SRV_TARGET),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_NAPTR => AnyRecord::NAPTR {
+                order: rr.u16(ARES_RR_NAPTR_ORDER),
+                preference: rr.u16(ARES_RR_NAPTR_PREFERENCE),
+                flags: rr.str(ARES_RR_NAPTR_FLAGS),
+                services: rr.str(ARES_RR_NAPTR_SERVICES),
+                regexp: rr.str(ARES_RR_NAPTR_REGEXP),
+                replacement: rr.str(ARES_RR_NAPTR_REPLACEMENT),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_TLSA => AnyRecord::TLSA(TLSAResult::from_rr(rr)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_SVCB => {
+                AnyRecord::SVCB(HTTPSResult::from_svcb_rr(rr))
+            }
+            ares_dns_rec_type_t::ARES_REC_TYPE_HTTPS => AnyRecord::HTTPS(HTTPSResult::from_rr(rr)),
+            ares_dns_rec_type_t::ARES_REC_TYPE_URI => AnyRecord::URI {
+                priority: rr.u16(ARES_RR_URI_PRIORITY),
+                weight: rr.u16(ARES_RR_URI_WEIGHT),
+                target: rr.str(ARES_RR_URI_TARGET),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_CAA => AnyRecord::CAA {
+                critical: rr.u8(ARES_RR_CAA_CRITICAL) != 0,
+                tag: rr.str(ARES_RR_CAA_TAG),
+                value: rr.bin(ARES_RR_CAA_VALUE),
+            },
+            ares_dns_rec_type_t::ARES_REC_TYPE_RAW_RR => {
+                let rr_type = rr.u16(ARES_RR_RAW_RR_TYPE);
+                let data = rr.bin(ARES_RR_RAW_RR_DATA);
+                let parsed = match rr_type {
+                    T_DS => DSResult::from_raw(&data).map(AnyRecord::DS),
+                    T_SSHFP => SSHFPResult::from_raw(&data).map(AnyRecord::SSHFP),
+                    T_RRSIG => RRSIGResult::from_raw(&data).map(AnyRecord::RRSIG),
+                    T_DNSKEY => DNSKEYResult::from_raw(&data).map(AnyRecord::DNSKEY),
+                    _ => None,
+                };
+                parsed.unwrap_or(AnyRecord::Other { rr_type, data })
+            }
+            other => AnyRecord::Other {
+                rr_type: other as u16,
+                data: Vec::new(),
+            },
+        };
+        Self {
+            name: rr.name(),
+            ttl: rr.ttl(),
+            record,
+        }
+    }
+}
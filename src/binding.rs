@@ -0,0 +1,30 @@
+use std::net::IpAddr;
+
+/// Where a resolver's queries are made from - see `Resolver::bound_to()`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LocalBinding {
+    /// A local IP address.  This applies only to servers of the same family: queries to servers
+    /// of the other family are made from wherever the system chooses.
+    Address(IpAddr),
+
+    /// A network interface, or VRF device, by name - eg `eth1`.  On Linux, binding to a device may
+    /// need the `CAP_NET_RAW` capability.
+    Device(String),
+}
+
+impl LocalBinding {
+    // Bind the sockets of `channel` accordingly.
+    pub(crate) fn apply(&self, channel: &mut c_ares::Channel) {
+        match self {
+            Self::Address(IpAddr::V4(ipv4)) => channel.set_local_ipv4(*ipv4),
+            Self::Address(IpAddr::V6(ipv6)) => channel.set_local_ipv6(ipv6),
+            Self::Device(device) => channel.set_local_device(device),
+        };
+    }
+}
+
+impl From<IpAddr> for LocalBinding {
+    fn from(address: IpAddr) -> Self {
+        Self::Address(address)
+    }
+}
@@ -1,12 +1,48 @@
+#[cfg(cares1_29)]
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::thread::ThreadId;
+use std::time::Duration;
 
+#[cfg(cares1_22)]
+use crate::any::AnyResults;
+#[cfg(cares1_24)]
+use crate::binding::LocalBinding;
+use crate::discovery::{self, ServiceInstance};
+#[cfg(cares1_22)]
+use crate::dnssec::{DNSKEYResults, DSResults, RRSIGResults};
+use crate::e164::EnumUri;
+use crate::email::{DkimRecord, DmarcRecord, SpfRecord};
 use crate::error::Error;
-use crate::host::HostResults;
+use crate::host::{self, HostResults};
+use crate::metrics::{LoadStats, Metrics};
+use crate::mx::{self, MailExchange};
 use crate::nameinfo::NameInfoResult;
+#[cfg(cares1_24)]
+use crate::queryflags::QueryFlags;
+use crate::querylog::QueryLogEntry;
+#[cfg(cares1_29)]
+use crate::raw::RawResponse;
+use crate::record::{DnsClass, RecordType};
+use crate::rejection;
+use crate::resolved::ResolvedConfig;
 use crate::resolver::{Options, Resolver};
-
+#[cfg(cares1_29)]
+use crate::response::QueryResponse;
+#[cfg(cares1_34)]
+use crate::server::Server;
 #[cfg(cares1_24)]
-use c_ares::AresString;
+use crate::server::ServerInfo;
+#[cfg(cares1_29)]
+use crate::server::ServerStats;
+use crate::srv::{self, SrvEndpoint};
+#[cfg(cares1_22)]
+use crate::sshfp::SSHFPResults;
+#[cfg(cares1_22)]
+use crate::svcb::HTTPSResults;
+#[cfg(cares1_22)]
+use crate::tlsa::TLSAResults;
 
 #[cfg(cares1_29)]
 use c_ares::ServerStateFlags;
@@ -15,7 +51,7 @@ use std::sync::mpsc;
 
 /// A blocking DNS resolver.
 pub struct BlockingResolver {
-    inner: Resolver,
+    inner: Arc<Resolver>,
 }
 
 // Most query implementations follow the same pattern: call through to the `Resolver`, arranging
@@ -28,6 +64,29 @@ macro_rules! blockify {
     }};
 }
 
+// Likewise for the `*_ext()` methods.
+#[cfg(cares1_29)]
+macro_rules! blockify_ext {
+    ($resolver:expr, $query:ident, $question:expr) => {{
+        let (tx, rx) = mpsc::sync_channel(1);
+        $resolver.$query($question, move |response| tx.send(response).unwrap());
+        rx.recv().unwrap()
+    }};
+}
+
+// Likewise for the `*_timeout()` methods, which stop waiting after `$timeout`.  The callback
+// ignores failure to send, since by then the caller may have given up.
+macro_rules! blockify_timeout {
+    ($resolver:expr, $query:ident, $question:expr, $timeout:expr) => {{
+        let (tx, rx) = mpsc::sync_channel(1);
+        $resolver.$query($question, move |result| {
+            let _ = tx.send(result);
+        });
+        rx.recv_timeout($timeout)
+            .unwrap_or(Err(c_ares::Error::ETIMEOUT))
+    }};
+}
+
 impl BlockingResolver {
     /// Create a new `BlockingResolver`, using default `Options`.
     pub fn new() -> Result<Self, Error> {
@@ -38,8 +97,42 @@ impl BlockingResolver {
     /// Create a new `BlockingResolver`, with the given `Options`.
     pub fn with_options(options: Options) -> Result<Self, Error> {
         let inner = Resolver::with_options(options)?;
-        let resolver = Self { inner };
-        Ok(resolver)
+        Ok(Self::from_resolver(inner))
+    }
+
+    // Wrap an existing `Resolver`.
+    pub(crate) fn from_resolver(inner: Resolver) -> Self {
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    // The underlying `Resolver`.
+    pub(crate) fn as_resolver(&self) -> &Resolver {
+        &self.inner
+    }
+
+    /// Returns a resolver that makes its queries over TCP, and is otherwise configured in the same
+    /// way as this one - see `Resolver::tcp()`.
+    #[cfg(cares1_24)]
+    pub fn tcp(&self) -> Result<Self, Error> {
+        let inner = Arc::clone(self.inner.tcp_resolver()?);
+        Ok(Self { inner })
+    }
+
+    /// Returns a resolver that makes its queries from `binding`, and is otherwise configured in
+    /// the same way as this one - see `Resolver::bound_to()`.
+    #[cfg(cares1_24)]
+    pub fn bound_to(&self, binding: &LocalBinding) -> Result<Self, Error> {
+        let inner = self.inner.bound_to(binding)?;
+        Ok(Self { inner })
+    }
+
+    /// Stop keeping the resolver returned by `bound_to()` for `binding`, returning whether there
+    /// was one.  See `Resolver::remove_binding()`.
+    #[cfg(cares1_24)]
+    pub fn remove_binding(&self, binding: &LocalBinding) -> bool {
+        self.inner.remove_binding(binding)
     }
 
     /// Reinitialize a channel from system configuration.
@@ -53,31 +146,115 @@ impl BlockingResolver {
     /// local named.
     ///
     /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
-    /// `[2001:4860:4860::8888]:53`.
+    /// `[2001:4860:4860::8888]:53`.  With the `tls` feature, servers can instead be given as
+    /// `tls://host@ip[:port]` - see `Resolver::set_servers()`.
+    ///
+    /// If a `Transport` is set then it, and not the list of servers, determines where queries go:
+    /// this fails with `c_ares::Error::ENOTIMP`.
     pub fn set_servers(&self, servers: &[&str]) -> c_ares::Result<&Self> {
         self.inner.set_servers(servers)?;
         Ok(self)
     }
 
-    /// Retrieves the list of servers in comma delimited format.
+    /// Set the list of servers to contact, in the comma delimited format that `c-ares` uses - see
+    /// `Resolver::set_servers_csv()`.
+    pub fn set_servers_csv(&self, servers: &str) -> c_ares::Result<&Self> {
+        self.inner.set_servers_csv(servers)?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, as socket addresses.
+    pub fn set_servers_addrs(&self, servers: &[SocketAddr]) -> c_ares::Result<&Self> {
+        self.inner.set_servers_addrs(servers)?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, as IP addresses.  Servers are contacted on the default
+    /// port - 53, unless configured otherwise in the `Options`.
+    pub fn set_servers_ips(&self, servers: &[IpAddr]) -> c_ares::Result<&Self> {
+        self.inner.set_servers_ips(servers)?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, each with its own UDP and TCP ports.
+    #[cfg(cares1_34)]
+    pub fn set_servers_ports(&self, servers: &[Server]) -> c_ares::Result<&Self> {
+        self.inner.set_servers_ports(servers)?;
+        Ok(self)
+    }
+
+    /// Returns the configuration in effect - see `Resolver::effective_options()`.
+    pub fn effective_options(&self) -> ResolvedConfig {
+        self.inner.effective_options()
+    }
+
+    /// Returns the domains searched by the `search_xxx()` methods - see
+    /// `Resolver::search_domains()`.
+    pub fn search_domains(&self) -> Vec<String> {
+        self.inner.search_domains()
+    }
+
+    /// Returns the number of dots which must be present in a name for it to be queried "as is" -
+    /// see `Resolver::ndots()`.
+    pub fn ndots(&self) -> u32 {
+        self.inner.ndots()
+    }
+
+    /// Set the domains to be searched by the `search_xxx()` methods - see
+    /// `Resolver::set_search_domains()`.
+    pub fn set_search_domains(&self, domains: &[&str]) -> &Self {
+        self.inner.set_search_domains(domains);
+        self
+    }
+
+    /// Retrieves the list of servers in comma delimited format - see `Resolver::get_servers()`.
     #[cfg(cares1_24)]
-    pub fn get_servers(&self) -> AresString {
+    pub fn get_servers(&self) -> String {
         self.inner.get_servers()
     }
 
-    /// Set the local IPv4 address from which to make queries.
+    /// Retrieves the list of servers in the comma delimited format that `c-ares` uses - see
+    /// `Resolver::get_servers_csv()`.
+    #[cfg(cares1_24)]
+    pub fn get_servers_csv(&self) -> String {
+        self.inner.get_servers_csv()
+    }
+
+    /// Add a server to the list of servers to contact, if it isn't already there - see
+    /// `Resolver::add_server()`.
+    #[cfg(cares1_24)]
+    pub fn add_server(&self, server: SocketAddr) -> c_ares::Result<&Self> {
+        self.inner.add_server(server)?;
+        Ok(self)
+    }
+
+    /// Remove a server from the list of servers to contact, if it is there - see
+    /// `Resolver::remove_server()`.
+    #[cfg(cares1_24)]
+    pub fn remove_server(&self, server: SocketAddr) -> c_ares::Result<&Self> {
+        self.inner.remove_server(server)?;
+        Ok(self)
+    }
+
+    /// Returns the servers that the resolver is using - see `Resolver::servers()`.
+    #[cfg(cares1_24)]
+    pub fn servers(&self) -> Vec<ServerInfo> {
+        self.inner.servers()
+    }
+
+    /// Set the local IPv4 address from which to make queries - see `Resolver::set_local_ipv4()`.
     pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
         self.inner.set_local_ipv4(ipv4);
         self
     }
 
-    /// Set the local IPv6 address from which to make queries.
+    /// Set the local IPv6 address from which to make queries - see `Resolver::set_local_ipv6()`.
     pub fn set_local_ipv6(&self, ipv6: &Ipv6Addr) -> &Self {
         self.inner.set_local_ipv6(ipv6);
         self
     }
 
-    /// Set the local device from which to make queries.
+    /// Set the local device from which to make queries - see `Resolver::set_local_device()`.
     pub fn set_local_device(&self, device: &str) -> &Self {
         self.inner.set_local_device(device);
         self
@@ -94,6 +271,81 @@ impl BlockingResolver {
         Ok(self)
     }
 
+    /// Pin `name` to `addresses`, replacing any override already set for it.
+    ///
+    /// Queries for the A and AAAA records of `name`, and host lookups of `name`, are then answered
+    /// from `addresses` without consulting the servers or the hosts file.  An override with no
+    /// addresses of the family asked for gives `ENODATA`.
+    pub fn add_host_override(&self, name: &str, addresses: &[IpAddr]) -> &Self {
+        self.inner.add_host_override(name, addresses);
+        self
+    }
+
+    /// Remove the override for `name`, returning whether there was one.
+    pub fn remove_host_override(&self, name: &str) -> bool {
+        self.inner.remove_host_override(name)
+    }
+
+    /// Remove all host overrides.
+    pub fn clear_host_overrides(&self) -> &Self {
+        self.inner.clear_host_overrides();
+        self
+    }
+
+    /// Set a callback function to be invoked whenever a query for a name completes - see
+    /// `Resolver::set_query_log()`.
+    pub fn set_query_log<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(&QueryLogEntry) + Send + Sync + 'static,
+    {
+        self.inner.set_query_log(callback);
+        self
+    }
+
+    /// Returns the counters and latency histograms that this resolver maintains for the queries
+    /// that it makes.  See `Resolver::metrics()`.
+    pub fn metrics(&self) -> &Metrics {
+        self.inner.metrics()
+    }
+
+    /// Returns how busy this resolver is.  See `Resolver::load()`.
+    pub fn load(&self) -> LoadStats {
+        self.inner.load()
+    }
+
+    /// Returns `Error::Overloaded` if a query made now would fail at once - see
+    /// `Resolver::check_capacity()`.
+    pub fn check_capacity(&self) -> Result<(), Error> {
+        self.inner.check_capacity()
+    }
+
+    /// Make a query with `query`, failing with a crate-level `Error` - so that a query that the
+    /// resolver rejects at once, without going to `c-ares`, fails with the reason: eg
+    /// `Error::Overloaded`, where the query itself gives only `c_ares::Error::ENOMEM`.  See
+    /// `Resolver::try_query()`.
+    ///
+    /// ```no_run
+    /// use c_ares_resolver::BlockingResolver;
+    ///
+    /// let resolver = BlockingResolver::new().unwrap();
+    /// let result = resolver.try_query(|resolver| resolver.query_a("example.com"));
+    /// ```
+    pub fn try_query<F, T>(&self, query: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Self) -> c_ares::Result<T>,
+    {
+        match rejection::catch(|| query(self)) {
+            (_, Some(error)) => Err(error),
+            (result, None) => result.map_err(Error::from),
+        }
+    }
+
+    /// The ID of the thread on which this resolver runs its event loop.  See
+    /// `Resolver::thread_id()`.
+    pub fn thread_id(&self) -> ThreadId {
+        self.inner.thread_id()
+    }
+
     /// Set a callback function to be invoked whenever a query on the channel completes.
     ///
     /// `callback(server, success, flags)` will be called when a query completes.
@@ -110,6 +362,12 @@ impl BlockingResolver {
         self
     }
 
+    /// Returns health statistics for each server that has been queried, keyed by server.
+    #[cfg(cares1_29)]
+    pub fn server_stats(&self) -> HashMap<String, ServerStats> {
+        self.inner.server_stats()
+    }
+
     /// Look up the A records associated with `name`.
     pub fn query_a(&self, name: &str) -> c_ares::Result<c_ares::AResults> {
         blockify!(self.inner, query_a, name)
@@ -130,6 +388,21 @@ impl BlockingResolver {
         blockify!(self.inner, search_aaaa, name)
     }
 
+    /// Look up all of the records associated with `name`, of whatever type.
+    ///
+    /// Following RFC 8482, many servers answer ANY queries with only some of their records - or
+    /// refuse them altogether.
+    #[cfg(cares1_22)]
+    pub fn query_any(&self, name: &str) -> c_ares::Result<AnyResults> {
+        blockify!(self.inner, query_any, name)
+    }
+
+    /// Search for all of the records associated with `name`, of whatever type.
+    #[cfg(cares1_22)]
+    pub fn search_any(&self, name: &str) -> c_ares::Result<AnyResults> {
+        blockify!(self.inner, search_any, name)
+    }
+
     /// Look up the CAA records associated with `name`.
     #[cfg(cares1_17)]
     pub fn query_caa(&self, name: &str) -> c_ares::Result<c_ares::CAAResults> {
@@ -152,6 +425,42 @@ impl BlockingResolver {
         blockify!(self.inner, search_cname, name)
     }
 
+    /// Look up the DNSKEY records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_dnskey(&self, name: &str) -> c_ares::Result<DNSKEYResults> {
+        blockify!(self.inner, query_dnskey, name)
+    }
+
+    /// Search for the DNSKEY records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_dnskey(&self, name: &str) -> c_ares::Result<DNSKEYResults> {
+        blockify!(self.inner, search_dnskey, name)
+    }
+
+    /// Look up the DS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_ds(&self, name: &str) -> c_ares::Result<DSResults> {
+        blockify!(self.inner, query_ds, name)
+    }
+
+    /// Search for the DS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_ds(&self, name: &str) -> c_ares::Result<DSResults> {
+        blockify!(self.inner, search_ds, name)
+    }
+
+    /// Look up the HTTPS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_https(&self, name: &str) -> c_ares::Result<HTTPSResults> {
+        blockify!(self.inner, query_https, name)
+    }
+
+    /// Search for the HTTPS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_https(&self, name: &str) -> c_ares::Result<HTTPSResults> {
+        blockify!(self.inner, search_https, name)
+    }
+
     /// Look up the MX records associated with `name`.
     pub fn query_mx(&self, name: &str) -> c_ares::Result<c_ares::MXResults> {
         blockify!(self.inner, query_mx, name)
@@ -192,6 +501,25 @@ impl BlockingResolver {
         blockify!(self.inner, search_ptr, name)
     }
 
+    /// Look up the PTR records for the address `ip` - at its `in-addr.arpa` or `ip6.arpa` name.
+    pub fn query_ptr_for(&self, ip: &IpAddr) -> c_ares::Result<c_ares::PTRResults> {
+        blockify!(self.inner, query_ptr_for, ip)
+    }
+
+    /// Look up the RRSIG records associated with `name`.
+    ///
+    /// Signatures are returned only by servers that support DNSSEC.
+    #[cfg(cares1_22)]
+    pub fn query_rrsig(&self, name: &str) -> c_ares::Result<RRSIGResults> {
+        blockify!(self.inner, query_rrsig, name)
+    }
+
+    /// Search for the RRSIG records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_rrsig(&self, name: &str) -> c_ares::Result<RRSIGResults> {
+        blockify!(self.inner, search_rrsig, name)
+    }
+
     /// Look up the SOA records associated with `name`.
     pub fn query_soa(&self, name: &str) -> c_ares::Result<c_ares::SOAResult> {
         blockify!(self.inner, query_soa, name)
@@ -212,6 +540,30 @@ impl BlockingResolver {
         blockify!(self.inner, search_srv, name)
     }
 
+    /// Look up the SSHFP records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_sshfp(&self, name: &str) -> c_ares::Result<SSHFPResults> {
+        blockify!(self.inner, query_sshfp, name)
+    }
+
+    /// Search for the SSHFP records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_sshfp(&self, name: &str) -> c_ares::Result<SSHFPResults> {
+        blockify!(self.inner, search_sshfp, name)
+    }
+
+    /// Look up the TLSA records associated with `name` - eg `_25._tcp.mail.example.com`.
+    #[cfg(cares1_22)]
+    pub fn query_tlsa(&self, name: &str) -> c_ares::Result<TLSAResults> {
+        blockify!(self.inner, query_tlsa, name)
+    }
+
+    /// Search for the TLSA records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_tlsa(&self, name: &str) -> c_ares::Result<TLSAResults> {
+        blockify!(self.inner, search_tlsa, name)
+    }
+
     /// Look up the TXT records associated with `name`.
     pub fn query_txt(&self, name: &str) -> c_ares::Result<c_ares::TXTResults> {
         blockify!(self.inner, query_txt, name)
@@ -232,6 +584,70 @@ impl BlockingResolver {
         blockify!(self.inner, search_uri, name)
     }
 
+    /// Look up the SPF record published at `domain`.
+    ///
+    /// It's an error - `EBADRESP` - for a domain to publish more than one SPF record.
+    pub fn lookup_spf(&self, domain: &str) -> c_ares::Result<SpfRecord> {
+        blockify!(self.inner, lookup_spf, domain)
+    }
+
+    /// Look up the DMARC record for `domain`, published at `_dmarc.<domain>`.
+    pub fn lookup_dmarc(&self, domain: &str) -> c_ares::Result<DmarcRecord> {
+        blockify!(self.inner, lookup_dmarc, domain)
+    }
+
+    /// Look up the DKIM key record for `selector` at `domain`, published at
+    /// `<selector>._domainkey.<domain>`.
+    pub fn lookup_dkim(&self, selector: &str, domain: &str) -> c_ares::Result<DkimRecord> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .lookup_dkim(selector, domain, move |result| tx.send(result).unwrap());
+        rx.recv().unwrap()
+    }
+
+    /// Look up the URIs for the E.164 telephone number `number` - eg `+14155551234` - via ENUM
+    /// (RFC 6116).
+    ///
+    /// This queries the NAPTR records at the number's `e164.arpa` domain, and applies their
+    /// rewrite rules to the number.  Only terminal rules are followed, and rules using regular
+    /// expression features beyond literals, `.`, bracket expressions, `*`, `+`, `?`, anchors and
+    /// groups are skipped.
+    ///
+    /// The candidate URIs are given in the order in which they should be tried.  A number that
+    /// isn't of the form `+<digits>` gives `EBADNAME`.
+    pub fn enum_lookup(&self, number: &str) -> c_ares::Result<Vec<EnumUri>> {
+        blockify!(self.inner, enum_lookup, number)
+    }
+
+    /// Discover the instances of a service - eg `_http._tcp.example.com` - per RFC 6763.
+    ///
+    /// This enumerates the instances with a PTR query, and then looks up the SRV and TXT records
+    /// of each instance and the addresses of its host.  Instances whose SRV records can't be found
+    /// are left out.
+    pub fn discover_services(&self, service: &str) -> c_ares::Result<Vec<ServiceInstance>> {
+        discovery::discover_blocking(self, service)
+    }
+
+    /// Look up the SRV records for `name` - eg `_sip._udp.example.com` - choose between their
+    /// targets per RFC 2782, and resolve the chosen target to addresses.
+    ///
+    /// Targets are tried in the order given by `SrvSelector`, until one has addresses.
+    pub fn resolve_srv(&self, name: &str) -> c_ares::Result<SrvEndpoint> {
+        srv::resolve_blocking(self, name)
+    }
+
+    /// Look up the hosts that accept mail for `domain`, as an SMTP sender would.
+    ///
+    /// The exchanges from the MX records are returned in order of preference, without duplicates.
+    /// If `resolve` is set, each exchange's addresses are looked up too.
+    ///
+    /// If `domain` has no MX records but has addresses, then - per RFC 5321 - it is its own
+    /// exchange, returned with those addresses.  If its only MX record is a null MX, per RFC 7505,
+    /// then there are no exchanges.
+    pub fn lookup_mx(&self, domain: &str, resolve: bool) -> c_ares::Result<Vec<MailExchange>> {
+        mx::lookup_blocking(self, domain, resolve)
+    }
+
     /// Perform a host query by address.
     ///
     /// This method is one of the very few places where this library performs strictly more
@@ -255,22 +671,48 @@ impl BlockingResolver {
         name: &str,
         family: c_ares::AddressFamily,
     ) -> c_ares::Result<HostResults> {
+        self.start_get_host_by_name(name, family).recv().unwrap()
+    }
+
+    /// Perform host queries by name for both IPv4 and IPv6, returning the merged results - IPv4
+    /// addresses first, then IPv6.
+    ///
+    /// Both queries are made before waiting for either.  The lookup succeeds if either of them
+    /// does.
+    pub fn get_host_by_name_any(&self, name: &str) -> c_ares::Result<HostResults> {
+        let v4 = self.start_get_host_by_name(name, c_ares::AddressFamily::INET);
+        let v6 = self.start_get_host_by_name(name, c_ares::AddressFamily::INET6);
+        host::merge(v4.recv().unwrap(), v6.recv().unwrap())
+    }
+
+    // Start a host query by name, returning a receiver for its result.
+    fn start_get_host_by_name(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> mpsc::Receiver<c_ares::Result<HostResults>> {
         let (tx, rx) = mpsc::sync_channel(1);
+        if let Some(result) = self.inner.host_override(name, family) {
+            tx.send(result).unwrap();
+            return rx;
+        }
         self.inner.get_host_by_name(name, family, move |result| {
             tx.send(result.map(Into::into)).unwrap()
         });
-        rx.recv().unwrap()
+        rx
     }
 
     /// Address-to-nodename translation in protocol-independent manner.
     ///
+    /// `flags` may be given either as `c_ares::NIFlags` or as `NameInfoOptions`.
+    ///
     /// This method is one of the very few places where this library performs strictly more
     /// allocation than the underlying `c-ares` code.  If this is a problem for you, you should
     /// prefer to use the analogous method on the `Resolver`.
-    pub fn get_name_info<F>(
+    pub fn get_name_info(
         &self,
         address: &SocketAddr,
-        flags: c_ares::NIFlags,
+        flags: impl Into<c_ares::NIFlags>,
     ) -> c_ares::Result<NameInfoResult> {
         let (tx, rx) = mpsc::sync_channel(1);
         self.inner.get_name_info(address, flags, move |result| {
@@ -280,7 +722,7 @@ impl BlockingResolver {
     }
 
     /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
-    /// the provided parameters, taking values as defined in `arpa/nameser.h`.
+    /// the provided parameters, which may be given as numbers per `arpa/nameser.h`.
     ///
     /// This method is one of the very few places where this library performs strictly more
     /// allocation than the underlying `c-ares` code.  If this is a problem for you, you should
@@ -289,7 +731,12 @@ impl BlockingResolver {
     /// This method is provided so that users can query DNS types for which `c-ares` does not
     /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
     /// `query_xxx()` is available, that should be used.
-    pub fn query(&self, name: &str, dns_class: u16, query_type: u16) -> c_ares::Result<Vec<u8>> {
+    pub fn query(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> c_ares::Result<Vec<u8>> {
         let (tx, rx) = mpsc::sync_channel(1);
         self.inner
             .query(name, dns_class, query_type, move |result| {
@@ -299,7 +746,7 @@ impl BlockingResolver {
     }
 
     /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
-    /// query are per the provided parameters, taking values as defined in `arpa/nameser.h`.
+    /// query are per the provided parameters, which may be given as numbers per `arpa/nameser.h`.
     ///
     /// This method is one of the very few places where this library performs strictly more
     /// allocation than the underlying `c-ares` code.  If this is a problem for you, you should
@@ -308,7 +755,12 @@ impl BlockingResolver {
     /// This method is provided so that users can search DNS types for which `c-ares` does not
     /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
     /// `search_xxx()` is available, that should be used.
-    pub fn search(&self, name: &str, dns_class: u16, query_type: u16) -> c_ares::Result<Vec<u8>> {
+    pub fn search(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> c_ares::Result<Vec<u8>> {
         let (tx, rx) = mpsc::sync_channel(1);
         self.inner
             .search(name, dns_class, query_type, move |result| {
@@ -316,4 +768,286 @@ impl BlockingResolver {
             });
         rx.recv().unwrap()
     }
+
+    /// Search for the A records associated with `name` - with `flags` applied to this query only.
+    /// See `Resolver::search_a_opts()`.
+    #[cfg(cares1_24)]
+    pub fn search_a_opts(&self, name: &str, flags: QueryFlags) -> c_ares::Result<c_ares::AResults> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .search_a_opts(name, flags, move |result| tx.send(result).unwrap());
+        rx.recv().unwrap()
+    }
+
+    /// Search for the AAAA records associated with `name` - with `flags` applied to this query
+    /// only.  See `Resolver::search_a_opts()`.
+    #[cfg(cares1_24)]
+    pub fn search_aaaa_opts(
+        &self,
+        name: &str,
+        flags: QueryFlags,
+    ) -> c_ares::Result<c_ares::AAAAResults> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .search_aaaa_opts(name, flags, move |result| tx.send(result).unwrap());
+        rx.recv().unwrap()
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`, as by `search()` - with
+    /// `flags` applied to this query only.  See `Resolver::search_a_opts()`.
+    #[cfg(cares1_24)]
+    pub fn search_opts(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        flags: QueryFlags,
+    ) -> c_ares::Result<Vec<u8>> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .search_opts(name, dns_class, query_type, flags, move |result| {
+                tx.send(result.map(std::borrow::ToOwned::to_owned)).unwrap()
+            });
+        rx.recv().unwrap()
+    }
+
+    /// Look up the A records associated with `name`, as by `query_a()` - but waiting at most
+    /// `timeout` for the result, and failing with `ETIMEOUT` after that.
+    ///
+    /// A query that times out is abandoned rather than cancelled: `c-ares` carries on with it,
+    /// subject to the configured timeouts and tries, but its result is discarded.  The same goes
+    /// for the other `*_timeout()` methods.
+    pub fn query_a_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::AResults> {
+        blockify_timeout!(self.inner, query_a, name, timeout)
+    }
+
+    /// Look up the AAAA records associated with `name`, waiting at most `timeout`.
+    pub fn query_aaaa_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::AAAAResults> {
+        blockify_timeout!(self.inner, query_aaaa, name, timeout)
+    }
+
+    /// Look up the CNAME records associated with `name`, waiting at most `timeout`.
+    pub fn query_cname_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::CNameResults> {
+        blockify_timeout!(self.inner, query_cname, name, timeout)
+    }
+
+    /// Look up the MX records associated with `name`, waiting at most `timeout`.
+    pub fn query_mx_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::MXResults> {
+        blockify_timeout!(self.inner, query_mx, name, timeout)
+    }
+
+    /// Look up the NS records associated with `name`, waiting at most `timeout`.
+    pub fn query_ns_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::NSResults> {
+        blockify_timeout!(self.inner, query_ns, name, timeout)
+    }
+
+    /// Look up the PTR records associated with `name`, waiting at most `timeout`.
+    pub fn query_ptr_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::PTRResults> {
+        blockify_timeout!(self.inner, query_ptr, name, timeout)
+    }
+
+    /// Look up the SRV records associated with `name`, waiting at most `timeout`.
+    pub fn query_srv_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::SRVResults> {
+        blockify_timeout!(self.inner, query_srv, name, timeout)
+    }
+
+    /// Look up the TXT records associated with `name`, waiting at most `timeout`.
+    pub fn query_txt_timeout(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> c_ares::Result<c_ares::TXTResults> {
+        blockify_timeout!(self.inner, query_txt, name, timeout)
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query()`, waiting at most
+    /// `timeout`.
+    pub fn query_timeout(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        timeout: Duration,
+    ) -> c_ares::Result<Vec<u8>> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .query(name, dns_class, query_type, move |result| {
+                let _ = tx.send(result.map(std::borrow::ToOwned::to_owned));
+            });
+        rx.recv_timeout(timeout)
+            .unwrap_or(Err(c_ares::Error::ETIMEOUT))
+    }
+
+    /// Perform host queries for all of `names`, as by `get_host_by_name()`, returning the results
+    /// in the same order.
+    ///
+    /// All of the queries are made at once, and this waits for them all to complete - so that
+    /// resolving many names takes about as long as resolving the slowest of them.
+    pub fn resolve_all(
+        &self,
+        names: &[&str],
+        family: c_ares::AddressFamily,
+    ) -> Vec<c_ares::Result<HostResults>> {
+        let mut results: Vec<Option<c_ares::Result<HostResults>>> = Vec::new();
+        results.resize_with(names.len(), || None);
+        let (tx, rx) = mpsc::channel();
+        let mut outstanding = 0;
+        for (index, name) in names.iter().enumerate() {
+            if let Some(result) = self.inner.host_override(name, family) {
+                results[index] = Some(result);
+                continue;
+            }
+            let tx = tx.clone();
+            self.inner.get_host_by_name(name, family, move |result| {
+                tx.send((index, result.map(Into::into))).unwrap()
+            });
+            outstanding += 1;
+        }
+        for (index, result) in rx.iter().take(outstanding) {
+            results[index] = Some(result);
+        }
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(c_ares::Error::ECANCELLED)))
+            .collect()
+    }
+
+    /// Perform a host query by name, as by `get_host_by_name()`, waiting at most `timeout`.
+    pub fn get_host_by_name_timeout(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+        timeout: Duration,
+    ) -> c_ares::Result<HostResults> {
+        if let Some(result) = self.inner.host_override(name, family) {
+            return result;
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner.get_host_by_name(name, family, move |result| {
+            let _ = tx.send(result.map(Into::into));
+        });
+        rx.recv_timeout(timeout)
+            .unwrap_or(Err(c_ares::Error::ETIMEOUT))
+    }
+
+    /// Look up the A records associated with `name`, as by `query_a()` - and report how the
+    /// query was answered.
+    #[cfg(cares1_29)]
+    pub fn query_a_ext(&self, name: &str) -> QueryResponse<c_ares::AResults> {
+        blockify_ext!(self.inner, query_a_ext, name)
+    }
+
+    /// Search for the A records associated with `name`, as by `search_a()` - and report how the
+    /// query was answered.
+    #[cfg(cares1_29)]
+    pub fn search_a_ext(&self, name: &str) -> QueryResponse<c_ares::AResults> {
+        blockify_ext!(self.inner, search_a_ext, name)
+    }
+
+    /// Look up the AAAA records associated with `name`, as by `query_aaaa()` - and report how the
+    /// query was answered.
+    #[cfg(cares1_29)]
+    pub fn query_aaaa_ext(&self, name: &str) -> QueryResponse<c_ares::AAAAResults> {
+        blockify_ext!(self.inner, query_aaaa_ext, name)
+    }
+
+    /// Search for the AAAA records associated with `name`, as by `search_aaaa()` - and report how
+    /// the query was answered.
+    #[cfg(cares1_29)]
+    pub fn search_aaaa_ext(&self, name: &str) -> QueryResponse<c_ares::AAAAResults> {
+        blockify_ext!(self.inner, search_aaaa_ext, name)
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query()` - and report how the query
+    /// was answered.
+    #[cfg(cares1_29)]
+    pub fn query_ext(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> QueryResponse<Vec<u8>> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .query_ext(name, dns_class, query_type, move |response| {
+                tx.send(response.map_result(std::borrow::ToOwned::to_owned))
+                    .unwrap()
+            });
+        rx.recv().unwrap()
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`, as by `search()` - and report
+    /// how the query was answered.
+    #[cfg(cares1_29)]
+    pub fn search_ext(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> QueryResponse<Vec<u8>> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .search_ext(name, dns_class, query_type, move |response| {
+                tx.send(response.map_result(std::borrow::ToOwned::to_owned))
+                    .unwrap()
+            });
+        rx.recv().unwrap()
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query_ext()` - with the header of
+    /// the response parsed.
+    ///
+    /// Note that `c-ares` reports a response with a failing response code as an error - eg
+    /// `ESERVFAIL` - so that the header is available only for successful responses.
+    #[cfg(cares1_29)]
+    pub fn query_raw_ext(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> QueryResponse<RawResponse> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.inner
+            .query_raw_ext(name, dns_class, query_type, move |response| {
+                tx.send(response.map_result(RawResponse::into_owned))
+                    .unwrap()
+            });
+        rx.recv().unwrap()
+    }
+
+    /// Cancel all requests made on this `BlockingResolver`.
+    ///
+    /// This is intended to be called from another thread: any lookups that are blocked waiting
+    /// for an answer return `c_ares::Error::ECANCELLED`.
+    pub fn cancel(&self) {
+        self.inner.cancel()
+    }
 }
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// The approximate memory used by queued queries and by buffered `resolve_many()` results, and the
+// budget for it - as set by `Options::set_memory_budget()`.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryBudget {
+    limit: Option<usize>,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    // Whether the budget is used up, so that new queries should fail.
+    pub(crate) fn is_exceeded(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.used.load(Ordering::Relaxed) >= limit)
+    }
+
+    pub(crate) fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    // Charge `bytes` to the budget, until the returned charge is dropped.  With no budget, nothing
+    // is counted.
+    pub(crate) fn charge(self: &Arc<Self>, bytes: usize) -> Option<Charge> {
+        self.limit?;
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+        let charge = Charge {
+            budget: Arc::clone(self),
+            bytes,
+        };
+        Some(charge)
+    }
+}
+
+// Memory charged to a budget, released when dropped.
+pub(crate) struct Charge {
+    budget: Arc<MemoryBudget>,
+    bytes: usize,
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.budget.used.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
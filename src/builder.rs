@@ -0,0 +1,226 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::blockingresolver::BlockingResolver;
+use crate::error::Error;
+use crate::futureresolver::FutureResolver;
+use crate::resolver::{Options, Resolver};
+#[cfg(cares1_34)]
+use crate::server::Server;
+
+#[cfg(cares1_29)]
+use c_ares::ServerStateFlags;
+
+// Well-known public resolvers.
+const CLOUDFLARE: &[&str] = &[
+    "1.1.1.1",
+    "1.0.0.1",
+    "2606:4700:4700::1111",
+    "2606:4700:4700::1001",
+];
+const GOOGLE: &[&str] = &[
+    "8.8.8.8",
+    "8.8.4.4",
+    "2001:4860:4860::8888",
+    "2001:4860:4860::8844",
+];
+const QUAD9: &[&str] = &["9.9.9.9", "149.112.112.112", "2620:fe::fe", "2620:fe::9"];
+
+#[cfg(cares1_29)]
+type ServerStateCallback = Box<dyn FnMut(&str, bool, ServerStateFlags) + Send + 'static>;
+
+/// Used to build a resolver, collecting together both the `Options` with which the channel is
+/// created and the configuration that is otherwise applied to a resolver after it is created - eg
+/// `set_servers()`.
+///
+/// All of the configuration is applied before the resolver is returned from `build()`, so no
+/// query is ever made with a partial configuration.
+#[derive(Default)]
+pub struct ResolverBuilder {
+    options: Options,
+    servers: Option<Vec<String>>,
+    sortlist: Option<Vec<String>>,
+    local_ipv4: Option<Ipv4Addr>,
+    local_ipv6: Option<Ipv6Addr>,
+    local_device: Option<String>,
+    #[cfg(cares1_29)]
+    server_state_callback: Option<ServerStateCallback>,
+}
+
+impl ResolverBuilder {
+    /// Returns a fresh `ResolverBuilder`, using default `Options`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a fresh `ResolverBuilder`, using the given `Options`.
+    pub fn with_options(options: Options) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Returns a fresh `ResolverBuilder`, configured from environment variables as described at
+    /// `Options::from_env()`.
+    pub fn from_env() -> Self {
+        let (options, servers) = Options::from_env();
+        let mut builder = Self::with_options(options);
+        if !servers.is_empty() {
+            builder.servers = Some(servers);
+        }
+        builder
+    }
+
+    /// Returns a fresh `ResolverBuilder`, using default `Options` and Cloudflare's public DNS
+    /// servers - `1.1.1.1` and friends, over both IPv4 and IPv6.
+    pub fn with_cloudflare() -> Self {
+        Self::with_servers(CLOUDFLARE)
+    }
+
+    /// Returns a fresh `ResolverBuilder`, using default `Options` and Google's public DNS servers -
+    /// `8.8.8.8` and friends, over both IPv4 and IPv6.
+    pub fn with_google() -> Self {
+        Self::with_servers(GOOGLE)
+    }
+
+    /// Returns a fresh `ResolverBuilder`, using default `Options` and Quad9's public DNS servers -
+    /// `9.9.9.9` and friends, over both IPv4 and IPv6.
+    pub fn with_quad9() -> Self {
+        Self::with_servers(QUAD9)
+    }
+
+    fn with_servers(servers: &[&str]) -> Self {
+        let mut builder = Self::new();
+        builder.set_servers(servers);
+        builder
+    }
+
+    /// The `Options` with which the channel will be created.
+    pub fn options(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
+    /// local named.
+    ///
+    /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
+    /// `[2001:4860:4860::8888]:53`.
+    pub fn set_servers(&mut self, servers: &[&str]) -> &mut Self {
+        self.servers = Some(servers.iter().map(|&s| s.to_owned()).collect());
+        self
+    }
+
+    /// Set the list of servers to contact, as socket addresses.
+    pub fn set_servers_addrs(&mut self, servers: &[SocketAddr]) -> &mut Self {
+        self.servers = Some(servers.iter().map(SocketAddr::to_string).collect());
+        self
+    }
+
+    /// Set the list of servers to contact, as IP addresses.  Servers are contacted on the default
+    /// port - 53, unless configured otherwise in the `Options`.
+    pub fn set_servers_ips(&mut self, servers: &[IpAddr]) -> &mut Self {
+        self.servers = Some(servers.iter().map(IpAddr::to_string).collect());
+        self
+    }
+
+    /// Set the list of servers to contact, each with its own UDP and TCP ports.
+    #[cfg(cares1_34)]
+    pub fn set_servers_ports(&mut self, servers: &[Server]) -> &mut Self {
+        self.servers = Some(servers.iter().map(|server| server.to_csv_entry()).collect());
+        self
+    }
+
+    /// Set the local IPv4 address from which to make queries.
+    pub fn set_local_ipv4(&mut self, ipv4: Ipv4Addr) -> &mut Self {
+        self.local_ipv4 = Some(ipv4);
+        self
+    }
+
+    /// Set the local IPv6 address from which to make queries.
+    pub fn set_local_ipv6(&mut self, ipv6: &Ipv6Addr) -> &mut Self {
+        self.local_ipv6 = Some(*ipv6);
+        self
+    }
+
+    /// Set the local device from which to make queries.
+    pub fn set_local_device(&mut self, device: &str) -> &mut Self {
+        self.local_device = Some(device.to_owned());
+        self
+    }
+
+    /// Initializes an address sortlist configuration, so that addresses returned by
+    /// `get_host_by_name()` are sorted according to the sortlist.
+    ///
+    /// Each element of the sortlist holds an IP-address/netmask pair. The netmask is optional but
+    /// follows the address after a slash if present. For example: "130.155.160.0/255.255.240.0",
+    /// or "130.155.0.0".
+    pub fn set_sortlist(&mut self, sortlist: &[&str]) -> &mut Self {
+        self.sortlist = Some(sortlist.iter().map(|&s| s.to_owned()).collect());
+        self
+    }
+
+    /// Set a callback function to be invoked whenever a query on the channel completes.
+    ///
+    /// `callback(server, success, flags)` will be called when a query completes.
+    ///
+    /// - `server` indicates the DNS server that was used for the query.
+    /// - `success` indicates whether the query succeeded or not.
+    /// - `flags` is a bitmask of flags describing various aspects of the query.
+    #[cfg(cares1_29)]
+    pub fn set_server_state_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: FnMut(&str, bool, ServerStateFlags) + Send + 'static,
+    {
+        self.server_state_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Build a `Resolver`.
+    pub fn build(self) -> Result<Resolver, Error> {
+        let Self {
+            options,
+            servers,
+            sortlist,
+            local_ipv4,
+            local_ipv6,
+            local_device,
+            #[cfg(cares1_29)]
+            server_state_callback,
+        } = self;
+        let resolver = Resolver::with_configuration(options, move |channel| {
+            if let Some(servers) = servers {
+                let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+                channel.set_servers(&servers)?;
+            }
+            if let Some(sortlist) = sortlist {
+                let sortlist: Vec<&str> = sortlist.iter().map(String::as_str).collect();
+                channel.set_sortlist(&sortlist)?;
+            }
+            if let Some(ipv4) = local_ipv4 {
+                channel.set_local_ipv4(ipv4);
+            }
+            if let Some(ipv6) = local_ipv6 {
+                channel.set_local_ipv6(&ipv6);
+            }
+            if let Some(device) = local_device {
+                channel.set_local_device(&device);
+            }
+            Ok(())
+        })?;
+        #[cfg(cares1_29)]
+        if let Some(callback) = server_state_callback {
+            resolver.set_server_state_callback(callback);
+        }
+        Ok(resolver)
+    }
+
+    /// Build a `FutureResolver`.
+    pub fn build_future(self) -> Result<FutureResolver, Error> {
+        self.build().map(FutureResolver::from_resolver)
+    }
+
+    /// Build a `BlockingResolver`.
+    pub fn build_blocking(self) -> Result<BlockingResolver, Error> {
+        self.build().map(BlockingResolver::from_resolver)
+    }
+}
@@ -0,0 +1,91 @@
+#[cfg(cares1_24)]
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[cfg(cares1_24)]
+use crate::transport::Transport;
+
+/// The protocol over which a DNS message is sent.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum Protocol {
+    /// UDP.
+    Udp,
+
+    /// TCP.
+    Tcp,
+}
+
+/// Which way a captured DNS message was going.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum Direction {
+    /// A query, on its way to a server.
+    Outgoing,
+
+    /// A response, on its way back from a server.
+    Incoming,
+}
+
+/// A DNS message, as passed to a packet capture callback.
+#[derive(Clone, Copy, Debug)]
+pub struct Packet<'a> {
+    /// Which way the message was going.
+    pub direction: Direction,
+
+    /// The message, without any TCP length prefix.
+    pub data: &'a [u8],
+
+    /// The server that the message was sent to or received from - or `None` if the message was
+    /// handed to a `Transport`, which decides for itself where it goes.
+    pub peer: Option<SocketAddr>,
+
+    /// The protocol over which the message was sent - or `None` if it was handed to a `Transport`.
+    pub protocol: Option<Protocol>,
+}
+
+pub(crate) type PacketCapture = Arc<dyn Fn(&Packet) + Send + Sync>;
+
+// Pass a message to the capture callback, if there is one.
+pub(crate) fn capture(
+    capture: Option<&PacketCapture>,
+    direction: Direction,
+    data: &[u8],
+    peer: Option<SocketAddr>,
+    protocol: Option<Protocol>,
+) {
+    if let Some(capture) = capture {
+        let packet = Packet {
+            direction,
+            data,
+            peer,
+            protocol,
+        };
+        capture(&packet);
+    }
+}
+
+// A transport that captures the messages that pass through it, on their way to and from another
+// transport.
+#[cfg(cares1_24)]
+pub(crate) struct CapturingTransport {
+    inner: Arc<dyn Transport>,
+    capture: PacketCapture,
+}
+
+#[cfg(cares1_24)]
+impl CapturingTransport {
+    pub(crate) fn new(inner: Arc<dyn Transport>, capture: PacketCapture) -> Self {
+        Self { inner, capture }
+    }
+}
+
+#[cfg(cares1_24)]
+impl Transport for CapturingTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let capture = Some(&self.capture);
+        self::capture(capture, Direction::Outgoing, query, None, None);
+        let response = self.inner.exchange(query)?;
+        self::capture(capture, Direction::Incoming, &response, None, None);
+        Ok(response)
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::builder::ResolverBuilder;
+use crate::resolver::Options;
+
+/// A serializable representation of resolver configuration, suitable for loading from an
+/// application's configuration file.
+///
+/// Every field is optional: anything not set is left to `c-ares` defaults and system
+/// configuration.
+///
+/// Convert to `Options` with `to_options()`, or to a `ResolverBuilder` - which also applies the
+/// list of servers - with `to_builder()`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResolverConfig {
+    /// The number of milliseconds each name server is given to respond to a query on the first
+    /// try.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+
+    /// The number of tries the resolver will try contacting each name server before giving up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tries: Option<u32>,
+
+    /// The number of dots which must be present in a domain name for it to be queried for "as
+    /// is" prior to querying for it with the default domain extensions appended.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ndots: Option<u32>,
+
+    /// The domains to search.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domains: Option<Vec<String>>,
+
+    /// The servers to contact, in the `host[:port]` format accepted by `set_servers()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub servers: Option<Vec<String>>,
+
+    /// Whether to use round robin selection of nameservers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<bool>,
+
+    /// The UDP port to use for queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub udp_port: Option<u16>,
+
+    /// The TCP port to use for queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_port: Option<u16>,
+}
+
+impl ResolverConfig {
+    /// Returns the `Options` described by this configuration.
+    ///
+    /// Note that the list of servers is not part of the `Options`: prefer `to_builder()` to
+    /// have that applied too.
+    pub fn to_options(&self) -> Options {
+        let mut options = Options::new();
+        if let Some(timeout) = self.timeout {
+            options.set_timeout(timeout);
+        }
+        if let Some(tries) = self.tries {
+            options.set_tries(tries);
+        }
+        if let Some(ndots) = self.ndots {
+            options.set_ndots(ndots);
+        }
+        if let Some(ref domains) = self.domains {
+            let domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+            options.set_domains(&domains);
+        }
+        match self.rotate {
+            Some(true) => {
+                options.set_rotate();
+            }
+            Some(false) => {
+                options.set_no_rotate();
+            }
+            None => {}
+        }
+        if let Some(udp_port) = self.udp_port {
+            options.set_udp_port(udp_port);
+        }
+        if let Some(tcp_port) = self.tcp_port {
+            options.set_tcp_port(tcp_port);
+        }
+        options
+    }
+
+    /// Returns a `ResolverBuilder` configured as described by this configuration.
+    pub fn to_builder(&self) -> ResolverBuilder {
+        let mut builder = ResolverBuilder::with_options(self.to_options());
+        if let Some(ref servers) = self.servers {
+            let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+            builder.set_servers(&servers);
+        }
+        builder
+    }
+}
+
+impl From<&Options> for ResolverConfig {
+    fn from(options: &Options) -> Self {
+        Self {
+            timeout: options.timeout(),
+            tries: options.tries(),
+            ndots: options.ndots(),
+            domains: options.domains().map(<[String]>::to_vec),
+            servers: None,
+            rotate: options.rotate(),
+            udp_port: options.udp_port(),
+            tcp_port: options.tcp_port(),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! DNS-based service discovery, per RFC 6763.
+//!
+//! `FutureResolver::discover_services()` and `BlockingResolver::discover_services()` take a
+//! service type such as `_http._tcp.example.com`, enumerate the instances of that service, and
+//! look up where each instance can be reached.
+use std::future::Future;
+use std::net::IpAddr;
+
+use crate::blockingresolver::BlockingResolver;
+use crate::futureresolver::FutureResolver;
+
+/// An instance of a service, discovered via DNS.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServiceInstance {
+    /// The full name of the instance - eg `Printer._ipp._tcp.example.com`.
+    pub name: String,
+
+    /// The instance part of the name - eg `Printer`.
+    pub instance: String,
+
+    /// The host on which the instance runs.
+    pub host: String,
+
+    /// The port on which the instance runs.
+    pub port: u16,
+
+    /// The priority of the SRV record describing the instance.
+    pub priority: u16,
+
+    /// The weight of the SRV record describing the instance.
+    pub weight: u16,
+
+    /// The key/value pairs from the instance's TXT record.  A key with no `=` has no value.
+    pub txt: Vec<(String, Option<Vec<u8>>)>,
+
+    /// The addresses of `host`.
+    pub addresses: Vec<IpAddr>,
+}
+
+impl ServiceInstance {
+    fn new(name: String, service: &str, srv: &c_ares::SRVResults) -> Option<Self> {
+        let srv = srv
+            .iter()
+            .min_by_key(|srv| (srv.priority(), std::cmp::Reverse(srv.weight())))?;
+        let instance = name
+            .strip_suffix(service)
+            .and_then(|instance| instance.strip_suffix('.'))
+            .unwrap_or(&name)
+            .to_owned();
+        Some(Self {
+            instance,
+            host: srv.host().to_owned(),
+            port: srv.port(),
+            priority: srv.priority(),
+            weight: srv.weight(),
+            txt: Vec::new(),
+            addresses: Vec::new(),
+            name,
+        })
+    }
+
+    fn set_txt(&mut self, txt: c_ares::Result<c_ares::TXTResults>) {
+        let Ok(txt) = txt else { return };
+        for result in &txt {
+            let text = result.text();
+            if text.is_empty() {
+                continue;
+            }
+            let (key, value) = match text.iter().position(|&c| c == b'=') {
+                Some(index) => (&text[..index], Some(text[index + 1..].to_vec())),
+                None => (text, None),
+            };
+            let key = String::from_utf8_lossy(key).into_owned();
+            // Only the first occurrence of a key counts.
+            if !self.txt.iter().any(|(k, _)| k.eq_ignore_ascii_case(&key)) {
+                self.txt.push((key, value));
+            }
+        }
+    }
+
+    fn add_addresses(
+        &mut self,
+        a: c_ares::Result<c_ares::AResults>,
+        aaaa: c_ares::Result<c_ares::AAAAResults>,
+    ) {
+        if let Ok(a) = a {
+            self.addresses
+                .extend(a.iter().map(|result| IpAddr::V4(result.ipv4())));
+        }
+        if let Ok(aaaa) = aaaa {
+            self.addresses
+                .extend(aaaa.iter().map(|result| IpAddr::V6(result.ipv6())));
+        }
+    }
+}
+
+// The instance names in the answer to a PTR query.
+fn instance_names(ptr: &c_ares::PTRResults) -> Vec<String> {
+    let mut names: Vec<String> = ptr.aliases().map(str::to_owned).collect();
+    if names.is_empty() {
+        names.push(ptr.hostname().to_owned());
+    }
+    names.dedup();
+    names
+}
+
+pub(crate) fn discover(
+    resolver: FutureResolver,
+    service: &str,
+) -> impl Future<Output = c_ares::Result<Vec<ServiceInstance>>> + Send {
+    let service = service.to_owned();
+    async move {
+        let ptr = resolver.query_ptr(&service).await?;
+
+        // Make all of the queries for each step before waiting for any of them.
+        let lookups: Vec<_> = instance_names(&ptr)
+            .into_iter()
+            .map(|name| {
+                let srv = resolver.query_srv(&name);
+                let txt = resolver.query_txt(&name);
+                (name, srv, txt)
+            })
+            .collect();
+        let mut instances = Vec::new();
+        for (name, srv, txt) in lookups {
+            let Ok(srv) = srv.await else { continue };
+            if let Some(mut instance) = ServiceInstance::new(name, &service, &srv) {
+                instance.set_txt(txt.await);
+                instances.push(instance);
+            }
+        }
+
+        let lookups: Vec<_> = instances
+            .iter()
+            .map(|instance| {
+                let a = resolver.query_a(&instance.host);
+                let aaaa = resolver.query_aaaa(&instance.host);
+                (a, aaaa)
+            })
+            .collect();
+        for (instance, (a, aaaa)) in instances.iter_mut().zip(lookups) {
+            instance.add_addresses(a.await, aaaa.await);
+        }
+        Ok(instances)
+    }
+}
+
+pub(crate) fn discover_blocking(
+    resolver: &BlockingResolver,
+    service: &str,
+) -> c_ares::Result<Vec<ServiceInstance>> {
+    let ptr = resolver.query_ptr(service)?;
+    let mut instances = Vec::new();
+    for name in instance_names(&ptr) {
+        let Ok(srv) = resolver.query_srv(&name) else {
+            continue;
+        };
+        if let Some(mut instance) = ServiceInstance::new(name, service, &srv) {
+            instance.set_txt(resolver.query_txt(&instance.name));
+            let a = resolver.query_a(&instance.host);
+            let aaaa = resolver.query_aaaa(&instance.host);
+            instance.add_addresses(a, aaaa);
+            instances.push(instance);
+        }
+    }
+    Ok(instances)
+}
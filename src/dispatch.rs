@@ -0,0 +1,301 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::budget::{Charge, MemoryBudget};
+use crate::error::Error;
+use crate::eventloop::TaskSubmitter;
+use crate::metrics::LoadStats;
+use crate::priority::Priority;
+use crate::rejection;
+#[cfg(cares1_29)]
+use crate::response;
+use crate::timer;
+
+// A query to be made on the event loop - or, given no channel, to be failed because it can be
+// neither made nor queued.
+type Task = Box<dyn FnOnce(Option<&mut c_ares::Channel>) + Send>;
+
+// The error with which handlers are called when queries are failed because the queue is full, or
+// the memory budget is used up.  `c_ares::Error` has nothing closer: `try_query()` gives
+// `Error::Overloaded` or `Error::MemoryBudgetExceeded` instead.
+pub(crate) const OVERLOADED: c_ares::Error = c_ares::Error::ENOMEM;
+
+// The approximate memory used by a queued query, besides its name.
+const QUEUED_QUERY_SIZE: usize = 256;
+
+// A token bucket, holding up to `burst` tokens and refilled at `rate` tokens per second.  Each
+// query takes a token.
+struct Bucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: u32, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            rate: f64::from(rate.max(1)),
+            burst,
+            tokens: burst,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.updated = now;
+    }
+
+    // The time until a token is available.
+    fn wait(&self) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.rate).max(0.0))
+    }
+}
+
+// A query waiting to be made: its tracker ID, the event loop on which to make it, and its charge
+// to the memory budget, held while it waits.
+struct Queued {
+    id: u64,
+    submitter: TaskSubmitter,
+    task: Task,
+    _charge: Option<Charge>,
+}
+
+// Queries waiting to be made, one queue for each priority - highest first.
+#[derive(Default)]
+struct Queue([VecDeque<Queued>; 3]);
+
+impl Queue {
+    fn len(&self) -> usize {
+        self.0.iter().map(VecDeque::len).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(VecDeque::is_empty)
+    }
+
+    fn push(&mut self, priority: Priority, queued: Queued) {
+        self.0[priority as usize].push_back(queued);
+    }
+
+    fn pop(&mut self) -> Option<Queued> {
+        self.0.iter_mut().find_map(VecDeque::pop_front)
+    }
+}
+
+// The queries that have been made and not yet completed, and those waiting to be made - keyed by
+// the IDs of their trackers.
+#[derive(Default)]
+struct State {
+    outstanding: HashSet<u64>,
+    queue: Queue,
+    bucket: Option<Bucket>,
+    timer: bool,
+}
+
+// Passes queries to the event loop, holding them back in a queue while there are `max_outstanding`
+// queries that have not yet completed, or while the rate limit is exceeded - as set by
+// `Options::set_max_outstanding_queries()` and `Options::set_rate_limit()`.
+//
+// A resolver shares its dispatcher with the resolvers derived from it - eg by `tcp()` - so the
+// limits and the memory budget apply to all of them together.  Each query is passed to the event
+// loop of the resolver that made it.
+pub(crate) struct Dispatcher {
+    max_outstanding: Option<usize>,
+    max_queued: Option<usize>,
+    budget: Arc<MemoryBudget>,
+    state: Mutex<State>,
+}
+
+impl Dispatcher {
+    pub(crate) fn new(
+        max_outstanding: Option<usize>,
+        max_queued: Option<usize>,
+        rate_limit: Option<(u32, u32)>,
+        budget: Arc<MemoryBudget>,
+    ) -> Self {
+        let state = State {
+            bucket: rate_limit.map(|(rate, burst)| Bucket::new(rate, burst)),
+            ..State::default()
+        };
+        Self {
+            max_outstanding,
+            max_queued,
+            budget,
+            state: Mutex::new(state),
+        }
+    }
+
+    fn is_limited(&self, state: &State) -> bool {
+        self.max_outstanding.is_some() || state.bucket.is_some()
+    }
+
+    fn is_full(&self, state: &State) -> bool {
+        self.max_queued
+            .is_some_and(|max_queued| state.queue.len() >= max_queued)
+    }
+
+    // Whether a query can be made now, without exceeding either limit.
+    fn can_make(&self, state: &mut State) -> bool {
+        if self
+            .max_outstanding
+            .is_some_and(|max_outstanding| state.outstanding.len() >= max_outstanding)
+        {
+            return false;
+        }
+        match state.bucket {
+            Some(ref mut bucket) => {
+                bucket.refill();
+                bucket.tokens >= 1.0
+            }
+            None => true,
+        }
+    }
+
+    // Note that the query with tracker `id` is being made.
+    fn make(&self, state: &mut State, id: u64) {
+        if self.max_outstanding.is_some() {
+            state.outstanding.insert(id);
+        }
+        if let Some(ref mut bucket) = state.bucket {
+            bucket.tokens -= 1.0;
+        }
+    }
+
+    fn submit(submitter: &TaskSubmitter, task: Task) {
+        submitter.submit(move |channel| {
+            // Nothing reported before the query is made is about it - eg if it's answered from the
+            // cache.
+            #[cfg(cares1_29)]
+            response::clear_contact();
+            task(Some(channel));
+        });
+    }
+
+    // Fail a query, for the reason given by `error` - on the event loop, as if it had been made.
+    fn reject(submitter: &TaskSubmitter, task: Task, error: Error) {
+        rejection::note(error);
+        submitter.submit(move |_| task(None));
+    }
+
+    // Make the query with tracker `id`, for a name of length `name_len`, on the event loop of
+    // `submitter` - or queue it at the current priority if either limit is reached, or fail it if
+    // the queue is full or the memory budget is used up.
+    pub(crate) fn dispatch<F>(
+        self: &Arc<Self>,
+        submitter: &TaskSubmitter,
+        id: u64,
+        name_len: usize,
+        task: F,
+    ) where
+        F: FnOnce(Option<&mut c_ares::Channel>) + Send + 'static,
+    {
+        if self.budget.is_exceeded() {
+            Self::reject(submitter, Box::new(task), Error::MemoryBudgetExceeded);
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if !self.is_limited(&state) {
+            drop(state);
+            Self::submit(submitter, Box::new(task));
+        } else if state.queue.is_empty() && self.can_make(&mut state) {
+            self.make(&mut state, id);
+            drop(state);
+            Self::submit(submitter, Box::new(task));
+        } else if self.is_full(&state) {
+            drop(state);
+            Self::reject(submitter, Box::new(task), Error::Overloaded);
+        } else {
+            let queued = Queued {
+                id,
+                submitter: submitter.clone(),
+                task: Box::new(task),
+                _charge: self.budget.charge(QUEUED_QUERY_SIZE + name_len),
+            };
+            state.queue.push(Priority::current(), queued);
+            self.start_timer(&mut state);
+        }
+    }
+
+    // Note that the query with tracker `id` has completed, making queued queries in its place if
+    // it was outstanding.
+    pub(crate) fn finished(self: &Arc<Self>, id: u64) {
+        if self.max_outstanding.is_none() {
+            return;
+        }
+        let removed = self.state.lock().unwrap().outstanding.remove(&id);
+        if removed {
+            self.make_queued();
+        }
+    }
+
+    // Make as many queued queries as the limits allow.
+    fn make_queued(self: &Arc<Self>) {
+        let mut made = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            while !state.queue.is_empty() && self.can_make(&mut state) {
+                let queued = state.queue.pop().unwrap();
+                self.make(&mut state, queued.id);
+                made.push(queued);
+            }
+            self.start_timer(&mut state);
+        }
+        for queued in made {
+            Self::submit(&queued.submitter, queued.task);
+        }
+    }
+
+    // If queries are waiting only for a token, arrange to make them once there is one.
+    fn start_timer(self: &Arc<Self>, state: &mut State) {
+        if state.timer || state.queue.is_empty() {
+            return;
+        }
+        let Some(ref bucket) = state.bucket else {
+            return;
+        };
+        if bucket.tokens >= 1.0 {
+            return;
+        }
+        state.timer = true;
+        let wait = bucket.wait();
+        let dispatcher = Arc::downgrade(self);
+        timer::after(wait, move || {
+            if let Some(dispatcher) = dispatcher.upgrade() {
+                dispatcher.state.lock().unwrap().timer = false;
+                dispatcher.make_queued();
+            }
+        });
+    }
+
+    // Whether a query made now would be made or queued, rather than failed.
+    pub(crate) fn check_capacity(&self) -> Result<(), Error> {
+        if self.budget.is_exceeded() {
+            return Err(Error::MemoryBudgetExceeded);
+        }
+        let mut state = self.state.lock().unwrap();
+        if !self.is_limited(&state) || (state.queue.is_empty() && self.can_make(&mut state)) {
+            return Ok(());
+        }
+        if self.is_full(&state) {
+            return Err(Error::Overloaded);
+        }
+        Ok(())
+    }
+
+    // Add the state of the queue, the rate limiter and the memory budget to `load`.
+    pub(crate) fn add_load(&self, load: &mut LoadStats) {
+        load.memory_used = self.budget.used();
+        let mut state = self.state.lock().unwrap();
+        load.queued = state.queue.len();
+        load.rate_limit_tokens = state.bucket.as_mut().map(|bucket| {
+            bucket.refill();
+            bucket.tokens
+        });
+    }
+}
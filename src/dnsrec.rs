@@ -0,0 +1,343 @@
+// A minimal safe wrapper around the `c-ares` dnsrec API, which parses DNS messages - including
+// record types for which `c-ares` has no dedicated parser.
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ptr;
+use std::slice;
+
+use c_ares_sys::{
+    ares_dns_rec_type_t, ares_dns_record_t, ares_dns_rr_key_t, ares_dns_rr_t, ares_dns_section_t,
+    ares_status_t,
+};
+
+// The internet class.
+pub(crate) const C_IN: u16 = 1;
+
+// Record types.
+pub(crate) const T_HTTPS: u16 = ares_dns_rec_type_t::ARES_REC_TYPE_HTTPS as u16;
+pub(crate) const T_TLSA: u16 = ares_dns_rec_type_t::ARES_REC_TYPE_TLSA as u16;
+pub(crate) const T_ANY: u16 = ares_dns_rec_type_t::ARES_REC_TYPE_ANY as u16;
+pub(crate) const T_DS: u16 = 43;
+pub(crate) const T_SSHFP: u16 = 44;
+pub(crate) const T_RRSIG: u16 = 46;
+pub(crate) const T_DNSKEY: u16 = 48;
+
+// Defines a collection of results, parsed from the answers in a DNS message:
+//
+// - of type `$rec_type`, by `$result::from_rr`
+// - of a type that `c-ares` doesn't know, `raw $rr_type`, by `$result::from_raw` - which returns
+//   `None` if the data is malformed
+// - or of any type, if no type is given, by `$result::from_rr`.
+macro_rules! dns_results {
+    ($(#[$meta:meta])* $results:ident, $result:ident, $rec_type:ident) => {
+        dns_results!(
+            @define $(#[$meta])* $results,
+            $result,
+            |record| record.answers(c_ares_sys::ares_dns_rec_type_t::$rec_type),
+            |rr| Some($result::from_rr(rr))
+        );
+    };
+    ($(#[$meta:meta])* $results:ident, $result:ident, raw $rr_type:expr) => {
+        dns_results!(
+            @define $(#[$meta])* $results,
+            $result,
+            |record| record.raw_answers($rr_type),
+            |data| $result::from_raw(data)
+        );
+    };
+    ($(#[$meta:meta])* $results:ident, $result:ident) => {
+        dns_results!(
+            @define $(#[$meta])* $results,
+            $result,
+            |record| record.all_answers(),
+            |rr| Some($result::from_rr(rr))
+        );
+    };
+    (
+        @define $(#[$meta:meta])* $results:ident,
+        $result:ident,
+        |$record:ident| $answers:expr,
+        $convert:expr
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
+        pub struct $results {
+            records: Vec<$result>,
+        }
+
+        impl $results {
+            pub(crate) fn parse(data: &[u8]) -> c_ares::Result<Self> {
+                let $record = $crate::dnsrec::DnsRecord::parse(data)?;
+                let records = $answers
+                    .iter()
+                    .map($convert)
+                    .collect::<Option<Vec<$result>>>()
+                    .ok_or(c_ares::Error::EBADRESP)?;
+                if records.is_empty() {
+                    return Err(c_ares::Error::ENODATA);
+                }
+                Ok(Self { records })
+            }
+
+            #[doc = concat!("Returns an iterator over the `", stringify!($result), "` values in this `", stringify!($results), "`.")]
+            pub fn iter(&self) -> std::slice::Iter<'_, $result> {
+                self.records.iter()
+            }
+        }
+
+        impl IntoIterator for $results {
+            type Item = $result;
+            type IntoIter = std::vec::IntoIter<$result>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.records.into_iter()
+            }
+        }
+
+        impl<'a> IntoIterator for &'a $results {
+            type Item = &'a $result;
+            type IntoIter = std::slice::Iter<'a, $result>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.records.iter()
+            }
+        }
+    };
+}
+
+// A parsed DNS message.
+pub(crate) struct DnsRecord {
+    record: *mut ares_dns_record_t,
+}
+
+impl DnsRecord {
+    pub(crate) fn parse(data: &[u8]) -> c_ares::Result<Self> {
+        let mut record = ptr::null_mut();
+        let status =
+            unsafe { c_ares_sys::ares_dns_parse(data.as_ptr(), data.len(), 0, &mut record) };
+        if status != ares_status_t::ARES_SUCCESS {
+            return Err(c_ares::Error::from(status as i32));
+        }
+        Ok(Self { record })
+    }
+
+    // The records in the answer section.
+    pub(crate) fn all_answers(&self) -> Vec<DnsRr<'_>> {
+        let section = ares_dns_section_t::ARES_SECTION_ANSWER;
+        let count = unsafe { c_ares_sys::ares_dns_record_rr_cnt(self.record, section) };
+        (0..count)
+            .map(|index| {
+                let rr = unsafe {
+                    c_ares_sys::ares_dns_record_rr_get_const(self.record, section, index)
+                };
+                DnsRr {
+                    rr,
+                    _record: PhantomData,
+                }
+            })
+            .collect()
+    }
+
+    // The data of the records of type `rr_type` in the answer section, where that is a type that
+    // `c-ares` doesn't know.
+    pub(crate) fn raw_answers(&self, rr_type: u16) -> Vec<Vec<u8>> {
+        self.answers(ares_dns_rec_type_t::ARES_REC_TYPE_RAW_RR)
+            .iter()
+            .filter(|rr| rr.u16(ares_dns_rr_key_t::ARES_RR_RAW_RR_TYPE) == rr_type)
+            .map(|rr| rr.bin(ares_dns_rr_key_t::ARES_RR_RAW_RR_DATA))
+            .collect()
+    }
+
+    // The name in the question section, if there is exactly one question.
+    pub(crate) fn question_name(&self) -> Option<String> {
+        if unsafe { c_ares_sys::ares_dns_record_query_cnt(self.record) } != 1 {
+            return None;
+        }
+        let mut name = ptr::null();
+        let status = unsafe {
+            c_ares_sys::ares_dns_record_query_get(
+                self.record,
+                0,
+                &mut name,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if status != ares_status_t::ARES_SUCCESS || name.is_null() {
+            return None;
+        }
+        let name = unsafe { CStr::from_ptr(name) };
+        Some(name.to_string_lossy().into_owned())
+    }
+
+    // Remove the records in the answer section for which `keep` returns false, returning how many
+    // were removed.
+    pub(crate) fn retain_answers<F>(&mut self, mut keep: F) -> usize
+    where
+        F: FnMut(&DnsRr) -> bool,
+    {
+        let section = ares_dns_section_t::ARES_SECTION_ANSWER;
+        let remove: Vec<usize> = self
+            .all_answers()
+            .iter()
+            .enumerate()
+            .filter(|(_, rr)| !keep(rr))
+            .map(|(index, _)| index)
+            .collect();
+        for &index in remove.iter().rev() {
+            unsafe { c_ares_sys::ares_dns_record_rr_del(self.record, section, index) };
+        }
+        remove.len()
+    }
+
+    // Encode the message.
+    pub(crate) fn write(&self) -> c_ares::Result<Vec<u8>> {
+        let mut buf = ptr::null_mut();
+        let mut len = 0;
+        let status = unsafe { c_ares_sys::ares_dns_write(self.record, &mut buf, &mut len) };
+        if status != ares_status_t::ARES_SUCCESS {
+            return Err(c_ares::Error::from(status as i32));
+        }
+        let data = unsafe { slice::from_raw_parts(buf, len) }.to_vec();
+        unsafe { c_ares_sys::ares_free_string(buf.cast()) };
+        Ok(data)
+    }
+
+    // The records of type `rec_type` in the answer section.
+    pub(crate) fn answers(&self, rec_type: ares_dns_rec_type_t) -> Vec<DnsRr<'_>> {
+        let mut answers = self.all_answers();
+        answers.retain(|rr| rr.rec_type() == rec_type);
+        answers
+    }
+}
+
+impl Drop for DnsRecord {
+    fn drop(&mut self) {
+        unsafe { c_ares_sys::ares_dns_record_destroy(self.record) }
+    }
+}
+
+// A resource record, borrowed from a `DnsRecord`.
+pub(crate) struct DnsRr<'a> {
+    rr: *const ares_dns_rr_t,
+    _record: PhantomData<&'a DnsRecord>,
+}
+
+impl DnsRr<'_> {
+    pub(crate) fn rec_type(&self) -> ares_dns_rec_type_t {
+        unsafe { c_ares_sys::ares_dns_rr_get_type(self.rr) }
+    }
+
+    pub(crate) fn name(&self) -> String {
+        let name = unsafe { c_ares_sys::ares_dns_rr_get_name(self.rr) };
+        if name.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub(crate) fn ttl(&self) -> u32 {
+        unsafe { c_ares_sys::ares_dns_rr_get_ttl(self.rr) }
+    }
+
+    pub(crate) fn addr(&self, key: ares_dns_rr_key_t) -> Ipv4Addr {
+        let addr = unsafe { c_ares_sys::ares_dns_rr_get_addr(self.rr, key) };
+        if addr.is_null() {
+            return Ipv4Addr::UNSPECIFIED;
+        }
+        Ipv4Addr::from(unsafe { *addr.cast::<[u8; 4]>() })
+    }
+
+    pub(crate) fn addr6(&self, key: ares_dns_rr_key_t) -> Ipv6Addr {
+        let addr = unsafe { c_ares_sys::ares_dns_rr_get_addr6(self.rr, key) };
+        if addr.is_null() {
+            return Ipv6Addr::UNSPECIFIED;
+        }
+        Ipv6Addr::from(unsafe { (*addr)._S6_un._S6_u8 })
+    }
+
+    pub(crate) fn u8(&self, key: ares_dns_rr_key_t) -> u8 {
+        unsafe { c_ares_sys::ares_dns_rr_get_u8(self.rr, key) }
+    }
+
+    pub(crate) fn u16(&self, key: ares_dns_rr_key_t) -> u16 {
+        unsafe { c_ares_sys::ares_dns_rr_get_u16(self.rr, key) }
+    }
+
+    pub(crate) fn u32(&self, key: ares_dns_rr_key_t) -> u32 {
+        unsafe { c_ares_sys::ares_dns_rr_get_u32(self.rr, key) }
+    }
+
+    pub(crate) fn str(&self, key: ares_dns_rr_key_t) -> String {
+        let value = unsafe { c_ares_sys::ares_dns_rr_get_str(self.rr, key) };
+        if value.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(value) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub(crate) fn bin(&self, key: ares_dns_rr_key_t) -> Vec<u8> {
+        let mut len = 0;
+        let value = unsafe { c_ares_sys::ares_dns_rr_get_bin(self.rr, key, &mut len) };
+        if value.is_null() {
+            return Vec::new();
+        }
+        unsafe { slice::from_raw_parts(value, len) }.to_vec()
+    }
+
+    // The options held under `key`, as (code, value) pairs.
+    pub(crate) fn opts(&self, key: ares_dns_rr_key_t) -> Vec<(u16, Vec<u8>)> {
+        let count = unsafe { c_ares_sys::ares_dns_rr_get_opt_cnt(self.rr, key) };
+        (0..count)
+            .map(|index| {
+                let mut value = ptr::null();
+                let mut len = 0;
+                let code = unsafe {
+                    c_ares_sys::ares_dns_rr_get_opt(self.rr, key, index, &mut value, &mut len)
+                };
+                let value = if value.is_null() {
+                    Vec::new()
+                } else {
+                    unsafe { slice::from_raw_parts(value, len) }.to_vec()
+                };
+                (code, value)
+            })
+            .collect()
+    }
+}
+
+// Split `data` into IPv4 addresses.
+pub(crate) fn ipv4_addrs(data: &[u8]) -> Vec<Ipv4Addr> {
+    data.chunks_exact(4)
+        .map(|chunk| Ipv4Addr::new(chunk[0], chunk[1], chunk[2], chunk[3]))
+        .collect()
+}
+
+// Split `data` into IPv6 addresses.
+pub(crate) fn ipv6_addrs(data: &[u8]) -> Vec<Ipv6Addr> {
+    data.chunks_exact(16)
+        .map(|chunk| {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(chunk);
+            Ipv6Addr::from(octets)
+        })
+        .collect()
+}
+
+// Split `data` into a list of length-prefixed strings.
+pub(crate) fn strings(mut data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    while let Some((&len, rest)) = data.split_first() {
+        let len = usize::from(len).min(rest.len());
+        strings.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+        data = &rest[len..];
+    }
+    strings
+}
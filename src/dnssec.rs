@@ -0,0 +1,160 @@
+use crate::dnsrec::{T_DNSKEY, T_DS, T_RRSIG};
+
+dns_results!(
+    /// The result of a successful DNSKEY lookup.
+    DNSKEYResults,
+    DNSKEYResult,
+    raw T_DNSKEY
+);
+
+dns_results!(
+    /// The result of a successful DS lookup.
+    DSResults,
+    DSResult,
+    raw T_DS
+);
+
+dns_results!(
+    /// The result of a successful RRSIG lookup.
+    RRSIGResults,
+    RRSIGResult,
+    raw T_RRSIG
+);
+
+/// The contents of a single DNSKEY record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DNSKEYResult {
+    /// The flags - eg 256 for a zone-signing key, 257 for a key-signing key.
+    pub flags: u16,
+
+    /// The protocol - always 3.
+    pub protocol: u8,
+
+    /// The algorithm of the key - eg 8 for RSA/SHA-256, 13 for ECDSA P-256/SHA-256.
+    pub algorithm: u8,
+
+    /// The public key.
+    pub public_key: Vec<u8>,
+}
+
+/// The contents of a single DS record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DSResult {
+    /// The key tag of the DNSKEY that this record refers to.
+    pub key_tag: u16,
+
+    /// The algorithm of the DNSKEY that this record refers to.
+    pub algorithm: u8,
+
+    /// The type of the digest - eg 2 for SHA-256.
+    pub digest_type: u8,
+
+    /// The digest of the DNSKEY that this record refers to.
+    pub digest: Vec<u8>,
+}
+
+/// The contents of a single RRSIG record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RRSIGResult {
+    /// The type of the records covered by this signature.
+    pub type_covered: u16,
+
+    /// The algorithm of the signature.
+    pub algorithm: u8,
+
+    /// The number of labels in the original owner name.
+    pub labels: u8,
+
+    /// The time-to-live of the covered records, as in the zone.
+    pub original_ttl: u32,
+
+    /// The time after which the signature is not valid, in seconds since the epoch - modulo 2^32.
+    pub expiration: u32,
+
+    /// The time before which the signature is not valid, in seconds since the epoch - modulo 2^32.
+    pub inception: u32,
+
+    /// The key tag of the DNSKEY that made the signature.
+    pub key_tag: u16,
+
+    /// The name of the zone whose DNSKEY made the signature.
+    pub signer_name: String,
+
+    /// The signature.
+    pub signature: Vec<u8>,
+}
+
+impl DNSKEYResult {
+    pub(crate) fn from_raw(data: &[u8]) -> Option<Self> {
+        match *data {
+            [f0, f1, protocol, algorithm, ref public_key @ ..] => Some(Self {
+                flags: u16::from_be_bytes([f0, f1]),
+                protocol,
+                algorithm,
+                public_key: public_key.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl DSResult {
+    pub(crate) fn from_raw(data: &[u8]) -> Option<Self> {
+        match *data {
+            [k0, k1, algorithm, digest_type, ref digest @ ..] => Some(Self {
+                key_tag: u16::from_be_bytes([k0, k1]),
+                algorithm,
+                digest_type,
+                digest: digest.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl RRSIGResult {
+    pub(crate) fn from_raw(data: &[u8]) -> Option<Self> {
+        let fixed = data.get(..18)?;
+        let u32_at = |index: usize| {
+            u32::from_be_bytes([
+                fixed[index],
+                fixed[index + 1],
+                fixed[index + 2],
+                fixed[index + 3],
+            ])
+        };
+        let (signer_name, rest) = read_name(&data[18..])?;
+        Some(Self {
+            type_covered: u16::from_be_bytes([fixed[0], fixed[1]]),
+            algorithm: fixed[2],
+            labels: fixed[3],
+            original_ttl: u32_at(4),
+            expiration: u32_at(8),
+            inception: u32_at(12),
+            key_tag: u16::from_be_bytes([fixed[16], fixed[17]]),
+            signer_name,
+            signature: rest.to_vec(),
+        })
+    }
+}
+
+// Read an uncompressed domain name from the start of `data`, returning it and the remaining data.
+fn read_name(mut data: &[u8]) -> Option<(String, &[u8])> {
+    let mut labels = Vec::new();
+    loop {
+        let (&len, rest) = data.split_first()?;
+        let len = usize::from(len);
+        if len == 0 {
+            return Some((labels.join("."), rest));
+        }
+        if len > 63 {
+            return None;
+        }
+        let label = rest.get(..len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        data = &rest[len..];
+    }
+}
@@ -0,0 +1,358 @@
+//! ENUM (RFC 6116): mapping E.164 telephone numbers to URIs via NAPTR records.
+//!
+//! `enum_lookup()` on each of the resolvers queries the NAPTR records for a number, and applies
+//! their rewrite rules to produce candidate URIs.
+
+/// A URI for a telephone number, found via ENUM.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumUri {
+    /// The order of the NAPTR record that produced this URI - lower is to be tried first.
+    pub order: u16,
+
+    /// The preference of the NAPTR record, between records of the same order - lower is
+    /// preferred.
+    pub preference: u16,
+
+    /// The services offered - eg `E2U+sip`.
+    pub services: String,
+
+    /// The URI.
+    pub uri: String,
+}
+
+/// Returns the ENUM domain for an E.164 number - eg `4.3.2.1.5.5.5.5.1.4.1.e164.arpa` for
+/// `+14155551234`.
+///
+/// Spaces, dashes, dots and parentheses are ignored.  Returns `None` if the number doesn't start
+/// with `+` or has no digits.
+pub fn enum_domain(number: &str) -> Option<String> {
+    let digits = number.strip_prefix('+')?;
+    let digits: Vec<char> = digits
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    if digits.is_empty() || !digits.iter().all(char::is_ascii_digit) {
+        return None;
+    }
+    let mut domain = String::with_capacity(digits.len() * 2 + 9);
+    for digit in digits.iter().rev() {
+        domain.push(*digit);
+        domain.push('.');
+    }
+    domain.push_str("e164.arpa");
+    Some(domain)
+}
+
+// Apply the terminal rules among the NAPTR records for `number`, giving the candidate URIs in the
+// order in which they should be tried.
+//
+// Non-terminal rules - which would continue the lookup at another domain - and rules whose regular
+// expressions can't be applied are skipped.
+pub(crate) fn enum_uris(number: &str, naptr: &c_ares::NAPTRResults) -> Vec<EnumUri> {
+    // The rules apply to the number with only its digits and the leading `+`.
+    let subject: String = number
+        .chars()
+        .filter(|c| *c == '+' || c.is_ascii_digit())
+        .collect();
+    let mut uris: Vec<EnumUri> = naptr
+        .iter()
+        .filter(|record| record.flags().eq_ignore_ascii_case("u"))
+        .filter_map(|record| {
+            let uri = rewrite(record.reg_exp(), &subject)?;
+            Some(EnumUri {
+                order: record.order(),
+                preference: record.preference(),
+                services: record.service_name().to_owned(),
+                uri,
+            })
+        })
+        .collect();
+    uris.sort_by_key(|uri| (uri.order, uri.preference));
+    uris
+}
+
+// Apply a NAPTR substitution expression - `<delim>ere<delim>replacement<delim>flags` - to
+// `subject`.
+fn rewrite(expression: &str, subject: &str) -> Option<String> {
+    let delimiter = expression.chars().next()?;
+    let mut parts = expression[delimiter.len_utf8()..].split(delimiter);
+    let (pattern, replacement, flags) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || !(flags.is_empty() || flags == "i") {
+        return None;
+    }
+    let pattern = Pattern::parse(pattern, flags == "i")?;
+    let captures = pattern.captures(subject)?;
+
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next()? {
+            digit @ '0'..='9' => {
+                let index = digit.to_digit(10)? as usize;
+                result.push_str(captures.get(index)?.unwrap_or(""));
+            }
+            other => result.push(other),
+        }
+    }
+    Some(result)
+}
+
+// A small subset of POSIX extended regular expressions - enough for the expressions found in
+// ENUM records: literals, `.`, bracket expressions, `*`, `+` and `?`, anchors, and groups.
+#[derive(Debug)]
+struct Pattern {
+    nodes: Vec<Node>,
+    anchored_start: bool,
+    anchored_end: bool,
+    groups: usize,
+    ignore_case: bool,
+}
+
+#[derive(Debug)]
+enum Atom {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    Group(usize, Vec<Node>),
+}
+
+#[derive(Debug)]
+struct Node {
+    atom: Atom,
+    min: usize,
+    max: Option<usize>,
+}
+
+// The start and end of the whole match, and of each group.
+type Captures = Vec<Option<(usize, usize)>>;
+
+// A continuation, to be applied to the end of a match - returning whether the overall match
+// succeeded.
+type Then<'a> = &'a mut dyn FnMut(usize, &mut Captures) -> bool;
+
+impl Pattern {
+    fn parse(pattern: &str, ignore_case: bool) -> Option<Self> {
+        let mut chars: Vec<char> = pattern.chars().collect();
+        let anchored_start = chars.first() == Some(&'^');
+        if anchored_start {
+            chars.remove(0);
+        }
+        let anchored_end = chars.last() == Some(&'$') && !ends_escaped(&chars);
+        if anchored_end {
+            chars.pop();
+        }
+        let mut groups = 0;
+        let mut position = 0;
+        let nodes = parse_sequence(&chars, &mut position, &mut groups)?;
+        if position != chars.len() {
+            return None;
+        }
+        Some(Self {
+            nodes,
+            anchored_start,
+            anchored_end,
+            groups,
+            ignore_case,
+        })
+    }
+
+    // Match against `subject`, returning the whole match and the groups.
+    fn captures<'a>(&self, subject: &'a str) -> Option<Vec<Option<&'a str>>> {
+        let mut starts: Vec<usize> = subject.char_indices().map(|(index, _)| index).collect();
+        starts.push(subject.len());
+        if self.anchored_start {
+            starts.truncate(1);
+        }
+        for start in starts {
+            let mut captures = vec![None; self.groups + 1];
+            let mut accept = |end: usize, captures: &mut Captures| {
+                if self.anchored_end && end != subject.len() {
+                    return false;
+                }
+                captures[0] = Some((start, end));
+                true
+            };
+            if self.match_sequence(&self.nodes, subject, start, &mut captures, &mut accept) {
+                let captures = captures
+                    .into_iter()
+                    .map(|capture| capture.map(|(start, end)| &subject[start..end]))
+                    .collect();
+                return Some(captures);
+            }
+        }
+        None
+    }
+
+    fn match_sequence(
+        &self,
+        nodes: &[Node],
+        subject: &str,
+        position: usize,
+        captures: &mut Captures,
+        then: Then,
+    ) -> bool {
+        let Some((node, rest)) = nodes.split_first() else {
+            return then(position, captures);
+        };
+        self.match_repeat(
+            node,
+            subject,
+            position,
+            0,
+            captures,
+            &mut |end, captures| self.match_sequence(rest, subject, end, captures, then),
+        )
+    }
+
+    // Match `node`, having already matched it `count` times.  Repetition is greedy.
+    fn match_repeat(
+        &self,
+        node: &Node,
+        subject: &str,
+        position: usize,
+        count: usize,
+        captures: &mut Captures,
+        then: Then,
+    ) -> bool {
+        if node.max.is_none_or(|max| count < max) {
+            let mut again = |end: usize, captures: &mut Captures| {
+                // An empty match can repeat forever without getting anywhere.
+                end != position && self.match_repeat(node, subject, end, count + 1, captures, then)
+            };
+            if self.match_atom(&node.atom, subject, position, captures, &mut again) {
+                return true;
+            }
+        }
+        count >= node.min && then(position, captures)
+    }
+
+    fn match_atom(
+        &self,
+        atom: &Atom,
+        subject: &str,
+        position: usize,
+        captures: &mut Captures,
+        then: Then,
+    ) -> bool {
+        let c = match atom {
+            Atom::Group(index, nodes) => {
+                let index = *index;
+                return self.match_sequence(
+                    nodes,
+                    subject,
+                    position,
+                    captures,
+                    &mut |end, captures| {
+                        let previous = captures[index].replace((position, end));
+                        if then(end, captures) {
+                            return true;
+                        }
+                        captures[index] = previous;
+                        false
+                    },
+                );
+            }
+            _ => match subject[position..].chars().next() {
+                Some(c) => c,
+                None => return false,
+            },
+        };
+        let matched = match atom {
+            Atom::Char(expected) => {
+                *expected == c || (self.ignore_case && expected.eq_ignore_ascii_case(&c))
+            }
+            Atom::Any => true,
+            Atom::Class(ranges, negated) => {
+                let contains =
+                    |c: char| ranges.iter().any(|&(low, high)| (low..=high).contains(&c));
+                let in_class = contains(c)
+                    || (self.ignore_case
+                        && (contains(c.to_ascii_lowercase()) || contains(c.to_ascii_uppercase())));
+                in_class != *negated
+            }
+            Atom::Group(..) => unreachable!(),
+        };
+        matched && then(position + c.len_utf8(), captures)
+    }
+}
+
+fn ends_escaped(chars: &[char]) -> bool {
+    let backslashes = chars[..chars.len() - 1]
+        .iter()
+        .rev()
+        .take_while(|&&c| c == '\\')
+        .count();
+    backslashes % 2 == 1
+}
+
+fn parse_sequence(chars: &[char], position: &mut usize, groups: &mut usize) -> Option<Vec<Node>> {
+    let mut nodes = Vec::new();
+    while let Some(&c) = chars.get(*position) {
+        let atom = match c {
+            ')' => break,
+            '(' => {
+                *position += 1;
+                *groups += 1;
+                let index = *groups;
+                let inner = parse_sequence(chars, position, groups)?;
+                if chars.get(*position) != Some(&')') {
+                    return None;
+                }
+                Atom::Group(index, inner)
+            }
+            '.' => Atom::Any,
+            '[' => {
+                *position += 1;
+                let negated = chars.get(*position) == Some(&'^');
+                if negated {
+                    *position += 1;
+                }
+                let mut ranges = Vec::new();
+                let mut first = true;
+                loop {
+                    let low = *chars.get(*position)?;
+                    if low == ']' && !first {
+                        break;
+                    }
+                    first = false;
+                    if chars.get(*position + 1) == Some(&'-')
+                        && chars.get(*position + 2).is_some_and(|&high| high != ']')
+                    {
+                        ranges.push((low, chars[*position + 2]));
+                        *position += 3;
+                    } else {
+                        ranges.push((low, low));
+                        *position += 1;
+                    }
+                }
+                Atom::Class(ranges, negated)
+            }
+            '\\' => {
+                *position += 1;
+                match *chars.get(*position)? {
+                    'd' => Atom::Class(vec![('0', '9')], false),
+                    escaped => Atom::Char(escaped),
+                }
+            }
+            '*' | '+' | '?' | '|' | '{' | '^' | '$' => return None,
+            literal => Atom::Char(literal),
+        };
+        *position += 1;
+        let (min, max) = match chars.get(*position) {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            _ => (1, Some(1)),
+        };
+        if !(min == 1 && max == Some(1)) {
+            *position += 1;
+        }
+        nodes.push(Node { atom, min, max });
+    }
+    Some(nodes)
+}
@@ -0,0 +1,308 @@
+//! Types for the email authentication records - SPF, DMARC and DKIM - that are published in DNS
+//! as TXT records.
+//!
+//! These are returned by the `lookup_spf()`, `lookup_dmarc()` and `lookup_dkim()` methods on each
+//! of the resolvers.
+
+/// An SPF record (RFC 7208), as published at a domain.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpfRecord {
+    /// The mechanisms, in the order that they are to be evaluated.
+    pub mechanisms: Vec<SpfMechanism>,
+
+    /// The domain named by the `redirect` modifier, if any.
+    pub redirect: Option<String>,
+
+    /// The domain named by the `exp` modifier, if any.
+    pub explanation: Option<String>,
+
+    /// The full text of the record.
+    pub text: String,
+}
+
+/// A single mechanism in an SPF record - eg `-all` or `include:_spf.example.com`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpfMechanism {
+    /// The result if the mechanism matches.
+    pub qualifier: SpfQualifier,
+
+    /// The name of the mechanism - eg `all`, `include`, `ip4`.
+    pub name: String,
+
+    /// The value of the mechanism - eg the domain for `include`, the network for `ip4` - if any.
+    pub value: Option<String>,
+}
+
+/// The qualifier of an SPF mechanism, determining the result if it matches.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpfQualifier {
+    /// `+`, the default.
+    Pass,
+
+    /// `-`.
+    Fail,
+
+    /// `~`.
+    SoftFail,
+
+    /// `?`.
+    Neutral,
+}
+
+/// A DMARC record (RFC 7489), as published at `_dmarc.<domain>`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DmarcRecord {
+    /// The policy for the domain.
+    pub policy: DmarcPolicy,
+
+    /// The policy for subdomains of the domain, if different.
+    pub subdomain_policy: Option<DmarcPolicy>,
+
+    /// The percentage of messages to which the policy applies.
+    pub percentage: u8,
+
+    /// The DKIM alignment mode.
+    pub dkim_alignment: DmarcAlignment,
+
+    /// The SPF alignment mode.
+    pub spf_alignment: DmarcAlignment,
+
+    /// The URIs to which aggregate reports should be sent.
+    pub aggregate_reports: Vec<String>,
+
+    /// The URIs to which failure reports should be sent.
+    pub failure_reports: Vec<String>,
+
+    /// All of the tags in the record, as (tag, value) pairs.
+    pub tags: Vec<(String, String)>,
+}
+
+/// A DMARC policy.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DmarcPolicy {
+    /// Take no action.
+    None,
+
+    /// Treat failing messages as suspicious.
+    Quarantine,
+
+    /// Reject failing messages.
+    Reject,
+}
+
+/// A DMARC identifier alignment mode.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DmarcAlignment {
+    /// Domains need only share an organizational domain.
+    Relaxed,
+
+    /// Domains must match exactly.
+    Strict,
+}
+
+/// A DKIM key record (RFC 6376), as published at `<selector>._domainkey.<domain>`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DkimRecord {
+    /// The key type - eg `rsa` or `ed25519`.
+    pub key_type: String,
+
+    /// The public key, base64-encoded.  An empty key means that the key has been revoked.
+    pub public_key: String,
+
+    /// The hash algorithms that may be used with the key - empty if any may be used.
+    pub hash_algorithms: Vec<String>,
+
+    /// The flags - eg `y` if the domain is testing DKIM.
+    pub flags: Vec<String>,
+
+    /// All of the tags in the record, as (tag, value) pairs.
+    pub tags: Vec<(String, String)>,
+}
+
+impl SpfRecord {
+    // Find the SPF record among the TXT records for a domain.  It's an error for there to be more
+    // than one.
+    pub(crate) fn find(results: &c_ares::TXTResults) -> c_ares::Result<Self> {
+        let mut records = txt_records(results)
+            .into_iter()
+            .filter_map(|text| Self::parse(&text));
+        let record = records.next().ok_or(c_ares::Error::ENODATA)?;
+        if records.next().is_some() {
+            return Err(c_ares::Error::EBADRESP);
+        }
+        Ok(record)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut terms = text.split_ascii_whitespace();
+        if !terms.next()?.eq_ignore_ascii_case("v=spf1") {
+            return None;
+        }
+        let mut record = Self {
+            mechanisms: Vec::new(),
+            redirect: None,
+            explanation: None,
+            text: text.to_owned(),
+        };
+        for term in terms {
+            if let Some((name, value)) = term.split_once('=') {
+                if name.eq_ignore_ascii_case("redirect") {
+                    record.redirect = Some(value.to_owned());
+                } else if name.eq_ignore_ascii_case("exp") {
+                    record.explanation = Some(value.to_owned());
+                }
+                continue;
+            }
+            let (qualifier, mechanism) = match term.as_bytes()[0] {
+                b'+' => (SpfQualifier::Pass, &term[1..]),
+                b'-' => (SpfQualifier::Fail, &term[1..]),
+                b'~' => (SpfQualifier::SoftFail, &term[1..]),
+                b'?' => (SpfQualifier::Neutral, &term[1..]),
+                _ => (SpfQualifier::Pass, term),
+            };
+            let (name, value) = match mechanism.find([':', '/']) {
+                Some(index) if mechanism.as_bytes()[index] == b':' => {
+                    (&mechanism[..index], Some(&mechanism[index + 1..]))
+                }
+                Some(index) => (&mechanism[..index], Some(&mechanism[index..])),
+                None => (mechanism, None),
+            };
+            record.mechanisms.push(SpfMechanism {
+                qualifier,
+                name: name.to_ascii_lowercase(),
+                value: value.map(str::to_owned),
+            });
+        }
+        Some(record)
+    }
+}
+
+impl DmarcRecord {
+    // Find the DMARC record among the TXT records at `_dmarc.<domain>`.
+    pub(crate) fn find(results: &c_ares::TXTResults) -> c_ares::Result<Self> {
+        txt_records(results)
+            .iter()
+            .find_map(|text| Self::parse(text))
+            .ok_or(c_ares::Error::ENODATA)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let tags = parse_tags(text);
+        let (version, policy) = (tags.first()?, tags.get(1)?);
+        if version.0 != "v" || version.1 != "DMARC1" || policy.0 != "p" {
+            return None;
+        }
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let uris = |name| {
+            tag(name)
+                .map(|value| value.split(',').map(|uri| uri.trim().to_owned()).collect())
+                .unwrap_or_default()
+        };
+        Some(Self {
+            policy: DmarcPolicy::parse(&policy.1)?,
+            subdomain_policy: tag("sp").and_then(DmarcPolicy::parse),
+            percentage: tag("pct").and_then(|pct| pct.parse().ok()).unwrap_or(100),
+            dkim_alignment: DmarcAlignment::parse(tag("adkim")),
+            spf_alignment: DmarcAlignment::parse(tag("aspf")),
+            aggregate_reports: uris("rua"),
+            failure_reports: uris("ruf"),
+            tags,
+        })
+    }
+}
+
+impl DmarcPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "quarantine" => Some(Self::Quarantine),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+impl DmarcAlignment {
+    fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some(value) if value.eq_ignore_ascii_case("s") => Self::Strict,
+            _ => Self::Relaxed,
+        }
+    }
+}
+
+impl DkimRecord {
+    // Find the DKIM key record among the TXT records at `<selector>._domainkey.<domain>`.
+    pub(crate) fn find(results: &c_ares::TXTResults) -> c_ares::Result<Self> {
+        txt_records(results)
+            .iter()
+            .find_map(|text| Self::parse(text))
+            .ok_or(c_ares::Error::ENODATA)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let tags = parse_tags(text);
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, value)| value.as_str())
+        };
+        if tag("v").is_some_and(|version| version != "DKIM1") {
+            return None;
+        }
+        let list = |name| {
+            tag(name)
+                .map(|value| {
+                    value
+                        .split(':')
+                        .map(|item| item.trim().to_owned())
+                        .filter(|item| !item.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let public_key = tag("p")?
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect();
+        Some(Self {
+            key_type: tag("k").unwrap_or("rsa").to_owned(),
+            public_key,
+            hash_algorithms: list("h"),
+            flags: list("t"),
+            tags,
+        })
+    }
+}
+
+// Join the strings of each TXT record.
+fn txt_records(results: &c_ares::TXTResults) -> Vec<String> {
+    let mut records: Vec<String> = Vec::new();
+    for result in results {
+        let text = String::from_utf8_lossy(result.text());
+        match records.last_mut() {
+            Some(record) if !result.record_start() => record.push_str(&text),
+            _ => records.push(text.into_owned()),
+        }
+    }
+    records
+}
+
+// Parse a list of `tag=value` pairs, separated by semicolons.
+fn parse_tags(text: &str) -> Vec<(String, String)> {
+    text.split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(tag, value)| (tag.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
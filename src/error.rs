@@ -2,14 +2,126 @@ use std::error;
 use std::fmt;
 use std::io;
 
+use crate::name::InvalidName;
+
 /// Error codes that the library might return.
+///
+/// More variants may be added in future, so matches on an `Error` need a wildcard arm.  The
+/// classification helpers - eg `is_retryable()` - are the stable way to decide what to do with an
+/// error.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// An `io::Error`.
     Io(io::Error),
 
     /// A `c_ares::Error`.
     Ares(c_ares::Error),
+
+    /// A name that can't be queried.
+    InvalidName(InvalidName),
+
+    /// The resolver has as many queries outstanding and queued as it is configured to allow - see
+    /// `Options::set_max_queued_queries()`.
+    Overloaded,
+
+    /// The memory used by queued queries and by buffered `resolve_many()` results has reached the
+    /// resolver's budget - see `Options::set_memory_budget()`.
+    MemoryBudgetExceeded,
+
+    /// The query is for a name outside the domains to which the resolver is restricted - see
+    /// `Options::set_allowed_domains()`.
+    DomainNotAllowed,
+
+    /// The query is for a name under `.local`, which the resolver is set to reject - see
+    /// `LocalNames::Reject`.
+    LocalName,
+
+    /// A lookup timed out.  Converting `c_ares::Error::ETIMEOUT` into an `Error` gives this.
+    Timeout,
+
+    /// The resolver's event loop couldn't be started - eg because its thread couldn't be spawned.
+    EventLoopFailed(io::Error),
+
+    /// The resolver already keeps as many bound resolvers as it is configured to allow - see
+    /// `Options::set_max_bindings()`.
+    TooManyBindings,
+}
+
+impl Error {
+    /// Whether the operation might succeed if made again at once - perhaps on another server.
+    ///
+    /// This is so for timeouts, for `ESERVFAIL` and `ECONNREFUSED` from `c-ares`, and for I/O
+    /// errors that suggest a lost or refused connection.
+    pub fn is_retryable(&self) -> bool {
+        match *self {
+            Self::Timeout => true,
+            Self::Ares(err) => matches!(
+                err,
+                c_ares::Error::ETIMEOUT | c_ares::Error::ESERVFAIL | c_ares::Error::ECONNREFUSED
+            ),
+            Self::Io(ref err) => matches!(
+                err.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ),
+            Self::InvalidName(_)
+            | Self::Overloaded
+            | Self::MemoryBudgetExceeded
+            | Self::DomainNotAllowed
+            | Self::LocalName
+            | Self::EventLoopFailed(_)
+            | Self::TooManyBindings => false,
+        }
+    }
+
+    /// Whether the name that was looked up doesn't exist - an NXDOMAIN answer, which `c-ares`
+    /// reports as `ENOTFOUND`.
+    ///
+    /// Contrast `ENODATA`, for a name that exists but has no records of the type asked for.
+    pub fn is_nxdomain(&self) -> bool {
+        matches!(*self, Self::Ares(c_ares::Error::ENOTFOUND))
+    }
+
+    /// Whether the failure reflects a passing condition, so that the operation might succeed later,
+    /// after backing off.
+    ///
+    /// This includes every retryable error, and also the resolver shedding load: see
+    /// `Error::Overloaded`, `Error::MemoryBudgetExceeded`, and `c_ares::Error::ENOMEM` with which
+    /// queries then fail.
+    pub fn is_temporary(&self) -> bool {
+        if self.is_retryable() {
+            return true;
+        }
+        match *self {
+            Self::Overloaded | Self::MemoryBudgetExceeded => true,
+            Self::Ares(err) => err == c_ares::Error::ENOMEM,
+            Self::Io(ref err) => err.kind() == io::ErrorKind::WouldBlock,
+            _ => false,
+        }
+    }
+}
+
+impl Error {
+    // The nearest `c_ares::Error` - with which query handlers, which can't be given an `Error`, are
+    // called when a query fails with this one.
+    pub(crate) fn to_ares(&self) -> c_ares::Error {
+        match *self {
+            Self::Ares(err) => err,
+            Self::InvalidName(_) => c_ares::Error::EBADNAME,
+            Self::Overloaded | Self::MemoryBudgetExceeded | Self::TooManyBindings => {
+                c_ares::Error::ENOMEM
+            }
+            Self::DomainNotAllowed => c_ares::Error::ENOSERVER,
+            Self::LocalName => c_ares::Error::ENOTFOUND,
+            Self::Timeout => c_ares::Error::ETIMEOUT,
+            Self::Io(_) => c_ares::Error::EFILE,
+            Self::EventLoopFailed(_) => c_ares::Error::ENOTINITIALIZED,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -17,6 +129,14 @@ impl fmt::Display for Error {
         match *self {
             Self::Io(ref err) => err.fmt(f),
             Self::Ares(ref err) => err.fmt(f),
+            Self::InvalidName(ref err) => err.fmt(f),
+            Self::Overloaded => write!(f, "too many queries outstanding"),
+            Self::MemoryBudgetExceeded => write!(f, "memory budget exceeded"),
+            Self::DomainNotAllowed => write!(f, "domain not allowed"),
+            Self::LocalName => write!(f, "names under .local are not looked up"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::EventLoopFailed(ref err) => write!(f, "event loop failed: {err}"),
+            Self::TooManyBindings => write!(f, "too many bound resolvers"),
         }
     }
 }
@@ -26,6 +146,14 @@ impl error::Error for Error {
         match *self {
             Self::Io(ref err) => Some(err),
             Self::Ares(ref err) => Some(err),
+            Self::InvalidName(ref err) => Some(err),
+            Self::EventLoopFailed(ref err) => Some(err),
+            Self::Overloaded
+            | Self::MemoryBudgetExceeded
+            | Self::DomainNotAllowed
+            | Self::LocalName
+            | Self::Timeout
+            | Self::TooManyBindings => None,
         }
     }
 }
@@ -38,6 +166,45 @@ impl From<io::Error> for Error {
 
 impl From<c_ares::Error> for Error {
     fn from(err: c_ares::Error) -> Self {
-        Self::Ares(err)
+        match err {
+            c_ares::Error::ETIMEOUT => Self::Timeout,
+            _ => Self::Ares(err),
+        }
+    }
+}
+
+/// Converts to an `io::Error` of the closest kind - eg `NotFound` for a name that doesn't exist, or
+/// `TimedOut` - with the `Error` as its source.  An `Error::Io` gives back the error it wraps.
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match err {
+            Error::Io(err) => return err,
+            Error::EventLoopFailed(ref err) => err.kind(),
+            Error::Ares(ref err) => match *err {
+                c_ares::Error::ENOTFOUND | c_ares::Error::ENODATA => io::ErrorKind::NotFound,
+                c_ares::Error::ETIMEOUT => io::ErrorKind::TimedOut,
+                c_ares::Error::ECONNREFUSED => io::ErrorKind::ConnectionRefused,
+                c_ares::Error::ENOMEM => io::ErrorKind::OutOfMemory,
+                c_ares::Error::EBADNAME
+                | c_ares::Error::EBADSTR
+                | c_ares::Error::EBADFAMILY
+                | c_ares::Error::EBADFLAGS
+                | c_ares::Error::EBADHINTS => io::ErrorKind::InvalidInput,
+                _ => io::ErrorKind::Other,
+            },
+            Error::InvalidName(_) => io::ErrorKind::InvalidInput,
+            Error::Timeout => io::ErrorKind::TimedOut,
+            Error::MemoryBudgetExceeded => io::ErrorKind::OutOfMemory,
+            Error::Overloaded | Error::TooManyBindings => io::ErrorKind::Other,
+            Error::DomainNotAllowed => io::ErrorKind::PermissionDenied,
+            Error::LocalName => io::ErrorKind::NotFound,
+        };
+        Self::new(kind, err)
+    }
+}
+
+impl From<InvalidName> for Error {
+    fn from(err: InvalidName) -> Self {
+        Self::InvalidName(err)
     }
 }
@@ -1,32 +1,237 @@
 use std::collections::HashMap;
-use std::io::ErrorKind;
+use std::io::{self, ErrorKind};
+use std::mem::ManuallyDrop;
+use std::net::{SocketAddr, UdpSocket};
 #[cfg(unix)]
-use std::os::fd::BorrowedFd;
+use std::os::fd::{BorrowedFd, FromRawFd};
 #[cfg(windows)]
-use std::os::windows::io::BorrowedSocket;
+use std::os::windows::io::{BorrowedSocket, FromRawSocket};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::{mpsc, Arc, Mutex, OnceLock, Weak};
+use std::thread::{self, ThreadId};
 use std::time::Duration;
 
 #[cfg(cares1_34)]
 use c_ares::{FdEventFlags, FdEvents, ProcessFlags};
 
 use crate::error::Error;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use crate::uring;
 use polling::Event;
 
 // Indicate an interest in read and/or write events.
 struct Interest(bool, bool);
 
+// A socket that is ready for reading and/or writing.
+pub struct SocketEvent {
+    pub socket: c_ares::Socket,
+    pub readable: bool,
+    pub writable: bool,
+}
+
+// Waits for events on sockets - using io_uring if the `io-uring` feature is enabled and the kernel
+// supports it, and otherwise `polling`.  `polling` uses epoll, kqueue, or on Windows AFD - none of
+// which limits the number of sockets, as `select()` does.
+//
+// Either way, interest in a socket is oneshot: it must be renewed after each event.
+pub enum Poller {
+    Polling(polling::Poller, Mutex<polling::Events>),
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    Uring(Box<uring::Poller>),
+}
+
+impl Poller {
+    fn new() -> io::Result<Self> {
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if let Ok(poller) = uring::Poller::new() {
+            return Ok(Self::Uring(Box::new(poller)));
+        }
+        let poller = polling::Poller::new()?;
+        Ok(Self::Polling(poller, Mutex::new(polling::Events::new())))
+    }
+
+    // Start waiting for events on `socket`.
+    //
+    // Safety: the socket must remain open until it is deleted.
+    unsafe fn add(&self, socket: c_ares::Socket, interest: &Interest) -> io::Result<()> {
+        match self {
+            Self::Polling(poller, _) => {
+                let key = usize::try_from(socket).unwrap();
+                let event = Event::new(key, interest.0, interest.1);
+                unsafe { poller.add(socket, event) }
+            }
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Self::Uring(poller) => poller.add(socket, interest.0, interest.1),
+        }
+    }
+
+    // Change, or renew, the events that we are waiting for on `socket`.
+    //
+    // Safety: the socket must still be open.
+    unsafe fn modify(&self, socket: c_ares::Socket, interest: &Interest) -> io::Result<()> {
+        match self {
+            Self::Polling(poller, _) => {
+                let key = usize::try_from(socket).unwrap();
+                let event = Event::new(key, interest.0, interest.1);
+                let source = unsafe { borrow_socket(socket) };
+                poller.modify(source, event)
+            }
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Self::Uring(poller) => poller.modify(socket, interest.0, interest.1),
+        }
+    }
+
+    // Stop waiting for events on `socket`.
+    //
+    // Safety: the socket must still be open.
+    unsafe fn delete(&self, socket: c_ares::Socket) -> io::Result<()> {
+        match self {
+            Self::Polling(poller, _) => {
+                let source = unsafe { borrow_socket(socket) };
+                poller.delete(source)
+            }
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Self::Uring(poller) => poller.delete(socket),
+        }
+    }
+
+    // Wake the thread that is waiting for events.
+    fn notify(&self) -> io::Result<()> {
+        match self {
+            Self::Polling(poller, _) => poller.notify(),
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Self::Uring(poller) => poller.notify(),
+        }
+    }
+
+    // Wait for events - or for a notification, or the timeout - adding them to `events`.
+    fn wait(&self, events: &mut Vec<SocketEvent>, timeout: Duration) -> io::Result<()> {
+        match self {
+            Self::Polling(poller, buffer) => {
+                let mut buffer = buffer.lock().unwrap();
+                buffer.clear();
+                poller.wait(&mut buffer, Some(timeout))?;
+                let ready = buffer.iter().map(|event| SocketEvent {
+                    socket: c_ares::Socket::try_from(event.key).unwrap(),
+                    readable: event.readable,
+                    writable: event.writable,
+                });
+                events.extend(ready);
+                Ok(())
+            }
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Self::Uring(poller) => poller.wait(events, timeout),
+        }
+    }
+}
+
+// A unit of work to be performed against the c-ares channel, on the event loop thread.
+type Task = Box<dyn FnOnce(&mut c_ares::Channel) + Send>;
+
+// Handle by which other threads submit work to the event loop.  Only the event loop thread ever
+// touches the c-ares channel, so submitting work never contends with the event loop for a lock.
+#[derive(Clone)]
+pub struct TaskSubmitter {
+    sender: mpsc::Sender<Task>,
+    poller: Arc<Poller>,
+    thread_id: Arc<OnceLock<ThreadId>>,
+}
+
+impl TaskSubmitter {
+    // Queue a task to be run on the event loop thread, and wake the event loop.
+    pub fn submit<F>(&self, task: F)
+    where
+        F: FnOnce(&mut c_ares::Channel) + Send + 'static,
+    {
+        // If the event loop has gone away, the task is dropped - which will drop any handler it
+        // holds without calling it.  Tasks queued when the event loop stops are still run, see
+        // `event_loop_thread()`.
+        if self.sender.send(Box::new(task)).is_ok() {
+            self.poller.notify().expect("Failed to notify poller");
+        }
+    }
+
+    // Run a function on the event loop thread, and wait for its result.
+    //
+    // Waiting on the event loop thread itself - eg from within a query handler - would deadlock,
+    // so that fails with `ENOTIMP`.  If the event loop has stopped, this fails with
+    // `EDESTRUCTION`.
+    pub fn call<F, R>(&self, f: F) -> c_ares::Result<R>
+    where
+        F: FnOnce(&mut c_ares::Channel) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        if self.thread_id.get() == Some(&thread::current().id()) {
+            return Err(c_ares::Error::ENOTIMP);
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.submit(move |channel| {
+            let _ = tx.send(f(channel));
+        });
+        rx.recv().map_err(|_| c_ares::Error::EDESTRUCTION)
+    }
+}
+
+// The sockets that an event loop's channel has open - by which the relay tells queries from `c-ares`
+// from anything else that reaches it.
+#[derive(Clone)]
+pub struct Sockets {
+    addresses: Weak<Mutex<HashMap<c_ares::Socket, SocketAddr>>>,
+    submitter: TaskSubmitter,
+}
+
+impl Sockets {
+    // Whether the event loop is still running.
+    pub fn is_live(&self) -> bool {
+        self.addresses.strong_count() > 0
+    }
+
+    // Whether one of the sockets is bound to `address`.
+    //
+    // `c-ares` can connect a TCP socket some time before it tells us about it - once it has written
+    // the query - so a connection can reach the relay first: if the address isn't found, look
+    // again once the event loop has caught up.
+    pub fn contains(&self, address: SocketAddr) -> bool {
+        if self.knows(address) {
+            return true;
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.submitter.submit(move |_| {
+            let _ = tx.send(());
+        });
+        rx.recv().is_ok() && self.knows(address)
+    }
+
+    // Whether one of the sockets that the event loop has been told about is bound to `address` -
+    // without waiting for it to catch up.
+    pub fn knows(&self, address: SocketAddr) -> bool {
+        let Some(addresses) = self.addresses.upgrade() else {
+            return false;
+        };
+        let addresses = addresses.lock().unwrap();
+        addresses.values().any(|&local| local == address)
+    }
+}
+
 // Object returned when the EventLoop is run.  When this is dropped, the EventLoop is stopped.
 pub struct EventLoopStopper {
-    poller: Arc<polling::Poller>,
+    poller: Arc<Poller>,
     quit: Arc<AtomicBool>,
+    thread_id: ThreadId,
 }
 
 impl EventLoopStopper {
-    pub fn new(poller: Arc<polling::Poller>, quit: Arc<AtomicBool>) -> Self {
-        Self { poller, quit }
+    pub fn new(poller: Arc<Poller>, quit: Arc<AtomicBool>, thread_id: ThreadId) -> Self {
+        Self {
+            poller,
+            quit,
+            thread_id,
+        }
+    }
+
+    // The ID of the event loop thread.
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
     }
 }
 
@@ -37,12 +242,15 @@ impl Drop for EventLoopStopper {
     }
 }
 
-// The EventLoop sets up a polling::Poller and use it to wait for events on sockets as directed by
-// the c-ares library.
+// The EventLoop sets up a Poller and uses it to wait for events on sockets as directed by the c-ares
+// library.
 pub struct EventLoop {
-    poller: Arc<polling::Poller>,
+    poller: Arc<Poller>,
     interests: Arc<Mutex<HashMap<c_ares::Socket, Interest>>>,
-    pub ares_channel: Arc<Mutex<c_ares::Channel>>,
+    addresses: Arc<Mutex<HashMap<c_ares::Socket, SocketAddr>>>,
+    ares_channel: c_ares::Channel,
+    tasks: mpsc::Receiver<Task>,
+    submitter: TaskSubmitter,
     quit: Arc<AtomicBool>,
 
     #[allow(dead_code)]
@@ -52,12 +260,15 @@ pub struct EventLoop {
 impl EventLoop {
     // Create a new event loop.
     pub fn new(mut options: c_ares::Options) -> Result<Self, Error> {
-        // Create a polling::Poller on which to wait for events, and a hashmap to record which
-        // sockets we are interested in.
-        let poller = Arc::new(polling::Poller::new()?);
+        // Create a Poller on which to wait for events, and a hashmap to record which sockets we
+        // are interested in.
+        let poller = Arc::new(Poller::new().map_err(Error::EventLoopFailed)?);
         let interests: HashMap<c_ares::Socket, Interest> = HashMap::new();
         let interests = Arc::new(Mutex::new(interests));
 
+        // And the addresses to which those sockets are bound, looked up as they are added.
+        let addresses = Arc::new(Mutex::new(HashMap::new()));
+
         // Whenever c-ares tells us that it cares about a socket, we'll update the poller
         // accordingly.
         //
@@ -66,31 +277,35 @@ impl EventLoop {
         {
             let poller = Arc::clone(&poller);
             let interests = Arc::clone(&interests);
+            let addresses = Arc::clone(&addresses);
             let sock_callback = move |socket: c_ares::Socket, readable: bool, writable: bool| {
                 let mut interests = interests.lock().unwrap();
                 if !readable && !writable {
+                    addresses.lock().unwrap().remove(&socket);
                     if interests.remove(&socket).is_some() {
-                        let source = unsafe { borrow_socket(socket) };
-                        poller
-                            .delete(source)
-                            .expect("Failed to remove socket from poller");
+                        unsafe {
+                            poller
+                                .delete(socket)
+                                .expect("Failed to remove socket from poller");
+                        }
                     }
                 } else {
-                    let key = usize::try_from(socket).unwrap();
-                    let event = Event::new(key, readable, writable);
                     let interest = Interest(readable, writable);
-                    if interests.insert(socket, interest).is_none() {
-                        unsafe {
+                    unsafe {
+                        if interests.contains_key(&socket) {
+                            poller
+                                .modify(socket, &interest)
+                                .expect("failed to update interest");
+                        } else {
                             poller
-                                .add(socket, event)
+                                .add(socket, &interest)
                                 .expect("failed to add socket to poller");
+                            if let Ok(local) = local_address(socket) {
+                                addresses.lock().unwrap().insert(socket, local);
+                            }
                         }
-                    } else {
-                        let source = unsafe { borrow_socket(socket) };
-                        poller
-                            .modify(source, event)
-                            .expect("failed to update interest");
                     }
+                    interests.insert(socket, interest);
                 }
             };
             options.set_socket_state_callback(sock_callback);
@@ -115,43 +330,99 @@ impl EventLoop {
             ares_channel.set_pending_write_callback(pending_write_callback);
         }
 
+        // Create the queue on which work is submitted to the event loop.
+        let (sender, tasks) = mpsc::channel();
+        let submitter = TaskSubmitter {
+            sender,
+            poller: Arc::clone(&poller),
+            thread_id: Arc::new(OnceLock::new()),
+        };
+
         // Create and return the event loop.
-        let locked_channel = Arc::new(Mutex::new(ares_channel));
         let event_loop = Self {
             poller,
             interests,
-            ares_channel: locked_channel,
+            addresses,
+            ares_channel,
+            tasks,
+            submitter,
             quit: Arc::new(AtomicBool::new(false)),
             pending_write,
         };
         Ok(event_loop)
     }
 
-    // Run the event loop.
-    pub fn run(self) -> EventLoopStopper {
-        // Create a stopper.
+    // Get direct access to the channel.  Once the event loop is running, the channel may only be
+    // accessed by submitting tasks.
+    pub fn channel_mut(&mut self) -> &mut c_ares::Channel {
+        &mut self.ares_channel
+    }
+
+    // Get a handle for submitting work to the event loop.
+    pub fn submitter(&self) -> TaskSubmitter {
+        self.submitter.clone()
+    }
+
+    // Get a view of the sockets that the channel has open.
+    pub fn sockets(&self) -> Sockets {
+        Sockets {
+            addresses: Arc::downgrade(&self.addresses),
+            submitter: self.submitter.clone(),
+        }
+    }
+
+    // Run the event loop, on a thread spawned by `builder` - which first calls `setup`, and runs
+    // the event loop only if that succeeds.
+    pub fn run<F>(self, builder: thread::Builder, setup: F) -> Result<EventLoopStopper, Error>
+    where
+        F: FnOnce() -> io::Result<()> + Send + 'static,
+    {
         let poller = Arc::clone(&self.poller);
         let quit = Arc::clone(&self.quit);
-        let stopper = EventLoopStopper::new(poller, quit);
+        let (tx, rx) = mpsc::sync_channel(1);
+        let handle = builder
+            .spawn(move || {
+                let _ = self.submitter.thread_id.set(thread::current().id());
+                let result = setup();
+                let ok = result.is_ok();
+                let _ = tx.send(result);
+                if ok {
+                    self.event_loop_thread();
+                }
+            })
+            .map_err(Error::EventLoopFailed)?;
+        rx.recv()
+            .unwrap_or_else(|_| Err(io::Error::other("event loop thread stopped")))
+            .map_err(Error::EventLoopFailed)?;
 
-        thread::spawn(|| self.event_loop_thread());
-        stopper
+        // Create a stopper.
+        let stopper = EventLoopStopper::new(poller, quit, handle.thread().id());
+        Ok(stopper)
     }
 
     // Event loop thread - waits for events, and handles them.
     fn event_loop_thread(mut self) {
-        let mut events = polling::Events::new();
+        let mut events = Vec::new();
+        let mut tasks = Vec::new();
         let timeout = Duration::from_millis(500);
         loop {
             // Wait for something to happen.
             events.clear();
-            let results = self.poller.wait(&mut events, Some(timeout));
+            let results = self.poller.wait(&mut events, timeout);
 
             // If we're asked to quit, then quit.
             if self.quit.load(Ordering::Relaxed) {
                 break;
             }
 
+            // Run any tasks that have been submitted.  Tasks submitted while these run wait until
+            // next time round - by when `c-ares` has finished writing queries - so that a task sees
+            // the effects of all those submitted before it.
+            tasks.extend(self.tasks.try_iter());
+            for task in tasks.drain(..) {
+                task(&mut self.ares_channel);
+            }
+
             // Interrupted is OK, we just retry.  Other errors are unexpected.
             if let Err(ref err) = results {
                 if err.kind() == ErrorKind::Interrupted {
@@ -163,39 +434,46 @@ impl EventLoop {
             // Process any pending write.
             #[cfg(cares1_34)]
             if self.pending_write.swap(false, Ordering::Relaxed) {
-                self.ares_channel.lock().unwrap().process_pending_write();
+                self.ares_channel.process_pending_write();
             }
 
             // Process any events.
             self.handle_events(&events);
 
-            // `polling` always operates in oneshot mode, but c-ares expects us to maintain an
+            // The poller always operates in oneshot mode, but c-ares expects us to maintain an
             // interest in sockets until told otherwise.
             //
             // So re-assert our interest in all reported sockets.
             {
                 let interests = self.interests.lock().unwrap();
-                for event in events.iter() {
-                    let socket = c_ares::Socket::try_from(event.key).unwrap();
-                    if let Some(Interest(readable, writable)) = interests.get(&socket) {
+                for event in &events {
+                    if let Some(interest) = interests.get(&event.socket) {
                         // Safety: we trust that since c-ares hasn't yet told us that it is done
                         // with this socket, it's still open.
-                        let source = unsafe { borrow_socket(socket) };
-                        let new_event = Event::new(event.key, *readable, *writable);
-                        self.poller
-                            .modify(source, new_event)
-                            .expect("failed to renew interest");
+                        unsafe {
+                            self.poller
+                                .modify(event.socket, interest)
+                                .expect("failed to renew interest");
+                        }
                     }
                 }
             }
         }
+
+        // Run any tasks that are still queued, rather than dropping their handlers unanswered.
+        // Queries that they make fail with `EDESTRUCTION` - as do those outstanding - when the
+        // channel is destroyed.
+        tasks.extend(self.tasks.try_iter());
+        for task in tasks.drain(..) {
+            task(&mut self.ares_channel);
+        }
     }
 
     #[cfg(cares1_34)]
-    fn handle_events(&mut self, events: &polling::Events) {
-        let mut fd_events: Vec<FdEvents> = Vec::with_capacity(events.capacity().into());
+    fn handle_events(&mut self, events: &[SocketEvent]) {
+        let mut fd_events: Vec<FdEvents> = Vec::with_capacity(events.len());
         let fd_events_iter = events.iter().map(|event| {
-            let socket = c_ares::Socket::try_from(event.key).unwrap();
+            let socket = event.socket;
             let mut event_flags = FdEventFlags::empty();
             if event.readable {
                 event_flags.insert(FdEventFlags::Read)
@@ -209,16 +487,14 @@ impl EventLoop {
 
         let _ = self
             .ares_channel
-            .lock()
-            .unwrap()
             .process_fds(&fd_events, ProcessFlags::empty());
     }
 
     #[cfg(not(cares1_34))]
-    fn handle_events(&mut self, events: &polling::Events) {
+    fn handle_events(&mut self, events: &[SocketEvent]) {
         let mut acted = false;
-        for event in events.iter() {
-            let socket = c_ares::Socket::try_from(event.key).unwrap();
+        for event in events {
+            let socket = event.socket;
 
             let rfd = if event.readable {
                 socket
@@ -232,15 +508,13 @@ impl EventLoop {
                 c_ares::SOCKET_BAD
             };
 
-            self.ares_channel.lock().unwrap().process_fd(rfd, wfd);
+            self.ares_channel.process_fd(rfd, wfd);
             acted = true;
         }
 
         if !acted {
             // No events.  Have c-ares process any timeouts.
             self.ares_channel
-                .lock()
-                .unwrap()
                 .process_fd(c_ares::SOCKET_BAD, c_ares::SOCKET_BAD);
         }
     }
@@ -255,3 +529,18 @@ unsafe fn borrow_socket(socket: c_ares::Socket) -> impl polling::AsSource {
 unsafe fn borrow_socket(socket: c_ares::Socket) -> impl polling::AsSource {
     unsafe { BorrowedSocket::borrow_raw(socket) }
 }
+
+// The address to which a socket is bound.
+//
+// Safety: the socket must be open.
+#[cfg(unix)]
+unsafe fn local_address(socket: c_ares::Socket) -> io::Result<SocketAddr> {
+    let socket = ManuallyDrop::new(unsafe { UdpSocket::from_raw_fd(socket) });
+    socket.local_addr()
+}
+
+#[cfg(windows)]
+unsafe fn local_address(socket: c_ares::Socket) -> io::Result<SocketAddr> {
+    let socket = ManuallyDrop::new(unsafe { UdpSocket::from_raw_socket(socket) });
+    socket.local_addr()
+}
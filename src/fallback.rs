@@ -0,0 +1,212 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::blockingresolver::BlockingResolver;
+use crate::error::Error;
+use crate::futureresolver::{CAresFuture, FutureResolver};
+use crate::host::HostResults;
+use crate::queries::wrapped_queries;
+use crate::record::{DnsClass, RecordType};
+use crate::retry::{sleep, RetryPolicy};
+
+/// A result from a `FallbackResolver`, saying which resolver provided it.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FallbackResult<T> {
+    /// The result of the query.
+    pub answer: T,
+
+    /// The index of the resolver that answered: 0 for the primary resolver, 1 for the first
+    /// fallback, and so on.
+    pub resolver: usize,
+}
+
+/// The type of future returned by methods on a `FallbackResolver<FutureResolver>`.
+pub type FallbackFuture<T> =
+    Pin<Box<dyn Future<Output = c_ares::Result<FallbackResult<T>>> + Send + 'static>>;
+
+/// A resolver that makes queries using a primary resolver, and retries them on one or more
+/// fallback resolvers if the primary resolver fails - eg using public DNS servers when the
+/// system's servers are unavailable.
+///
+/// Queries fall back only if they fail in a way that suggests a problem with the servers: with
+/// `ESERVFAIL`, `ETIMEOUT` or `ECONNREFUSED`.  Other results - including `ENOTFOUND` - are
+/// answers, and are returned as they are.
+///
+/// By default, each fallback is tried immediately after the previous resolver fails.  Use
+/// `set_retry_policy()` to wait between attempts, or to limit how many fallbacks are tried.
+///
+/// `FallbackResolver<FutureResolver>` and `FallbackResolver<BlockingResolver>` offer the same
+/// name-based queries as the resolvers that they wrap.  Each result says which resolver provided
+/// it.
+pub struct FallbackResolver<R> {
+    resolvers: Vec<Arc<R>>,
+    retry_policy: RetryPolicy,
+}
+
+// Whether a query that failed with this error should be tried on the next resolver.
+fn should_fall_back(error: c_ares::Error) -> bool {
+    Error::from(error).is_retryable()
+}
+
+// Attempts on resolvers after the first are retries.
+fn retry_number(index: usize) -> u32 {
+    u32::try_from(index).unwrap_or(u32::MAX)
+}
+
+impl<R> FallbackResolver<R> {
+    /// Create a new `FallbackResolver`, making queries first with `primary`.
+    pub fn new(primary: R) -> Self {
+        Self {
+            resolvers: vec![Arc::new(primary)],
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Add a resolver to try if all of the resolvers before it have failed.
+    pub fn add_fallback(&mut self, fallback: R) -> &mut Self {
+        self.resolvers.push(Arc::new(fallback));
+        self
+    }
+
+    /// Set the policy that decides whether, and when, to try the next resolver.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// The resolvers, in the order that they are tried.
+    pub fn resolvers(&self) -> impl Iterator<Item = &R> {
+        self.resolvers.iter().map(Arc::as_ref)
+    }
+}
+
+impl FallbackResolver<FutureResolver> {
+    // Make a query on each resolver in turn, until one of them answers.
+    fn fall_back<T, F>(&self, query: F) -> FallbackFuture<T>
+    where
+        T: Send + 'static,
+        F: Fn(&FutureResolver) -> CAresFuture<T> + Send + 'static,
+    {
+        let resolvers = self.resolvers.clone();
+        let retry_policy = self.retry_policy.clone();
+        Box::pin(async move {
+            retry_policy.record_request();
+            let mut last_error = c_ares::Error::ENOSERVER;
+            for (index, resolver) in resolvers.iter().enumerate() {
+                if index > 0 {
+                    match retry_policy.next_retry(retry_number(index)) {
+                        Some(delay) => sleep(delay).await,
+                        None => break,
+                    }
+                }
+                match query(resolver).await {
+                    Err(error) if should_fall_back(error) => last_error = error,
+                    result => {
+                        return result.map(|answer| FallbackResult {
+                            answer,
+                            resolver: index,
+                        })
+                    }
+                }
+            }
+            Err(last_error)
+        })
+    }
+}
+
+impl FallbackResolver<BlockingResolver> {
+    // Make a query on each resolver in turn, until one of them answers.
+    fn fall_back<T, F>(&self, query: F) -> c_ares::Result<FallbackResult<T>>
+    where
+        F: Fn(&BlockingResolver) -> c_ares::Result<T>,
+    {
+        self.retry_policy.record_request();
+        let mut last_error = c_ares::Error::ENOSERVER;
+        for (index, resolver) in self.resolvers.iter().enumerate() {
+            if index > 0 {
+                match self.retry_policy.next_retry(retry_number(index)) {
+                    Some(delay) => std::thread::sleep(delay),
+                    None => break,
+                }
+            }
+            match query(resolver) {
+                Err(error) if should_fall_back(error) => last_error = error,
+                result => {
+                    return result.map(|answer| FallbackResult {
+                        answer,
+                        resolver: index,
+                    })
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+// Generate methods that make a query with fallback.
+macro_rules! with_fallback {
+    ($(#[$attr:meta])* $query:ident -> $result:ty) => {
+        $(#[$attr])*
+        pub fn $query(&self, name: &str) -> $result {
+            let name = name.to_owned();
+            self.fall_back(move |resolver| resolver.$query(&name))
+        }
+    };
+}
+
+// Generate the name-based query methods, for the given result type.
+macro_rules! fallback_queries {
+    ($result:ident) => {
+        wrapped_queries!(with_fallback, $result);
+
+        /// Perform a host query by name.
+        pub fn get_host_by_name(
+            &self,
+            name: &str,
+            family: c_ares::AddressFamily,
+        ) -> $result<HostResults> {
+            let name = name.to_owned();
+            self.fall_back(move |resolver| resolver.get_host_by_name(&name, family))
+        }
+
+        /// Initiate a single-question DNS query for `name`.  The class and type of the query are
+        /// per the provided parameters, which may be given as numbers per `arpa/nameser.h`.
+        pub fn query(
+            &self,
+            name: &str,
+            dns_class: impl Into<DnsClass>,
+            query_type: impl Into<RecordType>,
+        ) -> $result<Vec<u8>> {
+            let (name, dns_class, query_type) =
+                (name.to_owned(), dns_class.into(), query_type.into());
+            self.fall_back(move |resolver| resolver.query(&name, dns_class, query_type))
+        }
+
+        /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
+        /// query are per the provided parameters, which may be given as numbers per
+        /// `arpa/nameser.h`.
+        pub fn search(
+            &self,
+            name: &str,
+            dns_class: impl Into<DnsClass>,
+            query_type: impl Into<RecordType>,
+        ) -> $result<Vec<u8>> {
+            let (name, dns_class, query_type) =
+                (name.to_owned(), dns_class.into(), query_type.into());
+            self.fall_back(move |resolver| resolver.search(&name, dns_class, query_type))
+        }
+    };
+}
+
+// The result of a query on a `FallbackResolver<BlockingResolver>`.
+type BlockingResult<T> = c_ares::Result<FallbackResult<T>>;
+
+impl FallbackResolver<FutureResolver> {
+    fallback_queries!(FallbackFuture);
+}
+
+impl FallbackResolver<BlockingResolver> {
+    fallback_queries!(BlockingResult);
+}
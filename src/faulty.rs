@@ -0,0 +1,240 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::thread;
+use std::time::Duration;
+
+use crate::host::HostResults;
+use crate::resolve::{BlockingResolve, Resolve, ResolveFuture};
+use crate::retry::sleep;
+
+// A fault to inject into a single lookup.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Fault {
+    None,
+    Fail(c_ares::Error),
+    Truncate,
+}
+
+/// A resolver that wraps another, injecting faults into a percentage of its lookups - for testing
+/// how code copes with slow or failing DNS, eg its retries and fallbacks.
+///
+/// `FaultyResolver<R>` implements `Resolve` if `R` does, and `BlockingResolve` if `R` does.  For
+/// each lookup, the resolver decides at random:
+///
+/// - whether to add latency, after the lookup completes
+/// - whether to fail the lookup with `ETIMEOUT` or `ESERVFAIL`, without passing it on
+/// - whether to truncate the response: raw responses are cut short and marked as truncated, and
+///   other lookups fail with `EBADRESP`.
+///
+/// Percentages are out of 100, and are capped there.  The failure and truncation percentages are
+/// of all lookups, so between them should add up to at most 100.
+///
+/// ```
+/// use c_ares_resolver::{BlockingResolve, FaultyResolver, MockResolver};
+///
+/// let mut mock = MockResolver::new();
+/// mock.add_ipv4("example.com", &["192.0.2.1".parse().unwrap()]);
+/// let mut resolver = FaultyResolver::new(mock);
+/// resolver.set_servfails(100);
+/// assert_eq!(
+///     resolver.lookup_ipv4("example.com"),
+///     Err(c_ares::Error::ESERVFAIL)
+/// );
+/// ```
+#[derive(Debug)]
+pub struct FaultyResolver<R> {
+    inner: R,
+    latency: Duration,
+    latency_percent: u8,
+    timeout_percent: u8,
+    servfail_percent: u8,
+    truncate_percent: u8,
+}
+
+fn random_percent() -> u8 {
+    let random = RandomState::new().build_hasher().finish();
+    (random % 100) as u8
+}
+
+// Cut a response short, and mark it as truncated.
+fn truncate(mut response: Vec<u8>) -> c_ares::Result<Vec<u8>> {
+    if response.len() < 12 {
+        return Err(c_ares::Error::EBADRESP);
+    }
+    let length = 12.max(response.len() / 2);
+    response.truncate(length);
+    response[2] |= 0x02;
+    Ok(response)
+}
+
+fn bad_response<T>(_: T) -> c_ares::Result<T> {
+    Err(c_ares::Error::EBADRESP)
+}
+
+impl<R> FaultyResolver<R> {
+    /// Create a new `FaultyResolver`, passing lookups on to `inner`.  No faults are injected until
+    /// they are configured.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            latency: Duration::ZERO,
+            latency_percent: 0,
+            timeout_percent: 0,
+            servfail_percent: 0,
+            truncate_percent: 0,
+        }
+    }
+
+    /// Add `latency` to `percent` of lookups.
+    pub fn set_latency(&mut self, latency: Duration, percent: u8) -> &mut Self {
+        self.latency = latency;
+        self.latency_percent = percent.min(100);
+        self
+    }
+
+    /// Fail `percent` of lookups with `ETIMEOUT`.
+    pub fn set_timeouts(&mut self, percent: u8) -> &mut Self {
+        self.timeout_percent = percent.min(100);
+        self
+    }
+
+    /// Fail `percent` of lookups with `ESERVFAIL`.
+    pub fn set_servfails(&mut self, percent: u8) -> &mut Self {
+        self.servfail_percent = percent.min(100);
+        self
+    }
+
+    /// Truncate the responses to `percent` of lookups.
+    pub fn set_truncations(&mut self, percent: u8) -> &mut Self {
+        self.truncate_percent = percent.min(100);
+        self
+    }
+
+    /// The resolver that lookups are passed on to.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    // Decide what to do to a lookup: how long to delay it, and what fault to inject.
+    fn choose(&self) -> (Duration, Fault) {
+        let delay = if random_percent() < self.latency_percent {
+            self.latency
+        } else {
+            Duration::ZERO
+        };
+        let roll = u32::from(random_percent());
+        let timeouts = u32::from(self.timeout_percent);
+        let servfails = timeouts + u32::from(self.servfail_percent);
+        let truncations = servfails + u32::from(self.truncate_percent);
+        let fault = if roll < timeouts {
+            Fault::Fail(c_ares::Error::ETIMEOUT)
+        } else if roll < servfails {
+            Fault::Fail(c_ares::Error::ESERVFAIL)
+        } else if roll < truncations {
+            Fault::Truncate
+        } else {
+            Fault::None
+        };
+        (delay, fault)
+    }
+}
+
+impl<R: Resolve> FaultyResolver<R> {
+    // Make a lookup with `lookup`, injecting faults.
+    fn lookup<T, L, U>(&self, lookup: L, truncate: U) -> ResolveFuture<T>
+    where
+        T: Send + 'static,
+        L: FnOnce(&R) -> ResolveFuture<T>,
+        U: FnOnce(T) -> c_ares::Result<T> + Send + 'static,
+    {
+        let (delay, fault) = self.choose();
+        let query = match fault {
+            Fault::Fail(error) => Err(error),
+            _ => Ok(lookup(&self.inner)),
+        };
+        Box::pin(async move {
+            let result = match query {
+                Ok(query) => query.await,
+                Err(error) => Err(error),
+            };
+            sleep(delay).await;
+            match fault {
+                Fault::Truncate => result.and_then(truncate),
+                _ => result,
+            }
+        })
+    }
+}
+
+impl<R: Resolve> Resolve for FaultyResolver<R> {
+    fn lookup_host(&self, name: &str, family: c_ares::AddressFamily) -> ResolveFuture<HostResults> {
+        self.lookup(|inner| inner.lookup_host(name, family), bad_response)
+    }
+
+    fn lookup_ipv4(&self, name: &str) -> ResolveFuture<Vec<Ipv4Addr>> {
+        self.lookup(|inner| inner.lookup_ipv4(name), bad_response)
+    }
+
+    fn lookup_ipv6(&self, name: &str) -> ResolveFuture<Vec<Ipv6Addr>> {
+        self.lookup(|inner| inner.lookup_ipv6(name), bad_response)
+    }
+
+    fn lookup_txt(&self, name: &str) -> ResolveFuture<Vec<Vec<u8>>> {
+        self.lookup(|inner| inner.lookup_txt(name), bad_response)
+    }
+
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> ResolveFuture<Vec<u8>> {
+        self.lookup(
+            |inner| inner.lookup_raw(name, dns_class, query_type),
+            truncate,
+        )
+    }
+}
+
+impl<R: BlockingResolve> FaultyResolver<R> {
+    // Make a lookup with `lookup`, injecting faults.
+    fn lookup_blocking<T, L, U>(&self, lookup: L, truncate: U) -> c_ares::Result<T>
+    where
+        L: FnOnce(&R) -> c_ares::Result<T>,
+        U: FnOnce(T) -> c_ares::Result<T>,
+    {
+        let (delay, fault) = self.choose();
+        let result = match fault {
+            Fault::None => lookup(&self.inner),
+            Fault::Fail(error) => Err(error),
+            Fault::Truncate => lookup(&self.inner).and_then(truncate),
+        };
+        thread::sleep(delay);
+        result
+    }
+}
+
+impl<R: BlockingResolve> BlockingResolve for FaultyResolver<R> {
+    fn lookup_host(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> c_ares::Result<HostResults> {
+        self.lookup_blocking(|inner| inner.lookup_host(name, family), bad_response)
+    }
+
+    fn lookup_ipv4(&self, name: &str) -> c_ares::Result<Vec<Ipv4Addr>> {
+        self.lookup_blocking(|inner| inner.lookup_ipv4(name), bad_response)
+    }
+
+    fn lookup_ipv6(&self, name: &str) -> c_ares::Result<Vec<Ipv6Addr>> {
+        self.lookup_blocking(|inner| inner.lookup_ipv6(name), bad_response)
+    }
+
+    fn lookup_txt(&self, name: &str) -> c_ares::Result<Vec<Vec<u8>>> {
+        self.lookup_blocking(|inner| inner.lookup_txt(name), bad_response)
+    }
+
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> c_ares::Result<Vec<u8>> {
+        self.lookup_blocking(
+            |inner| inner.lookup_raw(name, dns_class, query_type),
+            truncate,
+        )
+    }
+}
@@ -1,24 +1,69 @@
+#[cfg(cares1_29)]
+use std::collections::HashMap;
+use std::fmt;
 use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+use futures_core::FusedFuture;
 
+#[cfg(cares1_22)]
+use crate::any::AnyResults;
+#[cfg(cares1_24)]
+use crate::binding::LocalBinding;
+use crate::discovery::{self, ServiceInstance};
+#[cfg(cares1_22)]
+use crate::dnssec::{DNSKEYResults, DSResults, RRSIGResults};
+use crate::e164::EnumUri;
+use crate::email::{DkimRecord, DmarcRecord, SpfRecord};
 use crate::error::Error;
-use crate::host::HostResults;
+use crate::host::{self, HostResults};
+use crate::many::ResolveMany;
+use crate::metrics::{LoadStats, Metrics};
+use crate::mx::{self, MailExchange};
 use crate::nameinfo::NameInfoResult;
+use crate::permit::AcquirePermit;
+#[cfg(cares1_24)]
+use crate::queryflags::QueryFlags;
+use crate::querylog::QueryLogEntry;
+#[cfg(cares1_29)]
+use crate::raw::RawResponse;
+use crate::record::{DnsClass, RecordType};
+use crate::rejection;
+use crate::resolved::ResolvedConfig;
 use crate::resolver::{Options, Resolver};
-
+#[cfg(cares1_29)]
+use crate::response::QueryResponse;
+#[cfg(cares1_34)]
+use crate::server::Server;
 #[cfg(cares1_24)]
-use c_ares::AresString;
+use crate::server::ServerInfo;
+#[cfg(cares1_29)]
+use crate::server::ServerStats;
+use crate::srv::{self, SrvEndpoint};
+#[cfg(cares1_22)]
+use crate::sshfp::SSHFPResults;
+#[cfg(cares1_22)]
+use crate::svcb::HTTPSResults;
+#[cfg(cares1_22)]
+use crate::tlsa::TLSAResults;
+use crate::watch::WatchA;
 
 #[cfg(cares1_29)]
 use c_ares::ServerStateFlags;
 
 /// The type of future returned by methods on the `FutureResolver`.
+///
+/// The future is `Unpin`, whatever the type of its result - so it can be polled without pinning,
+/// eg in `select!`.
 #[must_use]
 pub struct CAresFuture<T> {
     inner: futures_channel::oneshot::Receiver<c_ares::Result<T>>,
+    terminated: bool,
     _resolver: Arc<Resolver>,
 }
 
@@ -29,24 +74,58 @@ impl<T> CAresFuture<T> {
     ) -> Self {
         Self {
             inner: promise,
+            terminated: false,
             _resolver: resolver,
         }
     }
 
-    fn pin_get_inner(
-        self: Pin<&mut Self>,
-    ) -> Pin<&mut futures_channel::oneshot::Receiver<c_ares::Result<T>>> {
-        unsafe { self.map_unchecked_mut(|s| &mut s.inner) }
+    /// Whether the future has given its result - either from being polled, or from `try_take()`.
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Take the result of the query if it has completed, without polling the future - returning
+    /// `None` if it is still in progress, or if the result has already been given.
+    pub fn try_take(&mut self) -> Option<c_ares::Result<T>> {
+        if self.terminated {
+            return None;
+        }
+        let result = match self.inner.try_recv() {
+            Ok(None) => return None,
+            Ok(Some(result)) => result,
+            Err(_) => Err(c_ares::Error::ECANCELLED),
+        };
+        self.terminated = true;
+        Some(result)
+    }
+}
+
+impl<T> fmt::Debug for CAresFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CAresFuture")
+            .field("inner", &self.inner)
+            .field("terminated", &self.terminated)
+            .finish_non_exhaustive()
     }
 }
 
 impl<T> Future for CAresFuture<T> {
     type Output = c_ares::Result<T>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        self.pin_get_inner()
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let poll = Pin::new(&mut self.inner)
             .poll(cx)
-            .map(|result| result.unwrap_or(Err(c_ares::Error::ECANCELLED)))
+            .map(|result| result.unwrap_or(Err(c_ares::Error::ECANCELLED)));
+        if poll.is_ready() {
+            self.terminated = true;
+        }
+        poll
+    }
+}
+
+impl<T> FusedFuture for CAresFuture<T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
     }
 }
 
@@ -54,6 +133,9 @@ impl<T> Future for CAresFuture<T> {
 ///
 /// Note that dropping the `FutureResolver` does *not* cause outstanding queries to fail - contrast
 /// the `Resolver` - because the returned futures hold a reference to the underlying resolver.
+///
+/// Cloning a `FutureResolver` is cheap, and gives another handle to the same underlying resolver.
+#[derive(Clone)]
 pub struct FutureResolver {
     inner: Arc<Resolver>,
 }
@@ -71,6 +153,19 @@ macro_rules! futurize {
     }};
 }
 
+// Likewise for the `*_ext()` methods, which can only fail by being cancelled.
+#[cfg(cares1_29)]
+macro_rules! futurize_ext {
+    ($resolver:expr, $query:ident, $question:expr) => {{
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        $resolver.$query($question, |response| {
+            let _ = sender.send(Ok(response));
+        });
+        let resolver = Arc::clone(&$resolver);
+        CAresFuture::new(receiver, resolver)
+    }};
+}
+
 impl FutureResolver {
     /// Create a new `FutureResolver`, using default `Options`.
     pub fn new() -> Result<Self, Error> {
@@ -81,10 +176,42 @@ impl FutureResolver {
     /// Create a new `FutureResolver`, with the given `Options`.
     pub fn with_options(options: Options) -> Result<Self, Error> {
         let inner = Resolver::with_options(options)?;
-        let resolver = Self {
+        Ok(Self::from_resolver(inner))
+    }
+
+    // Wrap an existing `Resolver`.
+    pub(crate) fn from_resolver(inner: Resolver) -> Self {
+        Self {
             inner: Arc::new(inner),
-        };
-        Ok(resolver)
+        }
+    }
+
+    // The underlying `Resolver`.
+    pub(crate) fn as_resolver(&self) -> &Resolver {
+        &self.inner
+    }
+
+    /// Returns a resolver that makes its queries over TCP, and is otherwise configured in the same
+    /// way as this one - see `Resolver::tcp()`.
+    #[cfg(cares1_24)]
+    pub fn tcp(&self) -> Result<Self, Error> {
+        let inner = Arc::clone(self.inner.tcp_resolver()?);
+        Ok(Self { inner })
+    }
+
+    /// Returns a resolver that makes its queries from `binding`, and is otherwise configured in
+    /// the same way as this one - see `Resolver::bound_to()`.
+    #[cfg(cares1_24)]
+    pub fn bound_to(&self, binding: &LocalBinding) -> Result<Self, Error> {
+        let inner = self.inner.bound_to(binding)?;
+        Ok(Self { inner })
+    }
+
+    /// Stop keeping the resolver returned by `bound_to()` for `binding`, returning whether there
+    /// was one.  See `Resolver::remove_binding()`.
+    #[cfg(cares1_24)]
+    pub fn remove_binding(&self, binding: &LocalBinding) -> bool {
+        self.inner.remove_binding(binding)
     }
 
     /// Reinitialize a channel from system configuration.
@@ -98,31 +225,115 @@ impl FutureResolver {
     /// local named.
     ///
     /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
-    /// `[2001:4860:4860::8888]:53`.
+    /// `[2001:4860:4860::8888]:53`.  With the `tls` feature, servers can instead be given as
+    /// `tls://host@ip[:port]` - see `Resolver::set_servers()`.
+    ///
+    /// If a `Transport` is set then it, and not the list of servers, determines where queries go:
+    /// this fails with `c_ares::Error::ENOTIMP`.
     pub fn set_servers(&self, servers: &[&str]) -> c_ares::Result<&Self> {
         self.inner.set_servers(servers)?;
         Ok(self)
     }
 
-    /// Retrieves the list of servers in comma delimited format.
+    /// Set the list of servers to contact, in the comma delimited format that `c-ares` uses - see
+    /// `Resolver::set_servers_csv()`.
+    pub fn set_servers_csv(&self, servers: &str) -> c_ares::Result<&Self> {
+        self.inner.set_servers_csv(servers)?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, as socket addresses.
+    pub fn set_servers_addrs(&self, servers: &[SocketAddr]) -> c_ares::Result<&Self> {
+        self.inner.set_servers_addrs(servers)?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, as IP addresses.  Servers are contacted on the default
+    /// port - 53, unless configured otherwise in the `Options`.
+    pub fn set_servers_ips(&self, servers: &[IpAddr]) -> c_ares::Result<&Self> {
+        self.inner.set_servers_ips(servers)?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, each with its own UDP and TCP ports.
+    #[cfg(cares1_34)]
+    pub fn set_servers_ports(&self, servers: &[Server]) -> c_ares::Result<&Self> {
+        self.inner.set_servers_ports(servers)?;
+        Ok(self)
+    }
+
+    /// Returns the configuration in effect - see `Resolver::effective_options()`.
+    pub fn effective_options(&self) -> ResolvedConfig {
+        self.inner.effective_options()
+    }
+
+    /// Returns the domains searched by the `search_xxx()` methods - see
+    /// `Resolver::search_domains()`.
+    pub fn search_domains(&self) -> Vec<String> {
+        self.inner.search_domains()
+    }
+
+    /// Returns the number of dots which must be present in a name for it to be queried "as is" -
+    /// see `Resolver::ndots()`.
+    pub fn ndots(&self) -> u32 {
+        self.inner.ndots()
+    }
+
+    /// Set the domains to be searched by the `search_xxx()` methods - see
+    /// `Resolver::set_search_domains()`.
+    pub fn set_search_domains(&self, domains: &[&str]) -> &Self {
+        self.inner.set_search_domains(domains);
+        self
+    }
+
+    /// Retrieves the list of servers in comma delimited format - see `Resolver::get_servers()`.
     #[cfg(cares1_24)]
-    pub fn get_servers(&self) -> AresString {
+    pub fn get_servers(&self) -> String {
         self.inner.get_servers()
     }
 
-    /// Set the local IPv4 address from which to make queries.
+    /// Retrieves the list of servers in the comma delimited format that `c-ares` uses - see
+    /// `Resolver::get_servers_csv()`.
+    #[cfg(cares1_24)]
+    pub fn get_servers_csv(&self) -> String {
+        self.inner.get_servers_csv()
+    }
+
+    /// Add a server to the list of servers to contact, if it isn't already there - see
+    /// `Resolver::add_server()`.
+    #[cfg(cares1_24)]
+    pub fn add_server(&self, server: SocketAddr) -> c_ares::Result<&Self> {
+        self.inner.add_server(server)?;
+        Ok(self)
+    }
+
+    /// Remove a server from the list of servers to contact, if it is there - see
+    /// `Resolver::remove_server()`.
+    #[cfg(cares1_24)]
+    pub fn remove_server(&self, server: SocketAddr) -> c_ares::Result<&Self> {
+        self.inner.remove_server(server)?;
+        Ok(self)
+    }
+
+    /// Returns the servers that the resolver is using - see `Resolver::servers()`.
+    #[cfg(cares1_24)]
+    pub fn servers(&self) -> Vec<ServerInfo> {
+        self.inner.servers()
+    }
+
+    /// Set the local IPv4 address from which to make queries - see `Resolver::set_local_ipv4()`.
     pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
         self.inner.set_local_ipv4(ipv4);
         self
     }
 
-    /// Set the local IPv6 address from which to make queries.
+    /// Set the local IPv6 address from which to make queries - see `Resolver::set_local_ipv6()`.
     pub fn set_local_ipv6(&self, ipv6: &Ipv6Addr) -> &Self {
         self.inner.set_local_ipv6(ipv6);
         self
     }
 
-    /// Set the local device from which to make queries.
+    /// Set the local device from which to make queries - see `Resolver::set_local_device()`.
     pub fn set_local_device(&self, device: &str) -> &Self {
         self.inner.set_local_device(device);
         self
@@ -139,6 +350,105 @@ impl FutureResolver {
         Ok(self)
     }
 
+    /// Pin `name` to `addresses`, replacing any override already set for it.
+    ///
+    /// Queries for the A and AAAA records of `name`, and host lookups of `name`, are then answered
+    /// from `addresses` without consulting the servers or the hosts file.  An override with no
+    /// addresses of the family asked for gives `ENODATA`.
+    pub fn add_host_override(&self, name: &str, addresses: &[IpAddr]) -> &Self {
+        self.inner.add_host_override(name, addresses);
+        self
+    }
+
+    /// Remove the override for `name`, returning whether there was one.
+    pub fn remove_host_override(&self, name: &str) -> bool {
+        self.inner.remove_host_override(name)
+    }
+
+    /// Remove all host overrides.
+    pub fn clear_host_overrides(&self) -> &Self {
+        self.inner.clear_host_overrides();
+        self
+    }
+
+    /// Set a callback function to be invoked whenever a query for a name completes - see
+    /// `Resolver::set_query_log()`.
+    pub fn set_query_log<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(&QueryLogEntry) + Send + Sync + 'static,
+    {
+        self.inner.set_query_log(callback);
+        self
+    }
+
+    /// Returns the counters and latency histograms that this resolver maintains for the queries
+    /// that it makes.  See `Resolver::metrics()`.
+    pub fn metrics(&self) -> &Metrics {
+        self.inner.metrics()
+    }
+
+    /// Returns how busy this resolver is.  See `Resolver::load()`.
+    pub fn load(&self) -> LoadStats {
+        self.inner.load()
+    }
+
+    /// Returns `Error::Overloaded` if a query made now would fail at once - see
+    /// `Resolver::check_capacity()`.
+    pub fn check_capacity(&self) -> Result<(), Error> {
+        self.inner.check_capacity()
+    }
+
+    /// Make a query with `query`, failing with a crate-level `Error` - so that a query that the
+    /// resolver rejects at once, without going to `c-ares`, fails with the reason: eg
+    /// `Error::Overloaded`, where the query's own future gives only `c_ares::Error::ENOMEM`.  See
+    /// `Resolver::try_query()`.
+    ///
+    /// Only queries made by `query` itself are checked - not those that futures make later as
+    /// they are polled, eg the address queries of `lookup_mx()`.
+    ///
+    /// ```no_run
+    /// use c_ares_resolver::FutureResolver;
+    ///
+    /// let resolver = FutureResolver::new().unwrap();
+    /// let query = resolver.try_query(|resolver| resolver.query_a("example.com"));
+    /// ```
+    pub fn try_query<F, Q, T>(&self, query: F) -> impl Future<Output = Result<T, Error>> + Send
+    where
+        F: FnOnce(&Self) -> Q,
+        Q: Future<Output = c_ares::Result<T>> + Send,
+    {
+        let (future, rejection) = rejection::catch(|| query(self));
+        async move {
+            match rejection {
+                Some(error) => Err(error),
+                None => future.await.map_err(Error::from),
+            }
+        }
+    }
+
+    /// The ID of the thread on which this resolver runs its event loop.  See
+    /// `Resolver::thread_id()`.
+    pub fn thread_id(&self) -> ThreadId {
+        self.inner.thread_id()
+    }
+
+    /// Wait for a permit to make a query - see `Options::set_max_in_flight()`.  Hold the permit
+    /// until the query completes.
+    ///
+    /// With no limit configured, a permit is always available at once.
+    pub fn acquire_permit(&self) -> AcquirePermit {
+        AcquirePermit::new(Arc::clone(self.inner.permits()))
+    }
+
+    /// Returns `Poll::Ready` if a permit is available now, and otherwise arranges for the task to
+    /// be woken when one is released - for callers that implement their own futures.
+    ///
+    /// This does not take the permit, so another caller may take it first: `acquire_permit()`
+    /// waits again if so.
+    pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.permits().poll_ready(cx)
+    }
+
     /// Set a callback function to be invoked whenever a query on the channel completes.
     ///
     /// `callback(server, success, flags)` will be called when a query completes.
@@ -155,6 +465,12 @@ impl FutureResolver {
         self
     }
 
+    /// Returns health statistics for each server that has been queried, keyed by server.
+    #[cfg(cares1_29)]
+    pub fn server_stats(&self) -> HashMap<String, ServerStats> {
+        self.inner.server_stats()
+    }
+
     /// Look up the A records associated with `name`.
     pub fn query_a(&self, name: &str) -> CAresFuture<c_ares::AResults> {
         futurize!(self.inner, query_a, name)
@@ -175,6 +491,21 @@ impl FutureResolver {
         futurize!(self.inner, search_aaaa, name)
     }
 
+    /// Look up all of the records associated with `name`, of whatever type.
+    ///
+    /// Following RFC 8482, many servers answer ANY queries with only some of their records - or
+    /// refuse them altogether.
+    #[cfg(cares1_22)]
+    pub fn query_any(&self, name: &str) -> CAresFuture<AnyResults> {
+        futurize!(self.inner, query_any, name)
+    }
+
+    /// Search for all of the records associated with `name`, of whatever type.
+    #[cfg(cares1_22)]
+    pub fn search_any(&self, name: &str) -> CAresFuture<AnyResults> {
+        futurize!(self.inner, search_any, name)
+    }
+
     /// Look up the CAA records associated with `name`.
     #[cfg(cares1_17)]
     pub fn query_caa(&self, name: &str) -> CAresFuture<c_ares::CAAResults> {
@@ -197,6 +528,42 @@ impl FutureResolver {
         futurize!(self.inner, search_cname, name)
     }
 
+    /// Look up the DNSKEY records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_dnskey(&self, name: &str) -> CAresFuture<DNSKEYResults> {
+        futurize!(self.inner, query_dnskey, name)
+    }
+
+    /// Search for the DNSKEY records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_dnskey(&self, name: &str) -> CAresFuture<DNSKEYResults> {
+        futurize!(self.inner, search_dnskey, name)
+    }
+
+    /// Look up the DS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_ds(&self, name: &str) -> CAresFuture<DSResults> {
+        futurize!(self.inner, query_ds, name)
+    }
+
+    /// Search for the DS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_ds(&self, name: &str) -> CAresFuture<DSResults> {
+        futurize!(self.inner, search_ds, name)
+    }
+
+    /// Look up the HTTPS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_https(&self, name: &str) -> CAresFuture<HTTPSResults> {
+        futurize!(self.inner, query_https, name)
+    }
+
+    /// Search for the HTTPS records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_https(&self, name: &str) -> CAresFuture<HTTPSResults> {
+        futurize!(self.inner, search_https, name)
+    }
+
     /// Look up the MX records associated with `name`.
     pub fn query_mx(&self, name: &str) -> CAresFuture<c_ares::MXResults> {
         futurize!(self.inner, query_mx, name)
@@ -237,6 +604,25 @@ impl FutureResolver {
         futurize!(self.inner, search_ptr, name)
     }
 
+    /// Look up the PTR records for the address `ip` - at its `in-addr.arpa` or `ip6.arpa` name.
+    pub fn query_ptr_for(&self, ip: &IpAddr) -> CAresFuture<c_ares::PTRResults> {
+        futurize!(self.inner, query_ptr_for, ip)
+    }
+
+    /// Look up the RRSIG records associated with `name`.
+    ///
+    /// Signatures are returned only by servers that support DNSSEC.
+    #[cfg(cares1_22)]
+    pub fn query_rrsig(&self, name: &str) -> CAresFuture<RRSIGResults> {
+        futurize!(self.inner, query_rrsig, name)
+    }
+
+    /// Search for the RRSIG records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_rrsig(&self, name: &str) -> CAresFuture<RRSIGResults> {
+        futurize!(self.inner, search_rrsig, name)
+    }
+
     /// Look up the SOA records associated with `name`.
     pub fn query_soa(&self, name: &str) -> CAresFuture<c_ares::SOAResult> {
         futurize!(self.inner, query_soa, name)
@@ -257,6 +643,30 @@ impl FutureResolver {
         futurize!(self.inner, search_srv, name)
     }
 
+    /// Look up the SSHFP records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn query_sshfp(&self, name: &str) -> CAresFuture<SSHFPResults> {
+        futurize!(self.inner, query_sshfp, name)
+    }
+
+    /// Search for the SSHFP records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_sshfp(&self, name: &str) -> CAresFuture<SSHFPResults> {
+        futurize!(self.inner, search_sshfp, name)
+    }
+
+    /// Look up the TLSA records associated with `name` - eg `_25._tcp.mail.example.com`.
+    #[cfg(cares1_22)]
+    pub fn query_tlsa(&self, name: &str) -> CAresFuture<TLSAResults> {
+        futurize!(self.inner, query_tlsa, name)
+    }
+
+    /// Search for the TLSA records associated with `name`.
+    #[cfg(cares1_22)]
+    pub fn search_tlsa(&self, name: &str) -> CAresFuture<TLSAResults> {
+        futurize!(self.inner, search_tlsa, name)
+    }
+
     /// Look up the TXT records associated with `name`.
     pub fn query_txt(&self, name: &str) -> CAresFuture<c_ares::TXTResults> {
         futurize!(self.inner, query_txt, name)
@@ -277,6 +687,139 @@ impl FutureResolver {
         futurize!(self.inner, search_uri, name)
     }
 
+    /// Look up the SPF record published at `domain`.
+    ///
+    /// It's an error - `EBADRESP` - for a domain to publish more than one SPF record.
+    pub fn lookup_spf(&self, domain: &str) -> CAresFuture<SpfRecord> {
+        futurize!(self.inner, lookup_spf, domain)
+    }
+
+    /// Look up the DMARC record for `domain`, published at `_dmarc.<domain>`.
+    pub fn lookup_dmarc(&self, domain: &str) -> CAresFuture<DmarcRecord> {
+        futurize!(self.inner, lookup_dmarc, domain)
+    }
+
+    /// Look up the DKIM key record for `selector` at `domain`, published at
+    /// `<selector>._domainkey.<domain>`.
+    pub fn lookup_dkim(&self, selector: &str, domain: &str) -> CAresFuture<DkimRecord> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner.lookup_dkim(selector, domain, |result| {
+            let _ = sender.send(result);
+        });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Look up the URIs for the E.164 telephone number `number` - eg `+14155551234` - via ENUM
+    /// (RFC 6116).
+    ///
+    /// This queries the NAPTR records at the number's `e164.arpa` domain, and applies their
+    /// rewrite rules to the number.  Only terminal rules are followed, and rules using regular
+    /// expression features beyond literals, `.`, bracket expressions, `*`, `+`, `?`, anchors and
+    /// groups are skipped.
+    ///
+    /// The candidate URIs are given in the order in which they should be tried.  A number that
+    /// isn't of the form `+<digits>` gives `EBADNAME`.
+    pub fn enum_lookup(&self, number: &str) -> CAresFuture<Vec<EnumUri>> {
+        futurize!(self.inner, enum_lookup, number)
+    }
+
+    /// Discover the instances of a service - eg `_http._tcp.example.com` - per RFC 6763.
+    ///
+    /// This enumerates the instances with a PTR query, and then looks up the SRV and TXT records
+    /// of each instance and the addresses of its host.  Instances whose SRV records can't be found
+    /// are left out.
+    pub fn discover_services(
+        &self,
+        service: &str,
+    ) -> impl Future<Output = c_ares::Result<Vec<ServiceInstance>>> + Send {
+        let resolver = Self {
+            inner: Arc::clone(&self.inner),
+        };
+        discovery::discover(resolver, service)
+    }
+
+    /// Look up the SRV records for `name` - eg `_sip._udp.example.com` - choose between their
+    /// targets per RFC 2782, and resolve the chosen target to addresses.
+    ///
+    /// Targets are tried in the order given by `SrvSelector`, until one has addresses.
+    pub fn resolve_srv(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = c_ares::Result<SrvEndpoint>> + Send {
+        let resolver = Self {
+            inner: Arc::clone(&self.inner),
+        };
+        srv::resolve(resolver, name)
+    }
+
+    /// Look up the hosts that accept mail for `domain`, as an SMTP sender would.
+    ///
+    /// The exchanges from the MX records are returned in order of preference, without duplicates.
+    /// If `resolve` is set, each exchange's addresses are looked up too.
+    ///
+    /// If `domain` has no MX records but has addresses, then - per RFC 5321 - it is its own
+    /// exchange, returned with those addresses.  If its only MX record is a null MX, per RFC 7505,
+    /// then there are no exchanges.
+    pub fn lookup_mx(
+        &self,
+        domain: &str,
+        resolve: bool,
+    ) -> impl Future<Output = c_ares::Result<Vec<MailExchange>>> + Send {
+        let resolver = Self {
+            inner: Arc::clone(&self.inner),
+        };
+        mx::lookup(resolver, domain, resolve)
+    }
+
+    /// Look up the addresses of many names at once, as by `get_host_by_name()`.
+    ///
+    /// All of the lookups are made immediately, and the returned stream yields each name with its
+    /// result as soon as that lookup completes.
+    pub fn resolve_many<I>(&self, names: I, family: c_ares::AddressFamily) -> ResolveMany
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let resolver = Self {
+            inner: Arc::clone(&self.inner),
+        };
+        ResolveMany::new(resolver, names, family, usize::MAX)
+    }
+
+    /// Look up the addresses of many names, as by `resolve_many()` - but with at most
+    /// `max_in_flight` lookups outstanding at any time.
+    ///
+    /// This is for resolving more names than it is reasonable to query at once: further lookups
+    /// are made as earlier ones complete, while the stream is polled.
+    pub fn resolve_many_limited<I>(
+        &self,
+        names: I,
+        family: c_ares::AddressFamily,
+        max_in_flight: usize,
+    ) -> ResolveMany
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let resolver = Self {
+            inner: Arc::clone(&self.inner),
+        };
+        ResolveMany::new(resolver, names, family, max_in_flight)
+    }
+
+    /// Watch the IPv4 addresses of `name`, re-resolving it periodically and yielding its addresses
+    /// each time that they change - eg for a load balancer tracking the addresses of its backends.
+    ///
+    /// With no `interval`, `name` is re-resolved when its records expire, as given by their TTL.
+    /// Otherwise it is re-resolved every `interval`.
+    pub fn watch_a(&self, name: &str, interval: Option<Duration>) -> WatchA {
+        let resolver = Self {
+            inner: Arc::clone(&self.inner),
+        };
+        WatchA::new(resolver, name, interval)
+    }
+
     /// Perform a host query by address.
     ///
     /// This method is one of the very few places where this library performs strictly more
@@ -302,6 +845,10 @@ impl FutureResolver {
         family: c_ares::AddressFamily,
     ) -> CAresFuture<HostResults> {
         let (sender, receiver) = futures_channel::oneshot::channel();
+        if let Some(result) = self.inner.host_override(name, family) {
+            let _ = sender.send(result);
+            return CAresFuture::new(receiver, Arc::clone(&self.inner));
+        }
         self.inner.get_host_by_name(name, family, |result| {
             let _ = sender.send(result.map(Into::into));
         });
@@ -309,15 +856,30 @@ impl FutureResolver {
         CAresFuture::new(receiver, resolver)
     }
 
+    /// Perform host queries by name for both IPv4 and IPv6, returning the merged results - IPv4
+    /// addresses first, then IPv6.
+    ///
+    /// Both queries are made immediately.  The lookup succeeds if either of them does.
+    pub fn get_host_by_name_any(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = c_ares::Result<HostResults>> + Send {
+        let v4 = self.get_host_by_name(name, c_ares::AddressFamily::INET);
+        let v6 = self.get_host_by_name(name, c_ares::AddressFamily::INET6);
+        async move { host::merge(v4.await, v6.await) }
+    }
+
     /// Address-to-nodename translation in protocol-independent manner.
     ///
+    /// `flags` may be given either as `c_ares::NIFlags` or as `NameInfoOptions`.
+    ///
     /// This method is one of the very few places where this library performs strictly more
     /// allocation than the underlying `c-ares` code.  If this is a problem for you, you should
     /// prefer to use the analogous method on the `Resolver`.
-    pub fn get_name_info<F>(
+    pub fn get_name_info(
         &self,
         address: &SocketAddr,
-        flags: c_ares::NIFlags,
+        flags: impl Into<c_ares::NIFlags>,
     ) -> CAresFuture<NameInfoResult> {
         let (sender, receiver) = futures_channel::oneshot::channel();
         self.inner.get_name_info(address, flags, |result| {
@@ -328,7 +890,7 @@ impl FutureResolver {
     }
 
     /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
-    /// the provided parameters, taking values as defined in `arpa/nameser.h`.
+    /// the provided parameters, which may be given as numbers per `arpa/nameser.h`.
     ///
     /// This method is one of the very few places where this library performs strictly more
     /// allocation than the underlying `c-ares` code.  If this is a problem for you, you should
@@ -337,7 +899,12 @@ impl FutureResolver {
     /// This method is provided so that users can query DNS types for which `c-ares` does not
     /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
     /// `query_xxx()` is available, that should be used.
-    pub fn query(&self, name: &str, dns_class: u16, query_type: u16) -> CAresFuture<Vec<u8>> {
+    pub fn query(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> CAresFuture<Vec<u8>> {
         let (sender, receiver) = futures_channel::oneshot::channel();
         self.inner.query(name, dns_class, query_type, |result| {
             let _ = sender.send(result.map(std::borrow::ToOwned::to_owned));
@@ -347,7 +914,7 @@ impl FutureResolver {
     }
 
     /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
-    /// query are per the provided parameters, taking values as defined in `arpa/nameser.h`.
+    /// query are per the provided parameters, which may be given as numbers per `arpa/nameser.h`.
     ///
     /// This method is one of the very few places where this library performs strictly more
     /// allocation than the underlying `c-ares` code.  If this is a problem for you, you should
@@ -356,7 +923,12 @@ impl FutureResolver {
     /// This method is provided so that users can search DNS types for which `c-ares` does not
     /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
     /// `search_xxx()` is available, that should be used.
-    pub fn search(&self, name: &str, dns_class: u16, query_type: u16) -> CAresFuture<Vec<u8>> {
+    pub fn search(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> CAresFuture<Vec<u8>> {
         let (sender, receiver) = futures_channel::oneshot::channel();
         self.inner.search(name, dns_class, query_type, |result| {
             let _ = sender.send(result.map(std::borrow::ToOwned::to_owned));
@@ -365,7 +937,153 @@ impl FutureResolver {
         CAresFuture::new(receiver, resolver)
     }
 
-    /// Cancel all requests made on this `FutureResolver`.
+    /// Search for the A records associated with `name` - with `flags` applied to this query only.
+    /// See `Resolver::search_a_opts()`.
+    #[cfg(cares1_24)]
+    pub fn search_a_opts(&self, name: &str, flags: QueryFlags) -> CAresFuture<c_ares::AResults> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner.search_a_opts(name, flags, |result| {
+            let _ = sender.send(result);
+        });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Search for the AAAA records associated with `name` - with `flags` applied to this query
+    /// only.  See `Resolver::search_a_opts()`.
+    #[cfg(cares1_24)]
+    pub fn search_aaaa_opts(
+        &self,
+        name: &str,
+        flags: QueryFlags,
+    ) -> CAresFuture<c_ares::AAAAResults> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner.search_aaaa_opts(name, flags, |result| {
+            let _ = sender.send(result);
+        });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`, as by `search()` - with
+    /// `flags` applied to this query only.  See `Resolver::search_a_opts()`.
+    #[cfg(cares1_24)]
+    pub fn search_opts(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        flags: QueryFlags,
+    ) -> CAresFuture<Vec<u8>> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner
+            .search_opts(name, dns_class, query_type, flags, |result| {
+                let _ = sender.send(result.map(std::borrow::ToOwned::to_owned));
+            });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Look up the A records associated with `name`, as by `query_a()` - and report how the
+    /// query was answered.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn query_a_ext(&self, name: &str) -> CAresFuture<QueryResponse<c_ares::AResults>> {
+        futurize_ext!(self.inner, query_a_ext, name)
+    }
+
+    /// Search for the A records associated with `name`, as by `search_a()` - and report how the
+    /// query was answered.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn search_a_ext(&self, name: &str) -> CAresFuture<QueryResponse<c_ares::AResults>> {
+        futurize_ext!(self.inner, search_a_ext, name)
+    }
+
+    /// Look up the AAAA records associated with `name`, as by `query_aaaa()` - and report how the
+    /// query was answered.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn query_aaaa_ext(&self, name: &str) -> CAresFuture<QueryResponse<c_ares::AAAAResults>> {
+        futurize_ext!(self.inner, query_aaaa_ext, name)
+    }
+
+    /// Search for the AAAA records associated with `name`, as by `search_aaaa()` - and report how
+    /// the query was answered.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn search_aaaa_ext(&self, name: &str) -> CAresFuture<QueryResponse<c_ares::AAAAResults>> {
+        futurize_ext!(self.inner, search_aaaa_ext, name)
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query()` - and report how the query
+    /// was answered.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn query_ext(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> CAresFuture<QueryResponse<Vec<u8>>> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner
+            .query_ext(name, dns_class, query_type, |response| {
+                let _ = sender.send(Ok(response.map_result(std::borrow::ToOwned::to_owned)));
+            });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`, as by `search()` - and report
+    /// how the query was answered.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn search_ext(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> CAresFuture<QueryResponse<Vec<u8>>> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner
+            .search_ext(name, dns_class, query_type, |response| {
+                let _ = sender.send(Ok(response.map_result(std::borrow::ToOwned::to_owned)));
+            });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query_ext()` - with the header of
+    /// the response parsed.
+    ///
+    /// Note that `c-ares` reports a response with a failing response code as an error - eg
+    /// `ESERVFAIL` - so that the header is available only for successful responses.
+    ///
+    /// The future fails only if the query is cancelled: other errors are in the response.
+    #[cfg(cares1_29)]
+    pub fn query_raw_ext(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+    ) -> CAresFuture<QueryResponse<RawResponse>> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.inner
+            .query_raw_ext(name, dns_class, query_type, |response| {
+                let _ = sender.send(Ok(response.map_result(RawResponse::into_owned)));
+            });
+        let resolver = Arc::clone(&self.inner);
+        CAresFuture::new(receiver, resolver)
+    }
+
+    /// Cancel all requests made on this `FutureResolver` - see `Resolver::cancel()`.
     pub fn cancel(&self) {
         self.inner.cancel()
     }
@@ -0,0 +1,29 @@
+use std::sync::OnceLock;
+
+use crate::futureresolver::FutureResolver;
+
+static DEFAULT_RESOLVER: OnceLock<FutureResolver> = OnceLock::new();
+
+/// Returns the process-wide default `FutureResolver`.
+///
+/// Libraries built on this crate can use this to share a single channel and event loop, rather
+/// than each creating their own.
+///
+/// If no default has been installed by `set_default_resolver()`, then one is created using
+/// default `Options` the first time this is called.
+///
+/// # Panics
+///
+/// Panics if the default resolver needs to be created, and creating it fails.
+pub fn default_resolver() -> &'static FutureResolver {
+    DEFAULT_RESOLVER
+        .get_or_init(|| FutureResolver::new().expect("Failed to create default resolver"))
+}
+
+/// Install `resolver` as the process-wide default `FutureResolver`.
+///
+/// This should be called once, at startup, before anything calls `default_resolver()`.  If a
+/// default resolver is already in place then this fails, handing back `resolver`.
+pub fn set_default_resolver(resolver: FutureResolver) -> Result<(), FutureResolver> {
+    DEFAULT_RESOLVER.set(resolver)
+}
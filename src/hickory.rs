@@ -0,0 +1,293 @@
+// Conversions between this crate's results and the record types of `hickory_proto`.
+use hickory_proto::rr::rdata::{self, caa, sshfp, tlsa};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use hickory_proto::serialize::binary::{BinDecoder, BinEncodable, Restrict};
+use hickory_proto::ProtoError;
+
+use crate::any::{AnyRecord, AnyResult};
+use crate::dnsrec::{T_DNSKEY, T_DS, T_RRSIG, T_SSHFP};
+use crate::dnssec::{DNSKEYResult, DSResult, RRSIGResult};
+use crate::sshfp::SSHFPResult;
+use crate::tlsa::TLSAResult;
+
+// Record types, that `hickory_proto` may not parse.
+const T_CAA: u16 = 257;
+const T_URI: u16 = 256;
+
+// Names in results are written without the trailing dot.
+fn to_name(name: &str) -> Result<Name, ProtoError> {
+    let mut name = Name::from_ascii(name)?;
+    name.set_fqdn(true);
+    Ok(name)
+}
+
+fn from_name(name: &Name) -> String {
+    let name = name.to_ascii();
+    match name.strip_suffix('.') {
+        Some(stripped) => stripped.to_owned(),
+        None => name,
+    }
+}
+
+// Parse record data, of type `rr_type`, in wire format.
+fn read_rdata(rr_type: u16, data: &[u8]) -> Result<RData, ProtoError> {
+    let length = u16::try_from(data.len()).map_err(|_| ProtoError::from("record too long"))?;
+    let mut decoder = BinDecoder::new(data);
+    RData::read(
+        &mut decoder,
+        RecordType::from(rr_type),
+        Restrict::new(length),
+    )
+}
+
+// Write the data of the records that `hickory_proto` holds in their own types, in wire format.
+fn write_rdata(record: &AnyRecord) -> Result<Option<(u16, Vec<u8>)>, ProtoError> {
+    let written = match *record {
+        AnyRecord::DS(ref ds) => {
+            let mut data = ds.key_tag.to_be_bytes().to_vec();
+            data.extend_from_slice(&[ds.algorithm, ds.digest_type]);
+            data.extend_from_slice(&ds.digest);
+            Some((T_DS, data))
+        }
+        AnyRecord::DNSKEY(ref dnskey) => {
+            let mut data = dnskey.flags.to_be_bytes().to_vec();
+            data.extend_from_slice(&[dnskey.protocol, dnskey.algorithm]);
+            data.extend_from_slice(&dnskey.public_key);
+            Some((T_DNSKEY, data))
+        }
+        AnyRecord::RRSIG(ref rrsig) => {
+            let mut data = rrsig.type_covered.to_be_bytes().to_vec();
+            data.extend_from_slice(&[rrsig.algorithm, rrsig.labels]);
+            data.extend_from_slice(&rrsig.original_ttl.to_be_bytes());
+            data.extend_from_slice(&rrsig.expiration.to_be_bytes());
+            data.extend_from_slice(&rrsig.inception.to_be_bytes());
+            data.extend_from_slice(&rrsig.key_tag.to_be_bytes());
+            data.extend_from_slice(&to_name(&rrsig.signer_name)?.to_bytes()?);
+            data.extend_from_slice(&rrsig.signature);
+            Some((T_RRSIG, data))
+        }
+        AnyRecord::URI {
+            priority,
+            weight,
+            ref target,
+        } => {
+            let mut data = priority.to_be_bytes().to_vec();
+            data.extend_from_slice(&weight.to_be_bytes());
+            data.extend_from_slice(target.as_bytes());
+            Some((T_URI, data))
+        }
+        AnyRecord::CAA {
+            critical,
+            ref tag,
+            ref value,
+        } => {
+            let length = u8::try_from(tag.len()).map_err(|_| ProtoError::from("tag too long"))?;
+            let mut data = vec![if critical { 0x80 } else { 0 }, length];
+            data.extend_from_slice(tag.as_bytes());
+            data.extend_from_slice(value);
+            Some((T_CAA, data))
+        }
+        AnyRecord::Other {
+            rr_type, ref data, ..
+        } if !data.is_empty() => Some((rr_type, data.clone())),
+        _ => None,
+    };
+    Ok(written)
+}
+
+impl From<TLSAResult> for tlsa::TLSA {
+    fn from(result: TLSAResult) -> Self {
+        Self::new(
+            tlsa::CertUsage::from(result.cert_usage),
+            tlsa::Selector::from(result.selector),
+            tlsa::Matching::from(result.matching_type),
+            result.cert_data,
+        )
+    }
+}
+
+impl From<&tlsa::TLSA> for TLSAResult {
+    fn from(tlsa: &tlsa::TLSA) -> Self {
+        Self {
+            cert_usage: tlsa.cert_usage().into(),
+            selector: tlsa.selector().into(),
+            matching_type: tlsa.matching().into(),
+            cert_data: tlsa.cert_data().to_vec(),
+        }
+    }
+}
+
+impl From<SSHFPResult> for sshfp::SSHFP {
+    fn from(result: SSHFPResult) -> Self {
+        Self::new(
+            sshfp::Algorithm::from(result.algorithm),
+            sshfp::FingerprintType::from(result.fingerprint_type),
+            result.fingerprint,
+        )
+    }
+}
+
+impl From<&sshfp::SSHFP> for SSHFPResult {
+    fn from(sshfp: &sshfp::SSHFP) -> Self {
+        Self {
+            algorithm: sshfp.algorithm().into(),
+            fingerprint_type: sshfp.fingerprint_type().into(),
+            fingerprint: sshfp.fingerprint().to_vec(),
+        }
+    }
+}
+
+/// Convert a result to a `hickory_proto` record.
+///
+/// This fails if a name in the result is not valid, and for SVCB and HTTPS records - and for
+/// records of other types that `c-ares` parses, but without keeping their data.
+///
+/// `Record` has an inherent `try_from()` method, so use `try_into()` to make this conversion.
+impl TryFrom<&AnyResult> for Record {
+    type Error = ProtoError;
+
+    fn try_from(result: &AnyResult) -> Result<Self, Self::Error> {
+        let rdata = match result.record {
+            AnyRecord::A(address) => RData::A(address.into()),
+            AnyRecord::AAAA(address) => RData::AAAA(address.into()),
+            AnyRecord::CNAME(ref cname) => RData::CNAME(rdata::CNAME(to_name(cname)?)),
+            AnyRecord::NS(ref ns) => RData::NS(rdata::NS(to_name(ns)?)),
+            AnyRecord::PTR(ref ptr) => RData::PTR(rdata::PTR(to_name(ptr)?)),
+            AnyRecord::SOA {
+                ref mname,
+                ref rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => RData::SOA(rdata::SOA::new(
+                to_name(mname)?,
+                to_name(rname)?,
+                serial,
+                refresh as i32,
+                retry as i32,
+                expire as i32,
+                minimum,
+            )),
+            AnyRecord::HINFO { ref cpu, ref os } => {
+                RData::HINFO(rdata::HINFO::new(cpu.clone(), os.clone()))
+            }
+            AnyRecord::MX {
+                preference,
+                ref exchange,
+            } => RData::MX(rdata::MX::new(preference, to_name(exchange)?)),
+            AnyRecord::TXT(ref text) => {
+                let mut strings: Vec<&[u8]> = text.chunks(255).collect();
+                if strings.is_empty() {
+                    strings.push(&[]);
+                }
+                RData::TXT(rdata::TXT::from_bytes(strings))
+            }
+            AnyRecord::SRV {
+                priority,
+                weight,
+                port,
+                ref target,
+            } => RData::SRV(rdata::SRV::new(priority, weight, port, to_name(target)?)),
+            AnyRecord::NAPTR {
+                order,
+                preference,
+                ref flags,
+                ref services,
+                ref regexp,
+                ref replacement,
+            } => RData::NAPTR(rdata::NAPTR::new(
+                order,
+                preference,
+                flags.as_bytes().into(),
+                services.as_bytes().into(),
+                regexp.as_bytes().into(),
+                to_name(replacement)?,
+            )),
+            AnyRecord::SSHFP(ref sshfp) => RData::SSHFP(sshfp.clone().into()),
+            AnyRecord::TLSA(ref tlsa) => RData::TLSA(tlsa.clone().into()),
+            ref other => match write_rdata(other)? {
+                Some((rr_type, data)) => read_rdata(rr_type, &data)?,
+                None => return Err(ProtoError::from("record has no hickory_proto equivalent")),
+            },
+        };
+        let record = Self::from_rdata(to_name(&result.name)?, result.ttl, rdata);
+        Ok(record)
+    }
+}
+
+/// Convert a `hickory_proto` record to a result.
+///
+/// Records of types that have no variant of their own in `AnyRecord` become `AnyRecord::Other`,
+/// holding their data in wire format.  This fails only if that data cannot be written.
+impl TryFrom<&Record> for AnyResult {
+    type Error = ProtoError;
+
+    fn try_from(record: &Record) -> Result<Self, Self::Error> {
+        let record_data = match *record.data() {
+            RData::A(address) => AnyRecord::A(address.into()),
+            RData::AAAA(address) => AnyRecord::AAAA(address.into()),
+            RData::CNAME(ref cname) => AnyRecord::CNAME(from_name(cname)),
+            RData::NS(ref ns) => AnyRecord::NS(from_name(ns)),
+            RData::PTR(ref ptr) => AnyRecord::PTR(from_name(ptr)),
+            RData::SOA(ref soa) => AnyRecord::SOA {
+                mname: from_name(soa.mname()),
+                rname: from_name(soa.rname()),
+                serial: soa.serial(),
+                refresh: soa.refresh() as u32,
+                retry: soa.retry() as u32,
+                expire: soa.expire() as u32,
+                minimum: soa.minimum(),
+            },
+            RData::HINFO(ref hinfo) => AnyRecord::HINFO {
+                cpu: String::from_utf8_lossy(hinfo.cpu()).into_owned(),
+                os: String::from_utf8_lossy(hinfo.os()).into_owned(),
+            },
+            RData::MX(ref mx) => AnyRecord::MX {
+                preference: mx.preference(),
+                exchange: from_name(mx.exchange()),
+            },
+            RData::TXT(ref txt) => AnyRecord::TXT(txt.txt_data().concat()),
+            RData::SRV(ref srv) => AnyRecord::SRV {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: from_name(srv.target()),
+            },
+            RData::NAPTR(ref naptr) => AnyRecord::NAPTR {
+                order: naptr.order(),
+                preference: naptr.preference(),
+                flags: String::from_utf8_lossy(naptr.flags()).into_owned(),
+                services: String::from_utf8_lossy(naptr.services()).into_owned(),
+                regexp: String::from_utf8_lossy(naptr.regexp()).into_owned(),
+                replacement: from_name(naptr.replacement()),
+            },
+            RData::SSHFP(ref sshfp) => AnyRecord::SSHFP(sshfp.into()),
+            RData::TLSA(ref tlsa) => AnyRecord::TLSA(tlsa.into()),
+            RData::CAA(ref caa) => AnyRecord::CAA {
+                critical: caa.issuer_critical(),
+                tag: caa::Property::as_str(caa.tag()).to_owned(),
+                value: caa.raw_value().to_vec(),
+            },
+            ref other => {
+                let rr_type = u16::from(other.record_type());
+                let data = other.to_bytes()?;
+                let parsed = match rr_type {
+                    T_DS => DSResult::from_raw(&data).map(AnyRecord::DS),
+                    T_SSHFP => SSHFPResult::from_raw(&data).map(AnyRecord::SSHFP),
+                    T_RRSIG => RRSIGResult::from_raw(&data).map(AnyRecord::RRSIG),
+                    T_DNSKEY => DNSKEYResult::from_raw(&data).map(AnyRecord::DNSKEY),
+                    _ => None,
+                };
+                parsed.unwrap_or(AnyRecord::Other { rr_type, data })
+            }
+        };
+        let result = Self {
+            name: from_name(record.name()),
+            ttl: record.ttl(),
+            record: record_data,
+        };
+        Ok(result)
+    }
+}
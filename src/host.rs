@@ -1,7 +1,10 @@
-use std::net::IpAddr;
+use std::fmt;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 /// An owned version of `c_ares::HostResults`.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HostResults {
     /// The hostname returned by the lookup.
     pub hostname: String,
@@ -13,6 +16,99 @@ pub struct HostResults {
     pub aliases: Vec<String>,
 }
 
+impl HostResults {
+    /// The addresses returned by the lookup, each with the given port - eg ready to connect to.
+    pub fn to_socket_addrs(&self, port: u16) -> Vec<SocketAddr> {
+        self.addresses
+            .iter()
+            .map(|&address| SocketAddr::new(address, port))
+            .collect()
+    }
+
+    /// The IPv4 addresses returned by the lookup.
+    pub fn ipv4_addresses(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        self.addresses.iter().filter_map(|address| match address {
+            IpAddr::V4(address) => Some(*address),
+            IpAddr::V6(_) => None,
+        })
+    }
+
+    /// The IPv6 addresses returned by the lookup.
+    pub fn ipv6_addresses(&self) -> impl Iterator<Item = Ipv6Addr> + '_ {
+        self.addresses.iter().filter_map(|address| match address {
+            IpAddr::V4(_) => None,
+            IpAddr::V6(address) => Some(*address),
+        })
+    }
+
+    /// The addresses returned by the lookup, split into IPv4 and IPv6 - each in the order in which
+    /// they were returned.
+    pub fn partition_by_family(&self) -> (Vec<Ipv4Addr>, Vec<Ipv6Addr>) {
+        (
+            self.ipv4_addresses().collect(),
+            self.ipv6_addresses().collect(),
+        )
+    }
+
+    // The approximate memory used by the results, besides the struct itself.
+    pub(crate) fn heap_size(&self) -> usize {
+        let aliases: usize = self.aliases.iter().map(String::len).sum();
+        self.hostname.len()
+            + self.addresses.len() * mem::size_of::<IpAddr>()
+            + self.aliases.len() * mem::size_of::<String>()
+            + aliases
+    }
+}
+
+// Merge the results of IPv4 and IPv6 lookups of the same name: IPv4 addresses first, then IPv6.
+// The merged lookup succeeds if either did.  If neither did, then the IPv4 error is reported -
+// unless that only says that there were no IPv4 addresses.
+pub(crate) fn merge(
+    v4: c_ares::Result<HostResults>,
+    v6: c_ares::Result<HostResults>,
+) -> c_ares::Result<HostResults> {
+    let (mut merged, other) = match (v4, v6) {
+        (Ok(v4), Ok(v6)) => (v4, Some(v6)),
+        (Ok(results), Err(_)) | (Err(_), Ok(results)) => (results, None),
+        (Err(c_ares::Error::ENODATA), Err(error)) | (Err(error), Err(_)) => return Err(error),
+    };
+    if let Some(other) = other {
+        merged.addresses.extend(other.addresses);
+        for alias in other.aliases {
+            if !merged.aliases.contains(&alias) {
+                merged.aliases.push(alias);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+// Write `items` separated by commas, in square brackets.
+pub(crate) fn display_list<T: fmt::Display>(
+    f: &mut fmt::Formatter,
+    items: impl IntoIterator<Item = T>,
+) -> fmt::Result {
+    write!(f, "[")?;
+    for (index, item) in items.into_iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    write!(f, "]")
+}
+
+/// As for `c_ares::HostResults` - eg
+/// `Hostname: example.com, Addresses: [192.0.2.1, 192.0.2.2], Aliases: []`.
+impl fmt::Display for HostResults {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hostname: {}, Addresses: ", self.hostname)?;
+        display_list(f, &self.addresses)?;
+        write!(f, ", Aliases: ")?;
+        display_list(f, &self.aliases)
+    }
+}
+
 impl From<c_ares::HostResults<'_>> for HostResults {
     fn from(results: c_ares::HostResults) -> Self {
         Self {
@@ -0,0 +1,39 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Watches a hosts file, calling `on_change` whenever its modification time changes.  The watching
+// stops when this is dropped.
+pub(crate) struct HostsWatcher {
+    _stop: mpsc::Sender<()>,
+}
+
+impl HostsWatcher {
+    pub(crate) fn start<F>(path: &str, interval: Duration, on_change: F) -> Self
+    where
+        F: Fn() + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+        let path = PathBuf::from(path);
+        let mut last_modified = modified(&path);
+        thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let modified = modified(&path);
+                if modified != last_modified {
+                    last_modified = modified;
+                    on_change();
+                }
+            }
+        });
+        Self { _stop: stop }
+    }
+}
+
+// The modification time of the file - or `None` if it's missing.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
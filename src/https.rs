@@ -0,0 +1,254 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+
+use crate::error::Error;
+use crate::tls::default_client_config;
+use crate::transport::Transport;
+
+const DEFAULT_PORT: u16 = 443;
+
+/// A `Transport` that sends DNS queries over HTTPS, per RFC 8484.
+///
+/// Queries are sent as HTTP/1.1 POST requests to a DNS-over-HTTPS endpoint such as
+/// `https://dns.google/dns-query`.  Setting this transport on the `Options` for any of the
+/// resolvers gives a DNS-over-HTTPS resolver, with the same API as usual.
+///
+/// URLs are of the form `https://host[:port][/path]`, with an IPv6 host in square brackets - eg
+/// `https://[2606:4700:4700::1111]/dns-query`.
+///
+/// Each query is made on a fresh connection.  Responses must give the length of their body, with
+/// `Content-Length` or chunked encoding: one that ends early is an error.
+#[derive(Clone, Debug)]
+pub struct HttpsTransport {
+    host: String,
+    path: String,
+    name: ServerName<'static>,
+    addresses: Vec<SocketAddr>,
+    config: Arc<ClientConfig>,
+    timeout: Option<Duration>,
+}
+
+impl HttpsTransport {
+    /// Returns an `HttpsTransport` that sends queries to the endpoint at `url`, verifying its
+    /// certificate against the Mozilla root certificates.
+    ///
+    /// The host in the URL is looked up once, now, using the system resolver.  Prefer
+    /// `with_addresses()` if this is not acceptable.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let (host, port, _) = parse_url(url)?;
+        let addresses: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+        Self::with_addresses(url, &addresses)
+    }
+
+    /// Returns an `HttpsTransport` that sends queries to the endpoint at `url`, connecting to
+    /// the given addresses rather than looking up the host in the URL.
+    pub fn with_addresses(url: &str, addresses: &[SocketAddr]) -> Result<Self, Error> {
+        let config = default_client_config()?;
+        Self::with_client_config(url, addresses, config)
+    }
+
+    /// Returns an `HttpsTransport` that sends queries to the endpoint at `url`, connecting to
+    /// the given addresses and using the provided TLS configuration.
+    pub fn with_client_config(
+        url: &str,
+        addresses: &[SocketAddr],
+        config: Arc<ClientConfig>,
+    ) -> Result<Self, Error> {
+        let (host, port, path) = parse_url(url)?;
+        let name = ServerName::try_from(host.to_owned()).map_err(|_| bad_url(url))?;
+        let host = if host.contains(':') {
+            format!("[{host}]")
+        } else {
+            host.to_owned()
+        };
+        let host = if port == DEFAULT_PORT {
+            host
+        } else {
+            format!("{host}:{port}")
+        };
+        let transport = Self {
+            host,
+            path: path.to_owned(),
+            name,
+            addresses: addresses.to_vec(),
+            config,
+            timeout: None,
+        };
+        Ok(transport)
+    }
+
+    /// Set a timeout for connecting to the endpoint, and for each read and write thereafter.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no addresses");
+        for address in &self.addresses {
+            let result = match self.timeout {
+                Some(timeout) => TcpStream::connect_timeout(address, timeout),
+                None => TcpStream::connect(address),
+            };
+            match result {
+                Ok(socket) => return Ok(socket),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+impl Transport for HttpsTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let socket = self.connect()?;
+        socket.set_read_timeout(self.timeout)?;
+        socket.set_write_timeout(self.timeout)?;
+        let connection = ClientConnection::new(Arc::clone(&self.config), self.name.clone())
+            .map_err(io::Error::other)?;
+        let mut stream = StreamOwned::new(connection, socket);
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Content-Type: application/dns-message\r\n\
+             Accept: application/dns-message\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n",
+            self.path,
+            self.host,
+            query.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(query);
+        stream.write_all(&request)?;
+        stream.flush()?;
+
+        // Read until the response is complete, rather than until the connection is closed.  A
+        // connection that ends before then - cleanly or not - is an error.
+        let mut response = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            if let Some(body) = read_response(&response)? {
+                return Ok(body);
+            }
+            let length = match stream.read(&mut buffer) {
+                Ok(length) => length,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => 0,
+                Err(err) => return Err(err),
+            };
+            if length == 0 {
+                return Err(bad_response("truncated HTTP response"));
+            }
+            response.extend_from_slice(&buffer[..length]);
+        }
+    }
+}
+
+// Split an `https://` URL into host, port and path.  An IPv6 host is given in square brackets,
+// which are removed.
+fn parse_url(url: &str) -> io::Result<(&str, u16, &str)> {
+    let rest = url.strip_prefix("https://").ok_or_else(|| bad_url(url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').ok_or_else(|| bad_url(url))?,
+        None => authority.split_at(authority.find(':').unwrap_or(authority.len())),
+    };
+    let port = match port {
+        "" => DEFAULT_PORT,
+        port => port
+            .strip_prefix(':')
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(|| bad_url(url))?,
+    };
+    if host.is_empty() {
+        return Err(bad_url(url));
+    }
+    Ok((host, port, path))
+}
+
+fn bad_url(url: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("bad url {url}"))
+}
+
+fn bad_response(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Extract the body from an HTTP response - or return `None` if more of the response is needed.
+//
+// The body must be delimited by a `Content-Length` header, or by chunked encoding: there's no
+// telling a body that ends with the connection from one that was cut short.
+pub(crate) fn read_response(response: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let Some(header_end) = response.windows(4).position(|window| window == b"\r\n\r\n") else {
+        return Ok(None);
+    };
+    let head = std::str::from_utf8(&response[..header_end])
+        .map_err(|_| bad_response("malformed HTTP response"))?;
+    let body = &response[header_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| bad_response("malformed HTTP status line"))?;
+    if status != "200" {
+        return Err(io::Error::other(format!("HTTP status {status}")));
+    }
+
+    let mut content_length = None;
+    let mut chunked = false;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse::<usize>().ok();
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            }
+        }
+    }
+
+    if chunked {
+        decode_chunked(body)
+    } else if let Some(length) = content_length {
+        Ok(body.get(..length).map(<[u8]>::to_vec))
+    } else {
+        Err(bad_response("HTTP response without a length"))
+    }
+}
+
+// Decode a chunked body - or return `None` if more of it is needed.
+fn decode_chunked(mut body: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let mut decoded = Vec::new();
+    loop {
+        let Some(line_end) = body.windows(2).position(|window| window == b"\r\n") else {
+            return Ok(None);
+        };
+        let size = std::str::from_utf8(&body[..line_end])
+            .ok()
+            .and_then(|line| line.split(';').next())
+            .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+            .ok_or_else(|| bad_response("malformed chunk size"))?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(Some(decoded));
+        }
+        let Some(chunk) = body.get(..size) else {
+            return Ok(None);
+        };
+        decoded.extend_from_slice(chunk);
+        let Some(rest) = body.get(size + 2..) else {
+            return Ok(None);
+        };
+        body = rest;
+    }
+}
@@ -0,0 +1,40 @@
+use std::net::SocketAddr;
+use std::task::{Context, Poll};
+use std::vec;
+
+use hyper_util::client::legacy::connect::dns::Name;
+use tower_service::Service;
+
+use crate::futureresolver::FutureResolver;
+use crate::resolve::ResolveFuture;
+
+/// A `FutureResolver` can be used as the resolver in a `hyper_util` `HttpConnector`, in place of
+/// the default resolver - which makes blocking `getaddrinfo` calls on a thread pool.
+///
+/// Names are looked up by `get_host_by_name()`, for both IPv4 and IPv6 addresses.  The addresses
+/// have port 0: the connector sets the port from the URI.
+///
+/// ```no_run
+/// use c_ares_resolver::FutureResolver;
+/// use hyper_util::client::legacy::connect::HttpConnector;
+///
+/// let resolver = FutureResolver::new().unwrap();
+/// let connector = HttpConnector::new_with_resolver(resolver);
+/// ```
+impl Service<Name> for FutureResolver {
+    type Response = vec::IntoIter<SocketAddr>;
+    type Error = c_ares::Error;
+    type Future = ResolveFuture<Self::Response>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let lookup = self.get_host_by_name(name.as_str(), c_ares::AddressFamily::UNSPEC);
+        Box::pin(async move {
+            let results = lookup.await?;
+            Ok(results.to_socket_addrs(0).into_iter())
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use crate::name::InvalidName;
+
+/// How names containing non-ASCII characters are converted to A-labels - per IDNA - before they
+/// are queried.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IdnaMode {
+    /// Apply the STD3 rules, so that labels may contain only letters, digits and hyphens, and
+    /// check label hyphens and lengths.
+    ///
+    /// Note that this rejects names used by some protocols - eg names containing `_` in SRV and
+    /// TXT lookups.
+    Strict,
+
+    /// Convert any name that can be converted.
+    #[default]
+    Lenient,
+}
+
+// Convert `name` to the ASCII form to be queried.  ASCII names are left alone - so that they may
+// contain escapes, for instance.
+pub(crate) fn to_ascii(name: &str, mode: IdnaMode) -> Result<String, InvalidName> {
+    if name.is_ascii() {
+        return Ok(name.to_owned());
+    }
+    let (name, root) = match name.strip_suffix('.') {
+        Some(name) => (name, "."),
+        None => (name, ""),
+    };
+    let ascii = match mode {
+        IdnaMode::Strict => idna::domain_to_ascii_strict(name),
+        IdnaMode::Lenient => idna::domain_to_ascii(name),
+    };
+    let ascii = ascii.map_err(|_| InvalidName::Idna)?;
+    Ok(ascii + root)
+}
+
+/// Convert a name - eg from a PTR or host lookup - to its Unicode form, decoding any A-labels.
+///
+/// Labels that aren't valid A-labels are left as they are.
+pub fn domain_to_unicode(name: &str) -> String {
+    let (unicode, result) = idna::domain_to_unicode(name);
+    if result.is_ok() {
+        return unicode;
+    }
+
+    // Fall back to converting label by label.
+    name.split('.')
+        .map(|label| match idna::domain_to_unicode(label) {
+            (unicode, Ok(())) => unicode,
+            (_, Err(_)) => label.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
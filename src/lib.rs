@@ -11,6 +11,9 @@
 //! - The `BlockingResolver` isn't asynchronous at all - as the name suggests, it blocks until the
 //!   lookup completes.
 //!
+//! A process-wide `FutureResolver` is available from `default_resolver()`, so that libraries can
+//! share one channel and event loop rather than each creating their own.
+//!
 //! On all resolvers:
 //!
 //! - methods like `query_xxx` correspond to the `c-ares` function `ares_query`, which "initiates
@@ -44,20 +47,170 @@
 //! [here](https://github.com/dimbleby/c-ares-resolver/tree/main/examples).
 #![deny(missing_docs)]
 
+// Declared first, so that its macros are available to the other modules.
+#[cfg(cares1_22)]
+#[macro_use]
+mod dnsrec;
+
+#[cfg(feature = "actix")]
+mod actix;
+#[cfg(cares1_22)]
+mod any;
+mod binding;
 mod blockingresolver;
+mod budget;
+mod builder;
+mod capture;
+#[cfg(feature = "serde")]
+mod config;
+pub mod discovery;
+mod dispatch;
+#[cfg(cares1_22)]
+mod dnssec;
+pub mod e164;
+pub mod email;
 mod error;
 mod eventloop;
+mod fallback;
+mod faulty;
 mod futureresolver;
+mod global;
+#[cfg(all(feature = "hickory", cares1_22))]
+mod hickory;
 mod host;
+#[cfg(cares1_22)]
+mod hostswatch;
+#[cfg(feature = "https")]
+mod https;
+#[cfg(feature = "hyper")]
+mod hyper;
+#[cfg(feature = "idna")]
+mod idn;
+mod many;
+#[cfg(feature = "mdns")]
+mod mdns;
+pub mod metrics;
+mod mock;
+mod mx;
+mod name;
 mod nameinfo;
+#[cfg(all(feature = "net-watch", cares1_22))]
+mod netwatch;
+#[cfg(feature = "otel")]
+mod otel;
+mod overrides;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+mod permit;
+mod policy;
+mod priority;
+mod queries;
+mod queryflags;
+mod querylog;
+mod raw;
+mod record;
+mod rejection;
+#[cfg(cares1_24)]
+mod replay;
+#[cfg(feature = "reqwest")]
+mod reqwest;
+mod resolve;
+mod resolved;
 mod resolver;
+#[cfg(cares1_29)]
+mod response;
+mod retry;
+mod routing;
+mod server;
+mod socketaddrs;
+#[cfg(feature = "socks5")]
+mod socks5;
+mod srv;
+#[cfg(cares1_22)]
+mod sshfp;
+#[cfg(cares1_22)]
+mod svcb;
+pub mod testing;
+mod timer;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(cares1_22)]
+mod tlsa;
+#[cfg(feature = "tower")]
+mod tower;
+mod transport;
+#[cfg(feature = "thread-tuning")]
+mod tuning;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+mod watch;
 
 #[cfg(test)]
 mod tests;
 
+#[cfg(cares1_22)]
+pub use crate::any::{AnyRecord, AnyResult, AnyResults};
+pub use crate::binding::LocalBinding;
 pub use crate::blockingresolver::BlockingResolver;
+pub use crate::builder::ResolverBuilder;
+pub use crate::capture::{Direction, Packet, Protocol};
+#[cfg(feature = "serde")]
+pub use crate::config::ResolverConfig;
+#[cfg(cares1_22)]
+pub use crate::dnssec::{
+    DNSKEYResult, DNSKEYResults, DSResult, DSResults, RRSIGResult, RRSIGResults,
+};
 pub use crate::error::Error;
+pub use crate::fallback::{FallbackFuture, FallbackResolver, FallbackResult};
+pub use crate::faulty::FaultyResolver;
 pub use crate::futureresolver::{CAresFuture, FutureResolver};
+pub use crate::global::{default_resolver, set_default_resolver};
 pub use crate::host::HostResults;
-pub use crate::nameinfo::NameInfoResult;
+#[cfg(feature = "https")]
+pub use crate::https::HttpsTransport;
+#[cfg(feature = "idna")]
+pub use crate::idn::{domain_to_unicode, IdnaMode};
+pub use crate::many::ResolveMany;
+#[cfg(feature = "mdns")]
+pub use crate::mdns::MdnsTransport;
+pub use crate::metrics::LoadStats;
+pub use crate::mock::MockResolver;
+pub use crate::mx::MailExchange;
+pub use crate::name::{validate_name, InvalidName};
+pub use crate::nameinfo::{NameInfoOptions, NameInfoResult};
+pub use crate::permit::{AcquirePermit, Permit};
+pub use crate::policy::{Decision, QueryInfo};
+pub use crate::priority::Priority;
+pub use crate::queries::Queries;
+pub use crate::queryflags::QueryFlags;
+pub use crate::querylog::QueryLogEntry;
+pub use crate::raw::{MessageHeader, RawResponse};
+pub use crate::record::{DnsClass, RecordType};
+#[cfg(cares1_24)]
+pub use crate::replay::RecordingMode;
+pub use crate::resolve::{BlockingResolve, Resolve, ResolveFuture};
+pub use crate::resolved::ResolvedConfig;
 pub use crate::resolver::{Options, Resolver};
+#[cfg(cares1_29)]
+pub use crate::response::QueryResponse;
+pub use crate::retry::{RetryBudget, RetryPolicy};
+pub use crate::routing::RoutingResolver;
+pub use crate::server::{Server, ServerInfo, ServerStats};
+pub use crate::socketaddrs::CAresToSocketAddrs;
+#[cfg(feature = "socks5")]
+pub use crate::socks5::Socks5Transport;
+pub use crate::srv::{SrvEndpoint, SrvSelector, SrvTarget};
+#[cfg(cares1_22)]
+pub use crate::sshfp::{SSHFPResult, SSHFPResults};
+#[cfg(cares1_22)]
+pub use crate::svcb::{HTTPSResult, HTTPSResults};
+#[cfg(feature = "tls")]
+pub use crate::tls::TlsTransport;
+#[cfg(cares1_22)]
+pub use crate::tlsa::{TLSAResult, TLSAResults};
+#[cfg(feature = "tower")]
+pub use crate::tower::Name;
+#[cfg(cares1_24)]
+pub use crate::transport::LocalNames;
+pub use crate::transport::Transport;
+pub use crate::watch::WatchA;
@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_core::Stream;
+
+use crate::budget::Charge;
+use crate::futureresolver::FutureResolver;
+use crate::host::HostResults;
+
+type Lookup = (String, c_ares::Result<HostResults>);
+
+// A lookup awaiting consumption, with its charge to the resolver's memory budget.
+type Buffered = (Lookup, Option<Charge>);
+
+/// The type of stream returned by `FutureResolver::resolve_many()` and
+/// `FutureResolver::resolve_many_limited()`, yielding each name with the result of looking it up.
+///
+/// Results are yielded in the order in which the lookups complete.  The stream ends once every
+/// name has been looked up.
+#[must_use = "streams do nothing unless polled"]
+pub struct ResolveMany {
+    resolver: FutureResolver,
+    family: c_ares::AddressFamily,
+    pending: VecDeque<String>,
+    in_flight: usize,
+    max_in_flight: usize,
+    sender: mpsc::UnboundedSender<Buffered>,
+    results: mpsc::UnboundedReceiver<Buffered>,
+}
+
+impl ResolveMany {
+    pub(crate) fn new<I>(
+        resolver: FutureResolver,
+        names: I,
+        family: c_ares::AddressFamily,
+        max_in_flight: usize,
+    ) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let (sender, results) = mpsc::unbounded();
+        let mut many = Self {
+            resolver,
+            family,
+            pending: names.into_iter().map(Into::into).collect(),
+            in_flight: 0,
+            max_in_flight: max_in_flight.max(1),
+            sender,
+            results,
+        };
+        many.submit();
+        many
+    }
+
+    // Start lookups for pending names, up to the limit.
+    fn submit(&mut self) {
+        while self.in_flight < self.max_in_flight {
+            let Some(name) = self.pending.pop_front() else {
+                break;
+            };
+            self.in_flight += 1;
+            let inner = self.resolver.as_resolver();
+            if let Some(result) = inner.host_override(&name, self.family) {
+                let lookup = (name, result);
+                let charge = inner.memory_budget().charge(lookup_size(&lookup));
+                let _ = self.sender.unbounded_send((lookup, charge));
+            } else {
+                let sender = self.sender.clone();
+                let budget = Arc::clone(inner.memory_budget());
+                inner.get_host_by_name(&name.clone(), self.family, move |result| {
+                    let lookup = (name, result.map(Into::into));
+                    let charge = budget.charge(lookup_size(&lookup));
+                    let _ = sender.unbounded_send((lookup, charge));
+                });
+            }
+        }
+    }
+}
+
+// The approximate memory used by a lookup.
+fn lookup_size(lookup: &Lookup) -> usize {
+    let (ref name, ref result) = *lookup;
+    let results = result.as_ref().map_or(0, HostResults::heap_size);
+    mem::size_of::<Buffered>() + name.len() + results
+}
+
+impl Stream for ResolveMany {
+    type Item = Lookup;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Lookup>> {
+        if self.in_flight == 0 {
+            return Poll::Ready(None);
+        }
+        let lookup = Pin::new(&mut self.results)
+            .poll_next(cx)
+            .map(|buffered| buffered.map(|(lookup, _)| lookup));
+        if let Poll::Ready(Some(_)) = lookup {
+            self.in_flight -= 1;
+            self.submit();
+        }
+        lookup
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.in_flight + self.pending.len();
+        (remaining, Some(remaining))
+    }
+}
@@ -0,0 +1,92 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::transport::Transport;
+
+const MDNS_PORT: u16 = 5353;
+const MDNS_IPV4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_IPV6: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+
+/// A `Transport` that sends DNS queries via multicast DNS (RFC 6762), to the devices on the local
+/// link.
+///
+/// Queries are sent as "legacy unicast" queries - from an ephemeral port, so that responders
+/// answer with a conventional unicast response - and the first response is used.
+///
+/// This is usually used only for names under `.local` - see `Options::set_local_names()`.
+#[derive(Clone, Debug)]
+pub struct MdnsTransport {
+    ipv6: bool,
+    timeout: Duration,
+}
+
+impl Default for MdnsTransport {
+    fn default() -> Self {
+        Self {
+            ipv6: false,
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl MdnsTransport {
+    /// Returns an `MdnsTransport` that queries over IPv4.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Query over IPv6, rather than IPv4.
+    pub fn set_ipv6(&mut self, ipv6: bool) -> &mut Self {
+        self.ipv6 = ipv6;
+        self
+    }
+
+    /// Set how long to wait for a response.  The default is one second.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Transport for MdnsTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        if query.len() < 12 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "query too short",
+            ));
+        }
+        let (local, group): (SocketAddr, SocketAddr) = if self.ipv6 {
+            (
+                (Ipv6Addr::UNSPECIFIED, 0).into(),
+                (MDNS_IPV6, MDNS_PORT).into(),
+            )
+        } else {
+            (
+                (Ipv4Addr::UNSPECIFIED, 0).into(),
+                (MDNS_IPV4, MDNS_PORT).into(),
+            )
+        };
+        let socket = UdpSocket::bind(local)?;
+        if !self.ipv6 {
+            socket.set_multicast_ttl_v4(255)?;
+        }
+        socket.send_to(query, group)?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buffer = [0; 9000];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no mDNS response"));
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            let (length, _) = socket.recv_from(&mut buffer)?;
+            // Ignore anything that isn't a response to this query.
+            if length >= 12 && buffer[..2] == query[..2] && buffer[2] & 0x80 != 0 {
+                return Ok(buffer[..length].to_vec());
+            }
+        }
+    }
+}
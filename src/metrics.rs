@@ -0,0 +1,287 @@
+//! Counters and latency histograms, maintained by each resolver for the queries that it makes.
+//!
+//! `metrics()` on each of the resolvers returns its `Metrics`, and `Metrics::snapshot()` copies them
+//! into a `MetricsSnapshot`: a plain struct that can be passed on to any metrics exporter.
+//!
+//! ```no_run
+//! use c_ares_resolver::BlockingResolver;
+//!
+//! let resolver = BlockingResolver::new().unwrap();
+//! let _ = resolver.query_a("example.com");
+//! let snapshot = resolver.metrics().snapshot();
+//! println!("{} queries, {} errors", snapshot.queries, snapshot.errors.values().sum::<u64>());
+//! ```
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+#[cfg(feature = "otel")]
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "otel")]
+use crate::otel::Instruments;
+
+// The number of one-second buckets over which load is measured.
+const LOAD_WINDOW_SECS: u64 = 60;
+
+// The upper bounds of the latency histogram's buckets, in milliseconds.
+const LATENCY_BOUNDS_MS: [u64; 12] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000];
+
+/// The distribution of the times taken by queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The upper bounds of the buckets, in increasing order.
+    pub bounds: Vec<Duration>,
+
+    /// The number of queries in each bucket: queries that took at most the bucket's bound, and
+    /// more than the previous bucket's.  There is one more count than there are bounds, for queries
+    /// that took longer than the last bound.
+    pub counts: Vec<u64>,
+
+    /// The total number of queries.
+    pub count: u64,
+
+    /// The total time taken by all queries.
+    pub sum: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            bounds: LATENCY_BOUNDS_MS
+                .iter()
+                .map(|&ms| Duration::from_millis(ms))
+                .collect(),
+            counts: vec![0; LATENCY_BOUNDS_MS.len() + 1],
+            count: 0,
+            sum: Duration::ZERO,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, duration: Duration) {
+        let bucket = self.bounds.partition_point(|&bound| bound < duration);
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum += duration;
+    }
+}
+
+/// The metrics gathered by a resolver, as at the time that they were taken.
+///
+/// Only queries for names are counted - not lookups by address.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// The number of queries that have completed.
+    pub queries: u64,
+
+    /// The number of queries that have completed, by type - eg 1, for A records - or `None` for
+    /// host lookups.
+    pub queries_by_type: BTreeMap<Option<u16>, u64>,
+
+    /// The number of queries that have failed, by error.
+    pub errors: BTreeMap<c_ares::Error, u64>,
+
+    /// The number of queries that were answered without contacting a server - from the query
+    /// cache, or from the hosts file or host overrides.
+    ///
+    /// This is told from the server state callback, so is always zero before c-ares 1.29.
+    pub cache_hits: u64,
+
+    /// The number of queries that were sent to a server.
+    pub cache_misses: u64,
+
+    /// The times taken by queries.
+    pub latency: LatencyHistogram,
+}
+
+/// How busy a resolver is - as returned by `load()`.
+///
+/// The figures are gathered without locking, so may be slightly out when queries complete
+/// concurrently.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadStats {
+    /// The number of queries that have been made and not yet completed.
+    pub in_flight: usize,
+
+    /// The number of queries completed per second, over the last minute.
+    pub qps_1m: f64,
+
+    /// The average time taken by the queries completed in the last minute - or zero if there were
+    /// none.
+    pub avg_latency_1m: Duration,
+
+    /// The number of queries waiting to be made - see `Options::set_max_outstanding_queries()`
+    /// and `Options::set_rate_limit()`.
+    ///
+    /// This, the rate limiter's tokens and the memory used are known only to the resolver, so are
+    /// given only by `load()` on a resolver - not by `Metrics::load()`.
+    pub queued: usize,
+
+    /// The number of queries that the rate limiter would allow to be made at once now, if there is
+    /// a rate limit - see `Options::set_rate_limit()`.
+    pub rate_limit_tokens: Option<f64>,
+
+    /// The approximate memory used by queued queries and by buffered `resolve_many()` results, in
+    /// bytes - counted only if there is a memory budget, see `Options::set_memory_budget()`.
+    pub memory_used: usize,
+}
+
+// The queries completed in one second.
+#[derive(Debug, Default)]
+struct LoadBucket {
+    second: AtomicU64,
+    queries: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+// Queries in flight, and the queries completed in each of the last `LOAD_WINDOW_SECS` seconds.
+#[derive(Debug)]
+struct Load {
+    epoch: Instant,
+    in_flight: AtomicUsize,
+    buckets: [LoadBucket; LOAD_WINDOW_SECS as usize],
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            in_flight: AtomicUsize::new(0),
+            buckets: std::array::from_fn(|_| LoadBucket::default()),
+        }
+    }
+}
+
+impl Load {
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_secs()
+    }
+
+    fn record(&self, duration: Duration) {
+        let now = self.now();
+        let bucket = &self.buckets[(now % LOAD_WINDOW_SECS) as usize];
+        let second = bucket.second.load(Ordering::Relaxed);
+        if second != now
+            && bucket
+                .second
+                .compare_exchange(second, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            bucket.queries.store(0, Ordering::Relaxed);
+            bucket.latency_micros.store(0, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        bucket.queries.fetch_add(1, Ordering::Relaxed);
+        bucket.latency_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> LoadStats {
+        let now = self.now();
+        let (queries, latency_micros) = self
+            .buckets
+            .iter()
+            .filter(|bucket| now - bucket.second.load(Ordering::Relaxed) < LOAD_WINDOW_SECS)
+            .fold((0, 0), |(queries, latency), bucket| {
+                (
+                    queries + bucket.queries.load(Ordering::Relaxed),
+                    latency + bucket.latency_micros.load(Ordering::Relaxed),
+                )
+            });
+        let avg_latency_1m = latency_micros
+            .checked_div(queries)
+            .map_or(Duration::ZERO, Duration::from_micros);
+        LoadStats {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            qps_1m: queries as f64 / LOAD_WINDOW_SECS as f64,
+            avg_latency_1m,
+            queued: 0,
+            rate_limit_tokens: None,
+            memory_used: 0,
+        }
+    }
+}
+
+/// The metrics maintained by a resolver.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    inner: Mutex<MetricsSnapshot>,
+
+    // Kept apart from the rest, so that it can be read cheaply and often.
+    load: Load,
+
+    // OpenTelemetry instruments to which queries are also passed, if registered.
+    #[cfg(feature = "otel")]
+    instruments: RwLock<Option<Instruments>>,
+}
+
+impl Metrics {
+    // Note that a query has been made.
+    pub(crate) fn started(&self) {
+        self.load.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Note that a query has finished, whether or not it was recorded.
+    pub(crate) fn finished(&self) {
+        self.load.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Record a completed query.  `cached` is whether it was answered without contacting a server,
+    // if known.
+    pub(crate) fn record(
+        &self,
+        query_type: Option<u16>,
+        error: Option<c_ares::Error>,
+        cached: Option<bool>,
+        duration: Duration,
+    ) {
+        let mut metrics = self.inner.lock().unwrap();
+        metrics.queries += 1;
+        *metrics.queries_by_type.entry(query_type).or_default() += 1;
+        if let Some(error) = error {
+            *metrics.errors.entry(error).or_default() += 1;
+        }
+        match cached {
+            Some(true) => metrics.cache_hits += 1,
+            Some(false) => metrics.cache_misses += 1,
+            None => {}
+        }
+        metrics.latency.record(duration);
+        drop(metrics);
+        self.load.record(duration);
+
+        #[cfg(feature = "otel")]
+        if let Some(instruments) = self.instruments.read().unwrap().as_ref() {
+            instruments.record(query_type, error, cached, duration);
+        }
+    }
+
+    /// Returns a copy of the metrics as they are now.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.inner.lock().unwrap().clone()
+    }
+
+    /// Returns how busy the resolver is now.
+    pub fn load(&self) -> LoadStats {
+        self.load.stats()
+    }
+
+    /// Register counters and a histogram with `meter`, so that queries are also recorded in
+    /// OpenTelemetry:
+    ///
+    /// - `dns.client.queries`, `dns.client.errors`, `dns.client.cache.hits` and
+    ///   `dns.client.cache.misses` count queries, as in `MetricsSnapshot`
+    /// - `dns.client.duration` is the time taken by queries, in seconds.
+    ///
+    /// Each has a `dns.question.type` attribute, except for host lookups; and errors have an
+    /// `error.type` attribute, eg `ENOTFOUND`.
+    ///
+    /// Only queries that complete after registration are recorded.  Registering again replaces
+    /// the instruments.
+    #[cfg(feature = "otel")]
+    pub fn register(&self, meter: &opentelemetry::metrics::Meter) -> &Self {
+        *self.instruments.write().unwrap() = Some(Instruments::new(meter));
+        self
+    }
+}
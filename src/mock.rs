@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+
+use crate::host::HostResults;
+use crate::resolve::{BlockingResolve, Resolve, ResolveFuture};
+
+// The answers programmed for a name.
+#[derive(Clone, Debug, Default)]
+struct Fixture {
+    error: Option<c_ares::Error>,
+    ipv4: Vec<Ipv4Addr>,
+    ipv6: Vec<Ipv6Addr>,
+    txt: Vec<Vec<u8>>,
+    raw: HashMap<u16, Vec<u8>>,
+}
+
+/// A resolver whose answers are programmed in advance, for testing code that uses `Resolve` or
+/// `BlockingResolve` without the network.
+///
+/// Lookups for names with no answers programmed fail with `ENOTFOUND`, and lookups for names with
+/// answers of other types only fail with `ENODATA`.  Names are compared without regard to case or
+/// a trailing dot.
+///
+/// ```
+/// use c_ares_resolver::{BlockingResolve, MockResolver};
+///
+/// let mut resolver = MockResolver::new();
+/// resolver.add_ipv4("example.com", &["192.0.2.1".parse().unwrap()]);
+/// let addresses = resolver.lookup_ipv4("example.com").unwrap();
+/// assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 1)]);
+/// assert_eq!(resolver.lookups(), vec!["example.com".to_owned()]);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockResolver {
+    fixtures: HashMap<String, Fixture>,
+    lookups: Mutex<Vec<String>>,
+}
+
+// The form in which names are compared.
+fn key(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+impl MockResolver {
+    /// Create a new `MockResolver`, with no answers programmed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn fixture(&mut self, name: &str) -> &mut Fixture {
+        self.fixtures.entry(key(name)).or_default()
+    }
+
+    /// Add IPv4 addresses for `name`.
+    pub fn add_ipv4(&mut self, name: &str, addresses: &[Ipv4Addr]) -> &mut Self {
+        self.fixture(name).ipv4.extend_from_slice(addresses);
+        self
+    }
+
+    /// Add IPv6 addresses for `name`.
+    pub fn add_ipv6(&mut self, name: &str, addresses: &[Ipv6Addr]) -> &mut Self {
+        self.fixture(name).ipv6.extend_from_slice(addresses);
+        self
+    }
+
+    /// Add a TXT record for `name`.
+    pub fn add_txt(&mut self, name: &str, text: &[u8]) -> &mut Self {
+        self.fixture(name).txt.push(text.to_owned());
+        self
+    }
+
+    /// Set the raw response to queries for `name` of type `query_type`, as returned by
+    /// `lookup_raw()`.
+    pub fn set_raw(&mut self, name: &str, query_type: u16, response: &[u8]) -> &mut Self {
+        self.fixture(name)
+            .raw
+            .insert(query_type, response.to_owned());
+        self
+    }
+
+    /// Make all lookups for `name` fail with `error` - eg `ETIMEOUT` or `ESERVFAIL`.
+    pub fn set_error(&mut self, name: &str, error: c_ares::Error) -> &mut Self {
+        self.fixture(name).error = Some(error);
+        self
+    }
+
+    /// The names that have been looked up, in order.
+    pub fn lookups(&self) -> Vec<String> {
+        self.lookups.lock().unwrap().clone()
+    }
+
+    // Record a lookup of `name`, and find the answer.
+    fn answer<T, F>(&self, name: &str, select: F) -> c_ares::Result<T>
+    where
+        F: FnOnce(&Fixture) -> Option<T>,
+    {
+        self.lookups.lock().unwrap().push(name.to_owned());
+        let fixture = self
+            .fixtures
+            .get(&key(name))
+            .ok_or(c_ares::Error::ENOTFOUND)?;
+        if let Some(error) = fixture.error {
+            return Err(error);
+        }
+        select(fixture).ok_or(c_ares::Error::ENODATA)
+    }
+}
+
+fn non_empty<T: Clone>(items: &[T]) -> Option<Vec<T>> {
+    (!items.is_empty()).then(|| items.to_vec())
+}
+
+impl BlockingResolve for MockResolver {
+    fn lookup_host(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> c_ares::Result<HostResults> {
+        self.answer(name, |fixture| {
+            let ipv4 = fixture.ipv4.iter().copied().map(IpAddr::V4);
+            let ipv6 = fixture.ipv6.iter().copied().map(IpAddr::V6);
+            let addresses: Vec<IpAddr> = match family {
+                c_ares::AddressFamily::INET => ipv4.collect(),
+                c_ares::AddressFamily::INET6 => ipv6.collect(),
+                _ => ipv4.chain(ipv6).collect(),
+            };
+            non_empty(&addresses).map(|addresses| HostResults {
+                hostname: name.to_owned(),
+                addresses,
+                aliases: Vec::new(),
+            })
+        })
+        .map_err(|error| match error {
+            c_ares::Error::ENODATA => c_ares::Error::ENOTFOUND,
+            error => error,
+        })
+    }
+
+    fn lookup_ipv4(&self, name: &str) -> c_ares::Result<Vec<Ipv4Addr>> {
+        self.answer(name, |fixture| non_empty(&fixture.ipv4))
+    }
+
+    fn lookup_ipv6(&self, name: &str) -> c_ares::Result<Vec<Ipv6Addr>> {
+        self.answer(name, |fixture| non_empty(&fixture.ipv6))
+    }
+
+    fn lookup_txt(&self, name: &str) -> c_ares::Result<Vec<Vec<u8>>> {
+        self.answer(name, |fixture| non_empty(&fixture.txt))
+    }
+
+    fn lookup_raw(&self, name: &str, _dns_class: u16, query_type: u16) -> c_ares::Result<Vec<u8>> {
+        self.answer(name, |fixture| fixture.raw.get(&query_type).cloned())
+    }
+}
+
+impl Resolve for MockResolver {
+    fn lookup_host(&self, name: &str, family: c_ares::AddressFamily) -> ResolveFuture<HostResults> {
+        let result = BlockingResolve::lookup_host(self, name, family);
+        Box::pin(std::future::ready(result))
+    }
+
+    fn lookup_ipv4(&self, name: &str) -> ResolveFuture<Vec<Ipv4Addr>> {
+        let result = BlockingResolve::lookup_ipv4(self, name);
+        Box::pin(std::future::ready(result))
+    }
+
+    fn lookup_ipv6(&self, name: &str) -> ResolveFuture<Vec<Ipv6Addr>> {
+        let result = BlockingResolve::lookup_ipv6(self, name);
+        Box::pin(std::future::ready(result))
+    }
+
+    fn lookup_txt(&self, name: &str) -> ResolveFuture<Vec<Vec<u8>>> {
+        let result = BlockingResolve::lookup_txt(self, name);
+        Box::pin(std::future::ready(result))
+    }
+
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> ResolveFuture<Vec<u8>> {
+        let result = BlockingResolve::lookup_raw(self, name, dns_class, query_type);
+        Box::pin(std::future::ready(result))
+    }
+}
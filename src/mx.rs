@@ -0,0 +1,154 @@
+use std::fmt;
+use std::future::Future;
+use std::net::IpAddr;
+
+use crate::blockingresolver::BlockingResolver;
+use crate::futureresolver::FutureResolver;
+use crate::host::display_list;
+
+/// A host that accepts mail for a domain, as found by `lookup_mx()`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MailExchange {
+    /// The preference of the exchange - lower is to be tried first.
+    pub preference: u16,
+
+    /// The host name of the exchange.
+    pub host: String,
+
+    /// The addresses of the exchange.  These are empty unless addresses were asked for - or the
+    /// exchange is the implicit one.
+    pub addresses: Vec<IpAddr>,
+}
+
+impl MailExchange {
+    fn add_addresses(
+        &mut self,
+        a: c_ares::Result<c_ares::AResults>,
+        aaaa: c_ares::Result<c_ares::AAAAResults>,
+    ) {
+        if let Ok(a) = a {
+            self.addresses
+                .extend(a.iter().map(|result| IpAddr::V4(result.ipv4())));
+        }
+        if let Ok(aaaa) = aaaa {
+            self.addresses
+                .extend(aaaa.iter().map(|result| IpAddr::V6(result.ipv6())));
+        }
+    }
+}
+
+/// Eg `Host: mail.example.com, Preference: 10, Addresses: [192.0.2.25]`.
+impl fmt::Display for MailExchange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Host: {}, Preference: {}, ", self.host, self.preference)?;
+        write!(f, "Addresses: ")?;
+        display_list(f, &self.addresses)
+    }
+}
+
+// The exchanges named by MX records, in order of preference and with duplicates removed.  A single
+// record with target `.` - a null MX, per RFC 7505 - means that the domain accepts no mail.
+fn exchanges(mx: &c_ares::MXResults) -> Vec<MailExchange> {
+    let mut exchanges: Vec<MailExchange> = mx
+        .iter()
+        .map(|result| MailExchange {
+            preference: result.priority(),
+            host: result.host().to_owned(),
+            addresses: Vec::new(),
+        })
+        .collect();
+    if let [exchange] = exchanges.as_slice() {
+        if exchange.host.is_empty() || exchange.host == "." {
+            exchanges.clear();
+        }
+    }
+    exchanges.sort_by_key(|exchange| exchange.preference);
+    let mut seen: Vec<String> = Vec::new();
+    exchanges.retain(|exchange| {
+        let host = exchange.host.to_ascii_lowercase();
+        let new = !seen.contains(&host);
+        seen.push(host);
+        new
+    });
+    exchanges
+}
+
+// Per RFC 5321, a domain with no MX records is its own exchange - if it has addresses.
+fn implicit(
+    domain: &str,
+    a: c_ares::Result<c_ares::AResults>,
+    aaaa: c_ares::Result<c_ares::AAAAResults>,
+) -> c_ares::Result<Vec<MailExchange>> {
+    let a_error = a.as_ref().err().copied();
+    let mut exchange = MailExchange {
+        preference: 0,
+        host: domain.to_owned(),
+        addresses: Vec::new(),
+    };
+    exchange.add_addresses(a, aaaa);
+    if exchange.addresses.is_empty() {
+        return Err(a_error.unwrap_or(c_ares::Error::ENODATA));
+    }
+    Ok(vec![exchange])
+}
+
+pub(crate) fn lookup(
+    resolver: FutureResolver,
+    domain: &str,
+    resolve: bool,
+) -> impl Future<Output = c_ares::Result<Vec<MailExchange>>> + Send {
+    let domain = domain.to_owned();
+    async move {
+        let mx = match resolver.query_mx(&domain).await {
+            Ok(mx) => mx,
+            Err(c_ares::Error::ENODATA) => {
+                let a = resolver.query_a(&domain);
+                let aaaa = resolver.query_aaaa(&domain);
+                return implicit(&domain, a.await, aaaa.await);
+            }
+            Err(error) => return Err(error),
+        };
+        let mut exchanges = exchanges(&mx);
+        if resolve {
+            // Make all of the queries before waiting for any of them.
+            let lookups: Vec<_> = exchanges
+                .iter()
+                .map(|exchange| {
+                    let a = resolver.query_a(&exchange.host);
+                    let aaaa = resolver.query_aaaa(&exchange.host);
+                    (a, aaaa)
+                })
+                .collect();
+            for (exchange, (a, aaaa)) in exchanges.iter_mut().zip(lookups) {
+                exchange.add_addresses(a.await, aaaa.await);
+            }
+        }
+        Ok(exchanges)
+    }
+}
+
+pub(crate) fn lookup_blocking(
+    resolver: &BlockingResolver,
+    domain: &str,
+    resolve: bool,
+) -> c_ares::Result<Vec<MailExchange>> {
+    let mx = match resolver.query_mx(domain) {
+        Ok(mx) => mx,
+        Err(c_ares::Error::ENODATA) => {
+            let a = resolver.query_a(domain);
+            let aaaa = resolver.query_aaaa(domain);
+            return implicit(domain, a, aaaa);
+        }
+        Err(error) => return Err(error),
+    };
+    let mut exchanges = exchanges(&mx);
+    if resolve {
+        for exchange in &mut exchanges {
+            let a = resolver.query_a(&exchange.host);
+            let aaaa = resolver.query_aaaa(&exchange.host);
+            exchange.add_addresses(a, aaaa);
+        }
+    }
+    Ok(exchanges)
+}
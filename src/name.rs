@@ -0,0 +1,139 @@
+use std::error;
+use std::fmt;
+
+use crate::error::Error;
+
+// Limits on names, in octets, per RFC 1035.
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// The reasons that a name can be rejected, before making any query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidName {
+    /// The name contains a NUL character.
+    ContainsNul,
+
+    /// The name is too long: its encoding would take this many octets, where at most 255 are
+    /// allowed.
+    TooLong(usize),
+
+    /// The name has an empty label - eg `example..com`.
+    EmptyLabel,
+
+    /// A label - given here - is longer than 63 octets.
+    LabelTooLong(String),
+
+    /// The name contains an incomplete escape sequence - eg it ends with `\`.
+    BadEscape,
+
+    /// The name contains non-ASCII characters, but can't be converted to A-labels.
+    #[cfg(feature = "idna")]
+    Idna,
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::ContainsNul => write!(f, "name contains a NUL character"),
+            Self::TooLong(length) => write!(
+                f,
+                "name is {length} octets long, more than the maximum of {MAX_NAME_LENGTH}"
+            ),
+            Self::EmptyLabel => write!(f, "name contains an empty label"),
+            Self::LabelTooLong(ref label) => write!(
+                f,
+                "label '{label}' is longer than the maximum of {MAX_LABEL_LENGTH} octets"
+            ),
+            Self::BadEscape => write!(f, "name contains an incomplete escape sequence"),
+            #[cfg(feature = "idna")]
+            Self::Idna => write!(f, "name is not a valid internationalized domain name"),
+        }
+    }
+}
+
+impl error::Error for InvalidName {}
+
+/// Check that `name` can be queried.
+///
+/// The resolvers make this check before each query, failing queries for invalid names with
+/// `EBADNAME` - which doesn't say what is wrong.  This function gives the details, as does
+/// `try_query()` on each resolver: eg `Resolver::try_query()`.
+///
+/// Names may use the escapes `\X` and `\DDD`, as in zone files.  A trailing `.` is allowed, as is
+/// the root name on its own.
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    check_name(name).map_err(Error::InvalidName)
+}
+
+pub(crate) fn check_name(name: &str) -> Result<(), InvalidName> {
+    if name.contains('\0') {
+        return Err(InvalidName::ContainsNul);
+    }
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return Ok(());
+    }
+
+    // Each label takes its length plus one octet, and the root label takes one more.
+    let mut length = 1;
+    for label in split_labels(name)? {
+        let octets = label_length(label)?;
+        if octets == 0 {
+            return Err(InvalidName::EmptyLabel);
+        }
+        if octets > MAX_LABEL_LENGTH {
+            return Err(InvalidName::LabelTooLong(label.to_owned()));
+        }
+        length += octets + 1;
+    }
+    if length > MAX_NAME_LENGTH {
+        return Err(InvalidName::TooLong(length));
+    }
+    Ok(())
+}
+
+// Split a name into labels, at unescaped dots.
+fn split_labels(name: &str) -> Result<Vec<&str>, InvalidName> {
+    let mut labels = Vec::new();
+    let mut start = 0;
+    let mut bytes = name.bytes().enumerate();
+    while let Some((index, byte)) = bytes.next() {
+        match byte {
+            b'\\' => {
+                bytes.next().ok_or(InvalidName::BadEscape)?;
+            }
+            b'.' => {
+                labels.push(&name[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    labels.push(&name[start..]);
+    Ok(labels)
+}
+
+// The number of octets in a label, after unescaping.
+fn label_length(label: &str) -> Result<usize, InvalidName> {
+    let bytes = label.as_bytes();
+    let mut octets = 0;
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\' {
+            let digits = bytes[index + 1..]
+                .iter()
+                .take(3)
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
+            match digits {
+                0 => index += 2,
+                3 => index += 4,
+                _ => return Err(InvalidName::BadEscape),
+            }
+        } else {
+            index += 1;
+        }
+        octets += 1;
+    }
+    Ok(octets)
+}
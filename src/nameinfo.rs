@@ -1,5 +1,104 @@
+use std::fmt;
+
+use c_ares::NIFlags;
+
+/// Flags for `get_name_info()`, built up without assembling `c_ares::NIFlags` by hand.
+///
+/// By default, both the host and the service are looked up, and neither is required.
+///
+/// ```
+/// use c_ares_resolver::NameInfoOptions;
+///
+/// let mut options = NameInfoOptions::new();
+/// options.set_numeric_service(true).set_dgram(true);
+/// let flags = c_ares::NIFlags::from(&options);
+/// assert!(flags.contains(c_ares::NIFlags::NUMERICSERV | c_ares::NIFlags::UDP));
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct NameInfoOptions {
+    flags: NIFlags,
+}
+
+impl Default for NameInfoOptions {
+    fn default() -> Self {
+        Self {
+            flags: NIFlags::LOOKUPHOST | NIFlags::LOOKUPSERVICE,
+        }
+    }
+}
+
+impl NameInfoOptions {
+    /// Returns a fresh `NameInfoOptions`, looking up both the host and the service.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn set(&mut self, flag: NIFlags, value: bool) -> &mut Self {
+        self.flags.set(flag, value);
+        self
+    }
+
+    /// Set whether to look up the host.  Default `true`.
+    pub fn set_lookup_host(&mut self, lookup_host: bool) -> &mut Self {
+        self.set(NIFlags::LOOKUPHOST, lookup_host)
+    }
+
+    /// Set whether to look up the service.  Default `true`.
+    pub fn set_lookup_service(&mut self, lookup_service: bool) -> &mut Self {
+        self.set(NIFlags::LOOKUPSERVICE, lookup_service)
+    }
+
+    /// Set whether to return the host in numeric form, rather than looking up its name.  Default
+    /// `false`.
+    pub fn set_numeric_host(&mut self, numeric_host: bool) -> &mut Self {
+        self.set(NIFlags::NUMERICHOST, numeric_host)
+    }
+
+    /// Set whether to return the service in numeric form, rather than looking up its name.
+    /// Default `false`.
+    pub fn set_numeric_service(&mut self, numeric_service: bool) -> &mut Self {
+        self.set(NIFlags::NUMERICSERV, numeric_service)
+    }
+
+    /// Set whether to return only the hostname part of the host's name, for local hosts.  Default
+    /// `false`.
+    pub fn set_no_fqdn(&mut self, no_fqdn: bool) -> &mut Self {
+        self.set(NIFlags::NOFQDN, no_fqdn)
+    }
+
+    /// Set whether to fail if the host's name can't be found, rather than returning it in
+    /// numeric form.  Default `false`.
+    pub fn set_name_required(&mut self, name_required: bool) -> &mut Self {
+        self.set(NIFlags::NAMEREQD, name_required)
+    }
+
+    /// Set whether the service is a datagram service - eg so that port 512 is `biff` rather than
+    /// `exec`.  This is `NI_DGRAM`, which `c-ares` calls `NIFlags::UDP`.  Default `false`.
+    pub fn set_dgram(&mut self, dgram: bool) -> &mut Self {
+        self.set(NIFlags::UDP, dgram)
+    }
+
+    /// The flags that these options describe.
+    pub fn flags(&self) -> NIFlags {
+        self.flags
+    }
+}
+
+impl From<NameInfoOptions> for NIFlags {
+    fn from(options: NameInfoOptions) -> Self {
+        options.flags
+    }
+}
+
+impl From<&NameInfoOptions> for NIFlags {
+    fn from(options: &NameInfoOptions) -> Self {
+        options.flags
+    }
+}
+
 /// An owned version of `c_ares::NameInfoResult`.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NameInfoResult {
     /// The node returned by the lookup.
     pub node: Option<String>,
@@ -8,6 +107,16 @@ pub struct NameInfoResult {
     pub service: Option<String>,
 }
 
+/// As for `c_ares::NameInfoResult` - eg `Node: localhost, Service: http`, with `<None>` for a
+/// missing node or service.
+impl fmt::Display for NameInfoResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let node = self.node.as_deref().unwrap_or("<None>");
+        let service = self.service.as_deref().unwrap_or("<None>");
+        write!(f, "Node: {node}, Service: {service}")
+    }
+}
+
 impl From<c_ares::NameInfoResult<'_>> for NameInfoResult {
     fn from(result: c_ares::NameInfoResult) -> Self {
         Self {
@@ -0,0 +1,235 @@
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::sync::Arc;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::thread;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::time::Duration;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use polling::{Event, Events, Poller};
+
+// Changes tend to arrive in bursts - eg an interface coming up gets an address and several routes -
+// so we wait for things to settle before reporting them.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const SOCKET_KEY: usize = 0;
+
+// Watches for changes to the network configuration - addresses and routes - calling `on_change`
+// after each change.  The watching stops when this is dropped.
+pub(crate) struct NetworkWatcher {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    poller: Arc<Poller>,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    quit: Arc<AtomicBool>,
+}
+
+impl NetworkWatcher {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub(crate) fn start<F>(on_change: F) -> io::Result<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let socket = open_socket()?;
+        let poller = Arc::new(Poller::new()?);
+        unsafe { poller.add(&socket, Event::readable(SOCKET_KEY))? };
+        let quit = Arc::new(AtomicBool::new(false));
+        let watcher = Self {
+            poller: Arc::clone(&poller),
+            quit: Arc::clone(&quit),
+        };
+        thread::spawn(move || {
+            let _ = watch(&socket, &poller, &quit, on_change);
+            let _ = poller.delete(&socket);
+        });
+        Ok(watcher)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub(crate) fn start<F>(_on_change: F) -> io::Result<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "watching for network changes is not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Drop for NetworkWatcher {
+    fn drop(&mut self) {
+        self.quit.store(true, Ordering::Relaxed);
+        let _ = self.poller.notify();
+    }
+}
+
+// Wait for messages on the socket, calling `on_change` once they stop arriving.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn watch<F>(socket: &OwnedFd, poller: &Poller, quit: &AtomicBool, on_change: F) -> io::Result<()>
+where
+    F: Fn(),
+{
+    let mut events = Events::new();
+    let mut pending = false;
+    loop {
+        events.clear();
+        let timeout = pending.then_some(SETTLE_TIME);
+        poller.wait(&mut events, timeout)?;
+        if quit.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        if events.is_empty() {
+            if pending {
+                pending = false;
+                on_change();
+            }
+            continue;
+        }
+        pending |= read_changes(socket)?;
+        poller.modify(socket, Event::readable(SOCKET_KEY))?;
+    }
+}
+
+// Read all waiting messages from the socket, returning whether any reported a change.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn read_changes(socket: &OwnedFd) -> io::Result<bool> {
+    let mut changed = false;
+    let mut buffer = [0u8; 8192];
+    loop {
+        let length = unsafe {
+            libc::recv(
+                socket.as_raw_fd(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+                0,
+            )
+        };
+        if length < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::WouldBlock => Ok(changed),
+                io::ErrorKind::Interrupted => continue,
+
+                // Messages were dropped, so we can't tell what they were: assume the worst.
+                _ if error.raw_os_error() == Some(libc::ENOBUFS) => {
+                    changed = true;
+                    continue;
+                }
+                _ => Err(error),
+            };
+        }
+        changed |= is_change(&buffer[..length as usize]);
+    }
+}
+
+// Open a netlink socket, subscribed to changes to addresses and routes.
+#[cfg(target_os = "linux")]
+fn open_socket() -> io::Result<OwnedFd> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let groups = libc::RTMGRP_IPV4_IFADDR
+        | libc::RTMGRP_IPV6_IFADDR
+        | libc::RTMGRP_IPV4_ROUTE
+        | libc::RTMGRP_IPV6_ROUTE;
+    let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    address.nl_groups = groups as u32;
+    let result = unsafe {
+        libc::bind(
+            fd,
+            std::ptr::addr_of!(address).cast(),
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+// Whether a datagram from the netlink socket contains a message about addresses or routes.
+#[cfg(target_os = "linux")]
+fn is_change(datagram: &[u8]) -> bool {
+    // Each message starts with a header giving its length and type, and is padded to four bytes.
+    let mut remaining = datagram;
+    while remaining.len() >= std::mem::size_of::<libc::nlmsghdr>() {
+        let length = u32::from_ne_bytes([remaining[0], remaining[1], remaining[2], remaining[3]]);
+        let message_type = u16::from_ne_bytes([remaining[4], remaining[5]]);
+        if matches!(
+            message_type,
+            libc::RTM_NEWADDR | libc::RTM_DELADDR | libc::RTM_NEWROUTE | libc::RTM_DELROUTE
+        ) {
+            return true;
+        }
+        let length = (length as usize + 3) & !3;
+        if length == 0 || length > remaining.len() {
+            break;
+        }
+        remaining = &remaining[length..];
+    }
+    false
+}
+
+// Open a routing socket, which reports changes to addresses, interfaces and routes.
+//
+// This sees the same changes that the SystemConfiguration framework reports - without needing to
+// link against it, or to run a CFRunLoop.
+#[cfg(target_os = "macos")]
+fn open_socket() -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::socket(libc::PF_ROUTE, libc::SOCK_RAW, libc::AF_UNSPEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+    let result = unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK)
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+// Whether a message from the routing socket is about addresses, interfaces or routes.
+#[cfg(target_os = "macos")]
+fn is_change(message: &[u8]) -> bool {
+    // Message types, from <net/route.h>: not all are provided by `libc`.
+    const RTM_ADD: libc::c_int = 0x1;
+    const RTM_DELETE: libc::c_int = 0x2;
+    const RTM_CHANGE: libc::c_int = 0x3;
+
+    // Each message is a single datagram, starting with its length, version and type.
+    let Some(&message_type) = message.get(3) else {
+        return false;
+    };
+    let message_type = libc::c_int::from(message_type);
+    matches!(
+        message_type,
+        RTM_ADD
+            | RTM_DELETE
+            | RTM_CHANGE
+            | libc::RTM_NEWADDR
+            | libc::RTM_DELADDR
+            | libc::RTM_IFINFO
+    )
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use opentelemetry::metrics::{Counter, Histogram, Meter};
+use opentelemetry::KeyValue;
+
+// The bucket boundaries for the duration histogram, in seconds - as for `LatencyHistogram`.
+const DURATION_BOUNDARIES: [f64; 12] = [
+    0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0,
+];
+
+// The instruments to which a resolver's metrics are passed on.
+#[derive(Debug)]
+pub(crate) struct Instruments {
+    queries: Counter<u64>,
+    errors: Counter<u64>,
+    cache_hits: Counter<u64>,
+    cache_misses: Counter<u64>,
+    duration: Histogram<f64>,
+}
+
+impl Instruments {
+    pub(crate) fn new(meter: &Meter) -> Self {
+        Self {
+            queries: meter
+                .u64_counter("dns.client.queries")
+                .with_description("Completed DNS queries")
+                .with_unit("{query}")
+                .build(),
+            errors: meter
+                .u64_counter("dns.client.errors")
+                .with_description("Failed DNS queries")
+                .with_unit("{query}")
+                .build(),
+            cache_hits: meter
+                .u64_counter("dns.client.cache.hits")
+                .with_description("DNS queries answered without contacting a server")
+                .with_unit("{query}")
+                .build(),
+            cache_misses: meter
+                .u64_counter("dns.client.cache.misses")
+                .with_description("DNS queries sent to a server")
+                .with_unit("{query}")
+                .build(),
+            duration: meter
+                .f64_histogram("dns.client.duration")
+                .with_description("Time taken by DNS queries")
+                .with_unit("s")
+                .with_boundaries(DURATION_BOUNDARIES.to_vec())
+                .build(),
+        }
+    }
+
+    pub(crate) fn record(
+        &self,
+        query_type: Option<u16>,
+        error: Option<c_ares::Error>,
+        cached: Option<bool>,
+        duration: Duration,
+    ) {
+        // Host lookups may query several types, so have no type attribute.
+        let attributes: Vec<KeyValue> = query_type
+            .map(|query_type| KeyValue::new("dns.question.type", i64::from(query_type)))
+            .into_iter()
+            .collect();
+        self.queries.add(1, &attributes);
+        if let Some(error) = error {
+            let mut attributes = attributes.clone();
+            attributes.push(KeyValue::new("error.type", format!("{error:?}")));
+            self.errors.add(1, &attributes);
+        }
+        match cached {
+            Some(true) => self.cache_hits.add(1, &attributes),
+            Some(false) => self.cache_misses.add(1, &attributes),
+            None => {}
+        }
+        self.duration.record(duration.as_secs_f64(), &attributes);
+    }
+}
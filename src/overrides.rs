@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(cares1_19)]
+use std::fs::{self, File, OpenOptions};
+#[cfg(cares1_19)]
+use std::io::{self, Seek, Write};
+use std::net::IpAddr;
+#[cfg(unix)]
+#[cfg(cares1_19)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(cares1_19)]
+use std::path::PathBuf;
+#[cfg(cares1_19)]
+use std::sync::atomic::{self, AtomicU64};
+#[cfg(cares1_19)]
+use std::sync::Mutex;
+use std::sync::RwLock;
+#[cfg(cares1_19)]
+use std::time::SystemTime;
+#[cfg(cares1_19)]
+use std::{env, process};
+
+use crate::host::HostResults;
+
+// Addresses pinned to names at runtime, consulted before making queries.
+#[derive(Default)]
+pub(crate) struct HostOverrides {
+    hosts: RwLock<HashMap<String, Vec<IpAddr>>>,
+
+    // Counts changes to the overrides - under the lock - so that the hosts file can tell whether
+    // it is up to date.
+    #[cfg(cares1_19)]
+    generation: AtomicU64,
+
+    // The hosts file from which host lookups are answered, created on first use.
+    #[cfg(cares1_19)]
+    hosts_file: Mutex<Option<HostsFile>>,
+}
+
+impl HostOverrides {
+    pub(crate) fn add(&self, name: &str, addresses: &[IpAddr]) {
+        let mut hosts = self.hosts.write().unwrap();
+        hosts.insert(normalize(name), addresses.to_vec());
+        self.changed();
+    }
+
+    pub(crate) fn remove(&self, name: &str) -> bool {
+        let mut hosts = self.hosts.write().unwrap();
+        let removed = hosts.remove(&normalize(name)).is_some();
+        self.changed();
+        removed
+    }
+
+    pub(crate) fn clear(&self) {
+        self.hosts.write().unwrap().clear();
+        self.changed();
+    }
+
+    // Note that the overrides have changed.
+    fn changed(&self) {
+        #[cfg(cares1_19)]
+        self.generation.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    // Whether `name` has been overridden.
+    #[cfg(cares1_19)]
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    // The addresses pinned to `name`, if it has been overridden.
+    fn get(&self, name: &str) -> Option<Vec<IpAddr>> {
+        let hosts = self.hosts.read().unwrap();
+        if hosts.is_empty() {
+            return None;
+        }
+        hosts.get(&normalize(name)).cloned()
+    }
+
+    // The answer to an A query for `name`, if it has been overridden.
+    pub(crate) fn a(&self, name: &str) -> Option<c_ares::Result<c_ares::AResults>> {
+        let addresses = self.get(name)?;
+        let rdata = addresses.iter().filter_map(|address| match address {
+            IpAddr::V4(address) => Some(address.octets().to_vec()),
+            IpAddr::V6(_) => None,
+        });
+        let response = response(name, 1, rdata.collect());
+        Some(c_ares::AResults::parse_from(&response))
+    }
+
+    // The answer to an AAAA query for `name`, if it has been overridden.
+    pub(crate) fn aaaa(&self, name: &str) -> Option<c_ares::Result<c_ares::AAAAResults>> {
+        let addresses = self.get(name)?;
+        let rdata = addresses.iter().filter_map(|address| match address {
+            IpAddr::V4(_) => None,
+            IpAddr::V6(address) => Some(address.octets().to_vec()),
+        });
+        let response = response(name, 28, rdata.collect());
+        Some(c_ares::AAAAResults::parse_from(&response))
+    }
+
+    // The addresses of the given family pinned to `name`, if it has been overridden.
+    fn addresses(&self, name: &str, family: c_ares::AddressFamily) -> Option<Vec<IpAddr>> {
+        let mut addresses = self.get(name)?;
+        addresses.retain(|address| match family {
+            c_ares::AddressFamily::INET => address.is_ipv4(),
+            c_ares::AddressFamily::INET6 => address.is_ipv6(),
+            _ => true,
+        });
+        Some(addresses)
+    }
+
+    // The result of a host lookup for `name`, if it has been overridden.
+    pub(crate) fn host(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> Option<c_ares::Result<HostResults>> {
+        let addresses = self.addresses(name, family)?;
+        if addresses.is_empty() {
+            return Some(Err(c_ares::Error::ENODATA));
+        }
+        Some(Ok(HostResults {
+            hostname: name.to_owned(),
+            addresses,
+            aliases: Vec::new(),
+        }))
+    }
+
+    // Answer a host lookup for `name` with `handler`, if it has been overridden; else give
+    // `handler` back.  `handler` is called before this returns.
+    //
+    // `c-ares` gives no way to construct its host results, so they come from a lookup in a hosts
+    // file holding the overrides.  Other lookups wait while `handler` runs.
+    #[cfg(cares1_19)]
+    pub(crate) fn get_host_by_name<F>(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+        handler: F,
+    ) -> Result<(), F>
+    where
+        F: FnOnce(c_ares::Result<c_ares::HostResults>) + Send + 'static,
+    {
+        let Some(addresses) = self.addresses(name, family) else {
+            return Err(handler);
+        };
+        if addresses.is_empty() {
+            handler(Err(c_ares::Error::ENODATA));
+            return Ok(());
+        }
+        let name = normalize(name);
+        if !is_hosts_name(&name) {
+            handler(Err(c_ares::Error::EBADNAME));
+            return Ok(());
+        }
+        let mut hosts_file = self.hosts_file.lock().unwrap();
+        if hosts_file.is_none() {
+            match HostsFile::create() {
+                Ok(created) => *hosts_file = Some(created),
+                Err(_) => {
+                    handler(Err(c_ares::Error::EFILE));
+                    return Ok(());
+                }
+            }
+        }
+        let hosts_file = hosts_file.as_mut().unwrap();
+        if let Err(error) = self.update(hosts_file) {
+            handler(Err(error));
+            return Ok(());
+        }
+        hosts_file.channel.get_host_by_name(&name, family, handler);
+        Ok(())
+    }
+
+    // Rewrite the hosts file with the current overrides, if they have changed since it was last
+    // written.
+    #[cfg(cares1_19)]
+    fn update(&self, hosts_file: &mut HostsFile) -> c_ares::Result<()> {
+        let hosts = self.hosts.read().unwrap();
+        let generation = self.generation.load(atomic::Ordering::Relaxed);
+        if hosts_file.generation == Some(generation) {
+            return Ok(());
+        }
+        let contents: String = hosts
+            .iter()
+            .filter(|(name, _)| is_hosts_name(name))
+            .flat_map(|(name, addresses)| {
+                addresses
+                    .iter()
+                    .map(move |address| format!("{address} {name}\n"))
+            })
+            .collect();
+        hosts_file
+            .write(contents.as_bytes())
+            .map_err(|_| c_ares::Error::EFILE)?;
+        hosts_file.generation = Some(generation);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HostOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HostOverrides")
+            .field("hosts", &self.hosts)
+            .finish_non_exhaustive()
+    }
+}
+
+// A private hosts file holding the overrides, and a channel that looks names up only in it.
+#[cfg(cares1_19)]
+struct HostsFile {
+    path: PathBuf,
+    file: File,
+    channel: c_ares::Channel,
+
+    // The generation of the overrides last written, if any.
+    generation: Option<u64>,
+}
+
+#[cfg(cares1_19)]
+impl HostsFile {
+    // Create an empty hosts file, that no-one else can have prepared or can write to.
+    fn create() -> io::Result<Self> {
+        static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+        let (path, file) = loop {
+            let sequence = SEQUENCE.fetch_add(1, atomic::Ordering::Relaxed);
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.subsec_nanos());
+            let path = env::temp_dir().join(format!(
+                "c-ares-resolver-hosts-{}-{sequence}-{nanos}",
+                process::id()
+            ));
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            match options.open(&path) {
+                Ok(file) => break (path, file),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            }
+        };
+        let channel = path
+            .to_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "bad path"))
+            .and_then(|hosts_path| {
+                let mut options = c_ares::Options::new();
+                options
+                    .set_lookups("f")
+                    .set_domains(&[])
+                    .set_hosts_path(hosts_path);
+                c_ares::Channel::with_options(options).map_err(io::Error::other)
+            });
+        let channel = match channel {
+            Ok(channel) => channel,
+            Err(error) => {
+                let _ = fs::remove_file(&path);
+                return Err(error);
+            }
+        };
+        let hosts_file = Self {
+            path,
+            file,
+            channel,
+            generation: None,
+        };
+        Ok(hosts_file)
+    }
+
+    // Replace the contents of the file.  `c-ares` notices by its modification time, which is at
+    // least as late as when it last read the file.
+    fn write(&mut self, contents: &[u8]) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.file.write_all(contents)?;
+        self.file.flush()
+    }
+}
+
+#[cfg(cares1_19)]
+impl Drop for HostsFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Whether `name` can be written to a hosts file.
+#[cfg(cares1_19)]
+fn is_hosts_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '#')
+}
+
+// A name in a form suitable for comparison: lower case, and without any trailing dot.
+fn normalize(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+// Encode a response to a query for `name`, with the given answers.
+fn response(name: &str, rr_type: u16, answers: Vec<Vec<u8>>) -> Vec<u8> {
+    let ancount = u16::try_from(answers.len()).unwrap_or(u16::MAX);
+    let mut response = vec![0, 0, 0x81, 0x80, 0, 1];
+    response.extend_from_slice(&ancount.to_be_bytes());
+    response.extend_from_slice(&[0, 0, 0, 0]);
+    for label in normalize(name).split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        response.push(label.len() as u8);
+        response.extend_from_slice(label);
+    }
+    response.push(0);
+    response.extend_from_slice(&rr_type.to_be_bytes());
+    response.extend_from_slice(&[0, 1]);
+    for rdata in answers.iter().take(usize::from(ancount)) {
+        response.extend_from_slice(&[0xc0, 0x0c]);
+        response.extend_from_slice(&rr_type.to_be_bytes());
+        response.extend_from_slice(&[0, 1, 0, 0, 0, 0]);
+        response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        response.extend_from_slice(rdata);
+    }
+    response
+}
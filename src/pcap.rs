@@ -0,0 +1,229 @@
+//! Writing captured DNS messages to a file in pcap format, for inspection with eg Wireshark.
+//!
+//! `c-ares` doesn't say which local addresses and ports it uses, so the writer makes them up: the
+//! loopback address, and a port derived from the message ID - so that each query and its response
+//! form a conversation of their own.  Messages handed to a `Transport` have no known server, so are
+//! recorded as UDP to the loopback address on port 53.
+//!
+//! ```no_run
+//! use c_ares_resolver::pcap::PcapWriter;
+//! use c_ares_resolver::{BlockingResolver, Options};
+//!
+//! let writer = PcapWriter::create("dns.pcap").unwrap();
+//! let mut options = Options::new();
+//! options.set_packet_capture(writer.into_capture());
+//! let resolver = BlockingResolver::with_options(options).unwrap();
+//! ```
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::capture::{Direction, Packet, Protocol};
+
+// The link type for raw IPv4 and IPv6 packets, with no link-layer header.
+const LINKTYPE_RAW: u32 = 101;
+
+// The protocol numbers in IP headers.
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// Writes DNS messages to a pcap file, wrapped in IP and UDP or TCP headers.
+#[derive(Debug)]
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl PcapWriter<File> {
+    /// Create a file at `path`, and a `PcapWriter` that writes to it.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Self::new(file)
+    }
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Create a `PcapWriter`, writing the pcap file header to `writer` at once.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xa1b2_c3d4_u32.to_le_bytes());
+        header.extend_from_slice(&2_u16.to_le_bytes());
+        header.extend_from_slice(&4_u16.to_le_bytes());
+        header.extend_from_slice(&0_i32.to_le_bytes());
+        header.extend_from_slice(&0_u32.to_le_bytes());
+        header.extend_from_slice(&65535_u32.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self { writer })
+    }
+
+    /// Write a captured message, timestamped now.
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        let frame = frame(packet);
+        let length = u32::try_from(frame.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "packet too long"))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut record = Vec::with_capacity(16 + frame.len());
+        record.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&now.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&frame);
+        self.writer.write_all(&record)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Returns a callback, suitable for `Options::set_packet_capture()`, that writes each message
+    /// with this writer.  Errors are ignored.
+    pub fn into_capture(self) -> impl Fn(&Packet) + Send + Sync + 'static
+    where
+        W: Send + 'static,
+    {
+        let writer = Mutex::new(self);
+        move |packet| {
+            let mut writer = writer.lock().unwrap();
+            let _ = writer.write_packet(packet);
+        }
+    }
+}
+
+// Wrap a message in IP and UDP or TCP headers.
+fn frame(packet: &Packet) -> Vec<u8> {
+    let peer = packet.peer.unwrap_or((Ipv4Addr::LOCALHOST, 53).into());
+    let local_ip = match peer.ip() {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+    };
+    let id = packet
+        .data
+        .get(..2)
+        .map_or(0, |id| u16::from_be_bytes([id[0], id[1]]));
+    let local = SocketAddr::new(local_ip, 49152 + id % 16384);
+    let (source, destination) = match packet.direction {
+        Direction::Outgoing => (local, peer),
+        Direction::Incoming => (peer, local),
+    };
+
+    let (protocol, mut segment) = match packet.protocol.unwrap_or(Protocol::Udp) {
+        Protocol::Udp => (IPPROTO_UDP, udp_segment(source, destination, packet.data)),
+        Protocol::Tcp => (IPPROTO_TCP, tcp_segment(source, destination, packet.data)),
+    };
+    let checksum_offset = if protocol == IPPROTO_UDP { 6 } else { 16 };
+    let checksum = match checksum(&pseudo_header(
+        source.ip(),
+        destination.ip(),
+        protocol,
+        &segment,
+    )) {
+        // For UDP, zero means that there is no checksum.
+        0 if protocol == IPPROTO_UDP => 0xffff,
+        checksum => checksum,
+    };
+    segment[checksum_offset..checksum_offset + 2].copy_from_slice(&checksum.to_be_bytes());
+
+    let mut frame = ip_header(source.ip(), destination.ip(), protocol, segment.len());
+    frame.extend_from_slice(&segment);
+    frame
+}
+
+fn udp_segment(source: SocketAddr, destination: SocketAddr, data: &[u8]) -> Vec<u8> {
+    let mut segment = Vec::with_capacity(8 + data.len());
+    segment.extend_from_slice(&source.port().to_be_bytes());
+    segment.extend_from_slice(&destination.port().to_be_bytes());
+    segment.extend_from_slice(&((8 + data.len()) as u16).to_be_bytes());
+    segment.extend_from_slice(&[0, 0]);
+    segment.extend_from_slice(data);
+    segment
+}
+
+// A single segment carrying the whole message, with its length prefix.  There's no handshake, so
+// the sequence numbers are arbitrary.
+fn tcp_segment(source: SocketAddr, destination: SocketAddr, data: &[u8]) -> Vec<u8> {
+    let mut segment = Vec::with_capacity(22 + data.len());
+    segment.extend_from_slice(&source.port().to_be_bytes());
+    segment.extend_from_slice(&destination.port().to_be_bytes());
+    segment.extend_from_slice(&1_u32.to_be_bytes());
+    segment.extend_from_slice(&1_u32.to_be_bytes());
+    segment.extend_from_slice(&[5 << 4, 0x18]);
+    segment.extend_from_slice(&65535_u16.to_be_bytes());
+    segment.extend_from_slice(&[0, 0, 0, 0]);
+    segment.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    segment.extend_from_slice(data);
+    segment
+}
+
+fn ip_header(source: IpAddr, destination: IpAddr, protocol: u8, length: usize) -> Vec<u8> {
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            let mut header = vec![0x45, 0];
+            header.extend_from_slice(&((20 + length) as u16).to_be_bytes());
+            header.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0]);
+            header.extend_from_slice(&source.octets());
+            header.extend_from_slice(&destination.octets());
+            let checksum = checksum(&header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            header
+        }
+        _ => {
+            let mut header = vec![0x60, 0, 0, 0];
+            header.extend_from_slice(&(length as u16).to_be_bytes());
+            header.extend_from_slice(&[protocol, 64]);
+            header.extend_from_slice(&ipv6_octets(source));
+            header.extend_from_slice(&ipv6_octets(destination));
+            header
+        }
+    }
+}
+
+// The pseudo-header over which UDP and TCP checksums are calculated, followed by the segment.
+fn pseudo_header(source: IpAddr, destination: IpAddr, protocol: u8, segment: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(40 + segment.len());
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            data.extend_from_slice(&source.octets());
+            data.extend_from_slice(&destination.octets());
+            data.extend_from_slice(&[0, protocol]);
+            data.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+        }
+        _ => {
+            data.extend_from_slice(&ipv6_octets(source));
+            data.extend_from_slice(&ipv6_octets(destination));
+            data.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            data.extend_from_slice(&[0, 0, 0, protocol]);
+        }
+    }
+    data.extend_from_slice(segment);
+    data
+}
+
+fn ipv6_octets(address: IpAddr) -> [u8; 16] {
+    match address {
+        IpAddr::V4(address) => address.to_ipv6_mapped().octets(),
+        IpAddr::V6(address) => address.octets(),
+    }
+}
+
+// The Internet checksum (RFC 1071).
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+// The permits held, and the tasks waiting for one.
+#[derive(Default)]
+struct State {
+    held: usize,
+    waiters: Vec<Waker>,
+}
+
+impl State {
+    // Arrange for the task to be woken when a permit is released.
+    fn wait(&mut self, cx: &Context<'_>) {
+        if !self.waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+            self.waiters.push(cx.waker().clone());
+        }
+    }
+}
+
+// Permits to make queries, of which at most `limit` may be held at once - as set by
+// `Options::set_max_in_flight()`.  With no limit, a permit is always available.
+#[derive(Default)]
+pub(crate) struct Permits {
+    limit: Option<usize>,
+    state: Mutex<State>,
+}
+
+impl Permits {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            state: Mutex::default(),
+        }
+    }
+
+    fn available(&self, state: &State) -> bool {
+        self.limit.is_none_or(|limit| state.held < limit)
+    }
+
+    // Whether a permit is available, arranging for the task to be woken when one is released if
+    // not.
+    pub(crate) fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if self.available(&state) {
+            return Poll::Ready(());
+        }
+        state.wait(cx);
+        Poll::Pending
+    }
+
+    // Take a permit if one is available, else arrange for the task to be woken when one is
+    // released.
+    fn poll_acquire(self: &Arc<Self>, cx: &mut Context<'_>) -> Poll<Permit> {
+        let mut state = self.state.lock().unwrap();
+        if self.available(&state) {
+            state.held += 1;
+            let permit = Permit {
+                permits: Arc::clone(self),
+            };
+            return Poll::Ready(permit);
+        }
+        state.wait(cx);
+        Poll::Pending
+    }
+
+    // Release a permit, waking the waiting tasks so that they can compete for it.
+    fn release(&self) {
+        let waiters = {
+            let mut state = self.state.lock().unwrap();
+            state.held -= 1;
+            std::mem::take(&mut state.waiters)
+        };
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+}
+
+/// A permit to make a query, returned by `FutureResolver::acquire_permit()`.
+///
+/// The permit is released when it is dropped: hold it until the query that it was acquired for
+/// completes.
+#[must_use]
+pub struct Permit {
+    permits: Arc<Permits>,
+}
+
+impl fmt::Debug for Permit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Permit").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.permits.release();
+    }
+}
+
+/// The future returned by `FutureResolver::acquire_permit()`, which completes with a `Permit` once
+/// one is available.
+#[must_use]
+pub struct AcquirePermit {
+    permits: Arc<Permits>,
+}
+
+impl AcquirePermit {
+    pub(crate) fn new(permits: Arc<Permits>) -> Self {
+        Self { permits }
+    }
+}
+
+impl fmt::Debug for AcquirePermit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AcquirePermit").finish_non_exhaustive()
+    }
+}
+
+impl Future for AcquirePermit {
+    type Output = Permit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.permits.poll_acquire(cx)
+    }
+}
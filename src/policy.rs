@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+/// A query that is about to be made, as passed to a query policy.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct QueryInfo<'a> {
+    /// The name being queried - as given by the caller.
+    pub name: &'a str,
+
+    /// The type of record being queried - eg 1, for A records - or `None` for a host lookup, which
+    /// may query several types.
+    pub query_type: Option<u16>,
+
+    /// Whether the query is a search - applying the configured search domains.
+    pub search: bool,
+}
+
+/// What a query policy decides should happen to a query.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Decision {
+    /// Make the query.
+    Allow,
+
+    /// Don't make the query: fail it with the given error.
+    Deny(c_ares::Error),
+
+    /// Make the query, but for the given name instead.
+    Rewrite(String),
+}
+
+pub(crate) type QueryPolicy = Arc<dyn Fn(&QueryInfo) -> Decision + Send + Sync>;
@@ -0,0 +1,61 @@
+use std::cell::Cell;
+
+thread_local! {
+    // The priority at which queries made on this thread are dispatched.
+    static CURRENT: Cell<Priority> = const { Cell::new(Priority::Normal) };
+}
+
+/// The priority of a query, deciding the order in which queued queries are made - see
+/// `Options::set_max_outstanding_queries()` and `Options::set_rate_limit()`.
+///
+/// When queries are queued, those of higher priority are made first - so that eg lookups for
+/// user-facing connections aren't held up behind background batch jobs sharing the resolver.
+/// Queries of the same priority are made in the order in which they were made.
+///
+/// Queries are made at `Normal` priority, unless made within `scope()`:
+///
+/// ```no_run
+/// use c_ares_resolver::{FutureResolver, Priority};
+///
+/// let resolver = FutureResolver::new().unwrap();
+/// let query = Priority::High.scope(|| resolver.query_a("example.com"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Priority {
+    /// Made before other queued queries.
+    High,
+
+    /// The default.
+    #[default]
+    Normal,
+
+    /// Made only when no other queries are queued.
+    Low,
+}
+
+impl Priority {
+    /// Call `f`, making any queries that it makes at this priority.
+    ///
+    /// The priority applies to queries made by `f` on this thread.  Queries made later by futures
+    /// as they are polled - eg the address queries of `FutureResolver::lookup_mx()` - are made at
+    /// the priority in force on the thread that polls them.
+    pub fn scope<F, R>(self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        // Restore the previous priority even if `f` panics.
+        struct Restore(Priority);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.set(self.0);
+            }
+        }
+        let _restore = Restore(CURRENT.replace(self));
+        f()
+    }
+
+    /// The priority at which queries made now, on this thread, are made.
+    pub fn current() -> Self {
+        CURRENT.get()
+    }
+}
@@ -0,0 +1,259 @@
+use crate::blockingresolver::BlockingResolver;
+use crate::futureresolver::FutureResolver;
+use crate::record::{DnsClass, RecordType};
+use crate::resolver::Resolver;
+
+#[cfg(cares1_22)]
+use crate::any::AnyResults;
+#[cfg(cares1_22)]
+use crate::dnssec::{DNSKEYResults, DSResults, RRSIGResults};
+#[cfg(cares1_22)]
+use crate::sshfp::SSHFPResults;
+#[cfg(cares1_22)]
+use crate::svcb::HTTPSResults;
+#[cfg(cares1_22)]
+use crate::tlsa::TLSAResults;
+
+mod private {
+    use crate::resolver::Resolver;
+
+    // Gives access to the `Resolver` that does the work, and prevents `Queries` from being
+    // implemented outside the crate.
+    pub trait Sealed {
+        fn resolver(&self) -> &Resolver;
+    }
+}
+
+// Defines the trait's `query_xxx()` and `search_xxx()` methods, calling through to the
+// `Resolver`.
+macro_rules! queries {
+    ($($(#[$meta:meta])* $query:ident, $search:ident, $results:ty;)*) => {
+        /// The `query_xxx()` and `search_xxx()` methods, shared by the `Resolver`, the
+        /// `FutureResolver` and the `BlockingResolver` - so that code making queries can be written
+        /// once, and used with any of them.
+        ///
+        /// The methods take the form of those on the `Resolver`: on completion, `handler` is
+        /// called with the result, on the event loop.  On a `FutureResolver` or a
+        /// `BlockingResolver`, they make the query through the underlying `Resolver` - so never
+        /// block, and are not cancelled by dropping anything.
+        ///
+        /// This trait is sealed: it cannot be implemented outside this crate.
+        pub trait Queries: private::Sealed {
+            $(
+                $(#[$meta])*
+                fn $query<F>(&self, name: &str, handler: F)
+                where
+                    F: FnOnce(c_ares::Result<$results>) + Send + 'static,
+                {
+                    self.resolver().$query(name, handler);
+                }
+
+                $(#[$meta])*
+                fn $search<F>(&self, name: &str, handler: F)
+                where
+                    F: FnOnce(c_ares::Result<$results>) + Send + 'static,
+                {
+                    self.resolver().$search(name, handler);
+                }
+            )*
+
+            /// Initiate a single-question DNS query for `name`, of the given class and type - as by
+            /// `Resolver::query()`.
+            fn query<F>(&self, name: &str, dns_class: impl Into<DnsClass>, query_type: impl Into<RecordType>, handler: F)
+            where
+                F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
+            {
+                self.resolver().query(name, dns_class, query_type, handler);
+            }
+
+            /// Initiate a series of single-question DNS queries for `name`, of the given class and
+            /// type - as by `Resolver::search()`.
+            fn search<F>(&self, name: &str, dns_class: impl Into<DnsClass>, query_type: impl Into<RecordType>, handler: F)
+            where
+                F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
+            {
+                self.resolver().search(name, dns_class, query_type, handler);
+            }
+        }
+    };
+}
+
+queries! {
+    /// Look up the A records associated with `name`.
+    query_a, search_a, c_ares::AResults;
+    /// Look up the AAAA records associated with `name`.
+    query_aaaa, search_aaaa, c_ares::AAAAResults;
+    /// Look up records of any type associated with `name`.
+    #[cfg(cares1_22)]
+    query_any, search_any, AnyResults;
+    /// Look up the CAA records associated with `name`.
+    query_caa, search_caa, c_ares::CAAResults;
+    /// Look up the CNAME records associated with `name`.
+    query_cname, search_cname, c_ares::CNameResults;
+    /// Look up the DNSKEY records associated with `name`.
+    #[cfg(cares1_22)]
+    query_dnskey, search_dnskey, DNSKEYResults;
+    /// Look up the DS records associated with `name`.
+    #[cfg(cares1_22)]
+    query_ds, search_ds, DSResults;
+    /// Look up the HTTPS records associated with `name`.
+    #[cfg(cares1_22)]
+    query_https, search_https, HTTPSResults;
+    /// Look up the MX records associated with `name`.
+    query_mx, search_mx, c_ares::MXResults;
+    /// Look up the NAPTR records associated with `name`.
+    query_naptr, search_naptr, c_ares::NAPTRResults;
+    /// Look up the NS records associated with `name`.
+    query_ns, search_ns, c_ares::NSResults;
+    /// Look up the PTR records associated with `name`.
+    query_ptr, search_ptr, c_ares::PTRResults;
+    /// Look up the RRSIG records associated with `name`.
+    #[cfg(cares1_22)]
+    query_rrsig, search_rrsig, RRSIGResults;
+    /// Look up the SOA record associated with `name`.
+    query_soa, search_soa, c_ares::SOAResult;
+    /// Look up the SRV records associated with `name`.
+    query_srv, search_srv, c_ares::SRVResults;
+    /// Look up the SSHFP records associated with `name`.
+    #[cfg(cares1_22)]
+    query_sshfp, search_sshfp, SSHFPResults;
+    /// Look up the TLSA records associated with `name`.
+    #[cfg(cares1_22)]
+    query_tlsa, search_tlsa, TLSAResults;
+    /// Look up the TXT records associated with `name`.
+    query_txt, search_txt, c_ares::TXTResults;
+    /// Look up the URI records associated with `name`.
+    query_uri, search_uri, c_ares::URIResults;
+}
+
+// Calls `$each!` for each of the `query_xxx()` and `search_xxx()` methods offered by the resolvers
+// that wrap others - eg the `RoutingResolver` - with its doc comment and attributes, its name, and
+// its results wrapped in `$result`.  So each wrapper need list them only once.
+macro_rules! wrapped_queries {
+    ($each:ident, $result:ident) => {
+        $each!(
+            /// Look up the A records associated with `name`.
+            query_a -> $result<c_ares::AResults>
+        );
+        $each!(
+            /// Search for the A records associated with `name`.
+            search_a -> $result<c_ares::AResults>
+        );
+        $each!(
+            /// Look up the AAAA records associated with `name`.
+            query_aaaa -> $result<c_ares::AAAAResults>
+        );
+        $each!(
+            /// Search for the AAAA records associated with `name`.
+            search_aaaa -> $result<c_ares::AAAAResults>
+        );
+        $each!(
+            /// Look up the CAA records associated with `name`.
+            #[cfg(cares1_17)]
+            query_caa -> $result<c_ares::CAAResults>
+        );
+        $each!(
+            /// Search for the CAA records associated with `name`.
+            #[cfg(cares1_17)]
+            search_caa -> $result<c_ares::CAAResults>
+        );
+        $each!(
+            /// Look up the CNAME records associated with `name`.
+            query_cname -> $result<c_ares::CNameResults>
+        );
+        $each!(
+            /// Search for the CNAME records associated with `name`.
+            search_cname -> $result<c_ares::CNameResults>
+        );
+        $each!(
+            /// Look up the MX records associated with `name`.
+            query_mx -> $result<c_ares::MXResults>
+        );
+        $each!(
+            /// Search for the MX records associated with `name`.
+            search_mx -> $result<c_ares::MXResults>
+        );
+        $each!(
+            /// Look up the NAPTR records associated with `name`.
+            query_naptr -> $result<c_ares::NAPTRResults>
+        );
+        $each!(
+            /// Search for the NAPTR records associated with `name`.
+            search_naptr -> $result<c_ares::NAPTRResults>
+        );
+        $each!(
+            /// Look up the NS records associated with `name`.
+            query_ns -> $result<c_ares::NSResults>
+        );
+        $each!(
+            /// Search for the NS records associated with `name`.
+            search_ns -> $result<c_ares::NSResults>
+        );
+        $each!(
+            /// Look up the PTR records associated with `name`.
+            query_ptr -> $result<c_ares::PTRResults>
+        );
+        $each!(
+            /// Search for the PTR records associated with `name`.
+            search_ptr -> $result<c_ares::PTRResults>
+        );
+        $each!(
+            /// Look up the SOA record associated with `name`.
+            query_soa -> $result<c_ares::SOAResult>
+        );
+        $each!(
+            /// Search for the SOA record associated with `name`.
+            search_soa -> $result<c_ares::SOAResult>
+        );
+        $each!(
+            /// Look up the SRV records associated with `name`.
+            query_srv -> $result<c_ares::SRVResults>
+        );
+        $each!(
+            /// Search for the SRV records associated with `name`.
+            search_srv -> $result<c_ares::SRVResults>
+        );
+        $each!(
+            /// Look up the TXT records associated with `name`.
+            query_txt -> $result<c_ares::TXTResults>
+        );
+        $each!(
+            /// Search for the TXT records associated with `name`.
+            search_txt -> $result<c_ares::TXTResults>
+        );
+        $each!(
+            /// Look up the URI records associated with `name`.
+            query_uri -> $result<c_ares::URIResults>
+        );
+        $each!(
+            /// Search for the URI records associated with `name`.
+            search_uri -> $result<c_ares::URIResults>
+        );
+    };
+}
+
+pub(crate) use wrapped_queries;
+
+impl private::Sealed for Resolver {
+    fn resolver(&self) -> &Resolver {
+        self
+    }
+}
+
+impl Queries for Resolver {}
+
+impl private::Sealed for FutureResolver {
+    fn resolver(&self) -> &Resolver {
+        self.as_resolver()
+    }
+}
+
+impl Queries for FutureResolver {}
+
+impl private::Sealed for BlockingResolver {
+    fn resolver(&self) -> &Resolver {
+        self.as_resolver()
+    }
+}
+
+impl Queries for BlockingResolver {}
@@ -0,0 +1,49 @@
+use std::ops::{BitOr, BitOrAssign};
+
+/// Flags that change how a single query is made - see eg `Resolver::search_a_opts()`.
+///
+/// These correspond to settings that are otherwise made for all queries, on the `Options`.  Flags
+/// are combined with `|` - eg `QueryFlags::NO_SEARCH | QueryFlags::NO_CACHE`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct QueryFlags(u8);
+
+impl QueryFlags {
+    /// Query the name as it is, without trying the search domains - as by `Options::no_search()`.
+    pub const NO_SEARCH: Self = Self(1);
+
+    /// Don't apply host aliases - neither those set by `Options::set_host_aliases()`, nor those
+    /// in the file named by the `HOSTALIASES` environment variable.
+    pub const NO_ALIASES: Self = Self(2);
+
+    /// Don't answer the query from the cache, nor cache its answer.
+    pub const NO_CACHE: Self = Self(4);
+
+    /// No flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Whether no flags are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all of the flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for QueryFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for QueryFlags {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
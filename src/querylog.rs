@@ -0,0 +1,163 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use crate::dispatch::Dispatcher;
+use crate::metrics::Metrics;
+
+/// A record of a completed query, as passed to the query log.
+#[derive(Clone, Copy, Debug)]
+pub struct QueryLogEntry<'a> {
+    /// The ID of the query - see `QueryResponse::id`.
+    pub id: u64,
+
+    /// The name that was queried - after any rewriting, and conversion to A-labels.
+    pub name: &'a str,
+
+    /// The type of record that was queried - eg 1, for A records - or `None` for a host lookup,
+    /// which may query several types.
+    pub query_type: Option<u16>,
+
+    /// The error with which the query failed, if it did.
+    pub error: Option<c_ares::Error>,
+
+    /// The response code, where it can be told from the result: eg 0 (NOERROR) for success, or 3
+    /// (NXDOMAIN) for `ENOTFOUND`.  `None` if there was no response, eg on a timeout.
+    pub rcode: Option<u16>,
+
+    /// The time between making the query and its completion.
+    pub duration: Duration,
+
+    /// The server that answered the query, if known - see `QueryResponse::server`.
+    pub server: Option<&'a str>,
+}
+
+pub(crate) type QueryLog = Arc<dyn Fn(&QueryLogEntry) + Send + Sync>;
+
+// The ID for the next query - shared by all resolvers, so that IDs are unique in the process.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // The ID of the query whose handler is being called on this thread, if any.
+    static CURRENT_ID: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// The ID of the query whose handler is being called, if any.
+pub(crate) fn current_id() -> Option<u64> {
+    CURRENT_ID.get()
+}
+
+// A fresh query ID, never handed out before.
+pub(crate) fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Records a single query on completion, in the resolver's metrics and in the query log if there
+// is one.
+pub(crate) struct QueryTracker {
+    id: u64,
+    log: Option<QueryLog>,
+    metrics: Arc<Metrics>,
+    dispatcher: Weak<Dispatcher>,
+    name: String,
+    query_type: Option<u16>,
+    start: Instant,
+    finished: bool,
+}
+
+impl QueryTracker {
+    pub(crate) fn new(
+        log: Option<QueryLog>,
+        metrics: Arc<Metrics>,
+        dispatcher: Weak<Dispatcher>,
+        name: &str,
+        query_type: Option<u16>,
+    ) -> Self {
+        metrics.started();
+        Self {
+            id: next_id(),
+            log,
+            metrics,
+            dispatcher,
+            name: name.to_owned(),
+            query_type,
+            start: Instant::now(),
+            finished: false,
+        }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Note that the query is no longer outstanding, so that a queued query can be made in its
+    // place.
+    fn release(&self) {
+        self.metrics.finished();
+        if let Some(dispatcher) = self.dispatcher.upgrade() {
+            dispatcher.finished(self.id);
+        }
+    }
+
+    // Record the query, then call `handler` with its result.
+    pub(crate) fn finish<F>(mut self, error: Option<c_ares::Error>, handler: F)
+    where
+        F: FnOnce(),
+    {
+        let duration = self.start.elapsed();
+        #[cfg(cares1_29)]
+        let (server, cached) = {
+            let (server, contacted) = crate::response::current_contact();
+            (server, Some(!contacted))
+        };
+        #[cfg(not(cares1_29))]
+        let (server, cached): (Option<String>, Option<bool>) = (None, None);
+        self.release();
+        self.finished = true;
+        self.metrics
+            .record(self.query_type, error, cached, duration);
+        if let Some(log) = &self.log {
+            let entry = QueryLogEntry {
+                id: self.id,
+                name: &self.name,
+                query_type: self.query_type,
+                error,
+                rcode: rcode(error),
+                duration,
+                server: server.as_deref(),
+            };
+            log(&entry);
+        }
+        CURRENT_ID.set(Some(self.id));
+        handler();
+        CURRENT_ID.set(None);
+
+        // Whatever the handler didn't collect is of no use to the next query.
+        #[cfg(cares1_29)]
+        crate::response::clear_contact();
+    }
+}
+
+// A query whose handler is dropped without being called - eg because the resolver is going away -
+// is no longer in flight.
+impl Drop for QueryTracker {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.release();
+        }
+    }
+}
+
+// The response code implied by the result of a query, if any.
+fn rcode(error: Option<c_ares::Error>) -> Option<u16> {
+    match error {
+        None | Some(c_ares::Error::ENODATA) => Some(0),
+        Some(c_ares::Error::EFORMERR) => Some(1),
+        Some(c_ares::Error::ESERVFAIL) => Some(2),
+        Some(c_ares::Error::ENOTFOUND) => Some(3),
+        Some(c_ares::Error::ENOTIMP) => Some(4),
+        Some(c_ares::Error::EREFUSED) => Some(5),
+        Some(_) => None,
+    }
+}
@@ -0,0 +1,102 @@
+/// The header of a DNS message, per RFC 1035 section 4.1.1.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageHeader {
+    /// The ID of the message.
+    pub id: u16,
+
+    /// The kind of query - eg 0, for a standard query.
+    pub opcode: u8,
+
+    /// The response code - eg 2, for SERVFAIL.  This is the four bits in the header, without any
+    /// extension from an OPT record.
+    pub rcode: u8,
+
+    /// Whether the answer is authoritative (AA).
+    pub authoritative: bool,
+
+    /// Whether the message was truncated (TC).
+    pub truncated: bool,
+
+    /// Whether recursion was desired (RD).
+    pub recursion_desired: bool,
+
+    /// Whether recursion is available (RA).
+    pub recursion_available: bool,
+
+    /// Whether the server has authenticated the data (AD).
+    pub authentic_data: bool,
+
+    /// Whether checking was disabled (CD).
+    pub checking_disabled: bool,
+
+    /// The number of entries in the question section.
+    pub question_count: u16,
+
+    /// The number of records in the answer section.
+    pub answer_count: u16,
+
+    /// The number of records in the authority section.
+    pub authority_count: u16,
+
+    /// The number of records in the additional section.
+    pub additional_count: u16,
+}
+
+impl MessageHeader {
+    /// Parse the header at the start of `message` - or return `None` if it is too short to hold
+    /// one.
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        let header = message.get(..12)?;
+        let word = |index: usize| u16::from_be_bytes([header[index], header[index + 1]]);
+        let flags = word(2);
+        let flag = |bit: u16| flags & (1 << bit) != 0;
+        let header = Self {
+            id: word(0),
+            opcode: ((flags >> 11) & 0xf) as u8,
+            rcode: (flags & 0xf) as u8,
+            authoritative: flag(10),
+            truncated: flag(9),
+            recursion_desired: flag(8),
+            recursion_available: flag(7),
+            authentic_data: flag(5),
+            checking_disabled: flag(4),
+            question_count: word(4),
+            answer_count: word(6),
+            authority_count: word(8),
+            additional_count: word(10),
+        };
+        Some(header)
+    }
+}
+
+/// The response to a raw query, with its header parsed - as returned by `query_raw_ext()`.
+///
+/// This is so that code using some other parser for the records can eg notice a truncated answer
+/// without parsing the whole message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawResponse<T = Vec<u8>> {
+    /// The header of the response.
+    pub header: MessageHeader,
+
+    /// The whole response, including the header.
+    pub data: T,
+}
+
+impl<T: AsRef<[u8]>> RawResponse<T> {
+    // Parse the header of `data`, failing if it hasn't got one.
+    pub(crate) fn new(data: T) -> c_ares::Result<Self> {
+        let header = MessageHeader::parse(data.as_ref()).ok_or(c_ares::Error::EBADRESP)?;
+        Ok(Self { header, data })
+    }
+}
+
+impl RawResponse<&[u8]> {
+    // Copy the data, so that the response may outlive it.
+    pub(crate) fn into_owned(self) -> RawResponse {
+        RawResponse {
+            header: self.header,
+            data: self.data.to_vec(),
+        }
+    }
+}
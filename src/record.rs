@@ -0,0 +1,136 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// Defines an enum of well-known values, each with its number and mnemonic, and an `Other` variant
+// for any other number.  Values are compared by number, so that eg `Other(1)` is equal to the
+// variant for 1.
+macro_rules! numbered_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $prefix:literal {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:literal, $mnemonic:literal;)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+
+            /// Any other value.
+            Other(u16),
+        }
+
+        impl From<u16> for $name {
+            fn from(value: u16) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    other => Self::Other(other),
+                }
+            }
+        }
+
+        impl From<$name> for u16 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Other(other) => other,
+                }
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                u16::from(*self) == u16::from(*other)
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                u16::from(*self).hash(state);
+            }
+        }
+
+        /// The mnemonic for the value - or, per RFC 3597, its number with a prefix - eg
+        #[doc = concat!("`", $prefix, "65280`.")]
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match u16::from(*self) {
+                    $($value => write!(f, $mnemonic),)*
+                    other => write!(f, concat!($prefix, "{}"), other),
+                }
+            }
+        }
+    };
+}
+
+numbered_enum! {
+    /// The class of a DNS query, for `query()` and `search()`.
+    ///
+    /// Classes can also be given as numbers, as defined in `arpa/nameser.h`.
+    DnsClass, "CLASS" {
+        /// The internet - almost always the class wanted.
+        In = 1, "IN";
+        /// Chaos - eg for querying a server's version, as `version.bind`.
+        Ch = 3, "CH";
+        /// Hesiod.
+        Hs = 4, "HS";
+        /// No class - used only in updates.
+        None = 254, "NONE";
+        /// Any class.
+        Any = 255, "ANY";
+    }
+}
+
+numbered_enum! {
+    /// The type of a DNS query, for `query()` and `search()`.
+    ///
+    /// Types can also be given as numbers, as defined in `arpa/nameser.h`.
+    RecordType, "TYPE" {
+        /// An IPv4 address.
+        A = 1, "A";
+        /// An authoritative name server.
+        Ns = 2, "NS";
+        /// A canonical name.
+        Cname = 5, "CNAME";
+        /// The start of a zone of authority.
+        Soa = 6, "SOA";
+        /// A domain name pointer.
+        Ptr = 12, "PTR";
+        /// Host information.
+        Hinfo = 13, "HINFO";
+        /// A mail exchange.
+        Mx = 15, "MX";
+        /// Text strings.
+        Txt = 16, "TXT";
+        /// An IPv6 address.
+        Aaaa = 28, "AAAA";
+        /// A service location.
+        Srv = 33, "SRV";
+        /// A naming authority pointer.
+        Naptr = 35, "NAPTR";
+        /// A delegation signer.
+        Ds = 43, "DS";
+        /// An SSH key fingerprint.
+        Sshfp = 44, "SSHFP";
+        /// A DNSSEC signature.
+        Rrsig = 46, "RRSIG";
+        /// The next secure record.
+        Nsec = 47, "NSEC";
+        /// A DNSSEC public key.
+        Dnskey = 48, "DNSKEY";
+        /// A TLS certificate association.
+        Tlsa = 52, "TLSA";
+        /// A service binding.
+        Svcb = 64, "SVCB";
+        /// A service binding for HTTPS.
+        Https = 65, "HTTPS";
+        /// All records.
+        Any = 255, "ANY";
+        /// A URI.
+        Uri = 256, "URI";
+        /// A certification authority authorization.
+        Caa = 257, "CAA";
+    }
+}
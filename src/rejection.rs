@@ -0,0 +1,27 @@
+use std::cell::RefCell;
+
+use crate::error::Error;
+
+thread_local! {
+    // Why the resolver rejected a query made on this thread, within `catch()`.
+    static REJECTION: RefCell<Option<Error>> = const { RefCell::new(None) };
+}
+
+// Note that the resolver has rejected a query made on this thread, without going to `c-ares`, for
+// the reason given by `error`.  The query still fails through its handler, with the nearest
+// `c_ares::Error`: this lets `try_query()` give the reason.
+pub(crate) fn note(error: Error) {
+    REJECTION.with(|rejection| *rejection.borrow_mut() = Some(error));
+}
+
+// Call `f`, returning what it returns together with the reason for which the resolver rejected a
+// query made by it, if it did.
+pub(crate) fn catch<F, R>(f: F) -> (R, Option<Error>)
+where
+    F: FnOnce() -> R,
+{
+    REJECTION.with(|rejection| rejection.borrow_mut().take());
+    let result = f();
+    let rejection = REJECTION.with(|rejection| rejection.borrow_mut().take());
+    (result, rejection)
+}
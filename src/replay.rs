@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::transport::{parse_question, Transport};
+
+/// Whether a resolver records the responses to its queries, or replays responses recorded earlier
+/// - see `Options::set_recording()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RecordingMode {
+    /// Send queries to the servers as usual, writing each response to the file.
+    Record,
+
+    /// Answer queries from the file, without contacting any server.
+    Replay,
+}
+
+const REFUSED: u8 = 5;
+
+// Responses are filed by question: name, type and class.
+type Question = (String, u16, u16);
+
+// The responses recorded for a question, and the index of the next to replay.
+#[derive(Default)]
+struct Replies {
+    responses: Vec<Vec<u8>>,
+    next: usize,
+}
+
+// Each line of a recording holds one response: the question, and the message in hex.
+fn format_line(question: &Question, response: &[u8]) -> String {
+    let (name, query_type, dns_class) = question;
+    let mut line = format!("{name}. {query_type} {dns_class} ");
+    for byte in response {
+        let _ = write!(line, "{byte:02x}");
+    }
+    line.push('\n');
+    line
+}
+
+fn parse_line(line: &str) -> Option<(Question, Vec<u8>)> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?;
+    let name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+    let query_type = fields.next()?.parse().ok()?;
+    let dns_class = fields.next()?.parse().ok()?;
+    let hex = fields.next()?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let response = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(((name, query_type, dns_class), response))
+}
+
+// A transport that passes queries to another, writing the responses to a recording.
+pub(crate) struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    file: Mutex<File>,
+}
+
+impl RecordingTransport {
+    // Start a new recording at `path`, replacing any that is there.
+    pub(crate) fn create(inner: Arc<dyn Transport>, path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let transport = Self {
+            inner,
+            file: Mutex::new(file),
+        };
+        Ok(transport)
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let response = self.inner.exchange(query)?;
+        if let Some((name, query_type, dns_class, _)) = parse_question(query) {
+            let line = format_line(&(name, query_type, dns_class), &response);
+            self.file.lock().unwrap().write_all(line.as_bytes())?;
+        }
+        Ok(response)
+    }
+}
+
+// A transport that answers queries from a recording.  Responses to the same question are replayed
+// in the order in which they were recorded, and the last is repeated.  Questions that weren't
+// recorded are refused.
+pub(crate) struct ReplayTransport {
+    replies: Mutex<HashMap<Question, Replies>>,
+}
+
+impl ReplayTransport {
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut replies: HashMap<Question, Replies> = HashMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (question, response) = parse_line(line).ok_or_else(|| {
+                let message = format!("invalid recording at line {}", number + 1);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+            replies
+                .entry(question)
+                .or_default()
+                .responses
+                .push(response);
+        }
+        let transport = Self {
+            replies: Mutex::new(replies),
+        };
+        Ok(transport)
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let (name, query_type, dns_class, end) = parse_question(query)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid query"))?;
+        let mut replies = self.replies.lock().unwrap();
+        let recorded = replies
+            .get_mut(&(name, query_type, dns_class))
+            .and_then(|replies| {
+                let responses = &replies.responses;
+                let response = responses.get(replies.next).or(responses.last())?.clone();
+                replies.next += 1;
+                Some(response)
+            });
+        drop(replies);
+
+        let response = match recorded {
+            Some(mut response) if response.len() >= 2 => {
+                response[..2].copy_from_slice(&query[..2]);
+                response
+            }
+            _ => {
+                let mut response = query[..end].to_vec();
+                response[2] = 0x80 | (query[2] & 0x79);
+                response[3] = 0x80 | REFUSED;
+                response[6..12].fill(0);
+                response
+            }
+        };
+        Ok(response)
+    }
+}
@@ -0,0 +1,30 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::futureresolver::FutureResolver;
+
+/// A `FutureResolver` can be used as the resolver for a `reqwest` client, in place of the default
+/// resolver - which makes blocking `getaddrinfo` calls on a thread pool.
+///
+/// Names are looked up by `get_host_by_name()`, for both IPv4 and IPv6 addresses.  The addresses
+/// have port 0: `reqwest` sets the port from the URL.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use c_ares_resolver::FutureResolver;
+///
+/// let resolver = Arc::new(FutureResolver::new().unwrap());
+/// let client = reqwest::Client::builder()
+///     .dns_resolver(resolver)
+///     .build()
+///     .unwrap();
+/// ```
+impl Resolve for FutureResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let lookup = self.get_host_by_name(name.as_str(), c_ares::AddressFamily::UNSPEC);
+        Box::pin(async move {
+            let results = lookup.await?;
+            let addresses: Addrs = Box::new(results.to_socket_addrs(0).into_iter());
+            Ok(addresses)
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::pin::Pin;
+
+use crate::blockingresolver::BlockingResolver;
+use crate::futureresolver::FutureResolver;
+use crate::host::HostResults;
+
+/// The type of future returned by methods on `Resolve`.
+pub type ResolveFuture<T> = Pin<Box<dyn Future<Output = c_ares::Result<T>> + Send + 'static>>;
+
+/// The lookups that an asynchronous resolver offers, with owned results - so that code using a
+/// resolver can be written against this trait, and tested with a `MockResolver`.
+///
+/// The trait is object-safe, so can be used as `dyn Resolve`.  It's implemented by
+/// `FutureResolver`: see `BlockingResolve` for the blocking form.
+pub trait Resolve: Send + Sync {
+    /// Look up the addresses for `name`, as by `get_host_by_name()`.
+    fn lookup_host(&self, name: &str, family: c_ares::AddressFamily) -> ResolveFuture<HostResults>;
+
+    /// Look up the IPv4 addresses for `name`, as by `query_a()`.
+    fn lookup_ipv4(&self, name: &str) -> ResolveFuture<Vec<Ipv4Addr>>;
+
+    /// Look up the IPv6 addresses for `name`, as by `query_aaaa()`.
+    fn lookup_ipv6(&self, name: &str) -> ResolveFuture<Vec<Ipv6Addr>>;
+
+    /// Look up the TXT records for `name`, as by `query_txt()`.  Each record's strings are joined.
+    fn lookup_txt(&self, name: &str) -> ResolveFuture<Vec<Vec<u8>>>;
+
+    /// Make a query for `name` of the given class and type, as by `query()`, returning the raw
+    /// response.
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> ResolveFuture<Vec<u8>>;
+}
+
+/// The blocking form of `Resolve`, implemented by `BlockingResolver`.
+pub trait BlockingResolve: Send + Sync {
+    /// Look up the addresses for `name`, as by `get_host_by_name()`.
+    fn lookup_host(&self, name: &str, family: c_ares::AddressFamily)
+        -> c_ares::Result<HostResults>;
+
+    /// Look up the IPv4 addresses for `name`, as by `query_a()`.
+    fn lookup_ipv4(&self, name: &str) -> c_ares::Result<Vec<Ipv4Addr>>;
+
+    /// Look up the IPv6 addresses for `name`, as by `query_aaaa()`.
+    fn lookup_ipv6(&self, name: &str) -> c_ares::Result<Vec<Ipv6Addr>>;
+
+    /// Look up the TXT records for `name`, as by `query_txt()`.  Each record's strings are joined.
+    fn lookup_txt(&self, name: &str) -> c_ares::Result<Vec<Vec<u8>>>;
+
+    /// Make a query for `name` of the given class and type, as by `query()`, returning the raw
+    /// response.
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> c_ares::Result<Vec<u8>>;
+}
+
+fn ipv4_addresses(results: &c_ares::AResults) -> Vec<Ipv4Addr> {
+    results.iter().map(|result| result.ipv4()).collect()
+}
+
+fn ipv6_addresses(results: &c_ares::AAAAResults) -> Vec<Ipv6Addr> {
+    results.iter().map(|result| result.ipv6()).collect()
+}
+
+// `c-ares` returns each of a record's strings separately, marking the first.
+fn txt_records(results: &c_ares::TXTResults) -> Vec<Vec<u8>> {
+    let mut records: Vec<Vec<u8>> = Vec::new();
+    for result in results.iter() {
+        match records.last_mut() {
+            Some(record) if !result.record_start() => record.extend_from_slice(result.text()),
+            _ => records.push(result.text().to_owned()),
+        }
+    }
+    records
+}
+
+impl Resolve for FutureResolver {
+    fn lookup_host(&self, name: &str, family: c_ares::AddressFamily) -> ResolveFuture<HostResults> {
+        Box::pin(self.get_host_by_name(name, family))
+    }
+
+    fn lookup_ipv4(&self, name: &str) -> ResolveFuture<Vec<Ipv4Addr>> {
+        let query = self.query_a(name);
+        Box::pin(async move { query.await.map(|results| ipv4_addresses(&results)) })
+    }
+
+    fn lookup_ipv6(&self, name: &str) -> ResolveFuture<Vec<Ipv6Addr>> {
+        let query = self.query_aaaa(name);
+        Box::pin(async move { query.await.map(|results| ipv6_addresses(&results)) })
+    }
+
+    fn lookup_txt(&self, name: &str) -> ResolveFuture<Vec<Vec<u8>>> {
+        let query = self.query_txt(name);
+        Box::pin(async move { query.await.map(|results| txt_records(&results)) })
+    }
+
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> ResolveFuture<Vec<u8>> {
+        Box::pin(self.query(name, dns_class, query_type))
+    }
+}
+
+impl BlockingResolve for BlockingResolver {
+    fn lookup_host(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> c_ares::Result<HostResults> {
+        self.get_host_by_name(name, family)
+    }
+
+    fn lookup_ipv4(&self, name: &str) -> c_ares::Result<Vec<Ipv4Addr>> {
+        self.query_a(name).map(|results| ipv4_addresses(&results))
+    }
+
+    fn lookup_ipv6(&self, name: &str) -> c_ares::Result<Vec<Ipv6Addr>> {
+        self.query_aaaa(name)
+            .map(|results| ipv6_addresses(&results))
+    }
+
+    fn lookup_txt(&self, name: &str) -> c_ares::Result<Vec<Vec<u8>>> {
+        self.query_txt(name).map(|results| txt_records(&results))
+    }
+
+    fn lookup_raw(&self, name: &str, dns_class: u16, query_type: u16) -> c_ares::Result<Vec<u8>> {
+        self.query(name, dns_class, query_type)
+    }
+}
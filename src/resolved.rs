@@ -0,0 +1,152 @@
+use std::{env, fs};
+
+use crate::resolver::Options;
+
+// The defaults that `c-ares` uses, where neither the options nor system configuration say
+// otherwise.
+pub(crate) const DEFAULT_TIMEOUT: u32 = 2000;
+const DEFAULT_TRIES: u32 = 3;
+const DEFAULT_NDOTS: u32 = 1;
+const DEFAULT_LOOKUPS: &str = "fb";
+
+/// The configuration in effect for a resolver, once its `Options` have been merged with system
+/// configuration - as returned by `effective_options()`.
+///
+/// `c-ares` reports only the options that were explicitly set, so the rest is worked out here
+/// as `c-ares` does it: from the `RES_OPTIONS` and `LOCALDOMAIN` environment variables; on Unix
+/// platforms that configure `c-ares` from files, from `resolv.conf` and `nsswitch.conf`; and
+/// otherwise from the defaults.  Search domains that `c-ares` derives from the host name, when
+/// none are configured, are not reported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResolvedConfig {
+    /// The number of milliseconds each server is given to respond to a query on the first try.
+    pub timeout: u32,
+
+    /// The number of tries made with each server before giving up.
+    pub tries: u32,
+
+    /// The number of dots which must be present in a name for it to be queried "as is", before
+    /// the search domains are tried.
+    pub ndots: u32,
+
+    /// The domains to search, in order.
+    pub domains: Vec<String>,
+
+    /// The lookups to perform for host queries - `b` for DNS and `f` for the hosts file.
+    pub lookups: String,
+
+    /// Whether servers are selected round robin.
+    pub rotate: bool,
+}
+
+impl ResolvedConfig {
+    // Merge `options` with system configuration - the options taking precedence, then the
+    // environment, then files.
+    pub(crate) fn new(options: &Options) -> Self {
+        let res_options = env::var("RES_OPTIONS").ok();
+        let local_domain = env::var("LOCALDOMAIN").ok();
+        let (environment, _) =
+            Options::from_env_values(res_options.as_deref(), local_domain.as_deref(), None);
+        #[cfg(cares1_15)]
+        let resolvconf_path = options.resolvconf_path();
+        #[cfg(not(cares1_15))]
+        let resolvconf_path = None;
+        let files = SystemFiles::read(resolvconf_path);
+        let layers = [options, &environment, &files.options];
+
+        let domains = first(&layers, |options| options.domains().map(<[String]>::to_vec));
+        let lookups = options
+            .lookups()
+            .map(str::to_owned)
+            .or(files.lookups)
+            .unwrap_or_else(|| DEFAULT_LOOKUPS.to_owned());
+        Self {
+            timeout: first(&layers, Options::timeout).unwrap_or(DEFAULT_TIMEOUT),
+            tries: first(&layers, Options::tries).unwrap_or(DEFAULT_TRIES),
+            ndots: first(&layers, Options::ndots).unwrap_or(DEFAULT_NDOTS),
+            domains: domains.unwrap_or_default(),
+            lookups,
+            rotate: first(&layers, Options::rotate) == Some(true),
+        }
+    }
+}
+
+// The first value given by any of `layers`.
+fn first<T>(layers: &[&Options], get: impl Fn(&Options) -> Option<T>) -> Option<T> {
+    layers.iter().find_map(|options| get(options))
+}
+
+// The configuration read from system files.
+#[derive(Default)]
+struct SystemFiles {
+    options: Options,
+    lookups: Option<String>,
+}
+
+impl SystemFiles {
+    // Read the files that `c-ares` reads, on platforms where it reads them.
+    fn read(resolvconf_path: Option<&str>) -> Self {
+        let from_files = cfg!(all(
+            unix,
+            not(any(
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "android"
+            ))
+        ));
+        if !from_files {
+            return Self::default();
+        }
+        let path = resolvconf_path.unwrap_or("/etc/resolv.conf");
+        let resolv_conf = fs::read_to_string(path).unwrap_or_default();
+        let nsswitch_conf = fs::read_to_string("/etc/nsswitch.conf").unwrap_or_default();
+        Self::parse(&resolv_conf, &nsswitch_conf)
+    }
+
+    // Parse the contents of `resolv.conf` and `nsswitch.conf`.
+    fn parse(resolv_conf: &str, nsswitch_conf: &str) -> Self {
+        // The options are in the same form as in `RES_OPTIONS`, and the last `domain` or `search`
+        // line wins.
+        let mut res_options = Vec::new();
+        let mut domains = None;
+        let mut lookups = None;
+        for line in resolv_conf.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("options") => res_options.extend(words),
+                Some("domain") => domains = words.next().map(str::to_owned),
+                Some("search") => domains = Some(words.collect::<Vec<_>>().join(" ")),
+                Some("lookup" | "hostresorder") => lookups = parse_lookups(words).or(lookups),
+                _ => {}
+            }
+        }
+
+        // The `hosts:` line of `nsswitch.conf` takes precedence.
+        for line in nsswitch_conf.lines() {
+            if let Some(sources) = line.trim().strip_prefix("hosts:") {
+                lookups = parse_lookups(sources.split_whitespace()).or(lookups);
+            }
+        }
+
+        let res_options = res_options.join(" ");
+        let (options, _) = Options::from_env_values(Some(&res_options), domains.as_deref(), None);
+        Self { options, lookups }
+    }
+}
+
+// The lookups given by a list of sources - eg `files dns` - or `None` if there are none that
+// `c-ares` knows.
+fn parse_lookups<'a>(sources: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut lookups = String::new();
+    for source in sources {
+        let lookup = match source.to_ascii_lowercase().as_str() {
+            "dns" | "bind" | "resolv" | "resolve" => 'b',
+            "files" | "file" | "local" => 'f',
+            _ => continue,
+        };
+        if !lookups.contains(lookup) {
+            lookups.push(lookup);
+        }
+    }
+    (!lookups.is_empty()).then_some(lookups)
+}
@@ -1,19 +1,230 @@
+#[cfg(cares1_29)]
+use std::collections::HashMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::sync::{Arc, Mutex};
+#[cfg(cares1_24)]
+use std::path::{Path, PathBuf};
+#[cfg(cares1_24)]
+use std::sync::Weak;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread::{self, ThreadId};
+#[cfg(cares1_22)]
+use std::time::Duration;
+#[cfg(cares1_29)]
+use std::time::Instant;
 
+#[cfg(cares1_22)]
+use crate::any::AnyResults;
+use crate::binding::LocalBinding;
+use crate::budget::MemoryBudget;
+#[cfg(cares1_24)]
+use crate::capture::{CapturingTransport, Packet, PacketCapture};
+use crate::dispatch::{Dispatcher, OVERLOADED};
+#[cfg(cares1_22)]
+use crate::dnsrec::{C_IN, T_ANY, T_DNSKEY, T_DS, T_HTTPS, T_RRSIG, T_SSHFP, T_TLSA};
+#[cfg(cares1_22)]
+use crate::dnssec::{DNSKEYResults, DSResults, RRSIGResults};
+use crate::e164::{self, EnumUri};
+use crate::email::{DkimRecord, DmarcRecord, SpfRecord};
 use crate::error::Error;
-use crate::eventloop::{EventLoop, EventLoopStopper};
-
+use crate::eventloop::{EventLoop, EventLoopStopper, TaskSubmitter};
+use crate::host::HostResults;
+#[cfg(cares1_22)]
+use crate::hostswatch::HostsWatcher;
+#[cfg(feature = "idna")]
+use crate::idn::{self, IdnaMode};
+#[cfg(feature = "mdns")]
+use crate::mdns::MdnsTransport;
+use crate::metrics::{LoadStats, Metrics};
+use crate::name;
+#[cfg(all(feature = "net-watch", cares1_22))]
+use crate::netwatch::NetworkWatcher;
+use crate::overrides::HostOverrides;
+use crate::permit::Permits;
+use crate::policy::{Decision, QueryInfo, QueryPolicy};
+use crate::queryflags::QueryFlags;
+use crate::querylog::{QueryLog, QueryLogEntry, QueryTracker};
+#[cfg(cares1_29)]
+use crate::raw::RawResponse;
+use crate::record::{DnsClass, RecordType};
+use crate::rejection;
+#[cfg(cares1_24)]
+use crate::replay::{RecordingMode, RecordingTransport, ReplayTransport};
+use crate::resolved::{ResolvedConfig, DEFAULT_TIMEOUT};
+#[cfg(cares1_29)]
+use crate::response::{self, QueryResponse};
+#[cfg(cares1_34)]
+use crate::server::Server;
+#[cfg(cares1_24)]
+use crate::server::ServerInfo;
+#[cfg(cares1_29)]
+use crate::server::ServerStats;
+#[cfg(cares1_22)]
+use crate::sshfp::SSHFPResults;
+#[cfg(cares1_22)]
+use crate::svcb::HTTPSResults;
+#[cfg(cares1_29)]
+use crate::timer;
+#[cfg(all(feature = "tls", cares1_24))]
+use crate::tls::{self, TlsTransport};
+#[cfg(cares1_22)]
+use crate::tlsa::TLSAResults;
+#[cfg(all(feature = "mdns", cares1_24))]
+use crate::transport::LocalTransport;
 #[cfg(cares1_24)]
-use c_ares::AresString;
+use crate::transport::{
+    self, parse_servers, DirectTransport, EdnsTransport, LocalNames, SanitizingTransport,
+};
+use crate::transport::{Relay, RelayClients, RelayLimits, Transport};
+#[cfg(feature = "thread-tuning")]
+use crate::tuning;
 
 #[cfg(cares1_29)]
 use c_ares::{ServerFailoverOptions, ServerStateFlags};
 
+// Pass a query to the dispatcher, to be made with `$handler` - or, if it can be neither made nor
+// queued, to fail at once.
+macro_rules! dispatch {
+    ($resolver:expr, $id:expr, $name_len:expr, $handler:ident, |$channel:ident| $query:expr) => {
+        $resolver
+            .dispatcher
+            .dispatch(
+                &$resolver.submitter,
+                $id,
+                $name_len,
+                move |channel| match channel {
+                    Some($channel) => $query,
+                    None => $handler(Err(OVERLOADED)),
+                },
+            )
+    };
+}
+
+// The name to pass to `c-ares` for a query - or else reject the query, without going to `c-ares`,
+// if the name is invalid or the query policy denies it.
+macro_rules! query_name {
+    ($resolver:expr, $name:expr, $query_type:expr, $search:expr, $handler:expr) => {
+        match $resolver.query_name($name, $query_type, $search) {
+            Ok(name) => name,
+            Err(error) => {
+                $resolver.reject($handler, error);
+                return;
+            }
+        }
+    };
+}
+
+// Make a search for `$name` - across the search domains set by `set_search_domains()`, parsing
+// the answer with `$parse`, if there are any; and otherwise with `$search`.
+macro_rules! search {
+    (
+        $resolver:expr,
+        $id:expr,
+        $name:ident,
+        $dns_class:expr,
+        $query_type:expr,
+        $parse:expr,
+        $handler:ident,
+        |$channel:ident| $search:expr
+    ) => {
+        match $resolver.search_names(&$name) {
+            Some(names) => {
+                let submitter = $resolver.submitter.clone();
+                let (dns_class, query_type) = ($dns_class, $query_type);
+                let $handler =
+                    move |result: c_ares::Result<&[u8]>| $handler(result.and_then($parse));
+                dispatch!($resolver, $id, $name.len(), $handler, |channel| {
+                    search_each(
+                        channel, submitter, names, dns_class, query_type, false, $handler,
+                    )
+                })
+            }
+            None => dispatch!($resolver, $id, $name.len(), $handler, |$channel| $search),
+        }
+    };
+}
+
 /// Used to configure the behaviour of the resolver.
+///
+/// As well as being passed on to `c-ares`, the values set on an `Options` are recorded so that
+/// they can be read back - eg for logging the configuration of a resolver.  The `Debug`
+/// representation shows only those values that have been set.
+///
+/// Cloning an `Options` replays the recorded values onto a fresh `c-ares` options structure.
+/// `c-ares` does not allow a `c_ares::ServerFailoverOptions` to be read back, so server failover
+/// options are carried over to the clone only if they were set with `set_server_failover()`.
 #[derive(Default)]
 pub struct Options {
     inner: c_ares::Options,
+    flags: Option<c_ares::Flags>,
+    timeout: Option<u32>,
+    tries: Option<u32>,
+    ndots: Option<u32>,
+    udp_port: Option<u16>,
+    tcp_port: Option<u16>,
+    domains: Option<Vec<String>>,
+    lookups: Option<String>,
+    sock_send_buffer_size: Option<u32>,
+    sock_receive_buffer_size: Option<u32>,
+    rotate: Option<bool>,
+    ednspsz: Option<u32>,
+    #[cfg(cares1_15)]
+    resolvconf_path: Option<String>,
+    #[cfg(cares1_19)]
+    hosts_path: Option<String>,
+    #[cfg(cares1_22)]
+    watch_hosts_file: Option<Duration>,
+    #[cfg(all(feature = "net-watch", cares1_22))]
+    watch_network: bool,
+    #[cfg(cares1_20)]
+    udp_max_queries: Option<i32>,
+    #[cfg(cares1_22)]
+    max_timeout: Option<i32>,
+    #[cfg(cares1_23)]
+    query_cache_max_ttl: Option<u32>,
+    #[cfg(cares1_29)]
+    server_failover_options: bool,
+    #[cfg(cares1_29)]
+    server_failover: Option<(u16, Duration)>,
+    #[cfg(cares1_29)]
+    circuit_breaker: Option<(u64, Duration)>,
+    #[cfg(cares1_24)]
+    edns_client_subnet: Option<(IpAddr, u8)>,
+    #[cfg(cares1_34)]
+    dns_cookies: Option<bool>,
+    #[cfg(cares1_24)]
+    local_names: Option<LocalNames>,
+    #[cfg(cares1_24)]
+    sanitize_responses: Option<bool>,
+    #[cfg(cares1_24)]
+    packet_capture: Option<PacketCapture>,
+    #[cfg(cares1_24)]
+    recording: Option<(PathBuf, RecordingMode)>,
+    #[cfg(all(feature = "tls", cares1_24))]
+    tls_client_config: Option<Arc<rustls::ClientConfig>>,
+    #[cfg(feature = "idna")]
+    idna_mode: Option<IdnaMode>,
+    query_policy: Option<QueryPolicy>,
+    allowed_domains: Option<Vec<String>>,
+    host_aliases: Option<BTreeMap<String, String>>,
+    max_in_flight: Option<usize>,
+    max_outstanding_queries: Option<usize>,
+    max_queued_queries: Option<usize>,
+    rate_limit: Option<(u32, u32)>,
+    memory_budget: Option<usize>,
+    thread_name: Option<String>,
+    thread_stack_size: Option<usize>,
+    #[cfg(feature = "thread-tuning")]
+    thread_priority: Option<i32>,
+    #[cfg(feature = "thread-tuning")]
+    thread_affinity: Option<Vec<usize>>,
+    transport: Option<Arc<dyn Transport>>,
+    relay_limits: Option<(usize, usize, usize)>,
+    max_bindings: Option<usize>,
 }
 
 impl Options {
@@ -22,17 +233,384 @@ impl Options {
         Self::default()
     }
 
+    /// Returns an `Options` configured from environment variables, together with a list of
+    /// servers.
+    ///
+    /// - `RES_OPTIONS` is a space-separated list of resolv.conf style options.  `ndots:n`,
+    ///   `timeout:n` (in seconds), `attempts:n`, `rotate`, `edns0` and `use-vc` are understood;
+    ///   anything else is ignored.
+    /// - `LOCALDOMAIN` is a space-separated list of search domains.
+    /// - `CARES_SERVERS` is a comma-separated list of servers, in the `host[:port]` format accepted
+    ///   by `set_servers()`.
+    ///
+    /// Values that are not set by the environment are left to `c-ares` defaults.  The list of
+    /// servers is empty if `CARES_SERVERS` is not set.
+    pub fn from_env() -> (Self, Vec<String>) {
+        let res_options = env::var("RES_OPTIONS").ok();
+        let local_domain = env::var("LOCALDOMAIN").ok();
+        let servers = env::var("CARES_SERVERS").ok();
+        Self::from_env_values(
+            res_options.as_deref(),
+            local_domain.as_deref(),
+            servers.as_deref(),
+        )
+    }
+
+    // The guts of `from_env()`, separated out for testing.
+    pub(crate) fn from_env_values(
+        res_options: Option<&str>,
+        local_domain: Option<&str>,
+        servers: Option<&str>,
+    ) -> (Self, Vec<String>) {
+        let mut options = Self::new();
+        let mut flags = c_ares::Flags::empty();
+        for option in res_options.unwrap_or_default().split_whitespace() {
+            let (name, value) = match option.split_once(':') {
+                Some((name, value)) => (name, value.parse::<u32>().ok()),
+                None => (option, None),
+            };
+            match (name, value) {
+                ("ndots", Some(ndots)) => {
+                    options.set_ndots(ndots);
+                }
+                ("timeout", Some(seconds)) => {
+                    options.set_timeout(seconds.saturating_mul(1000));
+                }
+                ("attempts", Some(tries)) => {
+                    options.set_tries(tries);
+                }
+                ("rotate", None) => {
+                    options.set_rotate();
+                }
+                ("edns0", None) => flags.insert(c_ares::Flags::EDNS),
+                ("use-vc", None) => flags.insert(c_ares::Flags::USEVC),
+                _ => {}
+            }
+        }
+        if !flags.is_empty() {
+            options.add_flags(flags);
+        }
+
+        if let Some(local_domain) = local_domain {
+            let domains: Vec<&str> = local_domain.split_whitespace().collect();
+            options.set_domains(&domains);
+        }
+
+        let servers = servers
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|server| !server.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        (options, servers)
+    }
+
+    /// Set a `Transport`, to which all queries will be sent instead of to the network.
+    pub fn set_transport<T>(&mut self, transport: T) -> &mut Self
+    where
+        T: Transport,
+    {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Limit the work done at once by the relay that passes queries to a `Transport` - or that
+    /// modifies them on their way to the servers, eg for `set_edns_client_subnet()`.
+    ///
+    /// - `threads` is the number of UDP queries that are exchanged with the transport at once,
+    ///   each on its own thread, and `queued` the number of further UDP queries that wait for one
+    ///   of those threads.  Beyond that, queries wait to be read from the relay's socket - and may
+    ///   be dropped, if its buffer fills.
+    /// - `connections` is the number of TCP connections that are served at once, each on its own
+    ///   thread.  Further connections wait to be accepted until one of those is closed.
+    ///
+    /// The defaults are 16 threads, 64 queued queries and 16 connections.  Values of zero for
+    /// `threads` and `connections` are taken as one.
+    pub fn set_relay_limits(
+        &mut self,
+        threads: usize,
+        queued: usize,
+        connections: usize,
+    ) -> &mut Self {
+        self.relay_limits = Some((threads, queued, connections));
+        self
+    }
+
+    /// Set the number of bound resolvers - each with its own event loop thread - that
+    /// `Resolver::bound_to()` keeps at once.  Beyond that, binding elsewhere fails with
+    /// `Error::TooManyBindings` until a binding is removed with `remove_binding()`.
+    ///
+    /// The default is 64.
+    pub fn set_max_bindings(&mut self, max_bindings: usize) -> &mut Self {
+        self.max_bindings = Some(max_bindings);
+        self
+    }
+
+    /// Set a policy, to be applied to every query that names a domain before it is made.
+    ///
+    /// The policy can allow the query, deny it - failing it with the given error - or have it made
+    /// for another name instead.  This gives a single point at which to control what is looked
+    /// up.  Lookups by address - eg `get_host_by_address()` - are not subject to the policy.
+    pub fn set_query_policy<F>(&mut self, policy: F) -> &mut Self
+    where
+        F: Fn(&QueryInfo) -> Decision + Send + Sync + 'static,
+    {
+        self.query_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Permit queries only for names within `domains` - that is, for each domain and its
+    /// subdomains.  Other queries fail at once with `ENOSERVER`, which they otherwise get only from
+    /// a resolver that has no servers - or, by way of `Resolver::try_query()`, with
+    /// `Error::DomainNotAllowed`.
+    ///
+    /// This is intended for sandboxes and test environments, where looking up arbitrary names must
+    /// be prevented.  Lookups by address are permitted only if the `in-addr.arpa` or `ip6.arpa`
+    /// name is within `domains`.  Searches are checked against the name as given: take care that
+    /// the search domains don't take them elsewhere.
+    pub fn set_allowed_domains(&mut self, domains: &[&str]) -> &mut Self {
+        let domains = domains.iter().map(|domain| normalize_domain(domain));
+        self.allowed_domains = Some(domains.collect());
+        self
+    }
+
+    /// Set host aliases, as pairs of an alias and the name that it stands for.  When a search is
+    /// made for a name that has no dots and is an alias - ignoring case - the name that it stands
+    /// for is queried instead, without applying the search domains.
+    ///
+    /// This is the same as `c-ares` does with the aliases file named by the `HOSTALIASES`
+    /// environment variable, but without depending on the environment.  That file is still used
+    /// for names that aren't aliases here, unless `c_ares::Flags::NOALIASES` is set.
+    pub fn set_host_aliases(&mut self, aliases: &[(&str, &str)]) -> &mut Self {
+        let aliases = aliases
+            .iter()
+            .map(|&(alias, name)| (alias.to_ascii_lowercase(), name.to_owned()));
+        self.host_aliases = Some(aliases.collect());
+        self
+    }
+
+    /// Set host aliases from a file in the format used by the `HOSTALIASES` environment variable:
+    /// each line holds an alias and the name that it stands for, separated by whitespace.  See
+    /// `set_host_aliases()`.
+    ///
+    /// Fails if the file can't be read.
+    pub fn set_host_aliases_file(&mut self, path: &str) -> io::Result<&mut Self> {
+        let contents = fs::read_to_string(path)?;
+        let aliases: Vec<(&str, &str)> = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?, fields.next()?))
+            })
+            .collect();
+        Ok(self.set_host_aliases(&aliases))
+    }
+
+    /// Add an EDNS Client Subnet option (RFC 7871) to all queries, so that servers can tailor
+    /// their answers to the location of the client - eg CDNs returning nearby addresses.
+    ///
+    /// `address` is truncated to the given `prefix` length before it is sent: a prefix of 24 for
+    /// IPv4, or 56 for IPv6, is usual for privacy.  A prefix of 0 asks the server not to use the
+    /// client's address at all.
+    ///
+    /// `c-ares` cannot add this option itself, so queries are relayed through the resolver on their
+    /// way to the servers - or to the `Transport`, if one is set.  A query to which the option
+    /// cannot be added is not sent.
+    ///
+    /// When queries are relayed on to the servers, the resolver tries each server in turn, in the
+    /// order given, until one answers: `c-ares`' settings for tries, rotation and server failover
+    /// apply only to the relay, not to the servers beyond it.
+    #[cfg(cares1_24)]
+    pub fn set_edns_client_subnet(&mut self, address: IpAddr, prefix: u8) -> &mut Self {
+        self.edns_client_subnet = Some((address, prefix));
+        self
+    }
+
+    /// Enable or disable DNS cookies (RFC 7873), which protect against spoofed responses and
+    /// amplification attacks.
+    ///
+    /// `c-ares` sends cookies whenever EDNS is in use, so enabling cookies enables EDNS.  `c-ares`
+    /// has no setting to disable them, so when cookies are disabled, queries are relayed through
+    /// the resolver and the cookies are removed on the way - as for `set_edns_client_subnet()`.
+    ///
+    /// `c-ares` keeps the cookies that it has exchanged with each server to itself, so the resolver
+    /// cannot say which servers support them: `Resolver::server_stats()` does not report them.  To
+    /// check that a server returns cookies, use `set_packet_capture()`, which shows each response
+    /// together with the server that sent it.
+    #[cfg(cares1_34)]
+    pub fn set_dns_cookies(&mut self, enable: bool) -> &mut Self {
+        self.dns_cookies = Some(enable);
+        if enable {
+            self.edns();
+        }
+        self
+    }
+
+    /// Set how queries for names under `.local` are handled.
+    ///
+    /// Such names are reserved for multicast DNS, so unicast servers usually know nothing of them:
+    /// by default they are sent to the servers anyway, and so often time out.  To send them via
+    /// multicast DNS, queries are relayed through the resolver - as for
+    /// `set_edns_client_subnet()`.
+    #[cfg(cares1_24)]
+    pub fn set_local_names(&mut self, local_names: LocalNames) -> &mut Self {
+        self.local_names = Some(local_names);
+        self
+    }
+
+    /// Enable or disable sanitizing of responses.
+    ///
+    /// When enabled, records in the answer section of a response are kept only if they belong to
+    /// the name that was queried - or to an alias of it, via a chain of CNAME records.  This
+    /// protects against servers that return unrelated records, whether through carelessness or
+    /// malice.  To do this, queries are relayed through the resolver - as for
+    /// `set_edns_client_subnet()`.
+    #[cfg(cares1_24)]
+    pub fn set_sanitize_responses(&mut self, enable: bool) -> &mut Self {
+        self.sanitize_responses = Some(enable);
+        self
+    }
+
+    /// Set a callback function to be invoked with each DNS message that is sent to or received
+    /// from a server - eg for debugging interoperability problems.
+    ///
+    /// `c-ares` cannot show its messages, so queries are relayed through the resolver on their way
+    /// to the servers - or to the `Transport`, if one is set, in which case the peer and protocol
+    /// of the captured messages are not known.  The callback is called on the relay's threads.
+    #[cfg(cares1_24)]
+    pub fn set_packet_capture<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Packet) + Send + Sync + 'static,
+    {
+        self.packet_capture = Some(Arc::new(callback));
+        self
+    }
+
+    /// Set the TLS configuration with which to make queries to `tls://` servers, as given to
+    /// `Resolver::set_servers()`.  By default, their certificates are verified against the Mozilla
+    /// root certificates.
+    #[cfg(all(feature = "tls", cares1_24))]
+    pub fn set_tls_client_config(&mut self, config: Arc<rustls::ClientConfig>) -> &mut Self {
+        self.tls_client_config = Some(config);
+        self
+    }
+
+    /// Record the responses to queries in the file at `path`, or replay responses recorded there
+    /// earlier - eg so that tests of an application can run against real answers without the
+    /// network.
+    ///
+    /// - When recording, queries are relayed through the resolver on their way to the servers -
+    ///   or to the `Transport`, if one is set - and each response is written to the file, replacing
+    ///   whatever was there.
+    ///
+    /// - When replaying, no server is contacted: each query is answered with the response recorded
+    ///   for the same question.  Responses to a question are replayed in the order in which they
+    ///   were recorded, repeating the last.  Questions that weren't recorded are refused, so fail
+    ///   with `EREFUSED`.  Any `Transport` is ignored.
+    ///
+    /// Creating the resolver fails if the file can't be created or read.
+    #[cfg(cares1_24)]
+    pub fn set_recording<P: AsRef<Path>>(&mut self, path: P, mode: RecordingMode) -> &mut Self {
+        self.recording = Some((path.as_ref().to_owned(), mode));
+        self
+    }
+
+    /// Set how names containing non-ASCII characters are converted to A-labels before they are
+    /// queried.  The default is `IdnaMode::Lenient`.
+    #[cfg(feature = "idna")]
+    pub fn set_idna_mode(&mut self, idna_mode: IdnaMode) -> &mut Self {
+        self.idna_mode = Some(idna_mode);
+        self
+    }
+
     /// Set flags controlling the behaviour of the resolver.
     pub fn set_flags(&mut self, flags: c_ares::Flags) -> &mut Self {
         self.inner.set_flags(flags);
+        self.flags = Some(flags);
         self
     }
 
+    // Add to whatever flags are already set - or, if none are, to those that `c-ares` uses by
+    // default.  Setting any flags replaces the default, so starting from nothing would lose it.
+    fn add_flags(&mut self, flags: c_ares::Flags) -> &mut Self {
+        let flags = self.flags.unwrap_or_else(default_flags) | flags;
+        self.set_flags(flags)
+    }
+
+    /// Always use TCP queries instead of UDP queries.  Normally, TCP is only used if a UDP query
+    /// yields a truncated result.
+    pub fn use_tcp(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::USEVC)
+    }
+
+    /// Only query the first server in the list of servers.
+    pub fn primary(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::PRIMARY)
+    }
+
+    /// If a truncated response to a UDP query is received, do not fall back to TCP; simply continue
+    /// on with the truncated response.
+    pub fn ignore_truncation(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::IGNTC)
+    }
+
+    /// Do not set the "recursion desired" bit on outgoing queries.
+    pub fn no_recurse(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::NORECURSE)
+    }
+
+    /// Do not close communications sockets when the number of active queries drops to zero.
+    pub fn stay_open(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::STAYOPEN)
+    }
+
+    /// Do not use the default search domains; only query hostnames as-is or as aliases.
+    pub fn no_search(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::NOSEARCH)
+    }
+
+    /// Do not honor the HOSTALIASES environment variable.
+    pub fn no_aliases(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::NOALIASES)
+    }
+
+    /// Include an EDNS pseudo-resource record in generated requests.
+    ///
+    /// Note that, since `c-ares` v1.22, this is on by default if flags are otherwise not set - so
+    /// you probably want to call this if you use any of the other flag helpers.
+    pub fn edns(&mut self) -> &mut Self {
+        self.add_flags(c_ares::Flags::EDNS)
+    }
+
+    /// Enable or disable DNS 0x20: randomizing the case of the letters in each query name.
+    ///
+    /// Servers copy the query name into their responses exactly, so when this is enabled, responses
+    /// whose question doesn't match the query's mix of cases are rejected.  This makes it harder to
+    /// spoof responses, since an attacker must guess the case of each letter as well as the query
+    /// ID and port.
+    ///
+    /// The tradeoff is that a few servers and middleboxes don't preserve case.  Responses from
+    /// these are all rejected, so queries fail.  Long names gain the most protection, while names
+    /// with few letters gain little.  It applies only to queries over UDP.
+    #[cfg(cares1_34)]
+    pub fn set_dns0x20(&mut self, enable: bool) -> &mut Self {
+        if enable {
+            self.add_flags(c_ares::Flags::DNS_0X20)
+        } else {
+            let flags = self.flags.unwrap_or_else(default_flags) - c_ares::Flags::DNS_0X20;
+            self.set_flags(flags)
+        }
+    }
+
     /// Set the number of milliseconds each name server is given to respond to a query on the first
     /// try.  (After the first try, the timeout algorithm becomes more complicated, but scales
-    /// linearly with the value of timeout).  The default is 5000ms.
+    /// linearly with the value of timeout).  The default is 2000ms.
     pub fn set_timeout(&mut self, ms: u32) -> &mut Self {
         self.inner.set_timeout(ms);
+        self.timeout = Some(ms);
         self
     }
 
@@ -40,6 +618,7 @@ impl Options {
     /// The default is four tries.
     pub fn set_tries(&mut self, tries: u32) -> &mut Self {
         self.inner.set_tries(tries);
+        self.tries = Some(tries);
         self
     }
 
@@ -48,6 +627,7 @@ impl Options {
     /// value is 1 unless set otherwise by resolv.conf or the RES_OPTIONS environment variable.
     pub fn set_ndots(&mut self, ndots: u32) -> &mut Self {
         self.inner.set_ndots(ndots);
+        self.ndots = Some(ndots);
         self
     }
 
@@ -55,6 +635,7 @@ impl Options {
     /// port.
     pub fn set_udp_port(&mut self, udp_port: u16) -> &mut Self {
         self.inner.set_udp_port(udp_port);
+        self.udp_port = Some(udp_port);
         self
     }
 
@@ -62,6 +643,7 @@ impl Options {
     /// port.
     pub fn set_tcp_port(&mut self, tcp_port: u16) -> &mut Self {
         self.inner.set_tcp_port(tcp_port);
+        self.tcp_port = Some(tcp_port);
         self
     }
 
@@ -69,6 +651,7 @@ impl Options {
     /// derived from the kernel hostname variable.
     pub fn set_domains(&mut self, domains: &[&str]) -> &mut Self {
         self.inner.set_domains(domains);
+        self.domains = Some(domains.iter().map(|&d| d.to_owned()).collect());
         self
     }
 
@@ -77,36 +660,89 @@ impl Options {
     /// hosts file.
     pub fn set_lookups(&mut self, lookups: &str) -> &mut Self {
         self.inner.set_lookups(lookups);
+        self.lookups = Some(lookups.to_owned());
+        self
+    }
+
+    /// Set the lookups to perform for host queries from the `hosts:` line of an nsswitch.conf
+    /// file - eg `/etc/nsswitch.conf` - so that host lookups are made in the same order as by
+    /// glibc-based programs.
+    ///
+    /// The sources `files` and `dns` are used, in the order given; other sources - eg `myhostname`
+    /// or `mdns4_minimal` - are skipped, as are actions such as `[NOTFOUND=return]`.  If the file
+    /// has no `hosts:` line then glibc's default of `dns files` is used.  If none of the sources
+    /// is supported then the lookups are left unchanged.
+    ///
+    /// Fails if the file can't be read.
+    pub fn set_lookups_from_nsswitch(&mut self, path: &str) -> io::Result<&mut Self> {
+        let contents = fs::read_to_string(path)?;
+        Ok(self.set_lookups_from_nsswitch_contents(&contents))
+    }
+
+    // The guts of `set_lookups_from_nsswitch()`, separated out for testing.
+    pub(crate) fn set_lookups_from_nsswitch_contents(&mut self, contents: &str) -> &mut Self {
+        let sources = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .filter_map(|line| line.split_once(':'))
+            .find(|(database, _)| database.trim() == "hosts")
+            .map_or("dns files", |(_, sources)| sources);
+
+        let mut lookups = String::new();
+        let mut in_action = false;
+        for source in sources.split_whitespace() {
+            // Actions are bracketed, and may contain spaces: eg `[NOTFOUND = return]`.
+            if in_action || source.starts_with('[') {
+                in_action = !source.ends_with(']');
+                continue;
+            }
+            let lookup = match source {
+                "files" => 'f',
+                "dns" => 'b',
+                _ => continue,
+            };
+            if !lookups.contains(lookup) {
+                lookups.push(lookup);
+            }
+        }
+        if !lookups.is_empty() {
+            self.set_lookups(&lookups);
+        }
         self
     }
 
     /// Set the socket send buffer size.
     pub fn set_sock_send_buffer_size(&mut self, size: u32) -> &mut Self {
         self.inner.set_sock_send_buffer_size(size);
+        self.sock_send_buffer_size = Some(size);
         self
     }
 
     /// Set the socket receive buffer size.
     pub fn set_sock_receive_buffer_size(&mut self, size: u32) -> &mut Self {
         self.inner.set_sock_receive_buffer_size(size);
+        self.sock_receive_buffer_size = Some(size);
         self
     }
 
     /// Configure round robin selection of nameservers.
     pub fn set_rotate(&mut self) -> &mut Self {
         self.inner.set_rotate();
+        self.rotate = Some(true);
         self
     }
 
     /// Prevent round robin selection of nameservers.
     pub fn set_no_rotate(&mut self) -> &mut Self {
         self.inner.set_no_rotate();
+        self.rotate = Some(false);
         self
     }
 
     /// Set the EDNS packet size.
     pub fn set_ednspsz(&mut self, size: u32) -> &mut Self {
         self.inner.set_ednspsz(size);
+        self.ednspsz = Some(size);
         self
     }
 
@@ -116,6 +752,7 @@ impl Options {
     #[cfg(cares1_15)]
     pub fn set_resolvconf_path(&mut self, resolvconf_path: &str) -> &mut Self {
         self.inner.set_resolvconf_path(resolvconf_path);
+        self.resolvconf_path = Some(resolvconf_path.to_owned());
         self
     }
 
@@ -124,6 +761,30 @@ impl Options {
     #[cfg(cares1_19)]
     pub fn set_hosts_path(&mut self, hosts_path: &str) -> &mut Self {
         self.inner.set_hosts_path(hosts_path);
+        self.hosts_path = Some(hosts_path.to_owned());
+        self
+    }
+
+    /// Watch the hosts file set by `set_hosts_path()`, checking every `interval` for a change to
+    /// its modification time.  When it changes, the resolver is reinitialized - as by `reinit()` -
+    /// so that the new hosts are used without restarting.
+    ///
+    /// Has no effect unless `set_hosts_path()` is also used.
+    #[cfg(cares1_22)]
+    pub fn set_watch_hosts_file(&mut self, interval: Duration) -> &mut Self {
+        self.watch_hosts_file = Some(interval);
+        self
+    }
+
+    /// Watch for changes to the network configuration - addresses and routes - and reinitialize
+    /// the resolver when they happen, as by `reinit()`.  This picks up new servers, eg after
+    /// moving to a different network, and flushes the query cache.
+    ///
+    /// On Linux this listens for netlink notifications, and on macOS it reads a routing socket.
+    /// On other platforms, creating the resolver fails with an error of kind `Unsupported`.
+    #[cfg(all(feature = "net-watch", cares1_22))]
+    pub fn set_watch_network(&mut self, enable: bool) -> &mut Self {
+        self.watch_network = enable;
         self
     }
 
@@ -133,6 +794,7 @@ impl Options {
     #[cfg(cares1_20)]
     pub fn set_udp_max_queries(&mut self, udp_max_queries: i32) -> &mut Self {
         self.inner.set_udp_max_queries(udp_max_queries);
+        self.udp_max_queries = Some(udp_max_queries);
         self
     }
 
@@ -142,6 +804,7 @@ impl Options {
     #[cfg(cares1_22)]
     pub fn set_max_timeout(&mut self, max_timeout: i32) -> &mut Self {
         self.inner.set_max_timeout(max_timeout);
+        self.max_timeout = Some(max_timeout);
         self
     }
 
@@ -154,6 +817,7 @@ impl Options {
     #[cfg(cares1_23)]
     pub fn set_query_cache_max_ttl(&mut self, qcache_max_ttl: u32) -> &mut Self {
         self.inner.set_query_cache_max_ttl(qcache_max_ttl);
+        self.query_cache_max_ttl = Some(qcache_max_ttl);
         self
     }
 
@@ -167,6 +831,9 @@ impl Options {
     ///
     /// If this option is not specified then c-ares will use a retry chance of 10% and a minimum
     /// delay of 5 seconds.
+    ///
+    /// Options set in this way cannot be read back, so are not carried over when the `Options` is
+    /// cloned - eg for `Resolver::tcp()`.  Prefer `set_server_failover()`.
     #[cfg(cares1_29)]
     pub fn set_server_failover_options(
         &mut self,
@@ -174,195 +841,1920 @@ impl Options {
     ) -> &mut Self {
         self.inner
             .set_server_failover_options(server_failover_options);
+        self.server_failover_options = true;
+        self.server_failover = None;
         self
     }
-}
-
-/// An asynchronous DNS resolver, which returns results via callbacks.
-///
-/// Note that dropping the resolver will cause all outstanding requests to fail with result
-/// `c_ares::Error::EDESTRUCTION`.
-pub struct Resolver {
-    ares_channel: Arc<Mutex<c_ares::Channel>>,
-    _event_loop_stopper: EventLoopStopper,
-}
 
-impl Resolver {
-    /// Create a new `Resolver`, using default `Options`.
-    pub fn new() -> Result<Self, Error> {
-        let options = Options::default();
-        Self::with_options(options)
+    /// Set server failover options, as by `set_server_failover_options()`: a failed server is
+    /// retried on a query with probability 1 in `retry_chance` - or never, if that is zero - and no
+    /// sooner than `retry_delay` after it failed.
+    ///
+    /// Unlike `set_server_failover_options()`, these values are recorded, and so carried over
+    /// when the `Options` is cloned.
+    #[cfg(cares1_29)]
+    pub fn set_server_failover(&mut self, retry_chance: u16, retry_delay: Duration) -> &mut Self {
+        let mut server_failover_options = ServerFailoverOptions::new();
+        server_failover_options
+            .set_retry_chance(retry_chance)
+            .set_retry_delay(usize::try_from(retry_delay.as_millis()).unwrap_or(usize::MAX));
+        self.set_server_failover_options(&server_failover_options);
+        self.server_failover = Some((retry_chance, retry_delay));
+        self
     }
 
-    /// Create a new `Resolver`, with the given `Options`.
-    pub fn with_options(options: Options) -> Result<Self, Error> {
-        // Create and run the event loop.
-        let event_loop = EventLoop::new(options.inner)?;
-        let channel = Arc::clone(&event_loop.ares_channel);
-        let stopper = event_loop.run();
-
-        // Return the Resolver.
-        let resolver = Self {
-            ares_channel: channel,
-            _event_loop_stopper: stopper,
-        };
-        Ok(resolver)
+    /// Set a circuit breaker, which stops sending queries to a server once `failure_threshold`
+    /// queries to it in a row have failed.
+    ///
+    /// While a server's circuit is open, it is taken out of the list of servers.  After
+    /// `probe_interval` it is put back in its place, so that it can be probed with real queries:
+    /// one success closes the circuit, while one more failure opens it again.  The last remaining
+    /// server is never taken out.
+    ///
+    /// Setting the servers - eg by `Resolver::set_servers()` - closes all circuits.  Resolvers
+    /// derived from this one - eg by `Resolver::tcp()` - follow its circuits.
+    ///
+    /// This is stricter than `c-ares`' own failover, which only prefers healthier servers - and
+    /// which continues to retry failing servers on its own schedule.
+    #[cfg(cares1_29)]
+    pub fn set_circuit_breaker(
+        &mut self,
+        failure_threshold: u64,
+        probe_interval: Duration,
+    ) -> &mut Self {
+        self.circuit_breaker = Some((failure_threshold, probe_interval));
+        self
     }
 
-    /// Reinitialize a channel from system configuration.
-    #[cfg(cares1_22)]
-    pub fn reinit(&self) -> c_ares::Result<&Self> {
-        self.ares_channel.lock().unwrap().reinit()?;
-        Ok(self)
+    /// Limit the number of queries that async callers have in flight, to `max_in_flight`.
+    ///
+    /// The limit is kept by callers acquiring a permit with `FutureResolver::acquire_permit()`
+    /// before each query, and holding it until the query completes.  Callers can then wait for
+    /// capacity instead of submitting queries without bound.  Queries made without a permit are
+    /// neither counted nor held back.
+    pub fn set_max_in_flight(&mut self, max_in_flight: usize) -> &mut Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
     }
 
-    /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
-    /// local named.
+    /// Limit the number of queries that the resolver has outstanding, to `max_outstanding`.
     ///
-    /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
-    /// `[2001:4860:4860::8888]:53`.
-    pub fn set_servers(&self, servers: &[&str]) -> c_ares::Result<&Self> {
-        self.ares_channel.lock().unwrap().set_servers(servers)?;
-        Ok(self)
+    /// Queries made beyond the limit are queued, and made in turn as outstanding queries complete -
+    /// in order of their `Priority`.
+    /// As in `LoadStats`, only queries for names are counted: lookups by address are always made
+    /// at once.
+    pub fn set_max_outstanding_queries(&mut self, max_outstanding: usize) -> &mut Self {
+        self.max_outstanding_queries = Some(max_outstanding);
+        self
     }
 
-    /// Retrieves the list of servers in comma delimited format.
-    #[cfg(cares1_24)]
-    pub fn get_servers(&self) -> AresString {
-        self.ares_channel.lock().unwrap().get_servers()
+    /// Limit the number of queries that are queued by `set_max_outstanding_queries()`, to
+    /// `max_queued`.  Queries made beyond this limit fail at once with `c_ares::Error::ENOMEM` -
+    /// or `Error::Overloaded`, by way of `Resolver::try_query()`.  See also
+    /// `Resolver::check_capacity()`.
+    ///
+    /// Without this limit, the queue is unbounded.
+    pub fn set_max_queued_queries(&mut self, max_queued: usize) -> &mut Self {
+        self.max_queued_queries = Some(max_queued);
+        self
     }
 
-    /// Set the local IPv4 address from which to make queries.
-    pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
-        self.ares_channel.lock().unwrap().set_local_ipv4(ipv4);
+    /// Limit the rate at which the resolver makes queries, to `queries_per_second` - allowing
+    /// bursts of up to `burst` queries after a quiet spell.  This guards against bulk lookups
+    /// flooding the servers.
+    ///
+    /// Queries made beyond the limit are queued, as by `set_max_outstanding_queries()`.  As there,
+    /// only queries for names are counted.  Values of zero are taken as one.
+    pub fn set_rate_limit(&mut self, queries_per_second: u32, burst: u32) -> &mut Self {
+        self.rate_limit = Some((queries_per_second, burst));
         self
     }
 
-    /// Set the local IPv6 address from which to make queries.
-    pub fn set_local_ipv6(&self, ipv6: &Ipv6Addr) -> &Self {
-        self.ares_channel.lock().unwrap().set_local_ipv6(ipv6);
+    /// Limit the approximate memory used by queries queued by `set_max_outstanding_queries()` or
+    /// `set_rate_limit()`, and by results buffered by `FutureResolver::resolve_many()` awaiting
+    /// consumption, to `bytes`.
+    ///
+    /// Only these are counted.  Queries that have been made, the answers that `c-ares` caches,
+    /// and results held elsewhere - eg by futures that haven't been polled - are not.
+    ///
+    /// While the budget is used up, new queries fail at once with `c_ares::Error::ENOMEM` - or
+    /// `Error::MemoryBudgetExceeded`, by way of `Resolver::try_query()`.  See also
+    /// `Resolver::check_capacity()`.  This protects long-running processes from unbounded growth
+    /// when queries are made, or `resolve_many()` results produced, faster than they are dealt
+    /// with.
+    pub fn set_memory_budget(&mut self, bytes: usize) -> &mut Self {
+        self.memory_budget = Some(bytes);
         self
     }
 
-    /// Set the local device from which to make queries.
-    pub fn set_local_device(&self, device: &str) -> &Self {
-        self.ares_channel.lock().unwrap().set_local_device(device);
+    /// Set the name of the thread on which the resolver runs its event loop - as seen in
+    /// debuggers, panic messages and eg `top -H`.
+    ///
+    /// Some platforms truncate thread names - eg Linux, to 15 bytes.  Without this, the thread is
+    /// unnamed.
+    pub fn set_thread_name(&mut self, name: &str) -> &mut Self {
+        self.thread_name = Some(name.to_owned());
         self
     }
 
-    /// Initializes an address sortlist configuration, so that addresses returned by
-    /// `get_host_by_name()` are sorted according to the sortlist.
+    /// Set the stack size, in bytes, of the thread on which the resolver runs its event loop.
     ///
-    /// Each element of the sortlist holds an IP-address/netmask pair. The netmask is optional but
-    /// follows the address after a slash if present. For example: "130.155.160.0/255.255.240.0",
-    /// or "130.155.0.0".
-    pub fn set_sortlist(&self, sortlist: &[&str]) -> c_ares::Result<&Self> {
-        self.ares_channel.lock().unwrap().set_sortlist(sortlist)?;
-        Ok(self)
+    /// Query handlers are called on this thread, so its stack must be large enough for them.
+    /// Without this, the thread has the default stack size for threads spawned by `std`.
+    pub fn set_thread_stack_size(&mut self, bytes: usize) -> &mut Self {
+        self.thread_stack_size = Some(bytes);
+        self
     }
 
-    /// Set a callback function to be invoked whenever a query on the channel completes.
+    /// Set the priority of the thread on which the resolver runs its event loop, as a nice value -
+    /// from -20, the highest priority, to 19, the lowest.
     ///
-    /// `callback(server, success, flags)` will be called when a query completes.
+    /// Raising the priority keeps DNS wakeups from being starved by busy worker threads, but
+    /// usually needs privileges - eg `CAP_SYS_NICE`.  If the priority can't be set, creating the
+    /// resolver fails.
     ///
-    /// - `server` indicates the DNS server that was used for the query.
-    /// - `success` indicates whether the query succeeded or not.
-    /// - `flags` is a bitmask of flags describing various aspects of the query.
-    #[cfg(cares1_29)]
-    pub fn set_server_state_callback<F>(&self, callback: F) -> &Self
-    where
-        F: FnMut(&str, bool, ServerStateFlags) + Send + 'static,
-    {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .set_server_state_callback(callback);
+    /// This is supported only on Linux: elsewhere, creating the resolver fails.
+    #[cfg(feature = "thread-tuning")]
+    pub fn set_thread_priority(&mut self, nice: i32) -> &mut Self {
+        self.thread_priority = Some(nice);
         self
     }
 
-    /// Look up the A records associated with `name`.
+    /// Pin the thread on which the resolver runs its event loop to the CPUs numbered in `cpus`.
     ///
-    /// On completion, `handler` is called with the result.
-    pub fn query_a<F>(&self, name: &str, handler: F)
-    where
-        F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
-    {
-        self.ares_channel.lock().unwrap().query_a(name, handler)
+    /// If the thread can't be pinned - eg because none of the CPUs are available to the process -
+    /// creating the resolver fails.
+    ///
+    /// This is supported only on Linux: elsewhere, creating the resolver fails.
+    #[cfg(feature = "thread-tuning")]
+    pub fn set_thread_affinity(&mut self, cpus: &[usize]) -> &mut Self {
+        self.thread_affinity = Some(cpus.to_vec());
+        self
     }
 
-    /// Search for the A records associated with `name`.
-    ///
-    /// On completion, `handler` is called with the result.
-    pub fn search_a<F>(&self, name: &str, handler: F)
-    where
-        F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
-    {
-        self.ares_channel.lock().unwrap().search_a(name, handler)
+    /// The flags that have been set, if any.
+    pub fn flags(&self) -> Option<c_ares::Flags> {
+        self.flags
     }
 
-    /// Look up the AAAA records associated with `name`.
-    ///
-    /// On completion, `handler` is called with the result.
-    pub fn query_aaaa<F>(&self, name: &str, handler: F)
-    where
-        F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
-    {
-        self.ares_channel.lock().unwrap().query_aaaa(name, handler)
+    /// The timeout that has been set, in milliseconds, if any.
+    pub fn timeout(&self) -> Option<u32> {
+        self.timeout
     }
 
-    /// Search for the AAAA records associated with `name`.
-    ///
-    /// On completion, `handler` is called with the result.
+    /// The number of tries that has been set, if any.
+    pub fn tries(&self) -> Option<u32> {
+        self.tries
+    }
+
+    /// The value of ndots that has been set, if any.
+    pub fn ndots(&self) -> Option<u32> {
+        self.ndots
+    }
+
+    /// The UDP port that has been set, if any.
+    pub fn udp_port(&self) -> Option<u16> {
+        self.udp_port
+    }
+
+    /// The TCP port that has been set, if any.
+    pub fn tcp_port(&self) -> Option<u16> {
+        self.tcp_port
+    }
+
+    /// The search domains that have been set, if any.
+    pub fn domains(&self) -> Option<&[String]> {
+        self.domains.as_deref()
+    }
+
+    /// The domains to which queries are restricted, if set.
+    pub fn allowed_domains(&self) -> Option<&[String]> {
+        self.allowed_domains.as_deref()
+    }
+
+    /// The host aliases that have been set, if any - keyed by the lower case alias.
+    pub fn host_aliases(&self) -> Option<&BTreeMap<String, String>> {
+        self.host_aliases.as_ref()
+    }
+
+    /// The maximum number of queries that async callers may have in flight, if set.
+    pub fn max_in_flight(&self) -> Option<usize> {
+        self.max_in_flight
+    }
+
+    /// The maximum number of queries that the resolver may have outstanding, if set.
+    pub fn max_outstanding_queries(&self) -> Option<usize> {
+        self.max_outstanding_queries
+    }
+
+    /// The maximum number of queries that may be queued, if set.
+    pub fn max_queued_queries(&self) -> Option<usize> {
+        self.max_queued_queries
+    }
+
+    /// The rate limit that has been set, as queries per second and burst, if any.
+    pub fn rate_limit(&self) -> Option<(u32, u32)> {
+        self.rate_limit
+    }
+
+    /// The memory budget that has been set, in bytes, if any.
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+
+    /// The name of the event loop thread that has been set, if any.
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// The stack size of the event loop thread that has been set, in bytes, if any.
+    pub fn thread_stack_size(&self) -> Option<usize> {
+        self.thread_stack_size
+    }
+
+    /// The relay limits that have been set, as threads, queued queries and connections, if any.
+    pub fn relay_limits(&self) -> Option<(usize, usize, usize)> {
+        self.relay_limits
+    }
+
+    /// The maximum number of bound resolvers that has been set, if any.
+    pub fn max_bindings(&self) -> Option<usize> {
+        self.max_bindings
+    }
+
+    // The limits for relays started for this resolver.
+    fn relay_limits_or_default(&self) -> RelayLimits {
+        let mut limits = RelayLimits::default();
+        if let Some((threads, queued, connections)) = self.relay_limits {
+            limits.threads = threads;
+            limits.queued = queued;
+            limits.connections = connections;
+        }
+        limits
+    }
+
+    /// The priority of the event loop thread that has been set, as a nice value, if any.
+    #[cfg(feature = "thread-tuning")]
+    pub fn thread_priority(&self) -> Option<i32> {
+        self.thread_priority
+    }
+
+    /// The CPUs to which the event loop thread is pinned, if set.
+    #[cfg(feature = "thread-tuning")]
+    pub fn thread_affinity(&self) -> Option<&[usize]> {
+        self.thread_affinity.as_deref()
+    }
+
+    /// The lookups that have been set, if any.
+    pub fn lookups(&self) -> Option<&str> {
+        self.lookups.as_deref()
+    }
+
+    /// The socket send buffer size that has been set, if any.
+    pub fn sock_send_buffer_size(&self) -> Option<u32> {
+        self.sock_send_buffer_size
+    }
+
+    /// The socket receive buffer size that has been set, if any.
+    pub fn sock_receive_buffer_size(&self) -> Option<u32> {
+        self.sock_receive_buffer_size
+    }
+
+    /// Whether round robin selection of nameservers has been configured (`Some(true)`) or
+    /// prevented (`Some(false)`), if either.
+    pub fn rotate(&self) -> Option<bool> {
+        self.rotate
+    }
+
+    /// The EDNS packet size that has been set, if any.
+    pub fn ednspsz(&self) -> Option<u32> {
+        self.ednspsz
+    }
+
+    /// The path to the resolv.conf file that has been set, if any.
+    #[cfg(cares1_15)]
+    pub fn resolvconf_path(&self) -> Option<&str> {
+        self.resolvconf_path.as_deref()
+    }
+
+    /// The path to the hosts file that has been set, if any.
+    #[cfg(cares1_19)]
+    pub fn hosts_path(&self) -> Option<&str> {
+        self.hosts_path.as_deref()
+    }
+
+    /// The interval at which the hosts file is watched for changes, if set.
+    #[cfg(cares1_22)]
+    pub fn watch_hosts_file(&self) -> Option<Duration> {
+        self.watch_hosts_file
+    }
+
+    /// Whether the network configuration is watched for changes.
+    #[cfg(all(feature = "net-watch", cares1_22))]
+    pub fn watch_network(&self) -> bool {
+        self.watch_network
+    }
+
+    /// The maximum number of udp queries per ephemeral port that has been set, if any.
+    #[cfg(cares1_20)]
+    pub fn udp_max_queries(&self) -> Option<i32> {
+        self.udp_max_queries
+    }
+
+    /// The upper bound for timeout between retries that has been set, in milliseconds, if any.
+    #[cfg(cares1_22)]
+    pub fn max_timeout(&self) -> Option<i32> {
+        self.max_timeout
+    }
+
+    /// The maximum TTL for the query cache that has been set, in seconds, if any.
+    #[cfg(cares1_23)]
+    pub fn query_cache_max_ttl(&self) -> Option<u32> {
+        self.query_cache_max_ttl
+    }
+
+    /// Whether server failover options have been set.
+    ///
+    /// `c-ares` does not allow the values of the server failover options to be read back: they are
+    /// known only if set with `set_server_failover()` - see `server_failover()`.
+    #[cfg(cares1_29)]
+    pub fn has_server_failover_options(&self) -> bool {
+        self.server_failover_options
+    }
+
+    /// The server failover retry chance and retry delay, if set with `set_server_failover()`.
+    #[cfg(cares1_29)]
+    pub fn server_failover(&self) -> Option<(u16, Duration)> {
+        self.server_failover
+    }
+
+    /// The circuit breaker's failure threshold and probe interval, if set.
+    #[cfg(cares1_29)]
+    pub fn circuit_breaker(&self) -> Option<(u64, Duration)> {
+        self.circuit_breaker
+    }
+
+    /// The EDNS Client Subnet address and prefix length, if set.
+    #[cfg(cares1_24)]
+    pub fn edns_client_subnet(&self) -> Option<(IpAddr, u8)> {
+        self.edns_client_subnet
+    }
+
+    /// Whether DNS cookies have been enabled or disabled, if set.
+    #[cfg(cares1_34)]
+    pub fn dns_cookies(&self) -> Option<bool> {
+        self.dns_cookies
+    }
+
+    /// How queries for names under `.local` are handled, if set.
+    #[cfg(cares1_24)]
+    pub fn local_names(&self) -> Option<LocalNames> {
+        self.local_names
+    }
+
+    /// Whether sanitizing of responses has been enabled or disabled, if set.
+    #[cfg(cares1_24)]
+    pub fn sanitize_responses(&self) -> Option<bool> {
+        self.sanitize_responses
+    }
+
+    /// The file in which responses are recorded, or from which they are replayed, if set.
+    #[cfg(cares1_24)]
+    pub fn recording(&self) -> Option<(&Path, RecordingMode)> {
+        self.recording
+            .as_ref()
+            .map(|(path, mode)| (path.as_path(), *mode))
+    }
+
+    /// How names containing non-ASCII characters are converted to A-labels, if set.
+    #[cfg(feature = "idna")]
+    pub fn idna_mode(&self) -> Option<IdnaMode> {
+        self.idna_mode
+    }
+}
+
+impl Clone for Options {
+    fn clone(&self) -> Self {
+        let mut options = Self::new();
+        if let Some(flags) = self.flags {
+            options.set_flags(flags);
+        }
+        if let Some(timeout) = self.timeout {
+            options.set_timeout(timeout);
+        }
+        if let Some(tries) = self.tries {
+            options.set_tries(tries);
+        }
+        if let Some(ndots) = self.ndots {
+            options.set_ndots(ndots);
+        }
+        if let Some(udp_port) = self.udp_port {
+            options.set_udp_port(udp_port);
+        }
+        if let Some(tcp_port) = self.tcp_port {
+            options.set_tcp_port(tcp_port);
+        }
+        if let Some(ref domains) = self.domains {
+            let domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+            options.set_domains(&domains);
+        }
+        if let Some(ref lookups) = self.lookups {
+            options.set_lookups(lookups);
+        }
+        if let Some(size) = self.sock_send_buffer_size {
+            options.set_sock_send_buffer_size(size);
+        }
+        if let Some(size) = self.sock_receive_buffer_size {
+            options.set_sock_receive_buffer_size(size);
+        }
+        match self.rotate {
+            Some(true) => {
+                options.set_rotate();
+            }
+            Some(false) => {
+                options.set_no_rotate();
+            }
+            None => {}
+        }
+        if let Some(size) = self.ednspsz {
+            options.set_ednspsz(size);
+        }
+        #[cfg(cares1_15)]
+        if let Some(ref resolvconf_path) = self.resolvconf_path {
+            options.set_resolvconf_path(resolvconf_path);
+        }
+        #[cfg(cares1_19)]
+        if let Some(ref hosts_path) = self.hosts_path {
+            options.set_hosts_path(hosts_path);
+        }
+        #[cfg(cares1_22)]
+        {
+            options.watch_hosts_file = self.watch_hosts_file;
+        }
+        #[cfg(all(feature = "net-watch", cares1_22))]
+        {
+            options.watch_network = self.watch_network;
+        }
+        #[cfg(cares1_20)]
+        if let Some(udp_max_queries) = self.udp_max_queries {
+            options.set_udp_max_queries(udp_max_queries);
+        }
+        #[cfg(cares1_22)]
+        if let Some(max_timeout) = self.max_timeout {
+            options.set_max_timeout(max_timeout);
+        }
+        #[cfg(cares1_23)]
+        if let Some(qcache_max_ttl) = self.query_cache_max_ttl {
+            options.set_query_cache_max_ttl(qcache_max_ttl);
+        }
+        #[cfg(cares1_24)]
+        {
+            options.edns_client_subnet = self.edns_client_subnet;
+            options.local_names = self.local_names;
+            options.sanitize_responses = self.sanitize_responses;
+            options.packet_capture = self.packet_capture.clone();
+            options.recording = self.recording.clone();
+        }
+        #[cfg(all(feature = "tls", cares1_24))]
+        {
+            options.tls_client_config = self.tls_client_config.clone();
+        }
+        #[cfg(feature = "idna")]
+        {
+            options.idna_mode = self.idna_mode;
+        }
+        #[cfg(cares1_34)]
+        {
+            options.dns_cookies = self.dns_cookies;
+        }
+        #[cfg(cares1_29)]
+        if let Some((retry_chance, retry_delay)) = self.server_failover {
+            options.set_server_failover(retry_chance, retry_delay);
+        }
+        #[cfg(cares1_29)]
+        {
+            options.circuit_breaker = self.circuit_breaker;
+        }
+        options.query_policy = self.query_policy.clone();
+        options.allowed_domains = self.allowed_domains.clone();
+        options.host_aliases = self.host_aliases.clone();
+        options.max_in_flight = self.max_in_flight;
+        options.max_outstanding_queries = self.max_outstanding_queries;
+        options.max_queued_queries = self.max_queued_queries;
+        options.rate_limit = self.rate_limit;
+        options.memory_budget = self.memory_budget;
+        options.thread_name = self.thread_name.clone();
+        options.thread_stack_size = self.thread_stack_size;
+        #[cfg(feature = "thread-tuning")]
+        {
+            options.thread_priority = self.thread_priority;
+            options.thread_affinity = self.thread_affinity.clone();
+        }
+        options.transport = self.transport.clone();
+        options.relay_limits = self.relay_limits;
+        options.max_bindings = self.max_bindings;
+        options
+    }
+}
+
+impl fmt::Debug for Options {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("Options");
+        macro_rules! field {
+            ($name:ident) => {
+                if let Some(ref value) = self.$name {
+                    debug.field(stringify!($name), value);
+                }
+            };
+        }
+        field!(flags);
+        field!(timeout);
+        field!(tries);
+        field!(ndots);
+        field!(udp_port);
+        field!(tcp_port);
+        field!(domains);
+        field!(lookups);
+        field!(sock_send_buffer_size);
+        field!(sock_receive_buffer_size);
+        field!(rotate);
+        field!(ednspsz);
+        #[cfg(cares1_15)]
+        field!(resolvconf_path);
+        #[cfg(cares1_19)]
+        field!(hosts_path);
+        #[cfg(cares1_22)]
+        field!(watch_hosts_file);
+        #[cfg(all(feature = "net-watch", cares1_22))]
+        if self.watch_network {
+            debug.field("watch_network", &true);
+        }
+        #[cfg(cares1_20)]
+        field!(udp_max_queries);
+        #[cfg(cares1_22)]
+        field!(max_timeout);
+        #[cfg(cares1_23)]
+        field!(query_cache_max_ttl);
+        #[cfg(cares1_29)]
+        match self.server_failover {
+            Some(ref server_failover) => {
+                debug.field("server_failover", server_failover);
+            }
+            None if self.server_failover_options => {
+                debug.field("server_failover_options", &"<set>");
+            }
+            None => {}
+        }
+        #[cfg(cares1_29)]
+        field!(circuit_breaker);
+        #[cfg(cares1_24)]
+        field!(edns_client_subnet);
+        #[cfg(cares1_34)]
+        field!(dns_cookies);
+        #[cfg(cares1_24)]
+        field!(local_names);
+        #[cfg(cares1_24)]
+        field!(sanitize_responses);
+        #[cfg(cares1_24)]
+        if self.packet_capture.is_some() {
+            debug.field("packet_capture", &"<set>");
+        }
+        #[cfg(cares1_24)]
+        field!(recording);
+        #[cfg(all(feature = "tls", cares1_24))]
+        if self.tls_client_config.is_some() {
+            debug.field("tls_client_config", &"<set>");
+        }
+        #[cfg(feature = "idna")]
+        field!(idna_mode);
+        if self.query_policy.is_some() {
+            debug.field("query_policy", &"<set>");
+        }
+        field!(allowed_domains);
+        field!(host_aliases);
+        field!(max_in_flight);
+        field!(max_outstanding_queries);
+        field!(max_queued_queries);
+        field!(rate_limit);
+        field!(memory_budget);
+        field!(thread_name);
+        field!(thread_stack_size);
+        #[cfg(feature = "thread-tuning")]
+        field!(thread_priority);
+        #[cfg(feature = "thread-tuning")]
+        field!(thread_affinity);
+        if self.transport.is_some() {
+            debug.field("transport", &"<set>");
+        }
+        field!(relay_limits);
+        field!(max_bindings);
+        debug.finish()
+    }
+}
+
+/// An asynchronous DNS resolver, which returns results via callbacks.
+///
+/// Note that dropping the resolver will cause all outstanding requests to fail with result
+/// `c_ares::Error::EDESTRUCTION` - including those made just before, that the event loop had not
+/// yet got round to.
+///
+/// Handlers are called on the resolver's event loop thread.  Methods that wait for the event loop -
+/// eg `set_servers()`, `set_sortlist()` and `reinit()` - would deadlock there, and so fail with
+/// `c_ares::Error::ENOTIMP` if called from a handler.  Methods such as `servers()` and
+/// `get_servers()`, that can't fail, return nothing.
+pub struct Resolver {
+    submitter: TaskSubmitter,
+    _event_loop_stopper: EventLoopStopper,
+    relay: Option<Relay>,
+    #[cfg_attr(not(cares1_24), allow(dead_code))]
+    relay_clients: RelayClients,
+    #[cfg(cares1_22)]
+    _hosts_watcher: Option<HostsWatcher>,
+    #[cfg(all(feature = "net-watch", cares1_22))]
+    _network_watcher: Option<NetworkWatcher>,
+
+    // When queries are relayed only so that they can be modified, this sends them on to the
+    // servers.
+    #[cfg(cares1_24)]
+    direct: Option<Arc<DirectTransport>>,
+
+    // When queries are relayed only so that they can go over TLS, the relay and what sends them on
+    // - started when TLS servers are first set, under the lock.
+    #[cfg(all(feature = "tls", cares1_24))]
+    tls_relay: Arc<OnceLock<TlsRelay>>,
+    #[cfg(all(feature = "tls", cares1_24))]
+    tls_lock: Mutex<()>,
+
+    // Statistics gathered from the server state callback, and the user's own callback.
+    #[cfg(cares1_29)]
+    server_state: Arc<ServerState>,
+
+    // Addresses pinned to names at runtime - shared with the TCP resolver.
+    host_overrides: Arc<HostOverrides>,
+
+    // Search domains set at runtime, if any - shared with the TCP resolver.
+    search_list: Arc<RwLock<Option<SearchList>>>,
+
+    // The user's query log, if any - shared with the TCP resolver.
+    query_log: Arc<RwLock<Option<QueryLog>>>,
+
+    // Counters and histograms for completed queries - shared with the TCP resolver.
+    metrics: Arc<Metrics>,
+
+    // Permits for async callers to make queries - shared with the TCP resolver.
+    permits: Arc<Permits>,
+
+    // Passes queries to the event loop, queueing them if there are too many outstanding or they are
+    // being made too quickly.
+    dispatcher: Arc<Dispatcher>,
+
+    // The memory used by queued queries and by results awaiting consumption.
+    budget: Arc<MemoryBudget>,
+
+    // The options this resolver was created with, and a resolver created from them that makes
+    // its queries over TCP - created on demand, under the lock.
+    #[cfg_attr(not(cares1_24), allow(dead_code))]
+    options: Options,
+    tcp: OnceLock<Arc<Resolver>>,
+    #[cfg_attr(not(cares1_24), allow(dead_code))]
+    tcp_lock: Mutex<()>,
+
+    // Resolvers that make their queries from elsewhere, created on demand.
+    #[cfg_attr(not(cares1_24), allow(dead_code))]
+    bound: Mutex<BTreeMap<LocalBinding, Arc<Resolver>>>,
+
+    // Resolvers that apply `QueryFlags` to all of their queries, created on demand.
+    #[cfg_attr(not(cares1_24), allow(dead_code))]
+    flagged: Mutex<BTreeMap<QueryFlags, Arc<Resolver>>>,
+
+    // All of the above that are still in use - shared with the circuit breaker, which passes on
+    // the servers when it changes them.
+    #[cfg(cares1_24)]
+    derived: DerivedResolvers,
+}
+
+// A relay started so that queries can go over TLS, and the transport that sends them on.
+#[cfg(all(feature = "tls", cares1_24))]
+struct TlsRelay {
+    relay: Relay,
+    direct: Arc<DirectTransport>,
+}
+
+// Resolvers derived from a resolver - eg by `tcp()` - which follow its servers.
+#[cfg(cares1_24)]
+type DerivedResolvers = Arc<Mutex<Vec<Weak<Resolver>>>>;
+
+impl Resolver {
+    /// Create a new `Resolver`, using default `Options`.
+    pub fn new() -> Result<Self, Error> {
+        let options = Options::default();
+        Self::with_options(options)
+    }
+
+    /// Create a new `Resolver`, with the given `Options`.
+    pub fn with_options(options: Options) -> Result<Self, Error> {
+        Self::with_configuration(options, |_| Ok(()))
+    }
+
+    // Create a new `Resolver`, with the given `Options`, applying `configure` to the channel before
+    // the event loop starts - and so before any queries can be made.
+    pub(crate) fn with_configuration<F>(options: Options, configure: F) -> Result<Self, Error>
+    where
+        F: FnOnce(&mut c_ares::Channel) -> c_ares::Result<()>,
+    {
+        // Keep a copy of the options, for creating a TCP resolver.  That sends its queries via
+        // this resolver's servers - and so via any relay - so needs no transport, and no changes to
+        // its queries.  Nor does it watch the hosts file or the network: reinitializing it would
+        // lose its servers.  Rejecting `.local` names needs no relay, so that is kept.
+        let mut recorded_options = options.clone();
+        recorded_options.transport = None;
+        #[cfg(cares1_22)]
+        {
+            recorded_options.watch_hosts_file = None;
+        }
+        #[cfg(all(feature = "net-watch", cares1_22))]
+        {
+            recorded_options.watch_network = false;
+        }
+        #[cfg(cares1_24)]
+        {
+            recorded_options.edns_client_subnet = None;
+            recorded_options.local_names = options
+                .local_names
+                .filter(|&local_names| local_names == LocalNames::Reject);
+            recorded_options.sanitize_responses = None;
+            recorded_options.packet_capture = None;
+            recorded_options.recording = None;
+        }
+        #[cfg(cares1_34)]
+        {
+            recorded_options.dns_cookies = None;
+        }
+
+        // Create and configure the event loop.
+        let relay_limits = options.relay_limits_or_default();
+        let mut transport = options.transport;
+        #[cfg(cares1_24)]
+        let udp_port = options.udp_port.unwrap_or(53);
+        #[cfg(cares1_24)]
+        let timeout =
+            std::time::Duration::from_millis(options.timeout.unwrap_or(DEFAULT_TIMEOUT).into());
+        let mut event_loop = EventLoop::new(options.inner)?;
+        #[cfg(cares1_24)]
+        let derived = DerivedResolvers::default();
+        #[cfg(cares1_29)]
+        let server_state = {
+            let server_state = Arc::new(ServerState::new(
+                event_loop.submitter(),
+                options.circuit_breaker,
+                Arc::clone(&derived),
+            ));
+            let callback_state = Arc::clone(&server_state);
+            event_loop
+                .channel_mut()
+                .set_server_state_callback(move |server, success, flags| {
+                    callback_state.record(server, success, flags);
+                    response::record_attempt(server, success, flags);
+                });
+            server_state
+        };
+        configure(event_loop.channel_mut())?;
+
+        // If queries must be modified, then they must be relayed: either to the transport, or
+        // directly to the servers.
+        #[cfg(cares1_24)]
+        let mut direct = None;
+        #[cfg(cares1_34)]
+        let strip_cookies = options.dns_cookies == Some(false);
+        #[cfg(all(cares1_24, not(cares1_34)))]
+        let strip_cookies = false;
+        #[cfg(cares1_24)]
+        let local_names = options.local_names.unwrap_or(LocalNames::Unicast);
+        #[cfg(cares1_24)]
+        let sanitize = options.sanitize_responses == Some(true);
+        #[cfg(cares1_24)]
+        let packet_capture = options.packet_capture;
+        #[cfg(cares1_24)]
+        let recording_path = match options.recording {
+            Some((path, RecordingMode::Record)) => Some(path),
+            Some((path, RecordingMode::Replay)) => {
+                transport = Some(Arc::new(ReplayTransport::load(&path)?));
+                None
+            }
+            None => None,
+        };
+        #[cfg(cares1_24)]
+        if options.edns_client_subnet.is_some()
+            || strip_cookies
+            || !matches!(local_names, LocalNames::Unicast | LocalNames::Reject)
+            || sanitize
+            || packet_capture.is_some()
+            || recording_path.is_some()
+        {
+            let inner = match (transport, packet_capture) {
+                (Some(transport), None) => transport,
+                (Some(transport), Some(capture)) => {
+                    Arc::new(CapturingTransport::new(transport, capture))
+                }
+                (None, capture) => {
+                    let servers = event_loop.channel_mut().get_servers();
+                    let servers = parse_servers(&[&servers], udp_port)?;
+                    let direct_transport =
+                        Arc::new(DirectTransport::new(servers, timeout, capture));
+                    direct = Some(Arc::clone(&direct_transport));
+                    direct_transport
+                }
+            };
+            let inner: Arc<dyn Transport> = match recording_path {
+                Some(path) => Arc::new(RecordingTransport::create(inner, &path)?),
+                None => inner,
+            };
+            let inner: Arc<dyn Transport> = if sanitize {
+                Arc::new(SanitizingTransport::new(inner))
+            } else {
+                inner
+            };
+            let inner: Arc<dyn Transport> = match local_names {
+                LocalNames::Unicast | LocalNames::Reject => inner,
+                #[cfg(feature = "mdns")]
+                LocalNames::Multicast => {
+                    let mdns = Arc::new(MdnsTransport::new());
+                    Arc::new(LocalTransport::new(inner, mdns))
+                }
+            };
+            let edns_transport =
+                EdnsTransport::new(inner, options.edns_client_subnet, strip_cookies);
+            transport = Some(Arc::new(edns_transport));
+        }
+
+        // If there's a transport, start relaying to it - for this resolver, and any derived from
+        // it.
+        let relay_clients = RelayClients::new(event_loop.sockets());
+        let relay = transport
+            .map(|transport| Relay::start(transport, Some(relay_clients.clone()), relay_limits))
+            .transpose()?;
+        if let Some(ref relay) = relay {
+            let server = relay.address().to_string();
+            event_loop.channel_mut().set_servers(&[&server])?;
+        }
+        #[cfg(cares1_29)]
+        server_state.check_servers(event_loop.channel_mut());
+
+        // Run the event loop.
+        let submitter = event_loop.submitter();
+        let budget = Arc::new(MemoryBudget::new(options.memory_budget));
+        let dispatcher = Arc::new(Dispatcher::new(
+            options.max_outstanding_queries,
+            options.max_queued_queries,
+            options.rate_limit,
+            Arc::clone(&budget),
+        ));
+        let mut builder = thread::Builder::new();
+        if let Some(name) = options.thread_name {
+            builder = builder.name(name);
+        }
+        if let Some(stack_size) = options.thread_stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        #[cfg(feature = "thread-tuning")]
+        let setup = {
+            let priority = options.thread_priority;
+            let affinity = options.thread_affinity;
+            move || tuning::tune_thread(priority, affinity.as_deref())
+        };
+        #[cfg(not(feature = "thread-tuning"))]
+        let setup = || Ok(());
+        let stopper = event_loop.run(builder, setup)?;
+
+        // Watchers reinitialize the channel when something changes.
+        #[cfg(all(feature = "tls", cares1_24))]
+        let tls_relay: Arc<OnceLock<TlsRelay>> = Arc::default();
+        #[cfg(cares1_22)]
+        let reinit_on_change = || {
+            let submitter = submitter.clone();
+            let relay_server = relay.as_ref().map(|relay| relay.address().to_string());
+            #[cfg(cares1_24)]
+            let direct = direct.clone();
+            #[cfg(all(feature = "tls", cares1_24))]
+            let tls_relay = Arc::clone(&tls_relay);
+            move || {
+                let relay_server = relay_server.clone();
+                #[cfg(cares1_24)]
+                let direct = direct.clone();
+                #[cfg(all(feature = "tls", cares1_24))]
+                let (relay_server, direct) = match tls_relay.get() {
+                    Some(tls_relay) if direct.is_none() => (
+                        Some(tls_relay.relay.address().to_string()),
+                        Some(Arc::clone(&tls_relay.direct)),
+                    ),
+                    _ => (relay_server, direct),
+                };
+                submitter.submit(move |channel| {
+                    let system_servers = reinit_channel(channel, relay_server.as_deref());
+                    #[cfg(cares1_24)]
+                    if let (Some(direct), Ok(Some(servers))) = (direct, system_servers) {
+                        if let Ok(servers) = parse_servers(&[&servers], udp_port) {
+                            direct.set_servers(servers);
+                        }
+                    }
+                    #[cfg(not(cares1_24))]
+                    let _ = system_servers;
+                });
+            }
+        };
+
+        // Watch the hosts file, if asked to.
+        #[cfg(cares1_22)]
+        let hosts_watcher = match (options.hosts_path, options.watch_hosts_file) {
+            (Some(path), Some(interval)) => {
+                Some(HostsWatcher::start(&path, interval, reinit_on_change()))
+            }
+            _ => None,
+        };
+
+        // Watch the network, if asked to.
+        #[cfg(all(feature = "net-watch", cares1_22))]
+        let network_watcher = if options.watch_network {
+            Some(NetworkWatcher::start(reinit_on_change())?)
+        } else {
+            None
+        };
+
+        // Return the Resolver.
+        let resolver = Self {
+            submitter,
+            _event_loop_stopper: stopper,
+            relay,
+            relay_clients,
+            #[cfg(cares1_22)]
+            _hosts_watcher: hosts_watcher,
+            #[cfg(all(feature = "net-watch", cares1_22))]
+            _network_watcher: network_watcher,
+            #[cfg(cares1_24)]
+            direct,
+            #[cfg(all(feature = "tls", cares1_24))]
+            tls_relay,
+            #[cfg(all(feature = "tls", cares1_24))]
+            tls_lock: Mutex::new(()),
+            #[cfg(cares1_29)]
+            server_state,
+            host_overrides: Arc::default(),
+            search_list: Arc::default(),
+            query_log: Arc::default(),
+            metrics: Arc::default(),
+            permits: Arc::new(Permits::new(options.max_in_flight)),
+            dispatcher,
+            budget,
+            options: recorded_options,
+            tcp: OnceLock::new(),
+            tcp_lock: Mutex::new(()),
+            bound: Mutex::default(),
+            flagged: Mutex::default(),
+            #[cfg(cares1_24)]
+            derived,
+        };
+        Ok(resolver)
+    }
+
+    /// Returns a resolver that makes its queries over TCP, and is otherwise configured in the same
+    /// way as this one - eg for a lookup that is expected to have a large answer.
+    ///
+    /// This is useful when only some queries should use TCP: to use TCP for all queries, set
+    /// `Options::use_tcp()` instead.
+    ///
+    /// The TCP resolver is created on first use, taking the servers that this resolver is using at
+    /// that time.  Later calls to `set_servers()` and `reinit()` on this resolver are passed on to
+    /// it.  It shares this resolver's limits on outstanding and queued queries, rate limit and
+    /// memory budget.  Note that server failover options are carried over only if they were set
+    /// with `Options::set_server_failover()`.
+    ///
+    /// If queries are relayed on to the servers - eg by `Options::set_edns_client_subnet()` - then
+    /// the relay sends this resolver's queries on over TCP too.
+    #[cfg(cares1_24)]
+    pub fn tcp(&self) -> Result<&Resolver, Error> {
+        self.tcp_resolver().map(Arc::as_ref)
+    }
+
+    // Get the TCP resolver, creating it if need be.
+    #[cfg(cares1_24)]
+    pub(crate) fn tcp_resolver(&self) -> Result<&Arc<Resolver>, Error> {
+        if let Some(tcp) = self.tcp.get() {
+            return Ok(tcp);
+        }
+        let _guard = self.tcp_lock.lock().unwrap();
+        if let Some(tcp) = self.tcp.get() {
+            return Ok(tcp);
+        }
+        let mut options = self.options.clone();
+        options.use_tcp();
+        let tcp = self.derive(options, |_| {})?;
+        Ok(self.tcp.get_or_init(|| tcp))
+    }
+
+    /// Returns a resolver that makes its queries from `binding`, and is otherwise configured in
+    /// the same way as this one - eg so that a multi-homed host can make the queries of each
+    /// tenant from its own address, or in its own VRF.
+    ///
+    /// `c-ares` binds all of a channel's sockets in the same way, so this is a separate resolver
+    /// sharing this one's host overrides, search domains, query log, metrics and permits - and its
+    /// limits on outstanding and queued queries, rate limit and memory budget.  It is created on
+    /// first use of `binding`, taking the servers that this resolver is using at that time: later
+    /// calls to `set_servers()` and `reinit()` on this resolver are passed on to it.
+    ///
+    /// Each binding's resolver runs its own event loop thread, and is kept until
+    /// `remove_binding()` - so an application that binds to many addresses in turn should remove
+    /// those that it no longer needs.  Once as many are kept as `Options::set_max_bindings()`
+    /// allows - by default 64 - binding elsewhere fails with `Error::TooManyBindings`.
+    ///
+    /// If a `Transport` is set then queries are relayed through the loopback interface, so
+    /// binding them elsewhere is not useful.
+    #[cfg(cares1_24)]
+    pub fn bound_to(&self, binding: &LocalBinding) -> Result<Arc<Resolver>, Error> {
+        let mut bound = self.bound.lock().unwrap();
+        if let Some(resolver) = bound.get(binding) {
+            return Ok(Arc::clone(resolver));
+        }
+        if bound.len() >= self.options.max_bindings.unwrap_or(64) {
+            return Err(Error::TooManyBindings);
+        }
+        let configure = {
+            let binding = binding.clone();
+            move |channel: &mut c_ares::Channel| binding.apply(channel)
+        };
+        let resolver = self.derive(self.options.clone(), configure)?;
+        bound.insert(binding.clone(), Arc::clone(&resolver));
+        Ok(resolver)
+    }
+
+    /// Stop keeping the resolver returned by `bound_to()` for `binding`, returning whether there
+    /// was one.  Its event loop thread stops once the last handle to it is dropped.
+    #[cfg(cares1_24)]
+    pub fn remove_binding(&self, binding: &LocalBinding) -> bool {
+        self.bound.lock().unwrap().remove(binding).is_some()
+    }
+
+    // Get a resolver that applies `flags` to all of its queries, creating it if need be - or
+    // `None` if there are no flags, and so this resolver will do.
+    #[cfg(cares1_24)]
+    fn flagged_resolver(&self, flags: QueryFlags) -> Result<Option<Arc<Resolver>>, Error> {
+        if flags.is_empty() {
+            return Ok(None);
+        }
+        let mut flagged = self.flagged.lock().unwrap();
+        if let Some(resolver) = flagged.get(&flags) {
+            return Ok(Some(Arc::clone(resolver)));
+        }
+        let mut options = self.options.clone();
+        if flags.contains(QueryFlags::NO_SEARCH) {
+            options.no_search();
+        }
+        if flags.contains(QueryFlags::NO_ALIASES) {
+            options.no_aliases();
+            options.host_aliases = None;
+        }
+        if flags.contains(QueryFlags::NO_CACHE) {
+            options.set_query_cache_max_ttl(0);
+        }
+        let resolver = self.derive(options, |_| {})?;
+        flagged.insert(flags, Arc::clone(&resolver));
+        Ok(Some(resolver))
+    }
+
+    // Create a resolver with `options`, applying `configure` to its channel, that uses this
+    // resolver's servers and shares its host overrides, search domains, query log, metrics,
+    // permits, dispatcher and memory budget.  It follows this resolver's circuit breaker, rather
+    // than running its own.
+    #[cfg(cares1_24)]
+    fn derive<F>(&self, mut options: Options, configure: F) -> Result<Arc<Resolver>, Error>
+    where
+        F: FnOnce(&mut c_ares::Channel),
+    {
+        #[cfg(cares1_29)]
+        {
+            options.circuit_breaker = None;
+        }
+        let servers = self.channel_servers()?;
+        let mut resolver = Self::with_configuration(options, move |channel| {
+            configure(channel);
+            channel.set_servers(&[&servers]).map(|_| ())
+        })?;
+        resolver.host_overrides = Arc::clone(&self.host_overrides);
+        resolver.search_list = Arc::clone(&self.search_list);
+        resolver.query_log = Arc::clone(&self.query_log);
+        resolver.metrics = Arc::clone(&self.metrics);
+        resolver.permits = Arc::clone(&self.permits);
+        resolver.dispatcher = Arc::clone(&self.dispatcher);
+        resolver.budget = Arc::clone(&self.budget);
+        self.relay_clients.join(&resolver.relay_clients);
+        resolver.relay_clients = self.relay_clients.clone();
+        let resolver = Arc::new(resolver);
+        self.derived.lock().unwrap().push(Arc::downgrade(&resolver));
+        Ok(resolver)
+    }
+
+    /// Reinitialize a channel from system configuration.
+    #[cfg(cares1_22)]
+    pub fn reinit(&self) -> c_ares::Result<&Self> {
+        // Reinitializing will re-read the servers from system configuration, so if we are using a
+        // transport then we must point the channel back at the relay.
+        //
+        // If queries are relayed only so that they can be modified, then the servers that have just
+        // been read are where they should be sent on to.
+        let relay_server = self.relay_server();
+        let system_servers = self
+            .submitter
+            .call(move |channel| reinit_channel(channel, relay_server.as_deref()))??;
+        if let Some(servers) = system_servers {
+            self.set_direct_servers(&[&servers])?;
+        }
+        self.sync_derived_servers()?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, instead of the servers specified in resolv.conf or the
+    /// local named.
+    ///
+    /// String format is `host[:port]`.  IPv6 addresses with ports require square brackets eg
+    /// `[2001:4860:4860::8888]:53`.
+    ///
+    /// With the `tls` feature, servers can instead be given as `tls://host@ip[:port]` - as
+    /// described for `TlsTransport` - and queries are then relayed through the loopback interface
+    /// and sent on to them over TLS.  Either all of the servers or none of them must be given in
+    /// this way.  See `Options::set_tls_client_config()` for how their certificates are verified.
+    ///
+    /// If a `Transport` is set then it, and not the list of servers, determines where queries go:
+    /// this fails with `c_ares::Error::ENOTIMP`.
+    pub fn set_servers(&self, servers: &[&str]) -> c_ares::Result<&Self> {
+        self.check_no_transport()?;
+        #[cfg(all(feature = "tls", cares1_24))]
+        if servers
+            .iter()
+            .flat_map(|servers| servers.split(','))
+            .any(tls::is_tls_server)
+        {
+            return self.set_tls_servers(servers);
+        }
+        if self.set_direct_servers(servers)? {
+            return Ok(self);
+        }
+        let servers: Vec<String> = servers.iter().map(|&s| s.to_owned()).collect();
+        self.submitter.call(move |channel| {
+            let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+            channel.set_servers(&servers).map(|_| ())
+        })??;
+        self.sync_derived_servers()?;
+        Ok(self)
+    }
+
+    /// Set the list of servers to contact, in the comma delimited format that `c-ares` uses - eg
+    /// `8.8.8.8,[2001:4860:4860::8888]:53,dns://9.9.9.9:853?tcpport=853` - as returned by
+    /// `get_servers_csv()`.
+    ///
+    /// This is useful for reading servers from configuration files in the same format as other
+    /// users of `c-ares`.
+    pub fn set_servers_csv(&self, servers: &str) -> c_ares::Result<&Self> {
+        self.set_servers(&[servers])
+    }
+
+    /// Set the list of servers to contact, as socket addresses.
+    pub fn set_servers_addrs(&self, servers: &[SocketAddr]) -> c_ares::Result<&Self> {
+        let servers: Vec<String> = servers.iter().map(SocketAddr::to_string).collect();
+        let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+        self.set_servers(&servers)
+    }
+
+    /// Set the list of servers to contact, as IP addresses.  Servers are contacted on the default
+    /// port - 53, unless configured otherwise in the `Options`.
+    pub fn set_servers_ips(&self, servers: &[IpAddr]) -> c_ares::Result<&Self> {
+        let servers: Vec<String> = servers.iter().map(IpAddr::to_string).collect();
+        let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+        self.set_servers(&servers)
+    }
+
+    /// Add a server to the list of servers to contact, if it isn't already there.
+    ///
+    /// Unlike replacing the whole list with `set_servers()`, this doesn't disturb `c-ares`' record
+    /// of how the other servers have been performing.
+    ///
+    /// As with `set_servers()`, this fails with `c_ares::Error::ENOTIMP` if a `Transport` is set.
+    #[cfg(cares1_24)]
+    pub fn add_server(&self, server: SocketAddr) -> c_ares::Result<&Self> {
+        let default_port = self.options.udp_port.unwrap_or(53);
+        self.update_servers(move |servers| {
+            if !servers
+                .iter()
+                .any(|entry| server_matches(entry, server, default_port))
+            {
+                servers.push(server.to_string());
+            }
+        })?;
+        Ok(self)
+    }
+
+    /// Remove a server from the list of servers to contact, if it is there.
+    ///
+    /// Unlike replacing the whole list with `set_servers()`, this doesn't disturb `c-ares`' record
+    /// of how the other servers have been performing.
+    ///
+    /// As with `set_servers()`, this fails with `c_ares::Error::ENOTIMP` if a `Transport` is set.
+    #[cfg(cares1_24)]
+    pub fn remove_server(&self, server: SocketAddr) -> c_ares::Result<&Self> {
+        let default_port = self.options.udp_port.unwrap_or(53);
+        self.update_servers(move |servers| {
+            servers.retain(|entry| !server_matches(entry, server, default_port));
+        })?;
+        Ok(self)
+    }
+
+    // Read the list of servers, update it, and write it back.  On the event loop, this happens
+    // without any other work on the channel in between.
+    #[cfg(cares1_24)]
+    fn update_servers<F>(&self, update: F) -> c_ares::Result<()>
+    where
+        F: FnOnce(&mut Vec<String>) + Send + 'static,
+    {
+        self.check_no_transport()?;
+        if let Some(direct) = self.direct() {
+            let mut servers: Vec<String> =
+                direct.servers().iter().map(SocketAddr::to_string).collect();
+            update(&mut servers);
+            let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+            return self.set_direct_servers(&servers).map(|_| ());
+        }
+        self.submitter.call(move |channel| {
+            let mut servers: Vec<String> = channel
+                .get_servers()
+                .split(',')
+                .filter(|server| !server.is_empty())
+                .map(str::to_owned)
+                .collect();
+            update(&mut servers);
+            let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+            channel.set_servers(&servers).map(|_| ())
+        })??;
+        self.sync_derived_servers()
+    }
+
+    /// Set the list of servers to contact, each with its own UDP and TCP ports.
+    #[cfg(cares1_34)]
+    pub fn set_servers_ports(&self, servers: &[Server]) -> c_ares::Result<&Self> {
+        let servers: Vec<String> = servers.iter().map(|server| server.to_csv_entry()).collect();
+        let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+        self.set_servers(&servers)
+    }
+
+    // Send queries over TLS to `servers`, relaying them if they aren't relayed already.
+    #[cfg(all(feature = "tls", cares1_24))]
+    fn set_tls_servers(&self, servers: &[&str]) -> c_ares::Result<&Self> {
+        let servers: Vec<&str> = servers
+            .iter()
+            .flat_map(|servers| servers.split(','))
+            .map(str::trim)
+            .filter(|server| !server.is_empty())
+            .collect();
+        if !servers.iter().all(|server| tls::is_tls_server(server)) {
+            return Err(c_ares::Error::EBADSTR);
+        }
+        let transport = match self.options.tls_client_config {
+            Some(ref config) => TlsTransport::with_client_config(&servers, Arc::clone(config)),
+            None => TlsTransport::new(&servers),
+        }
+        .map_err(|_| c_ares::Error::EBADSTR)?;
+        match self.direct() {
+            Some(direct) => direct.set_tls(transport),
+            None => {
+                let tls_relay = self.start_tls_relay()?;
+                tls_relay.direct.set_tls(transport);
+            }
+        }
+        self.sync_derived_servers()?;
+        Ok(self)
+    }
+
+    // Start relaying queries, so that they can go over TLS - unless that has already happened.
+    #[cfg(all(feature = "tls", cares1_24))]
+    fn start_tls_relay(&self) -> c_ares::Result<&TlsRelay> {
+        let _guard = self.tls_lock.lock().unwrap();
+        if let Some(tls_relay) = self.tls_relay.get() {
+            return Ok(tls_relay);
+        }
+        let timeout = Duration::from_millis(self.options.timeout.unwrap_or(DEFAULT_TIMEOUT).into());
+        let direct = Arc::new(DirectTransport::new(Vec::new(), timeout, None));
+        let relay = Relay::start(
+            direct.clone(),
+            Some(self.relay_clients.clone()),
+            self.options.relay_limits_or_default(),
+        )
+        .map_err(|_| c_ares::Error::ECONNREFUSED)?;
+        let server = relay.address().to_string();
+        self.submitter
+            .call(move |channel| channel.set_servers(&[&server]).map(|_| ()))??;
+        Ok(self.tls_relay.get_or_init(|| TlsRelay { relay, direct }))
+    }
+
+    // What sends queries on to the servers, if they are relayed only so that they can be modified
+    // or go over TLS.
+    #[cfg(cares1_24)]
+    fn direct(&self) -> Option<&Arc<DirectTransport>> {
+        #[cfg(feature = "tls")]
+        if let (None, Some(tls_relay)) = (&self.direct, self.tls_relay.get()) {
+            return Some(&tls_relay.direct);
+        }
+        self.direct.as_ref()
+    }
+
+    // The address of the relay to which the channel sends queries, if there is one.
+    #[cfg(cares1_22)]
+    fn relay_server(&self) -> Option<String> {
+        #[cfg(all(feature = "tls", cares1_24))]
+        if let (None, Some(tls_relay)) = (&self.relay, self.tls_relay.get()) {
+            return Some(tls_relay.relay.address().to_string());
+        }
+        self.relay.as_ref().map(|relay| relay.address().to_string())
+    }
+
+    // Fail if queries go to a `Transport`, which - rather than the list of servers - determines
+    // where they go.
+    fn check_no_transport(&self) -> c_ares::Result<()> {
+        #[cfg(cares1_24)]
+        if self.direct().is_some() {
+            return Ok(());
+        }
+        if self.relay.is_some() {
+            return Err(c_ares::Error::ENOTIMP);
+        }
+        Ok(())
+    }
+
+    // If queries are relayed only so that they can be modified, set the servers that they are sent
+    // on to, and return true.
+    fn set_direct_servers(&self, servers: &[&str]) -> c_ares::Result<bool> {
+        #[cfg(cares1_24)]
+        if let Some(direct) = self.direct() {
+            let udp_port = self.options.udp_port.unwrap_or(53);
+            let servers = parse_servers(servers, udp_port).map_err(|_| c_ares::Error::EBADSTR)?;
+            direct.set_servers(servers);
+            return Ok(true);
+        }
+        let _ = servers;
+        Ok(false)
+    }
+
+    // Pass this resolver's servers on to the TCP resolver and any others derived from it - and
+    // have the circuit breaker forget any circuits that were opened before they were set.
+    fn sync_derived_servers(&self) -> c_ares::Result<()> {
+        #[cfg(cares1_29)]
+        self.server_state.servers_set();
+        #[cfg(cares1_24)]
+        if !self.derived.lock().unwrap().is_empty() {
+            let servers = self.channel_servers()?;
+            sync_servers(&self.derived, &servers)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the configuration in effect: the `Options` with which the resolver was created,
+    /// merged with system configuration - eg to find out why a name is being queried as it is.
+    ///
+    /// System configuration is read afresh, so this reflects it as it is now - which may not be
+    /// as it was when the resolver was created.
+    pub fn effective_options(&self) -> ResolvedConfig {
+        ResolvedConfig::new(&self.options)
+    }
+
+    /// Returns the domains searched by the `search_xxx()` methods, in order - those set by
+    /// `set_search_domains()`, if any, and otherwise those in `effective_options()`.
+    pub fn search_domains(&self) -> Vec<String> {
+        match *self.search_list.read().unwrap() {
+            Some(ref search_list) => search_list.domains.clone(),
+            None => self.effective_options().domains,
+        }
+    }
+
+    /// Returns the number of dots which must be present in a name for it to be queried "as is",
+    /// before the search domains are tried - as in `effective_options()`.
+    pub fn ndots(&self) -> u32 {
+        self.effective_options().ndots
+    }
+
+    /// Set the domains to be searched by the `search_xxx()` methods, in order, replacing those
+    /// with which the resolver was created.  An empty list means that no domains are searched.
+    ///
+    /// `c-ares` does not allow the search domains of a channel to be changed, so the resolver
+    /// makes the search itself, querying each candidate name in turn.  Host lookups - eg
+    /// `get_host_by_name()` - are made by `c-ares`, and so continue to use the domains with which
+    /// the resolver was created.
+    pub fn set_search_domains(&self, domains: &[&str]) -> &Self {
+        let search_list = SearchList {
+            domains: domains.iter().map(|&domain| domain.to_owned()).collect(),
+            ndots: self.ndots(),
+        };
+        *self.search_list.write().unwrap() = Some(search_list);
+        self
+    }
+
+    /// Retrieves the list of servers in comma delimited format.
+    ///
+    /// If queries are relayed only so that they can be modified - see eg
+    /// `Options::set_edns_client_subnet()` - these are the servers to which they are sent on, as
+    /// in `servers()`.  If they go to `tls://` servers, these are those servers' addresses.
+    #[cfg(cares1_24)]
+    pub fn get_servers(&self) -> String {
+        if let Some(direct) = self.direct() {
+            let servers: Vec<String> = direct.servers().iter().map(SocketAddr::to_string).collect();
+            return servers.join(",");
+        }
+        self.channel_servers().unwrap_or_default()
+    }
+
+    // The servers set on the channel - which, if queries are relayed, is the relay.
+    #[cfg(cares1_24)]
+    fn channel_servers(&self) -> c_ares::Result<String> {
+        self.submitter
+            .call(|channel| channel.get_servers().to_string())
+    }
+
+    /// Retrieves the list of servers in the comma delimited format that `c-ares` uses, including
+    /// ports and any other attributes - in the form accepted by `set_servers_csv()`.
+    #[cfg(cares1_24)]
+    pub fn get_servers_csv(&self) -> String {
+        self.get_servers()
+    }
+
+    /// Returns the servers that the resolver is using - whether set explicitly, or read from
+    /// system configuration.
+    ///
+    /// If a `Transport` is set, queries are relayed to it through a server on the loopback
+    /// interface, and that is what is reported here.
+    #[cfg(cares1_24)]
+    pub fn servers(&self) -> Vec<ServerInfo> {
+        if let Some(direct) = self.direct() {
+            return direct
+                .servers()
+                .into_iter()
+                .map(|server| ServerInfo {
+                    addr: server.ip(),
+                    udp_port: server.port(),
+                    tcp_port: server.port(),
+                    interface: None,
+                })
+                .collect();
+        }
+        let default_port = self.options.udp_port.unwrap_or(53);
+        self.channel_servers()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|server| ServerInfo::parse(server, default_port))
+            .collect()
+    }
+
+    /// Set the local IPv4 address from which to make queries.
+    ///
+    /// This returns at once, and the address is set on the event loop - before any query made
+    /// after this returns.
+    pub fn set_local_ipv4(&self, ipv4: Ipv4Addr) -> &Self {
+        self.submitter.submit(move |channel| {
+            channel.set_local_ipv4(ipv4);
+        });
+        self
+    }
+
+    /// Set the local IPv6 address from which to make queries.
+    ///
+    /// This returns at once, and the address is set on the event loop - before any query made
+    /// after this returns.
+    pub fn set_local_ipv6(&self, ipv6: &Ipv6Addr) -> &Self {
+        let ipv6 = *ipv6;
+        self.submitter.submit(move |channel| {
+            channel.set_local_ipv6(&ipv6);
+        });
+        self
+    }
+
+    /// Set the local device from which to make queries.
+    ///
+    /// This returns at once, and the device is set on the event loop - before any query made after
+    /// this returns.
+    pub fn set_local_device(&self, device: &str) -> &Self {
+        let device = device.to_owned();
+        self.submitter.submit(move |channel| {
+            channel.set_local_device(&device);
+        });
+        self
+    }
+
+    /// Initializes an address sortlist configuration, so that addresses returned by
+    /// `get_host_by_name()` are sorted according to the sortlist.
+    ///
+    /// Each element of the sortlist holds an IP-address/netmask pair. The netmask is optional but
+    /// follows the address after a slash if present. For example: "130.155.160.0/255.255.240.0",
+    /// or "130.155.0.0".
+    pub fn set_sortlist(&self, sortlist: &[&str]) -> c_ares::Result<&Self> {
+        let sortlist: Vec<String> = sortlist.iter().map(|&s| s.to_owned()).collect();
+        self.submitter.call(move |channel| {
+            let sortlist: Vec<&str> = sortlist.iter().map(String::as_str).collect();
+            channel.set_sortlist(&sortlist).map(|_| ())
+        })??;
+        Ok(self)
+    }
+
+    /// Pin `name` to `addresses`, replacing any override already set for it.
+    ///
+    /// Queries for the A and AAAA records of `name`, and host lookups of `name`, are then answered
+    /// from `addresses`, without consulting the servers or the hosts file.  An override with no
+    /// addresses of the family asked for gives `ENODATA`.
+    ///
+    /// Host lookups through this `Resolver` honour overrides only with c-ares 1.19 or later, and
+    /// then - as for an entry in the hosts file - answer a lookup with family `UNSPEC` only with
+    /// the addresses of one family.
+    pub fn add_host_override(&self, name: &str, addresses: &[IpAddr]) -> &Self {
+        self.host_overrides.add(name, addresses);
+        self
+    }
+
+    /// Remove the override for `name`, returning whether there was one.
+    pub fn remove_host_override(&self, name: &str) -> bool {
+        self.host_overrides.remove(name)
+    }
+
+    /// Remove all host overrides.
+    pub fn clear_host_overrides(&self) -> &Self {
+        self.host_overrides.clear();
+        self
+    }
+
+    /// Set a callback function to be invoked whenever a query for a name completes, with a
+    /// record of the query - a lightweight way to log queries.
+    ///
+    /// The callback is called on the event loop, so should be quick.  It replaces any previous
+    /// callback, and applies to queries made after it is set.  Queries that fail before being
+    /// made - eg because the name is invalid - are not logged.
+    pub fn set_query_log<F>(&self, callback: F) -> &Self
+    where
+        F: Fn(&QueryLogEntry) + Send + Sync + 'static,
+    {
+        *self.query_log.write().unwrap() = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns the counters and latency histograms that this resolver maintains for the queries
+    /// that it makes - including those made by its TCP resolver.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Returns how busy this resolver is: the number of queries in flight, and the rate and
+    /// average latency of queries over the last minute - eg for a client that wants to back off
+    /// when the resolver is saturated.  It also gives the state of the queue and of the rate
+    /// limiter, if they are configured.
+    ///
+    /// As in `MetricsSnapshot`, only queries for names are counted.
+    pub fn load(&self) -> LoadStats {
+        let mut load = self.metrics.load();
+        self.dispatcher.add_load(&mut load);
+        load
+    }
+
+    /// Returns an error if a query made now would fail at once: `Error::Overloaded` if the resolver
+    /// has as many queries outstanding and queued as it is configured to allow - see
+    /// `Options::set_max_queued_queries()` - or `Error::MemoryBudgetExceeded` if its memory budget
+    /// is used up - see `Options::set_memory_budget()`.
+    ///
+    /// This lets callers shed load before making queries, rather than by handling their failures.
+    pub fn check_capacity(&self) -> Result<(), Error> {
+        self.dispatcher.check_capacity()
+    }
+
+    /// Make a query with `query`, returning an error if the resolver rejects it at once, without
+    /// going to `c-ares` - eg `Error::Overloaded` if its queue is full.
+    ///
+    /// A rejected query still fails through its handler, which - as for any other query - is
+    /// called on the event loop thread.  But the handler gets only the nearest `c_ares::Error`,
+    /// which doesn't say why: eg `ENOMEM` for both `Error::Overloaded` and
+    /// `Error::MemoryBudgetExceeded`.
+    ///
+    /// ```no_run
+    /// use c_ares_resolver::Resolver;
+    ///
+    /// let resolver = Resolver::new().unwrap();
+    /// if let Err(error) = resolver.try_query(|resolver| {
+    ///     resolver.query_a("example.com", |result| println!("{}", result.is_ok()))
+    /// }) {
+    ///     println!("rejected: {error}");
+    /// }
+    /// ```
+    pub fn try_query<F>(&self, query: F) -> Result<(), Error>
+    where
+        F: FnOnce(&Self),
+    {
+        let ((), rejection) = rejection::catch(|| query(self));
+        match rejection {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// The ID of the thread on which this resolver runs its event loop - and so calls query
+    /// handlers.  See `Options::set_thread_name()`.
+    ///
+    /// A resolver returned by `tcp()` has its own event loop thread.
+    pub fn thread_id(&self) -> ThreadId {
+        self._event_loop_stopper.thread_id()
+    }
+
+    // The budget to which results awaiting consumption are charged.
+    pub(crate) fn memory_budget(&self) -> &Arc<MemoryBudget> {
+        &self.budget
+    }
+
+    // The permits that async callers acquire before making queries.
+    pub(crate) fn permits(&self) -> &Arc<Permits> {
+        &self.permits
+    }
+
+    /// Set a callback function to be invoked whenever a query on the channel completes.
+    ///
+    /// `callback(server, success, flags)` will be called when a query completes.
+    ///
+    /// - `server` indicates the DNS server that was used for the query.
+    /// - `success` indicates whether the query succeeded or not.
+    /// - `flags` is a bitmask of flags describing various aspects of the query.
+    #[cfg(cares1_29)]
+    pub fn set_server_state_callback<F>(&self, callback: F) -> &Self
+    where
+        F: FnMut(&str, bool, ServerStateFlags) + Send + 'static,
+    {
+        *self.server_state.callback.lock().unwrap() = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns health statistics for each server that has been queried, keyed by server.
+    ///
+    /// These are gathered from the same information as is passed to the server state callback.
+    #[cfg(cares1_29)]
+    pub fn server_stats(&self) -> HashMap<String, ServerStats> {
+        self.server_state.stats.lock().unwrap().clone()
+    }
+
+    /// Look up the A records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_a<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(1), false, handler);
+        let (id, handler) = self.tracked(&name, Some(1), handler);
+        if let Some(result) = self.host_overrides.a(&name) {
+            self.submitter.submit(move |_| handler(result));
+            return;
+        }
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_a(&name, handler));
+    }
+
+    /// Search for the A records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_a<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(1), true, handler);
+        let (id, handler) = self.tracked(&name, Some(1), handler);
+        if let Some(result) = self.host_overrides.a(&name) {
+            self.submitter.submit(move |_| handler(result));
+            return;
+        }
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            1,
+            c_ares::AResults::parse_from,
+            handler,
+            |channel| channel.search_a(&name, handler)
+        );
+    }
+
+    /// Look up the AAAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_aaaa<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(28), false, handler);
+        let (id, handler) = self.tracked(&name, Some(28), handler);
+        if let Some(result) = self.host_overrides.aaaa(&name) {
+            self.submitter.submit(move |_| handler(result));
+            return;
+        }
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_aaaa(&name, handler));
+    }
+
+    /// Search for the AAAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
     pub fn search_aaaa<F>(&self, name: &str, handler: F)
     where
-        F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
+        F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(28), true, handler);
+        let (id, handler) = self.tracked(&name, Some(28), handler);
+        if let Some(result) = self.host_overrides.aaaa(&name) {
+            self.submitter.submit(move |_| handler(result));
+            return;
+        }
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            28,
+            c_ares::AAAAResults::parse_from,
+            handler,
+            |channel| channel.search_aaaa(&name, handler)
+        );
+    }
+
+    /// Look up all of the records associated with `name`, of whatever type.
+    ///
+    /// Following RFC 8482, many servers answer ANY queries with only some of their records - or
+    /// refuse them altogether.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn query_any<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<AnyResults>) + Send + 'static,
+    {
+        self.query(name, C_IN, T_ANY, move |result| {
+            handler(result.and_then(AnyResults::parse))
+        });
+    }
+
+    /// Search for all of the records associated with `name`, of whatever type.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn search_any<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<AnyResults>) + Send + 'static,
+    {
+        self.search(name, C_IN, T_ANY, move |result| {
+            handler(result.and_then(AnyResults::parse))
+        });
+    }
+
+    /// Look up the CAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_17)]
+    pub fn query_caa<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::CAAResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(257), false, handler);
+        let (id, handler) = self.tracked(&name, Some(257), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_caa(&name, handler));
+    }
+
+    /// Search for the CAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_17)]
+    pub fn search_caa<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::CAAResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(257), true, handler);
+        let (id, handler) = self.tracked(&name, Some(257), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            257,
+            c_ares::CAAResults::parse_from,
+            handler,
+            |channel| channel.search_caa(&name, handler)
+        );
+    }
+
+    /// Look up the CNAME records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_cname<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::CNameResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(5), false, handler);
+        let (id, handler) = self.tracked(&name, Some(5), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_cname(&name, handler));
+    }
+
+    /// Search for the CNAME records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn search_cname<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::CNameResults>) + Send + 'static,
+    {
+        let name = query_name!(self, name, Some(5), true, handler);
+        let (id, handler) = self.tracked(&name, Some(5), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            5,
+            c_ares::CNameResults::parse_from,
+            handler,
+            |channel| channel.search_cname(&name, handler)
+        );
+    }
+
+    /// Look up the DNSKEY records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn query_dnskey<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<DNSKEYResults>) + Send + 'static,
+    {
+        self.query(name, C_IN, T_DNSKEY, move |result| {
+            handler(result.and_then(DNSKEYResults::parse))
+        });
+    }
+
+    /// Search for the DNSKEY records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn search_dnskey<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<DNSKEYResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_aaaa(name, handler)
+        self.search(name, C_IN, T_DNSKEY, move |result| {
+            handler(result.and_then(DNSKEYResults::parse))
+        });
     }
 
-    /// Look up the CAA records associated with `name`.
+    /// Look up the DS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    #[cfg(cares1_17)]
-    pub fn query_caa<F>(&self, name: &str, handler: F)
+    #[cfg(cares1_22)]
+    pub fn query_ds<F>(&self, name: &str, handler: F)
     where
-        F: FnOnce(c_ares::Result<c_ares::CAAResults>) + Send + 'static,
+        F: FnOnce(c_ares::Result<DSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_caa(name, handler)
+        self.query(name, C_IN, T_DS, move |result| {
+            handler(result.and_then(DSResults::parse))
+        });
     }
 
-    /// Search for the CAA records associated with `name`.
+    /// Search for the DS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    #[cfg(cares1_17)]
-    pub fn search_caa<F>(&self, name: &str, handler: F)
+    #[cfg(cares1_22)]
+    pub fn search_ds<F>(&self, name: &str, handler: F)
     where
-        F: FnOnce(c_ares::Result<c_ares::CAAResults>) + Send + 'static,
+        F: FnOnce(c_ares::Result<DSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_caa(name, handler)
+        self.search(name, C_IN, T_DS, move |result| {
+            handler(result.and_then(DSResults::parse))
+        });
     }
 
-    /// Look up the CNAME records associated with `name`.
+    /// Look up the HTTPS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_cname<F>(&self, name: &str, handler: F)
+    #[cfg(cares1_22)]
+    pub fn query_https<F>(&self, name: &str, handler: F)
     where
-        F: FnOnce(c_ares::Result<c_ares::CNameResults>) + Send + 'static,
+        F: FnOnce(c_ares::Result<HTTPSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_cname(name, handler)
+        self.query(name, C_IN, T_HTTPS, move |result| {
+            handler(result.and_then(HTTPSResults::parse))
+        });
     }
 
-    /// Search for the CNAME records associated with `name`.
+    /// Search for the HTTPS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn search_cname<F>(&self, name: &str, handler: F)
+    #[cfg(cares1_22)]
+    pub fn search_https<F>(&self, name: &str, handler: F)
     where
-        F: FnOnce(c_ares::Result<c_ares::CNameResults>) + Send + 'static,
+        F: FnOnce(c_ares::Result<HTTPSResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search_cname(name, handler)
+        self.search(name, C_IN, T_HTTPS, move |result| {
+            handler(result.and_then(HTTPSResults::parse))
+        });
     }
 
     /// Look up the MX records associated with `name`.
@@ -372,7 +2764,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::MXResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_mx(name, handler)
+        let name = query_name!(self, name, Some(15), false, handler);
+        let (id, handler) = self.tracked(&name, Some(15), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_mx(&name, handler));
     }
 
     /// Search for the MX records associated with `name`.
@@ -382,7 +2777,18 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::MXResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_mx(name, handler)
+        let name = query_name!(self, name, Some(15), true, handler);
+        let (id, handler) = self.tracked(&name, Some(15), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            15,
+            c_ares::MXResults::parse_from,
+            handler,
+            |channel| channel.search_mx(&name, handler)
+        );
     }
 
     /// Look up the NAPTR records associated with `name`.
@@ -392,7 +2798,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NAPTRResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_naptr(name, handler)
+        let name = query_name!(self, name, Some(35), false, handler);
+        let (id, handler) = self.tracked(&name, Some(35), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_naptr(&name, handler));
     }
 
     /// Search for the NAPTR records associated with `name`.
@@ -402,10 +2811,18 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NAPTRResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search_naptr(name, handler)
+        let name = query_name!(self, name, Some(35), true, handler);
+        let (id, handler) = self.tracked(&name, Some(35), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            35,
+            c_ares::NAPTRResults::parse_from,
+            handler,
+            |channel| channel.search_naptr(&name, handler)
+        );
     }
 
     /// Look up the NS records associated with `name`.
@@ -415,7 +2832,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_ns(name, handler)
+        let name = query_name!(self, name, Some(2), false, handler);
+        let (id, handler) = self.tracked(&name, Some(2), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_ns(&name, handler));
     }
 
     /// Search for the NS records associated with `name`.
@@ -425,7 +2845,18 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::NSResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_ns(name, handler)
+        let name = query_name!(self, name, Some(2), true, handler);
+        let (id, handler) = self.tracked(&name, Some(2), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            2,
+            c_ares::NSResults::parse_from,
+            handler,
+            |channel| channel.search_ns(&name, handler)
+        );
     }
 
     /// Look up the PTR records associated with `name`.
@@ -435,7 +2866,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::PTRResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_ptr(name, handler)
+        let name = query_name!(self, name, Some(12), false, handler);
+        let (id, handler) = self.tracked(&name, Some(12), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_ptr(&name, handler));
     }
 
     /// Search for the PTR records associated with `name`.
@@ -445,7 +2879,56 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::PTRResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_ptr(name, handler)
+        let name = query_name!(self, name, Some(12), true, handler);
+        let (id, handler) = self.tracked(&name, Some(12), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            12,
+            c_ares::PTRResults::parse_from,
+            handler,
+            |channel| channel.search_ptr(&name, handler)
+        );
+    }
+
+    /// Look up the PTR records for the address `ip` - at its `in-addr.arpa` or `ip6.arpa` name.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_ptr_for<F>(&self, ip: &IpAddr, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::PTRResults>) + Send + 'static,
+    {
+        self.query_ptr(&reverse_name(ip), handler);
+    }
+
+    /// Look up the RRSIG records associated with `name`.
+    ///
+    /// Signatures are returned only by servers that support DNSSEC.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn query_rrsig<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<RRSIGResults>) + Send + 'static,
+    {
+        self.query(name, C_IN, T_RRSIG, move |result| {
+            handler(result.and_then(RRSIGResults::parse))
+        });
+    }
+
+    /// Search for the RRSIG records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn search_rrsig<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<RRSIGResults>) + Send + 'static,
+    {
+        self.search(name, C_IN, T_RRSIG, move |result| {
+            handler(result.and_then(RRSIGResults::parse))
+        });
     }
 
     /// Look up the SOA record associated with `name`.
@@ -455,7 +2938,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SOAResult>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_soa(name, handler)
+        let name = query_name!(self, name, Some(6), false, handler);
+        let (id, handler) = self.tracked(&name, Some(6), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_soa(&name, handler));
     }
 
     /// Search for the SOA record associated with `name`.
@@ -465,7 +2951,18 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SOAResult>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_soa(name, handler)
+        let name = query_name!(self, name, Some(6), true, handler);
+        let (id, handler) = self.tracked(&name, Some(6), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            6,
+            c_ares::SOAResult::parse_from,
+            handler,
+            |channel| channel.search_soa(&name, handler)
+        );
     }
 
     /// Look up the SRV records associated with `name`.
@@ -475,7 +2972,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SRVResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_srv(name, handler)
+        let name = query_name!(self, name, Some(33), false, handler);
+        let (id, handler) = self.tracked(&name, Some(33), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_srv(&name, handler));
     }
 
     /// Search for the SRV records associated with `name`.
@@ -485,7 +2985,70 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::SRVResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_srv(name, handler)
+        let name = query_name!(self, name, Some(33), true, handler);
+        let (id, handler) = self.tracked(&name, Some(33), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            33,
+            c_ares::SRVResults::parse_from,
+            handler,
+            |channel| channel.search_srv(&name, handler)
+        );
+    }
+
+    /// Look up the SSHFP records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn query_sshfp<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<SSHFPResults>) + Send + 'static,
+    {
+        self.query(name, C_IN, T_SSHFP, move |result| {
+            handler(result.and_then(SSHFPResults::parse))
+        });
+    }
+
+    /// Search for the SSHFP records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn search_sshfp<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<SSHFPResults>) + Send + 'static,
+    {
+        self.search(name, C_IN, T_SSHFP, move |result| {
+            handler(result.and_then(SSHFPResults::parse))
+        });
+    }
+
+    /// Look up the TLSA records associated with `name` - eg `_25._tcp.mail.example.com`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn query_tlsa<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<TLSAResults>) + Send + 'static,
+    {
+        self.query(name, C_IN, T_TLSA, move |result| {
+            handler(result.and_then(TLSAResults::parse))
+        });
+    }
+
+    /// Search for the TLSA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_22)]
+    pub fn search_tlsa<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<TLSAResults>) + Send + 'static,
+    {
+        self.search(name, C_IN, T_TLSA, move |result| {
+            handler(result.and_then(TLSAResults::parse))
+        });
     }
 
     /// Look up the TXT records associated with `name`.
@@ -495,7 +3058,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::TXTResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_txt(name, handler)
+        let name = query_name!(self, name, Some(16), false, handler);
+        let (id, handler) = self.tracked(&name, Some(16), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_txt(&name, handler));
     }
 
     /// Search for the TXT records associated with `name`.
@@ -505,7 +3071,18 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::TXTResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_txt(name, handler)
+        let name = query_name!(self, name, Some(16), true, handler);
+        let (id, handler) = self.tracked(&name, Some(16), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            16,
+            c_ares::TXTResults::parse_from,
+            handler,
+            |channel| channel.search_txt(&name, handler)
+        );
     }
 
     /// Look up the URI records associated with `name`.
@@ -515,7 +3092,10 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::URIResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().query_uri(name, handler)
+        let name = query_name!(self, name, Some(256), false, handler);
+        let (id, handler) = self.tracked(&name, Some(256), handler);
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query_uri(&name, handler));
     }
 
     /// Search for the URI records associated with `name`.
@@ -525,7 +3105,89 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::URIResults>) + Send + 'static,
     {
-        self.ares_channel.lock().unwrap().search_uri(name, handler)
+        let name = query_name!(self, name, Some(256), true, handler);
+        let (id, handler) = self.tracked(&name, Some(256), handler);
+        search!(
+            self,
+            id,
+            name,
+            C_IN,
+            256,
+            c_ares::URIResults::parse_from,
+            handler,
+            |channel| channel.search_uri(&name, handler)
+        );
+    }
+
+    /// Look up the SPF record published at `domain`.
+    ///
+    /// On completion, `handler` is called with the result.  It's an error - `EBADRESP` - for a
+    /// domain to publish more than one SPF record.
+    pub fn lookup_spf<F>(&self, domain: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<SpfRecord>) + Send + 'static,
+    {
+        self.query_txt(domain, move |result| {
+            handler(result.and_then(|results| SpfRecord::find(&results)))
+        });
+    }
+
+    /// Look up the DMARC record for `domain`, published at `_dmarc.<domain>`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn lookup_dmarc<F>(&self, domain: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<DmarcRecord>) + Send + 'static,
+    {
+        let name = format!("_dmarc.{domain}");
+        self.query_txt(&name, move |result| {
+            handler(result.and_then(|results| DmarcRecord::find(&results)))
+        });
+    }
+
+    /// Look up the DKIM key record for `selector` at `domain`, published at
+    /// `<selector>._domainkey.<domain>`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn lookup_dkim<F>(&self, selector: &str, domain: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<DkimRecord>) + Send + 'static,
+    {
+        let name = format!("{selector}._domainkey.{domain}");
+        self.query_txt(&name, move |result| {
+            handler(result.and_then(|results| DkimRecord::find(&results)))
+        });
+    }
+
+    /// Look up the URIs for the E.164 telephone number `number` - eg `+14155551234` - via ENUM
+    /// (RFC 6116).
+    ///
+    /// This queries the NAPTR records at the number's `e164.arpa` domain, and applies their
+    /// rewrite rules to the number.  Only terminal rules are followed, and rules using regular
+    /// expression features beyond literals, `.`, bracket expressions, `*`, `+`, `?`, anchors and
+    /// groups are skipped.
+    ///
+    /// On completion, `handler` is called with the candidate URIs, in the order in which they
+    /// should be tried.  A number that isn't of the form `+<digits>` gives `EBADNAME`.
+    pub fn enum_lookup<F>(&self, number: &str, handler: F)
+    where
+        F: FnOnce(c_ares::Result<Vec<EnumUri>>) + Send + 'static,
+    {
+        let Some(domain) = e164::enum_domain(number) else {
+            self.reject(handler, Error::Ares(c_ares::Error::EBADNAME));
+            return;
+        };
+        let number = number.to_owned();
+        self.query_naptr(&domain, move |result| {
+            let uris = result.map(|results| e164::enum_uris(&number, &results));
+            handler(uris.and_then(|uris| {
+                if uris.is_empty() {
+                    Err(c_ares::Error::ENODATA)
+                } else {
+                    Ok(uris)
+                }
+            }))
+        });
     }
 
     /// Perform a host query by address.
@@ -535,10 +3197,13 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::HostResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_host_by_address(address, handler)
+        if !self.is_allowed(&reverse_name(address)) {
+            self.reject(handler, Error::DomainNotAllowed);
+            return;
+        }
+        let address = *address;
+        self.submitter
+            .submit(move |channel| channel.get_host_by_address(&address, handler));
     }
 
     /// Perform a host query by name.
@@ -548,63 +3213,787 @@ impl Resolver {
     where
         F: FnOnce(c_ares::Result<c_ares::HostResults>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_host_by_name(name, family, handler);
+        let name = query_name!(self, name, None, false, handler);
+        let tracker = self.track_query(&name, None);
+        let id = tracker.id();
+        let handler = move |result: c_ares::Result<c_ares::HostResults>| {
+            let error = result.as_ref().err().copied();
+            tracker.finish(error, || handler(result));
+        };
+        #[cfg(cares1_19)]
+        if self.host_overrides.contains(&name) {
+            let host_overrides = Arc::clone(&self.host_overrides);
+            self.submitter.submit(move |channel| {
+                if let Err(handler) = host_overrides.get_host_by_name(&name, family, handler) {
+                    // The override has been removed since: look the name up after all.
+                    channel.get_host_by_name(&name, family, handler);
+                }
+            });
+            return;
+        }
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .get_host_by_name(&name, family, handler));
     }
 
     /// Address-to-nodename translation in protocol-independent manner.
     ///
+    /// `flags` may be given either as `c_ares::NIFlags` or as `NameInfoOptions`.
+    ///
     /// On completion, `handler` is called with the result.
-    pub fn get_name_info<F>(&self, address: &SocketAddr, flags: c_ares::NIFlags, handler: F)
-    where
+    pub fn get_name_info<F>(
+        &self,
+        address: &SocketAddr,
+        flags: impl Into<c_ares::NIFlags>,
+        handler: F,
+    ) where
         F: FnOnce(c_ares::Result<c_ares::NameInfoResult>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .get_name_info(address, flags, handler)
+        let flags = flags.into();
+        if !flags.contains(c_ares::NIFlags::NUMERICHOST)
+            && !self.is_allowed(&reverse_name(&address.ip()))
+        {
+            self.reject(handler, Error::DomainNotAllowed);
+            return;
+        }
+        let address = *address;
+        self.submitter
+            .submit(move |channel| channel.get_name_info(&address, flags, handler));
     }
 
     /// Initiate a single-question DNS query for `name`.  The class and type of the query are per
-    /// the provided parameters, taking values as defined in `arpa/nameser.h`.
+    /// the provided parameters, which may be given as numbers per `arpa/nameser.h`.
     ///
     /// On completion, `handler` is called with the result.
     ///
     /// This method is provided so that users can query DNS types for which `c-ares` does not
     /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
     /// `query_xxx()` is available, that should be used.
-    pub fn query<F>(&self, name: &str, dns_class: u16, query_type: u16, handler: F)
-    where
+    pub fn query<F>(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        handler: F,
+    ) where
         F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .query(name, dns_class, query_type, handler);
+        let dns_class = u16::from(dns_class.into());
+        let query_type = u16::from(query_type.into());
+        let name = query_name!(self, name, Some(query_type), false, handler);
+        let tracker = self.track_query(&name, Some(query_type));
+        let id = tracker.id();
+        let handler = move |result: c_ares::Result<&[u8]>| {
+            let error = result.as_ref().err().copied();
+            tracker.finish(error, || handler(result));
+        };
+        dispatch!(self, id, name.len(), handler, |channel| channel
+            .query(&name, dns_class, query_type, handler));
     }
 
     /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
-    /// query are per the provided parameters, taking values as defined in `arpa/nameser.h`.
+    /// query are per the provided parameters, which may be given as numbers per `arpa/nameser.h`.
     ///
     /// On completion, `handler` is called with the result.
     ///
     /// This method is provided so that users can search DNS types for which `c-ares` does not
     /// provide a parser; or in case a third-party parser is preferred.  Usually, if a suitable
     /// `search_xxx()` is available, that should be used.
-    pub fn search<F>(&self, name: &str, dns_class: u16, query_type: u16, handler: F)
+    pub fn search<F>(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        handler: F,
+    ) where
+        F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
+    {
+        let dns_class = u16::from(dns_class.into());
+        let query_type = u16::from(query_type.into());
+        let name = query_name!(self, name, Some(query_type), true, handler);
+        let tracker = self.track_query(&name, Some(query_type));
+        let id = tracker.id();
+        let handler = move |result: c_ares::Result<&[u8]>| {
+            let error = result.as_ref().err().copied();
+            tracker.finish(error, || handler(result));
+        };
+        search!(
+            self,
+            id,
+            name,
+            dns_class,
+            query_type,
+            Ok,
+            handler,
+            |channel| channel.search(&name, dns_class, query_type, handler)
+        );
+    }
+
+    /// Look up the A records associated with `name`, as by `query_a()` - and report how the
+    /// query was answered.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn query_a_ext<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(QueryResponse<c_ares::AResults>) + Send + 'static,
+    {
+        self.query_a(name, response::with_response(handler));
+    }
+
+    /// Search for the A records associated with `name`, as by `search_a()` - and report how the
+    /// query was answered.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn search_a_ext<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(QueryResponse<c_ares::AResults>) + Send + 'static,
+    {
+        self.search_a(name, response::with_response(handler));
+    }
+
+    /// Look up the AAAA records associated with `name`, as by `query_aaaa()` - and report how the
+    /// query was answered.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn query_aaaa_ext<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(QueryResponse<c_ares::AAAAResults>) + Send + 'static,
+    {
+        self.query_aaaa(name, response::with_response(handler));
+    }
+
+    /// Search for the AAAA records associated with `name`, as by `search_aaaa()` - and report how
+    /// the query was answered.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn search_aaaa_ext<F>(&self, name: &str, handler: F)
+    where
+        F: FnOnce(QueryResponse<c_ares::AAAAResults>) + Send + 'static,
+    {
+        self.search_aaaa(name, response::with_response(handler));
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query()` - and report how the query
+    /// was answered.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn query_ext<F>(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        handler: F,
+    ) where
+        F: FnOnce(QueryResponse<&[u8]>) + Send + 'static,
+    {
+        let start = Instant::now();
+        self.query(name, dns_class, query_type, move |result| {
+            handler(response::response(result, start));
+        });
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`, as by `search()` - and report
+    /// how the query was answered.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn search_ext<F>(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        handler: F,
+    ) where
+        F: FnOnce(QueryResponse<&[u8]>) + Send + 'static,
+    {
+        let start = Instant::now();
+        self.search(name, dns_class, query_type, move |result| {
+            handler(response::response(result, start));
+        });
+    }
+
+    /// Initiate a single-question DNS query for `name`, as by `query_ext()` - with the header of
+    /// the response parsed.
+    ///
+    /// Note that `c-ares` reports a response with a failing response code as an error - eg
+    /// `ESERVFAIL` - so that the header is available only for successful responses.
+    ///
+    /// On completion, `handler` is called with the response.
+    #[cfg(cares1_29)]
+    pub fn query_raw_ext<F>(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        handler: F,
+    ) where
+        F: FnOnce(QueryResponse<RawResponse<&[u8]>>) + Send + 'static,
+    {
+        self.query_ext(name, dns_class, query_type, move |response| {
+            handler(response.and_then_result(RawResponse::new));
+        });
+    }
+
+    /// Search for the A records associated with `name`, as by `search_a()` - with `flags` applied
+    /// to this query only.  Eg with `QueryFlags::NO_SEARCH`, this is as by `query_a()`.
+    ///
+    /// `c-ares` applies such settings to all of a channel's queries, so a query with flags is made
+    /// by a separate resolver for those flags: as for `tcp()`, it is created on first use, and
+    /// shares this resolver's servers and state - including its limits on outstanding and queued
+    /// queries, rate limit and memory budget.  There is at most one such resolver for each
+    /// combination of flags, each running its own event loop thread and kept for as long as this
+    /// resolver - so at most seven threads in all.  With no flags, the query is made by this
+    /// resolver.
+    ///
+    /// If the resolver for `flags` can't be created, the query fails with the nearest
+    /// `c_ares::Error` - or, by way of `try_query()`, with the error that creating it gave.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_24)]
+    pub fn search_a_opts<F>(&self, name: &str, flags: QueryFlags, handler: F)
+    where
+        F: FnOnce(c_ares::Result<c_ares::AResults>) + Send + 'static,
+    {
+        match self.flagged_resolver(flags) {
+            Ok(Some(resolver)) => resolver.search_a(name, handler),
+            Ok(None) => self.search_a(name, handler),
+            Err(error) => self.reject(handler, error),
+        }
+    }
+
+    /// Search for the AAAA records associated with `name`, as by `search_aaaa()` - with `flags`
+    /// applied to this query only.  See `search_a_opts()`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_24)]
+    pub fn search_aaaa_opts<F>(&self, name: &str, flags: QueryFlags, handler: F)
     where
+        F: FnOnce(c_ares::Result<c_ares::AAAAResults>) + Send + 'static,
+    {
+        match self.flagged_resolver(flags) {
+            Ok(Some(resolver)) => resolver.search_aaaa(name, handler),
+            Ok(None) => self.search_aaaa(name, handler),
+            Err(error) => self.reject(handler, error),
+        }
+    }
+
+    /// Initiate a series of single-question DNS queries for `name`, as by `search()` - with `flags`
+    /// applied to this query only.  See `search_a_opts()`.
+    ///
+    /// On completion, `handler` is called with the result.
+    #[cfg(cares1_24)]
+    pub fn search_opts<F>(
+        &self,
+        name: &str,
+        dns_class: impl Into<DnsClass>,
+        query_type: impl Into<RecordType>,
+        flags: QueryFlags,
+        handler: F,
+    ) where
         F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
     {
-        self.ares_channel
-            .lock()
-            .unwrap()
-            .search(name, dns_class, query_type, handler);
+        match self.flagged_resolver(flags) {
+            Ok(Some(resolver)) => resolver.search(name, dns_class, query_type, handler),
+            Ok(None) => self.search(name, dns_class, query_type, handler),
+            Err(error) => self.reject(handler, error),
+        }
     }
 
     /// Cancel all requests made on this `Resolver`.
+    ///
+    /// This returns at once, and the requests are cancelled on the event loop: their handlers are
+    /// called there with `c_ares::Error::ECANCELLED`.  Requests made after this returns are not
+    /// cancelled.
     pub fn cancel(&self) {
-        self.ares_channel.lock().unwrap().cancel();
+        self.submitter.submit(|channel| channel.cancel());
+    }
+
+    // A tracker for a query for `name`.
+    fn track_query(&self, name: &str, query_type: Option<u16>) -> QueryTracker {
+        let log = self.query_log.read().unwrap().clone();
+        let dispatcher = Arc::downgrade(&self.dispatcher);
+        QueryTracker::new(log, Arc::clone(&self.metrics), dispatcher, name, query_type)
+    }
+
+    // Wrap `handler` so that the query is recorded on completion - returning it with the ID of the
+    // query.
+    fn tracked<T, F>(
+        &self,
+        name: &str,
+        query_type: Option<u16>,
+        handler: F,
+    ) -> (u64, impl FnOnce(c_ares::Result<T>) + Send + 'static)
+    where
+        F: FnOnce(c_ares::Result<T>) + Send + 'static,
+    {
+        let tracker = self.track_query(name, query_type);
+        let id = tracker.id();
+        let handler = move |result: c_ares::Result<T>| {
+            let error = result.as_ref().err().copied();
+            tracker.finish(error, || handler(result));
+        };
+        (id, handler)
+    }
+
+    // The form in which `name` is passed to `c-ares` - after applying any query policy, converted
+    // to A-labels if need be, and checked.
+    fn query_name(
+        &self,
+        name: &str,
+        query_type: Option<u16>,
+        search: bool,
+    ) -> Result<String, Error> {
+        let rewritten;
+        let mut name = name;
+        if let Some(ref policy) = self.options.query_policy {
+            let info = QueryInfo {
+                name,
+                query_type,
+                search,
+            };
+            match policy(&info) {
+                Decision::Allow => {}
+                Decision::Deny(error) => return Err(Error::from(error)),
+                Decision::Rewrite(new_name) => {
+                    rewritten = new_name;
+                    name = &rewritten;
+                }
+            }
+        }
+        let aliased;
+        if let Some(alias) = self.host_alias(name, search) {
+            aliased = alias;
+            name = &aliased;
+        }
+        #[cfg(feature = "idna")]
+        let name = idn::to_ascii(name, self.options.idna_mode.unwrap_or_default())
+            .map_err(|_| name::InvalidName::Idna)?;
+        #[cfg(not(feature = "idna"))]
+        let name = name.to_owned();
+        name::check_name(&name)?;
+        if !self.is_allowed(&name) {
+            return Err(Error::DomainNotAllowed);
+        }
+        #[cfg(cares1_24)]
+        if self.options.local_names == Some(LocalNames::Reject) && transport::is_local_name(&name) {
+            return Err(Error::LocalName);
+        }
+        Ok(name)
+    }
+
+    // The names to query in turn when searching for `name`, if search domains have been set at
+    // runtime - following `c-ares`, which tries a name with enough dots "as is" first, and
+    // otherwise last.
+    fn search_names(&self, name: &str) -> Option<VecDeque<String>> {
+        let search_list = self.search_list.read().unwrap();
+        let search_list = search_list.as_ref()?;
+        let no_search = self
+            .options
+            .flags()
+            .is_some_and(|flags| flags.contains(c_ares::Flags::NOSEARCH));
+        if no_search || name.ends_with('.') {
+            return Some(VecDeque::from([name.to_owned()]));
+        }
+        let mut names: VecDeque<String> = search_list
+            .domains
+            .iter()
+            .map(|domain| format!("{name}.{}.", domain.trim_end_matches('.')))
+            .collect();
+        let dots = name.matches('.').count();
+        if u32::try_from(dots).unwrap_or(u32::MAX) >= search_list.ndots {
+            names.push_front(format!("{name}."));
+        } else {
+            names.push_back(format!("{name}."));
+        }
+        Some(names)
+    }
+
+    // The result of a host lookup for `name`, if it has been overridden.
+    pub(crate) fn host_override(
+        &self,
+        name: &str,
+        family: c_ares::AddressFamily,
+    ) -> Option<c_ares::Result<HostResults>> {
+        match self.query_name(name, None, false) {
+            Ok(name) => self.host_overrides.host(&name, family),
+            Err(error) => {
+                let code = error.to_ares();
+                rejection::note(error);
+                Some(Err(code))
+            }
+        }
+    }
+
+    // Fail a query that the resolver rejects, without going to `c-ares`, for the reason given by
+    // `error` - calling `handler` on the event loop thread, as for any other query.
+    fn reject<T, F>(&self, handler: F, error: Error)
+    where
+        F: FnOnce(c_ares::Result<T>) + Send + 'static,
+    {
+        let code = error.to_ares();
+        rejection::note(error);
+        self.submitter.submit(move |_| handler(Err(code)));
+    }
+
+    // The name to query instead of `name`, if it's a host alias.  It is made absolute, so that
+    // the search domains aren't applied to it.
+    fn host_alias(&self, name: &str, search: bool) -> Option<String> {
+        if !search || name.contains('.') {
+            return None;
+        }
+        let aliases = self.options.host_aliases.as_ref()?;
+        let alias = aliases.get(&name.to_ascii_lowercase())?;
+        match alias.ends_with('.') {
+            true => Some(alias.clone()),
+            false => Some(format!("{alias}.")),
+        }
+    }
+
+    // Whether queries for `name` are permitted by the allowed domains, if any.
+    fn is_allowed(&self, name: &str) -> bool {
+        let Some(ref domains) = self.options.allowed_domains else {
+            return true;
+        };
+        let name = normalize_domain(name);
+        domains.iter().any(|domain| {
+            domain.is_empty()
+                || name
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.'))
+        })
+    }
+}
+
+// Search domains set at runtime, with the number of dots at which a name is first queried "as is".
+struct SearchList {
+    domains: Vec<String>,
+    ndots: u32,
+}
+
+// Gathers statistics from the server state callback, and passes the information on to the user's
+// callback if there is one.  Also runs the circuit breaker, if there is one.
+#[cfg(cares1_29)]
+struct ServerState {
+    stats: Mutex<HashMap<String, ServerStats>>,
+    callback: Mutex<Option<ServerStateCallback>>,
+    submitter: TaskSubmitter,
+    circuit_breaker: Option<(u64, Duration)>,
+    circuits: Mutex<Circuits>,
+    derived: DerivedResolvers,
+}
+
+// The circuit breaker's view of the servers: as they were set, in order, and as they are in the
+// channel - without those whose circuits are open.  If the servers in the channel are not as
+// expected, then they have been set since, and the open circuits are forgotten.
+//
+// `c-ares` reorders its servers by how they are performing, so those in the channel are compared
+// without regard to order - and the order in which they were set is taken when they are set.
+#[cfg(cares1_29)]
+#[derive(Default)]
+struct Circuits {
+    configured: Vec<String>,
+    current: Vec<String>,
+    // The servers whose circuits are open, each with the number of the trip that opened it - so
+    // that a probe timer for an earlier trip doesn't close it.
+    open: HashMap<String, u64>,
+    trips: u64,
+}
+
+#[cfg(cares1_29)]
+type ServerStateCallback = Box<dyn FnMut(&str, bool, ServerStateFlags) + Send>;
+
+#[cfg(cares1_29)]
+impl ServerState {
+    fn new(
+        submitter: TaskSubmitter,
+        circuit_breaker: Option<(u64, Duration)>,
+        derived: DerivedResolvers,
+    ) -> Self {
+        Self {
+            stats: Mutex::new(HashMap::new()),
+            callback: Mutex::new(None),
+            submitter,
+            circuit_breaker,
+            circuits: Mutex::default(),
+            derived,
+        }
+    }
+
+    // Called on the event loop thread, from within `c-ares`.  So any change to the channel must be
+    // submitted as a task, to run afterwards.
+    fn record(self: &Arc<Self>, server: &str, success: bool, flags: ServerStateFlags) {
+        let trip = {
+            let mut stats = self.stats.lock().unwrap();
+            let stats = stats.entry(server.to_owned()).or_default();
+            stats.record(success);
+            let tripped = self
+                .circuit_breaker
+                .is_some_and(|(threshold, _)| stats.consecutive_failures >= threshold);
+            tripped && !stats.circuit_open
+        };
+        if trip {
+            self.open_circuit(server.to_owned());
+        }
+        if let Some(ref mut callback) = *self.callback.lock().unwrap() {
+            callback(server, success, flags);
+        }
+    }
+
+    // Take the server out of the list of servers, and arrange to put it back - in its place - after
+    // the probe interval.
+    fn open_circuit(self: &Arc<Self>, server: String) {
+        let server_state = Arc::clone(self);
+        self.submitter.submit(move |channel| {
+            let mut circuits = server_state.circuits.lock().unwrap();
+            server_state.forget_stale_circuits(&mut circuits, channel);
+            if circuits.open.contains_key(&server)
+                || !circuits
+                    .configured
+                    .iter()
+                    .any(|entry| same_server(entry, &server))
+            {
+                return;
+            }
+            circuits.trips += 1;
+            let trip = circuits.trips;
+            circuits.open.insert(server.clone(), trip);
+            if !set_closed_servers(&mut circuits, channel) {
+                circuits.open.remove(&server);
+                return;
+            }
+            let servers = circuits.current.join(",");
+            drop(circuits);
+            server_state.set_circuit_open(&server, true);
+            let _ = sync_servers(&server_state.derived, &servers);
+
+            let (_, probe_interval) = server_state.circuit_breaker.unwrap_or_default();
+            let server_state = Arc::downgrade(&server_state);
+            timer::after(probe_interval, move || {
+                if let Some(server_state) = server_state.upgrade() {
+                    server_state.close_circuit(server, trip);
+                }
+            });
+        });
+    }
+
+    // Put the server back into the list of servers, so that it can be probed - unless its circuit
+    // has been forgotten, or opened again since.
+    fn close_circuit(self: Arc<Self>, server: String, trip: u64) {
+        let server_state = Arc::clone(&self);
+        self.submitter.submit(move |channel| {
+            let mut circuits = server_state.circuits.lock().unwrap();
+            server_state.forget_stale_circuits(&mut circuits, channel);
+            if circuits.open.get(&server) != Some(&trip) {
+                return;
+            }
+            circuits.open.remove(&server);
+            set_closed_servers(&mut circuits, channel);
+            let servers = circuits.current.join(",");
+            drop(circuits);
+            server_state.set_circuit_open(&server, false);
+            let _ = sync_servers(&server_state.derived, &servers);
+        });
+    }
+
+    // Forget the open circuits if the servers have been set since they were opened, starting again
+    // from the servers as they are now.
+    fn forget_stale_circuits(&self, circuits: &mut Circuits, channel: &c_ares::Channel) {
+        let servers = channel_servers(channel);
+        if same_entries(&servers, &circuits.current) {
+            return;
+        }
+        for (server, _) in circuits.open.drain() {
+            self.set_circuit_open(&server, false);
+        }
+        circuits.configured.clone_from(&servers);
+        circuits.current = servers;
+    }
+
+    // Take note of the servers in the channel, which have just been set.
+    fn check_servers(&self, channel: &c_ares::Channel) {
+        if self.circuit_breaker.is_some() {
+            let mut circuits = self.circuits.lock().unwrap();
+            self.forget_stale_circuits(&mut circuits, channel);
+        }
+    }
+
+    // Called after the servers have been set: submitted to the event loop, so that it runs after
+    // the servers are changed.
+    fn servers_set(self: &Arc<Self>) {
+        if self.circuit_breaker.is_none() {
+            return;
+        }
+        let server_state = Arc::clone(self);
+        self.submitter
+            .submit(move |channel| server_state.check_servers(channel));
+    }
+
+    fn set_circuit_open(&self, server: &str, open: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.entry(server.to_owned()).or_default().circuit_open = open;
+    }
+}
+
+// The flags that `c-ares` uses when none are set: as of version 1.22, EDNS is on by default.
+fn default_flags() -> c_ares::Flags {
+    if cfg!(cares1_22) {
+        c_ares::Flags::EDNS
+    } else {
+        c_ares::Flags::empty()
+    }
+}
+
+// A domain name in a form suitable for comparison: lower case, and without any trailing dot.
+fn normalize_domain(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+// The name under which PTR records for `ip` are published.
+fn reverse_name(ip: &IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for octet in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", octet & 0xf, octet >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+// Whether two entries in a list of servers are the same server.
+#[cfg(cares1_29)]
+fn same_server(a: &str, b: &str) -> bool {
+    let key = |entry| ServerInfo::parse(entry, 53).map(|info| (info.addr, info.udp_port));
+    match (key(a), key(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Set the channel's servers to those configured for the circuit breaker, without those whose
+// circuits are open - returning false, and leaving them be, if that would leave none.
+#[cfg(cares1_29)]
+fn set_closed_servers(circuits: &mut Circuits, channel: &mut c_ares::Channel) -> bool {
+    let servers: Vec<&str> = circuits
+        .configured
+        .iter()
+        .filter(|entry| {
+            !circuits
+                .open
+                .keys()
+                .any(|server| same_server(entry, server))
+        })
+        .map(String::as_str)
+        .collect();
+    if servers.is_empty() || channel.set_servers(&servers).is_err() {
+        return false;
+    }
+    circuits.current = channel_servers(channel);
+    true
+}
+
+// The entries in the channel's list of servers.
+#[cfg(cares1_29)]
+fn channel_servers(channel: &c_ares::Channel) -> Vec<String> {
+    let servers = channel.get_servers();
+    let servers = servers.split(',').filter(|entry| !entry.is_empty());
+    servers.map(str::to_owned).collect()
+}
+
+// Whether two lists of servers have the same entries, in whatever order.
+#[cfg(cares1_29)]
+fn same_entries(a: &[String], b: &[String]) -> bool {
+    let mut a: Vec<&String> = a.iter().collect();
+    let mut b: Vec<&String> = b.iter().collect();
+    a.sort();
+    b.sort();
+    a == b
+}
+
+// Pass `servers` on to each of `derived`, forgetting any that have been dropped.
+#[cfg(cares1_24)]
+fn sync_servers(derived: &DerivedResolvers, servers: &str) -> c_ares::Result<()> {
+    let resolvers: Vec<Arc<Resolver>> = {
+        let mut derived = derived.lock().unwrap();
+        derived.retain(|resolver| resolver.strong_count() > 0);
+        derived.iter().filter_map(Weak::upgrade).collect()
+    };
+    for resolver in resolvers {
+        resolver.set_servers(&[servers])?;
+    }
+    Ok(())
+}
+
+// Whether an entry in the list of servers is the given server.
+#[cfg(cares1_24)]
+fn server_matches(entry: &str, server: SocketAddr, default_port: u16) -> bool {
+    ServerInfo::parse(entry, default_port)
+        .is_some_and(|info| SocketAddr::new(info.addr, info.udp_port) == server)
+}
+
+// Query each of `names` in turn until one is found, as `c-ares` does when searching.  If none is
+// found, the error is `ENODATA` if any of the names exists - and otherwise the last error.
+fn search_each<F>(
+    channel: &mut c_ares::Channel,
+    submitter: TaskSubmitter,
+    mut names: VecDeque<String>,
+    dns_class: u16,
+    query_type: u16,
+    no_data: bool,
+    handler: F,
+) where
+    F: FnOnce(c_ares::Result<&[u8]>) + Send + 'static,
+{
+    let Some(name) = names.pop_front() else {
+        handler(Err(c_ares::Error::ENOTFOUND));
+        return;
+    };
+    channel.query(&name, dns_class, query_type, move |result| match result {
+        Err(error @ (c_ares::Error::ENOTFOUND | c_ares::Error::ENODATA)) if !names.is_empty() => {
+            let no_data = no_data || error == c_ares::Error::ENODATA;
+            submitter.clone().submit(move |channel| {
+                search_each(
+                    channel, submitter, names, dns_class, query_type, no_data, handler,
+                );
+            });
+        }
+        Err(c_ares::Error::ENOTFOUND) if no_data => handler(Err(c_ares::Error::ENODATA)),
+        result => handler(result),
+    });
+}
+
+// Reinitialize a channel from system configuration, pointing it back at the relay if there is
+// one.  Returns the servers read from system configuration - if `c-ares` can tell us.
+#[cfg(cares1_22)]
+fn reinit_channel(
+    channel: &mut c_ares::Channel,
+    relay_server: Option<&str>,
+) -> c_ares::Result<Option<String>> {
+    channel.reinit()?;
+    let system_servers = servers_csv(channel);
+    if let Some(server) = relay_server {
+        channel.set_servers(&[server])?;
     }
+    Ok(system_servers)
+}
+
+// The channel's servers, in comma delimited format - if `c-ares` can tell us.
+#[cfg(cares1_24)]
+fn servers_csv(channel: &c_ares::Channel) -> Option<String> {
+    Some(channel.get_servers().to_string())
+}
+
+#[cfg(all(cares1_22, not(cares1_24)))]
+fn servers_csv(_channel: &c_ares::Channel) -> Option<String> {
+    None
 }
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use c_ares::ServerStateFlags;
+
+use crate::capture::Protocol;
+
+/// The result of a query, along with information about how it was answered - as returned by the
+/// `*_ext()` methods.
+///
+/// The server and protocol are those reported to the server state callback, so are the relay
+/// rather than the upstream servers when queries are relayed - eg to a `Transport`.
+///
+/// The information is gathered on the event loop, where `c-ares` reports the server that answered
+/// a query just before completing it.  They are `None` when no server answered: eg when the answer
+/// came from the query cache or the hosts file, or when the query failed.  `c-ares` doesn't say
+/// which query a server's failure belongs to, so the number of attempts is not given.
+#[derive(Clone, Debug)]
+pub struct QueryResponse<T> {
+    /// The result of the query.
+    pub result: c_ares::Result<T>,
+
+    /// The ID that the query was given - unique within the process, and also passed to the query
+    /// log, so that application logs can be correlated with the resolver's.  A query that never
+    /// reached the query log - eg because its name was rejected - still gets an ID of its own.
+    pub id: u64,
+
+    /// The server that answered the query, if any - in the same form as the keys of
+    /// `server_stats()`.
+    pub server: Option<String>,
+
+    /// The protocol used with that server, if any.
+    pub transport: Option<Protocol>,
+
+    /// The time between making the query and its completion.
+    pub elapsed: Duration,
+}
+
+impl<T> QueryResponse<T> {
+    // Apply `f` to the result, keeping the rest.
+    pub(crate) fn map_result<U, F>(self, f: F) -> QueryResponse<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        QueryResponse {
+            result: self.result.map(f),
+            id: self.id,
+            server: self.server,
+            transport: self.transport,
+            elapsed: self.elapsed,
+        }
+    }
+
+    // Apply `f` to the result, if it succeeded, keeping the rest.
+    pub(crate) fn and_then_result<U, F>(self, f: F) -> QueryResponse<U>
+    where
+        F: FnOnce(T) -> c_ares::Result<U>,
+    {
+        QueryResponse {
+            result: self.result.and_then(f),
+            id: self.id,
+            server: self.server,
+            transport: self.transport,
+            elapsed: self.elapsed,
+        }
+    }
+}
+
+// What the server state callback last reported on the event loop.
+//
+// `c-ares` reports that a server answered a query just before completing that query, so an
+// answer is about the next query to complete.  A failure is reported before a query is retried,
+// or fails: so the query that completes next, if any, was not answered.
+enum Contact {
+    Answered(String, Protocol),
+    Failed,
+}
+
+thread_local! {
+    static CONTACT: RefCell<Option<Contact>> = const { RefCell::new(None) };
+}
+
+// Record a server's response, as reported to the server state callback.
+pub(crate) fn record_attempt(server: &str, success: bool, flags: ServerStateFlags) {
+    let protocol = if flags.contains(ServerStateFlags::TCP) {
+        Protocol::Tcp
+    } else {
+        Protocol::Udp
+    };
+    let contact = if success {
+        Contact::Answered(server.to_owned(), protocol)
+    } else {
+        Contact::Failed
+    };
+    CONTACT.set(Some(contact));
+}
+
+// The server that answered the current query, if any, and whether any server was contacted -
+// without collecting them.
+pub(crate) fn current_contact() -> (Option<String>, bool) {
+    CONTACT.with_borrow(|contact| match contact {
+        Some(Contact::Answered(server, _)) => (Some(server.clone()), true),
+        Some(Contact::Failed) => (None, true),
+        None => (None, false),
+    })
+}
+
+// Discard whatever was recorded: it is of no use to the next query to complete - either because
+// it belongs to the query that just completed, or because the next query is yet to be made.
+pub(crate) fn clear_contact() {
+    CONTACT.take();
+}
+
+// Wrap `handler` so that it receives a `QueryResponse`, timed from now.
+pub(crate) fn with_response<T, F>(handler: F) -> impl FnOnce(c_ares::Result<T>) + Send + 'static
+where
+    F: FnOnce(QueryResponse<T>) + Send + 'static,
+{
+    let start = Instant::now();
+    move |result| handler(response(result, start))
+}
+
+// Make a `QueryResponse` for a query made at `start`, collecting the servers that handled it.
+pub(crate) fn response<T>(result: c_ares::Result<T>, start: Instant) -> QueryResponse<T> {
+    let (server, transport) = match CONTACT.take() {
+        Some(Contact::Answered(server, protocol)) => (Some(server), Some(protocol)),
+        _ => (None, None),
+    };
+    QueryResponse {
+        result,
+        id: crate::querylog::current_id().unwrap_or_else(crate::querylog::next_id),
+        server,
+        transport,
+        elapsed: start.elapsed(),
+    }
+}
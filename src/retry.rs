@@ -0,0 +1,249 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::timer;
+
+type DelayFn = dyn Fn(u32) -> Option<Duration> + Send + Sync;
+
+/// Decides whether, and after how long, the crate's own retry paths should try again - eg a
+/// `FallbackResolver` moving on to the next resolver.
+///
+/// This is separate from the retries that `c-ares` itself makes within a single query, which are
+/// controlled by `Options::set_tries()` and `Options::set_timeout()`.
+///
+/// The default policy retries immediately, with no limit on the number of retries.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    delay: Arc<DelayFn>,
+    max_retries: Option<u32>,
+    budget: Option<RetryBudget>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::custom(|_| Some(Duration::ZERO))
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_retries", &self.max_retries)
+            .field("budget", &self.budget)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetryPolicy {
+    /// Retry immediately.
+    pub fn immediate() -> Self {
+        Self::default()
+    }
+
+    /// Wait for the same `delay` before every retry.
+    pub fn fixed(delay: Duration) -> Self {
+        Self::custom(move |_| Some(delay))
+    }
+
+    /// Wait for an exponentially increasing time before each retry, starting at `initial` and
+    /// doubling up to at most `max`.
+    ///
+    /// Each delay is then jittered - chosen at random between half and all of that time - so that
+    /// many clients retrying at once spread out their retries.
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Self::custom(move |retry| {
+            let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+            let delay = initial.saturating_mul(factor).min(max);
+            Some(jitter(delay))
+        })
+    }
+
+    /// Decide on retries using `delay`, which is passed the number of the retry - starting at 1 -
+    /// and returns how long to wait before making it, or `None` to give up.
+    pub fn custom<F>(delay: F) -> Self
+    where
+        F: Fn(u32) -> Option<Duration> + Send + Sync + 'static,
+    {
+        Self {
+            delay: Arc::new(delay),
+            max_retries: None,
+            budget: None,
+        }
+    }
+
+    /// Make at most `max_retries` retries.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Make retries only when `budget` allows.  Clones of a `RetryBudget` share their budget, so
+    /// the same budget can be used by many policies - and so across many resolvers.
+    pub fn with_budget(mut self, budget: RetryBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// How long to wait before making the given retry - starting at 1 - or `None` if it should not
+    /// be made.
+    pub fn delay(&self, retry: u32) -> Option<Duration> {
+        if self.max_retries.is_some_and(|max| retry > max) {
+            return None;
+        }
+        (self.delay)(retry)
+    }
+
+    // Record that a request is being made, for the purposes of any budget.
+    pub(crate) fn record_request(&self) {
+        if let Some(ref budget) = self.budget {
+            budget.record_request();
+        }
+    }
+
+    // Decide whether to make the given retry, and if so how long to wait first.  A retry that is
+    // going to be made is charged to any budget.
+    pub(crate) fn next_retry(&self, retry: u32) -> Option<Duration> {
+        let delay = self.delay(retry)?;
+        match self.budget {
+            Some(ref budget) if !budget.withdraw() => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+// The budget's window is divided into this many buckets, which expire one at a time.
+const BUCKETS: u32 = 10;
+
+/// A limit on retries, relative to the number of requests made recently - so that a failing
+/// upstream doesn't cause a storm of retries.
+///
+/// Over a sliding window, retries are allowed only while they number no more than `ratio` times
+/// the number of requests - plus a minimum allowance, so that clients making few requests can
+/// still retry.
+///
+/// Clones share the same budget.
+#[derive(Clone, Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_retries: u32,
+    window: Duration,
+    state: Arc<Mutex<BudgetState>>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    start: Instant,
+    // The index of each recent bucket, with the requests and retries made in it - most recent
+    // last.
+    buckets: Vec<(u64, u64, u64)>,
+}
+
+impl RetryBudget {
+    /// Create a `RetryBudget` allowing retries of up to `ratio` of the requests made over the last
+    /// `window` - eg 0.2 for 20%.
+    pub fn new(ratio: f64, window: Duration) -> Self {
+        let state = BudgetState {
+            start: Instant::now(),
+            buckets: Vec::new(),
+        };
+        Self {
+            ratio,
+            min_retries: 0,
+            window,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Allow `min_retries` over the window, however few requests have been made.
+    pub fn with_min_retries(mut self, min_retries: u32) -> Self {
+        self.min_retries = min_retries;
+        self
+    }
+
+    /// Whether a retry could be made now.
+    pub fn can_retry(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        self.expire(&mut state);
+        self.has_allowance(&state)
+    }
+
+    fn record_request(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.expire(&mut state);
+        if let Some(bucket) = state.buckets.last_mut() {
+            bucket.1 += 1;
+        }
+    }
+
+    // Take a retry from the budget, if there is one to take.
+    fn withdraw(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        self.expire(&mut state);
+        let allowed = self.has_allowance(&state);
+        if allowed {
+            if let Some(bucket) = state.buckets.last_mut() {
+                bucket.2 += 1;
+            }
+        }
+        allowed
+    }
+
+    fn has_allowance(&self, state: &BudgetState) -> bool {
+        let (requests, retries) = state
+            .buckets
+            .iter()
+            .fold((0, 0), |(requests, retries), bucket| {
+                (requests + bucket.1, retries + bucket.2)
+            });
+        #[allow(clippy::cast_precision_loss)]
+        let allowance = requests as f64 * self.ratio + f64::from(self.min_retries);
+        #[allow(clippy::cast_precision_loss)]
+        let retries = retries as f64;
+        retries < allowance
+    }
+
+    // Drop buckets that have left the window, and make sure that there's a current bucket.
+    fn expire(&self, state: &mut BudgetState) {
+        let bucket_width = (self.window / BUCKETS).max(Duration::from_millis(1));
+        let elapsed = state.start.elapsed().as_nanos() / bucket_width.as_nanos();
+        let current = u64::try_from(elapsed).unwrap_or(u64::MAX);
+        let oldest = current.saturating_sub(u64::from(BUCKETS) - 1);
+        state.buckets.retain(|bucket| bucket.0 >= oldest);
+        if state
+            .buckets
+            .last()
+            .is_none_or(|bucket| bucket.0 != current)
+        {
+            state.buckets.push((current, 0, 0));
+        }
+    }
+}
+
+// Choose a duration at random between half and all of `delay`.
+fn jitter(delay: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let half = delay / 2;
+    let nanos = u64::try_from(half.as_nanos()).unwrap_or(u64::MAX);
+    let extra = if nanos == 0 { 0 } else { random % nanos };
+    half + Duration::from_nanos(extra)
+}
+
+// A future that completes after `delay`.  There's no async runtime to hand, so this is woken by
+// the timer thread that all resolvers share.
+pub(crate) fn sleep(delay: Duration) -> impl Future<Output = ()> + Send {
+    let (sender, receiver) = futures_channel::oneshot::channel();
+    if delay.is_zero() {
+        let _ = sender.send(());
+    } else {
+        timer::after(delay, move || {
+            let _ = sender.send(());
+        });
+    }
+    async move {
+        let _ = receiver.await;
+    }
+}
@@ -0,0 +1,156 @@
+use crate::blockingresolver::BlockingResolver;
+use crate::futureresolver::{CAresFuture, FutureResolver};
+use crate::host::HostResults;
+use crate::queries::wrapped_queries;
+use crate::record::{DnsClass, RecordType};
+
+/// A resolver that routes each query to one of several underlying resolvers, according to the name
+/// being looked up.
+///
+/// This is sometimes called split-horizon resolution, or conditional forwarding: for instance,
+/// queries for a corporate domain might go to the corporate DNS servers over a VPN, while all other
+/// queries go to the system's usual servers.
+///
+/// Routes are given as a domain, which matches that domain and all names within it - or as
+/// `*.domain`, which matches only names within it.  Where several routes match, the most specific
+/// wins.  Names that match no route go to the default resolver.
+///
+/// Queries are routed on the name as given.  In particular, `search_xxx()` queries - and host
+/// lookups, which search too - are routed before any search domains are applied: a search for
+/// `intranet` goes to the resolver for `intranet`, even if the search domains would make it a
+/// lookup of `intranet.corp.example`.  Search domains are applied by the resolver routed to, and
+/// the route for a short name can be added explicitly if need be.
+///
+/// `RoutingResolver<FutureResolver>` and `RoutingResolver<BlockingResolver>` offer the same
+/// name-based queries as the resolvers that they route to.  For anything else, `resolver_for()`
+/// gives access to the underlying resolvers directly.
+pub struct RoutingResolver<R> {
+    routes: Vec<Route<R>>,
+    default: R,
+}
+
+struct Route<R> {
+    domain: String,
+    include_apex: bool,
+    resolver: R,
+}
+
+impl<R> Route<R> {
+    // How specifically this route matches `name`, if it matches at all.
+    fn matches(&self, name: &str) -> Option<usize> {
+        if name == self.domain {
+            return self.include_apex.then_some(self.domain.len() + 1);
+        }
+        let prefix = name.strip_suffix(&self.domain)?;
+        (prefix.ends_with('.') || self.domain.is_empty()).then_some(self.domain.len())
+    }
+}
+
+// Names compare case-insensitively, and without any trailing dot.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+impl<R> RoutingResolver<R> {
+    /// Create a new `RoutingResolver`, sending queries that match no route to `default`.
+    pub fn new(default: R) -> Self {
+        Self {
+            routes: Vec::new(),
+            default,
+        }
+    }
+
+    /// Route queries for names matching `pattern` to `resolver`.
+    ///
+    /// `pattern` is a domain such as `corp.example`, matching that domain and all names within
+    /// it, or `*.corp.example`, matching only names within it.
+    pub fn add_route(&mut self, pattern: &str, resolver: R) -> &mut Self {
+        let (domain, include_apex) = match pattern.strip_prefix("*.") {
+            Some(domain) => (domain, false),
+            None => (pattern, true),
+        };
+        let route = Route {
+            domain: normalize(domain),
+            include_apex,
+            resolver,
+        };
+        self.routes.push(route);
+        self
+    }
+
+    /// The resolver to which queries for `name` are routed.
+    pub fn resolver_for(&self, name: &str) -> &R {
+        let name = normalize(name);
+        self.routes
+            .iter()
+            .filter_map(|route| route.matches(&name).map(|score| (score, &route.resolver)))
+            .max_by_key(|(score, _)| *score)
+            .map_or(&self.default, |(_, resolver)| resolver)
+    }
+
+    /// The resolver to which queries that match no route are sent.
+    pub fn default_route(&self) -> &R {
+        &self.default
+    }
+}
+
+// Generate methods that pass a query on to whichever resolver the name is routed to.
+macro_rules! routed {
+    ($(#[$attr:meta])* $query:ident -> $result:ty) => {
+        $(#[$attr])*
+        pub fn $query(&self, name: &str) -> $result {
+            self.resolver_for(name).$query(name)
+        }
+    };
+}
+
+// Generate the name-based query methods, for the given result type.
+macro_rules! routed_queries {
+    ($result:ident) => {
+        wrapped_queries!(routed, $result);
+
+        /// Perform a host query by name.
+        pub fn get_host_by_name(
+            &self,
+            name: &str,
+            family: c_ares::AddressFamily,
+        ) -> $result<HostResults> {
+            self.resolver_for(name).get_host_by_name(name, family)
+        }
+
+        /// Initiate a single-question DNS query for `name`.  The class and type of the query are
+        /// per the provided parameters, which may be given as numbers per `arpa/nameser.h`.
+        pub fn query(
+            &self,
+            name: &str,
+            dns_class: impl Into<DnsClass>,
+            query_type: impl Into<RecordType>,
+        ) -> $result<Vec<u8>> {
+            self.resolver_for(name).query(name, dns_class, query_type)
+        }
+
+        /// Initiate a series of single-question DNS queries for `name`.  The class and type of the
+        /// query are per the provided parameters, which may be given as numbers per
+        /// `arpa/nameser.h`.  The query is routed on `name` as given, before any search domains are
+        /// applied.
+        pub fn search(
+            &self,
+            name: &str,
+            dns_class: impl Into<DnsClass>,
+            query_type: impl Into<RecordType>,
+        ) -> $result<Vec<u8>> {
+            self.resolver_for(name).search(name, dns_class, query_type)
+        }
+    };
+}
+
+// The result of a query on a `RoutingResolver<BlockingResolver>`.
+type BlockingResult<T> = c_ares::Result<T>;
+
+impl RoutingResolver<FutureResolver> {
+    routed_queries!(CAresFuture);
+}
+
+impl RoutingResolver<BlockingResolver> {
+    routed_queries!(BlockingResult);
+}
@@ -0,0 +1,157 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
+
+/// A DNS server, with separate ports for UDP and TCP - as accepted by `set_servers_ports()`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+pub struct Server {
+    /// The address of the server.
+    pub addr: IpAddr,
+
+    /// The port to which UDP queries are sent.
+    pub udp_port: u16,
+
+    /// The port to which TCP queries are sent.
+    pub tcp_port: u16,
+}
+
+impl Server {
+    /// Returns a `Server` at the given address, using the standard port 53 for both UDP and TCP.
+    pub fn new(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            udp_port: 53,
+            tcp_port: 53,
+        }
+    }
+
+    // Format the server as `c-ares` expects.  Where the ports differ, that requires a URI.
+    #[cfg_attr(not(cares1_34), allow(dead_code))]
+    pub(crate) fn to_csv_entry(self) -> String {
+        let address = SocketAddr::new(self.addr, self.udp_port);
+        if self.udp_port == self.tcp_port {
+            address.to_string()
+        } else {
+            format!("dns://{address}?tcpport={}", self.tcp_port)
+        }
+    }
+}
+
+impl From<SocketAddr> for Server {
+    fn from(address: SocketAddr) -> Self {
+        Self {
+            addr: address.ip(),
+            udp_port: address.port(),
+            tcp_port: address.port(),
+        }
+    }
+}
+
+/// A DNS server in use by a resolver, as reported by `servers()`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+pub struct ServerInfo {
+    /// The address of the server.
+    pub addr: IpAddr,
+
+    /// The port to which UDP queries are sent.
+    pub udp_port: u16,
+
+    /// The port to which TCP queries are sent.
+    pub tcp_port: u16,
+
+    /// The network interface through which the server is contacted, if specified.
+    pub interface: Option<String>,
+}
+
+impl ServerInfo {
+    // Parse a single server, in the format that `c-ares` uses for `ares_get_servers_csv()`.  That's
+    // `host[:port][%iface]`, where IPv6 addresses with ports have square brackets - or, if it's
+    // needed to say more, a URI like `dns://host[:port][%iface]?tcpport=port`.
+    #[cfg_attr(not(cares1_24), allow(dead_code))]
+    pub(crate) fn parse(server: &str, default_port: u16) -> Option<Self> {
+        let server = server.trim();
+        let server = server.strip_prefix("dns://").unwrap_or(server);
+        let (server, query) = server.split_once('?').unwrap_or((server, ""));
+        let server = server.trim_end_matches('/');
+
+        // Take out the interface, if there is one.  It runs to the end, or to the closing bracket
+        // of an IPv6 address.
+        let (server, interface) = match server.split_once('%') {
+            Some((before, after)) => match after.split_once(']') {
+                Some((interface, rest)) => (format!("{before}]{rest}"), Some(interface)),
+                None => (before.to_owned(), Some(after)),
+            },
+            None => (server.to_owned(), None),
+        };
+        let interface = interface.map(str::to_owned);
+
+        // Split off the port, if there is one.
+        let (host, port) = if let Some(rest) = server.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']')?;
+            let port = rest.strip_prefix(':').map(str::parse).transpose().ok()?;
+            (host, port)
+        } else {
+            match server.split_once(':') {
+                Some((host, port)) if !port.contains(':') => (host, Some(port.parse().ok()?)),
+                _ => (server.as_str(), None),
+            }
+        };
+
+        let addr = host.parse().ok()?;
+        let udp_port = port.unwrap_or(default_port);
+        let tcp_port = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| key.eq_ignore_ascii_case("tcpport"))
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(udp_port);
+        let info = Self {
+            addr,
+            udp_port,
+            tcp_port,
+            interface,
+        };
+        Some(info)
+    }
+}
+
+/// Health statistics for a DNS server, as reported by `server_stats()`.
+///
+/// Whether the server supports DNS cookies is not known - see `Options::set_dns_cookies()`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default)]
+pub struct ServerStats {
+    /// The number of queries to the server that succeeded.
+    pub successes: u64,
+
+    /// The number of queries to the server that failed.
+    pub failures: u64,
+
+    /// The number of queries to the server that have failed since the last success.
+    pub consecutive_failures: u64,
+
+    /// When a query to the server last succeeded, if ever.
+    pub last_success: Option<Instant>,
+
+    /// When a query to the server last failed, if ever.
+    pub last_failure: Option<Instant>,
+
+    /// Whether the circuit breaker has taken the server out of use - see
+    /// `Options::set_circuit_breaker()`.
+    pub circuit_open: bool,
+}
+
+impl ServerStats {
+    // Record the outcome of a query.
+    #[cfg_attr(not(cares1_29), allow(dead_code))]
+    pub(crate) fn record(&mut self, success: bool) {
+        let now = Instant::now();
+        if success {
+            self.successes += 1;
+            self.consecutive_failures = 0;
+            self.last_success = Some(now);
+        } else {
+            self.failures += 1;
+            self.consecutive_failures += 1;
+            self.last_failure = Some(now);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::vec;
+
+use crate::blockingresolver::BlockingResolver;
+use crate::error::Error;
+
+/// A host and port, to be resolved by a `BlockingResolver` - for passing to APIs that take
+/// `impl ToSocketAddrs`, such as `TcpStream::connect()`, in place of a string that would be
+/// resolved by the system.
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use c_ares_resolver::{BlockingResolver, CAresToSocketAddrs};
+///
+/// let resolver = BlockingResolver::new().unwrap();
+/// let address = CAresToSocketAddrs::new(&resolver, "example.com:80").unwrap();
+/// let stream = TcpStream::connect(address).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct CAresToSocketAddrs<'a> {
+    resolver: &'a BlockingResolver,
+    host: String,
+    port: u16,
+}
+
+impl<'a> CAresToSocketAddrs<'a> {
+    /// Create a `CAresToSocketAddrs` from a string of the form `host:port`.  IPv6 addresses must
+    /// be in square brackets, eg `[2001:db8::1]:443`.
+    pub fn new(resolver: &'a BlockingResolver, address: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address");
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+        let port = port.parse().map_err(|_| invalid())?;
+        let host = match host.strip_prefix('[') {
+            Some(host) => host.strip_suffix(']').ok_or_else(invalid)?,
+            None if host.contains(':') => return Err(invalid()),
+            None => host,
+        };
+        Ok(Self::with_port(resolver, host, port))
+    }
+
+    /// Create a `CAresToSocketAddrs` from a host and a port.
+    pub fn with_port(resolver: &'a BlockingResolver, host: &str, port: u16) -> Self {
+        Self {
+            resolver,
+            host: host.to_owned(),
+            port,
+        }
+    }
+
+    /// The host to be resolved.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The port.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl ToSocketAddrs for CAresToSocketAddrs<'_> {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    // Addresses are used as they are, without a lookup.
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        if let Ok(address) = self.host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(address, self.port)].into_iter());
+        }
+        let results = self
+            .resolver
+            .get_host_by_name(&self.host, c_ares::AddressFamily::UNSPEC)
+            .map_err(|err| io::Error::from(Error::from(err)))?;
+        Ok(results.to_socket_addrs(self.port).into_iter())
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::transport::Transport;
+
+const SOCKS_VERSION: u8 = 5;
+const NO_AUTHENTICATION: u8 = 0;
+const USERNAME_PASSWORD: u8 = 2;
+const NO_ACCEPTABLE_METHODS: u8 = 0xff;
+const CONNECT: u8 = 1;
+const IPV4: u8 = 1;
+const DOMAIN_NAME: u8 = 3;
+const IPV6: u8 = 4;
+
+/// A `Transport` that sends DNS queries over TCP, through a SOCKS5 proxy.
+///
+/// Each query is made on a fresh connection through the proxy to the upstream DNS server.
+#[derive(Clone, Debug)]
+pub struct Socks5Transport {
+    proxy: SocketAddr,
+    server: SocketAddr,
+    credentials: Option<(String, String)>,
+    timeout: Option<Duration>,
+}
+
+impl Socks5Transport {
+    /// Returns a `Socks5Transport` that sends queries to `server`, through the proxy at `proxy`.
+    pub fn new(proxy: SocketAddr, server: SocketAddr) -> Self {
+        Self {
+            proxy,
+            server,
+            credentials: None,
+            timeout: None,
+        }
+    }
+
+    /// Authenticate with the proxy using a username and password.
+    pub fn set_credentials(&mut self, username: &str, password: &str) -> &mut Self {
+        self.credentials = Some((username.to_owned(), password.to_owned()));
+        self
+    }
+
+    /// Set a timeout for connecting to the proxy, and for each read and write thereafter.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Connect through the proxy to the upstream server.
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut stream = match self.timeout {
+            Some(timeout) => TcpStream::connect_timeout(&self.proxy, timeout)?,
+            None => TcpStream::connect(self.proxy)?,
+        };
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+        stream.set_nodelay(true)?;
+
+        // Negotiate an authentication method.
+        let method = if self.credentials.is_some() {
+            USERNAME_PASSWORD
+        } else {
+            NO_AUTHENTICATION
+        };
+        stream.write_all(&[SOCKS_VERSION, 1, method])?;
+        let mut reply = [0; 2];
+        stream.read_exact(&mut reply)?;
+        if reply[0] != SOCKS_VERSION {
+            return Err(protocol_error("unexpected SOCKS version"));
+        }
+        if reply[1] == NO_ACCEPTABLE_METHODS || reply[1] != method {
+            return Err(protocol_error("no acceptable authentication method"));
+        }
+
+        // Authenticate, if necessary.
+        if let Some((ref username, ref password)) = self.credentials {
+            let username_length = u8::try_from(username.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "username too long"))?;
+            let password_length = u8::try_from(password.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "password too long"))?;
+            let mut request = vec![1, username_length];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password_length);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request)?;
+            stream.read_exact(&mut reply)?;
+            if reply[1] != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "SOCKS authentication failed",
+                ));
+            }
+        }
+
+        // Ask the proxy to connect to the server.
+        let mut request = vec![SOCKS_VERSION, CONNECT, 0];
+        match self.server {
+            SocketAddr::V4(address) => {
+                request.push(IPV4);
+                request.extend_from_slice(&address.ip().octets());
+            }
+            SocketAddr::V6(address) => {
+                request.push(IPV6);
+                request.extend_from_slice(&address.ip().octets());
+            }
+        }
+        request.extend_from_slice(&self.server.port().to_be_bytes());
+        stream.write_all(&request)?;
+
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply)?;
+        if reply[0] != SOCKS_VERSION {
+            return Err(protocol_error("unexpected SOCKS version"));
+        }
+        if reply[1] != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("SOCKS connect failed with reply code {}", reply[1]),
+            ));
+        }
+
+        // Discard the bound address.
+        let address_length = match reply[3] {
+            IPV4 => 4,
+            IPV6 => 16,
+            DOMAIN_NAME => {
+                let mut length = [0; 1];
+                stream.read_exact(&mut length)?;
+                usize::from(length[0])
+            }
+            _ => return Err(protocol_error("unexpected address type")),
+        };
+        let mut bound = vec![0; address_length + 2];
+        stream.read_exact(&mut bound)?;
+
+        Ok(stream)
+    }
+}
+
+impl Transport for Socks5Transport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stream = self.connect()?;
+        let length = u16::try_from(query.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "query too long"))?;
+        stream.write_all(&length.to_be_bytes())?;
+        stream.write_all(query)?;
+
+        let mut prefix = [0; 2];
+        stream.read_exact(&mut prefix)?;
+        let mut response = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        stream.read_exact(&mut response)?;
+        Ok(response)
+    }
+}
+
+fn protocol_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
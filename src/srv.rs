@@ -0,0 +1,193 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::blockingresolver::BlockingResolver;
+use crate::futureresolver::FutureResolver;
+use crate::host::display_list;
+
+/// A target of an SRV record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SrvTarget {
+    /// The host providing the service.
+    pub host: String,
+
+    /// The port on which the service is provided.
+    pub port: u16,
+
+    /// The priority of the target - lower is to be tried first.
+    pub priority: u16,
+
+    /// The weight of the target, relative to others of the same priority.
+    pub weight: u16,
+}
+
+/// A target of an SRV record, together with its addresses.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SrvEndpoint {
+    /// The target.
+    pub target: SrvTarget,
+
+    /// The addresses of the target, with its port.
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// As for `c_ares::SRVResult` - eg `Host: sip.example.com, Port: 5060, Priority: 10, Weight: 5`.
+impl fmt::Display for SrvTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Host: {}, Port: {}, ", self.host, self.port)?;
+        write!(f, "Priority: {}, Weight: {}", self.priority, self.weight)
+    }
+}
+
+/// Eg `Host: sip.example.com, Port: 5060, Priority: 10, Weight: 5, Addresses: [192.0.2.5:5060]`.
+impl fmt::Display for SrvEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, Addresses: ", self.target)?;
+        display_list(f, &self.addresses)
+    }
+}
+
+/// Chooses between the targets of SRV records, per RFC 2782.
+///
+/// This is an iterator over the targets in the order in which they should be tried: lower
+/// priorities first, and - among targets of the same priority - in a random order, weighted so
+/// that each target is chosen first with a probability proportional to its weight.
+///
+/// If the only record has target `.`, the service is explicitly unavailable and there are no
+/// targets.
+#[derive(Clone, Debug)]
+pub struct SrvSelector {
+    // Sorted by descending priority, so that the next group is at the end.
+    targets: Vec<SrvTarget>,
+}
+
+impl SrvSelector {
+    /// Create a selector over the targets of `results`.
+    pub fn new(results: &c_ares::SRVResults) -> Self {
+        results
+            .iter()
+            .map(|result| SrvTarget {
+                host: result.host().to_owned(),
+                port: result.port(),
+                priority: result.priority(),
+                weight: result.weight(),
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<SrvTarget> for SrvSelector {
+    fn from_iter<I: IntoIterator<Item = SrvTarget>>(iter: I) -> Self {
+        let mut targets: Vec<SrvTarget> = iter.into_iter().collect();
+        if let [target] = targets.as_slice() {
+            if target.host.is_empty() || target.host == "." {
+                targets.clear();
+            }
+        }
+        targets.sort_by_key(|target| std::cmp::Reverse(target.priority));
+        Self { targets }
+    }
+}
+
+impl Iterator for SrvSelector {
+    type Item = SrvTarget;
+
+    fn next(&mut self) -> Option<SrvTarget> {
+        let priority = self.targets.last()?.priority;
+        let start = self
+            .targets
+            .iter()
+            .position(|target| target.priority == priority)?;
+
+        // Targets of weight zero go first, so that they have a small chance of being chosen.
+        let group = &mut self.targets[start..];
+        group.sort_by_key(|target| target.weight);
+        let total: u64 = group.iter().map(|target| u64::from(target.weight)).sum();
+        let chosen = random() % (total + 1);
+        let mut sum = 0;
+        let index = group
+            .iter()
+            .position(|target| {
+                sum += u64::from(target.weight);
+                sum >= chosen
+            })
+            .unwrap_or(0);
+        Some(self.targets.remove(start + index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.targets.len(), Some(self.targets.len()))
+    }
+}
+
+impl ExactSizeIterator for SrvSelector {}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn endpoint(
+    target: SrvTarget,
+    a: c_ares::Result<c_ares::AResults>,
+    aaaa: c_ares::Result<c_ares::AAAAResults>,
+) -> c_ares::Result<SrvEndpoint> {
+    let mut addresses = Vec::new();
+    let a_error = match a {
+        Ok(a) => {
+            let ipv4 = a.iter().map(|result| IpAddr::V4(result.ipv4()));
+            addresses.extend(ipv4.map(|ip| SocketAddr::new(ip, target.port)));
+            None
+        }
+        Err(error) => Some(error),
+    };
+    if let Ok(aaaa) = aaaa {
+        let ipv6 = aaaa.iter().map(|result| IpAddr::V6(result.ipv6()));
+        addresses.extend(ipv6.map(|ip| SocketAddr::new(ip, target.port)));
+    }
+    if addresses.is_empty() {
+        return Err(a_error.unwrap_or(c_ares::Error::ENODATA));
+    }
+    Ok(SrvEndpoint { target, addresses })
+}
+
+pub(crate) fn resolve(
+    resolver: FutureResolver,
+    name: &str,
+) -> impl Future<Output = c_ares::Result<SrvEndpoint>> + Send {
+    let name = name.to_owned();
+    async move {
+        let srv = resolver.query_srv(&name).await?;
+        let mut error = c_ares::Error::ENODATA;
+        for target in SrvSelector::new(&srv) {
+            let a = resolver.query_a(&target.host);
+            let aaaa = resolver.query_aaaa(&target.host);
+            match endpoint(target, a.await, aaaa.await) {
+                Ok(endpoint) => return Ok(endpoint),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+}
+
+pub(crate) fn resolve_blocking(
+    resolver: &BlockingResolver,
+    name: &str,
+) -> c_ares::Result<SrvEndpoint> {
+    let srv = resolver.query_srv(name)?;
+    let mut error = c_ares::Error::ENODATA;
+    for target in SrvSelector::new(&srv) {
+        let a = resolver.query_a(&target.host);
+        let aaaa = resolver.query_aaaa(&target.host);
+        match endpoint(target, a, aaaa) {
+            Ok(endpoint) => return Ok(endpoint),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
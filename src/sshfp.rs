@@ -0,0 +1,35 @@
+use crate::dnsrec::T_SSHFP;
+
+dns_results!(
+    /// The result of a successful SSHFP lookup.
+    SSHFPResults,
+    SSHFPResult,
+    raw T_SSHFP
+);
+
+/// The contents of a single SSHFP record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SSHFPResult {
+    /// The algorithm of the host key - eg 1 for RSA, 4 for Ed25519.
+    pub algorithm: u8,
+
+    /// The type of the fingerprint - 1 for SHA-1, 2 for SHA-256.
+    pub fingerprint_type: u8,
+
+    /// The fingerprint of the host key.
+    pub fingerprint: Vec<u8>,
+}
+
+impl SSHFPResult {
+    pub(crate) fn from_raw(data: &[u8]) -> Option<Self> {
+        match *data {
+            [algorithm, fingerprint_type, ref fingerprint @ ..] => Some(Self {
+                algorithm,
+                fingerprint_type,
+                fingerprint: fingerprint.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
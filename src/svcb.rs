@@ -0,0 +1,95 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::dnsrec::{self, DnsRr};
+
+use c_ares_sys::ares_dns_rr_key_t;
+
+// SvcParamKeys, per RFC 9460.
+const ALPN: u16 = 1;
+const NO_DEFAULT_ALPN: u16 = 2;
+const PORT: u16 = 3;
+const IPV4HINT: u16 = 4;
+const ECH: u16 = 5;
+const IPV6HINT: u16 = 6;
+
+dns_results!(
+    /// The result of a successful HTTPS lookup.
+    HTTPSResults,
+    HTTPSResult,
+    ARES_REC_TYPE_HTTPS
+);
+
+/// The contents of a single HTTPS record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HTTPSResult {
+    /// The priority of this record - or 0, for an alias to `target`.
+    pub priority: u16,
+
+    /// The target name - or empty, for the owner name itself.
+    pub target: String,
+
+    /// The ALPN protocol identifiers supported by the service.
+    pub alpn: Vec<String>,
+
+    /// Whether the service does not support the default protocol, so that only those in `alpn`
+    /// may be used.
+    pub no_default_alpn: bool,
+
+    /// The port on which the service is offered, if not the default.
+    pub port: Option<u16>,
+
+    /// IPv4 addresses that clients may use to reach the service.
+    pub ipv4hint: Vec<Ipv4Addr>,
+
+    /// IPv6 addresses that clients may use to reach the service.
+    pub ipv6hint: Vec<Ipv6Addr>,
+
+    /// The encrypted client hello configuration, if any.
+    pub ech: Option<Vec<u8>>,
+
+    /// All of the service parameters, as raw (key, value) pairs - including any not otherwise
+    /// decoded.
+    pub params: Vec<(u16, Vec<u8>)>,
+}
+
+impl HTTPSResult {
+    pub(crate) fn from_rr(rr: &DnsRr) -> Self {
+        Self::new(
+            rr.u16(ares_dns_rr_key_t::ARES_RR_HTTPS_PRIORITY),
+            rr.str(ares_dns_rr_key_t::ARES_RR_HTTPS_TARGET),
+            rr.opts(ares_dns_rr_key_t::ARES_RR_HTTPS_PARAMS),
+        )
+    }
+
+    // SVCB records have the same format as HTTPS records.
+    pub(crate) fn from_svcb_rr(rr: &DnsRr) -> Self {
+        Self::new(
+            rr.u16(ares_dns_rr_key_t::ARES_RR_SVCB_PRIORITY),
+            rr.str(ares_dns_rr_key_t::ARES_RR_SVCB_TARGET),
+            rr.opts(ares_dns_rr_key_t::ARES_RR_SVCB_PARAMS),
+        )
+    }
+
+    fn new(priority: u16, target: String, params: Vec<(u16, Vec<u8>)>) -> Self {
+        let value = |key| {
+            params
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value.as_slice())
+        };
+        Self {
+            priority,
+            target,
+            alpn: value(ALPN).map(dnsrec::strings).unwrap_or_default(),
+            no_default_alpn: value(NO_DEFAULT_ALPN).is_some(),
+            port: value(PORT)
+                .and_then(|port| port.try_into().ok())
+                .map(u16::from_be_bytes),
+            ipv4hint: value(IPV4HINT).map(dnsrec::ipv4_addrs).unwrap_or_default(),
+            ipv6hint: value(IPV6HINT).map(dnsrec::ipv6_addrs).unwrap_or_default(),
+            ech: value(ECH).map(<[u8]>::to_vec),
+            params,
+        }
+    }
+}
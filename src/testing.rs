@@ -0,0 +1,284 @@
+//! Utilities for testing code that makes DNS queries, without the network.
+//!
+//! A `DnsServer` listens on the loopback interface, answers queries from the records that it has
+//! been given, and records the questions that it is asked.
+//!
+//! ```
+//! use c_ares_resolver::testing::DnsServer;
+//! use c_ares_resolver::BlockingResolver;
+//!
+//! let server = DnsServer::start().unwrap();
+//! server.add_a("example.com", "192.0.2.1".parse().unwrap());
+//!
+//! let resolver = BlockingResolver::new().unwrap();
+//! resolver.set_servers(&[&server.address().to_string()]).unwrap();
+//! let results = resolver.query_a("example.com").unwrap();
+//! assert_eq!(results.iter().next().unwrap().ipv4().octets(), [192, 0, 2, 1]);
+//! assert_eq!(server.questions()[0].name, "example.com");
+//! ```
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use crate::transport::{self, Relay, RelayLimits, Transport};
+
+// Response codes.
+const NOERROR: u8 = 0;
+const FORMERR: u8 = 1;
+const NXDOMAIN: u8 = 3;
+
+/// A question received by a `DnsServer`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Question {
+    /// The name that was asked about, in lower case and without a trailing dot.
+    pub name: String,
+
+    /// The type of record that was asked for - eg 1, for A records.
+    pub query_type: u16,
+
+    /// The class of record that was asked for - usually 1, for the internet.
+    pub dns_class: u16,
+}
+
+// A record in the zone.
+#[derive(Clone, Debug)]
+struct Record {
+    name: String,
+    record_type: u16,
+    ttl: u32,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Zone {
+    records: Vec<Record>,
+    rcodes: Vec<(String, u8)>,
+    questions: Vec<Question>,
+}
+
+/// A DNS server on the loopback interface, serving records given to it over UDP and TCP.
+///
+/// Names with no records are answered with NXDOMAIN; names with records only of other types
+/// have an empty answer.  Names are compared without regard to case or a trailing dot.
+///
+/// The server stops when dropped.
+pub struct DnsServer {
+    zone: Arc<Mutex<Zone>>,
+    relay: Relay,
+}
+
+// The form in which names are compared.
+fn normalize(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+impl DnsServer {
+    /// Start a server, with no records.
+    pub fn start() -> io::Result<Self> {
+        let zone = Arc::new(Mutex::new(Zone::default()));
+        let relay = Relay::start(
+            Arc::new(ZoneTransport(Arc::clone(&zone))),
+            None,
+            RelayLimits::default(),
+        )?;
+        Ok(Self { zone, relay })
+    }
+
+    /// The address on which the server is listening, for both UDP and TCP - suitable for passing
+    /// to `set_servers()`.
+    pub fn address(&self) -> SocketAddr {
+        self.relay.address()
+    }
+
+    /// Add a record for `name`, with the given type and data - in wire format.
+    pub fn add_record(&self, name: &str, record_type: u16, ttl: u32, data: &[u8]) -> &Self {
+        let record = Record {
+            name: normalize(name),
+            record_type,
+            ttl,
+            data: data.to_owned(),
+        };
+        self.zone.lock().unwrap().records.push(record);
+        self
+    }
+
+    /// Add an A record for `name`.
+    pub fn add_a(&self, name: &str, address: Ipv4Addr) -> &Self {
+        self.add_record(name, 1, 60, &address.octets())
+    }
+
+    /// Add an AAAA record for `name`.
+    pub fn add_aaaa(&self, name: &str, address: Ipv6Addr) -> &Self {
+        self.add_record(name, 28, 60, &address.octets())
+    }
+
+    /// Add a CNAME record, making `name` an alias for `target`.
+    pub fn add_cname(&self, name: &str, target: &str) -> &Self {
+        self.add_record(name, 5, 60, &encode_name(target))
+    }
+
+    /// Add an MX record for `name`, naming `exchange` as a mail exchange.
+    pub fn add_mx(&self, name: &str, preference: u16, exchange: &str) -> &Self {
+        let mut data = preference.to_be_bytes().to_vec();
+        data.extend_from_slice(&encode_name(exchange));
+        self.add_record(name, 15, 60, &data)
+    }
+
+    /// Add a TXT record for `name`, with a single string.
+    ///
+    /// # Panics
+    ///
+    /// If `text` is longer than 255 bytes.
+    pub fn add_txt(&self, name: &str, text: &str) -> &Self {
+        let length = u8::try_from(text.len()).expect("TXT string too long");
+        let mut data = vec![length];
+        data.extend_from_slice(text.as_bytes());
+        self.add_record(name, 16, 60, &data)
+    }
+
+    /// Answer all queries for `name` with the response code `rcode` - eg 2, for SERVFAIL - and no
+    /// records.
+    pub fn set_rcode(&self, name: &str, rcode: u8) -> &Self {
+        let mut zone = self.zone.lock().unwrap();
+        let name = normalize(name);
+        zone.rcodes.retain(|(n, _)| *n != name);
+        zone.rcodes.push((name, rcode));
+        self
+    }
+
+    /// The questions that the server has received, in order.
+    pub fn questions(&self) -> Vec<Question> {
+        self.zone.lock().unwrap().questions.clone()
+    }
+
+    /// Forget the questions that the server has received.
+    pub fn clear_questions(&self) -> &Self {
+        self.zone.lock().unwrap().questions.clear();
+        self
+    }
+}
+
+impl std::fmt::Debug for DnsServer {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("DnsServer")
+            .field("address", &self.address())
+            .finish()
+    }
+}
+
+// Answers queries from a zone - the server is a relay to this.
+struct ZoneTransport(Arc<Mutex<Zone>>);
+
+impl Transport for ZoneTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let Some((question, end)) = parse_question(query) else {
+            let mut response = query.get(..12).unwrap_or(&[0; 12]).to_vec();
+            response.resize(12, 0);
+            response[2] = 0x80 | (response[2] & 0x79);
+            response[3] = 0x80 | FORMERR;
+            response[4..12].fill(0);
+            return Ok(response);
+        };
+
+        let mut zone = self.0.lock().unwrap();
+        zone.questions.push(question.clone());
+        let rcode = zone
+            .rcodes
+            .iter()
+            .find(|(name, _)| *name == question.name)
+            .map(|(_, rcode)| *rcode);
+        let answers = match rcode {
+            Some(_) => Vec::new(),
+            None => answers(&zone.records, &question),
+        };
+        let rcode = rcode.unwrap_or_else(|| {
+            let known = zone.records.iter().any(|r| r.name == question.name);
+            if known {
+                NOERROR
+            } else {
+                NXDOMAIN
+            }
+        });
+        drop(zone);
+
+        let mut response = query[..end].to_vec();
+        response[2] = 0x84 | (query[2] & 0x79);
+        response[3] = 0x80 | rcode;
+        let count = u16::try_from(answers.len()).unwrap_or(u16::MAX);
+        response[6..8].copy_from_slice(&count.to_be_bytes());
+        response[8..12].fill(0);
+        for record in answers {
+            response.extend_from_slice(&encode_name(&record.name));
+            response.extend_from_slice(&record.record_type.to_be_bytes());
+            response.extend_from_slice(&question.dns_class.to_be_bytes());
+            response.extend_from_slice(&record.ttl.to_be_bytes());
+            let length = u16::try_from(record.data.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "record too long"))?;
+            response.extend_from_slice(&length.to_be_bytes());
+            response.extend_from_slice(&record.data);
+        }
+        Ok(response)
+    }
+}
+
+// The records answering a question: those of the type asked for, following CNAMEs.
+fn answers(records: &[Record], question: &Question) -> Vec<Record> {
+    let mut answers = Vec::new();
+    let mut name = question.name.clone();
+    for _ in 0..8 {
+        let owned = records.iter().filter(|r| r.name == name);
+        let matching: Vec<&Record> = owned
+            .clone()
+            .filter(|r| r.record_type == question.query_type || question.query_type == 255)
+            .collect();
+        if !matching.is_empty() {
+            answers.extend(matching.into_iter().cloned());
+            break;
+        }
+        let Some(cname) = owned.into_iter().find(|r| r.record_type == 5) else {
+            break;
+        };
+        answers.push(cname.clone());
+        match decode_name(&cname.data) {
+            Some(target) => name = target,
+            None => break,
+        }
+    }
+    answers
+}
+
+// Parse the question from a query, returning it and the offset of its end.
+fn parse_question(query: &[u8]) -> Option<(Question, usize)> {
+    let (name, query_type, dns_class, end) = transport::parse_question(query)?;
+    let question = Question {
+        name,
+        query_type,
+        dns_class,
+    };
+    Some((question, end))
+}
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in normalize(name).split('.').filter(|l| !l.is_empty()) {
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    encoded
+}
+
+// Decode an uncompressed name, as written by `encode_name()`.
+fn decode_name(data: &[u8]) -> Option<String> {
+    let mut labels = Vec::new();
+    let mut offset = 0;
+    loop {
+        let length = usize::from(*data.get(offset)?);
+        offset += 1;
+        if length == 0 {
+            return Some(labels.join("."));
+        }
+        labels.push(String::from_utf8_lossy(data.get(offset..offset + length)?).into_owned());
+        offset += length;
+    }
+}
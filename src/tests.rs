@@ -2,6 +2,7 @@ use super::*;
 
 fn assert_send<T: Send>() {}
 fn assert_sync<T: Sync>() {}
+fn assert_unpin<T: Unpin>() {}
 
 #[test]
 fn options_is_send() {
@@ -13,6 +14,11 @@ fn options_is_sync() {
     assert_sync::<Options>();
 }
 
+#[test]
+fn resolver_builder_is_send() {
+    assert_send::<ResolverBuilder>();
+}
+
 #[test]
 fn resolver_is_send() {
     assert_send::<Resolver>();
@@ -23,6 +29,44 @@ fn resolver_is_sync() {
     assert_sync::<Resolver>();
 }
 
+#[test]
+fn cares_future_is_unpin() {
+    assert_unpin::<CAresFuture<c_ares::AResults>>();
+    assert_unpin::<CAresFuture<std::marker::PhantomPinned>>();
+}
+
+#[test]
+fn cares_futures_can_be_inspected() {
+    use futures_core::FusedFuture;
+
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 75)));
+    let resolver = FutureResolver::with_options(options).unwrap();
+
+    // The result can be taken without polling.
+    let mut query = resolver.query_a("example.com");
+    assert!(!query.is_terminated());
+    assert!(format!("{query:?}").starts_with("CAresFuture"));
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let result = loop {
+        if let Some(result) = query.try_take() {
+            break result;
+        }
+        assert!(std::time::Instant::now() < deadline);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert!(result.is_ok());
+    assert!(query.is_terminated());
+    assert!(FusedFuture::is_terminated(&query));
+    assert!(query.try_take().is_none());
+
+    // Or by polling.
+    let mut query = resolver.query_a("example.com");
+    assert!(futures_executor::block_on(&mut query).is_ok());
+    assert!(query.is_terminated());
+    assert!(query.try_take().is_none());
+}
+
 #[test]
 fn blocking_resolver_is_send() {
     assert_send::<BlockingResolver>();
@@ -63,6 +107,66 @@ fn error_is_sync() {
     assert_sync::<Error>();
 }
 
+#[test]
+fn errors_are_classified() {
+    let timeout = Error::from(c_ares::Error::ETIMEOUT);
+    assert!(matches!(timeout, Error::Timeout));
+    assert!(timeout.is_retryable() && timeout.is_temporary() && !timeout.is_nxdomain());
+
+    let servfail = Error::from(c_ares::Error::ESERVFAIL);
+    assert!(servfail.is_retryable() && servfail.is_temporary());
+
+    let nxdomain = Error::from(c_ares::Error::ENOTFOUND);
+    assert!(nxdomain.is_nxdomain());
+    assert!(!nxdomain.is_retryable() && !nxdomain.is_temporary());
+    assert!(!Error::from(c_ares::Error::ENODATA).is_nxdomain());
+
+    // Shedding load is temporary, but retrying at once would fail again.
+    for error in [
+        Error::Overloaded,
+        Error::MemoryBudgetExceeded,
+        Error::from(c_ares::Error::ENOMEM),
+    ] {
+        assert!(!error.is_retryable() && error.is_temporary(), "{error}");
+    }
+
+    let reset = Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+    assert!(reset.is_retryable());
+    let failed = Error::EventLoopFailed(std::io::Error::other("no threads"));
+    assert!(!failed.is_retryable() && !failed.is_temporary());
+    assert_eq!(failed.to_string(), "event loop failed: no threads");
+    assert!(std::error::Error::source(&failed).is_some());
+}
+
+#[test]
+fn errors_convert_to_io_errors() {
+    use std::io;
+
+    let err = io::Error::from(Error::from(c_ares::Error::ENOTFOUND));
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    let source = err.into_inner().unwrap().downcast::<Error>().unwrap();
+    assert!(source.is_nxdomain());
+
+    let err = io::Error::from(Error::from(c_ares::Error::ETIMEOUT));
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    let err = io::Error::from(Error::from(c_ares::Error::EBADNAME));
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = io::Error::from(Error::MemoryBudgetExceeded);
+    assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+    assert_eq!(err.to_string(), "memory budget exceeded");
+
+    // Wrapped I/O errors are given back as they were.
+    let err = io::Error::new(io::ErrorKind::ConnectionReset, "reset");
+    let err = io::Error::from(Error::from(err));
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(err.to_string(), "reset");
+    let err = io::Error::from(Error::EventLoopFailed(
+        io::ErrorKind::PermissionDenied.into(),
+    ));
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    assert!(err.to_string().starts_with("event loop failed"));
+}
+
 #[test]
 fn host_results_is_send() {
     assert_send::<HostResults>();
@@ -82,3 +186,3755 @@ fn name_info_result_is_send() {
 fn name_info_result_is_sync() {
     assert_sync::<NameInfoResult>();
 }
+
+#[test]
+fn default_resolver_cannot_be_replaced() {
+    let _ = default_resolver();
+    let resolver = FutureResolver::new().unwrap();
+    assert!(set_default_resolver(resolver).is_err());
+}
+
+#[test]
+fn builder_rejects_bad_servers() {
+    let mut builder = ResolverBuilder::new();
+    builder.set_servers(&["not an address"]);
+    assert!(builder.build().is_err());
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn set_servers_from_addresses() {
+    let resolver = Resolver::new().unwrap();
+    let servers: Vec<std::net::SocketAddr> = vec![
+        "192.0.2.1:5353".parse().unwrap(),
+        "[2001:db8::1]:53".parse().unwrap(),
+    ];
+    resolver.set_servers_addrs(&servers).unwrap();
+    assert_eq!(&*resolver.get_servers(), "192.0.2.1:5353,[2001:db8::1]:53");
+
+    let servers: Vec<std::net::IpAddr> =
+        vec!["192.0.2.1".parse().unwrap(), "2001:db8::1".parse().unwrap()];
+    resolver.set_servers_ips(&servers).unwrap();
+    assert_eq!(&*resolver.get_servers(), "192.0.2.1:53,[2001:db8::1]:53");
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn servers_round_trip_as_csv() {
+    let resolver = BlockingResolver::new().unwrap();
+    resolver
+        .set_servers_csv("192.0.2.1:5353,[2001:db8::1]:53,dns://192.0.2.2?tcpport=5300")
+        .unwrap();
+    let csv = resolver.get_servers_csv();
+    assert!(csv.starts_with("192.0.2.1:5353,[2001:db8::1]:53,"), "{csv}");
+    assert!(csv.contains("5300"), "{csv}");
+
+    let other = FutureResolver::new().unwrap();
+    other.set_servers_csv(&csv).unwrap();
+    assert_eq!(other.get_servers_csv(), csv);
+    assert!(other.set_servers_csv("not a server").is_err());
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn queries_can_be_bound_to_a_local_address() {
+    let server = testing::DnsServer::start().unwrap();
+    server.add_a("example.com", std::net::Ipv4Addr::new(192, 0, 2, 1));
+    let resolver = BlockingResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let loopback = LocalBinding::from(std::net::IpAddr::from([127, 0, 0, 1]));
+    let bound = resolver.bound_to(&loopback).unwrap();
+    assert_eq!(bound.get_servers_csv(), resolver.get_servers_csv());
+    let results = bound.query_a("example.com").unwrap();
+    assert_eq!(results.iter().count(), 1);
+
+    // Servers set later are passed on.
+    let other = testing::DnsServer::start().unwrap();
+    other.add_a("example.net", std::net::Ipv4Addr::new(192, 0, 2, 2));
+    resolver
+        .set_servers(&[&other.address().to_string()])
+        .unwrap();
+    assert_eq!(bound.get_servers_csv(), resolver.get_servers_csv());
+    assert!(bound.query_a("example.net").is_ok());
+
+    // Queries from an address that isn't local can't be made.
+    let elsewhere = LocalBinding::Address(std::net::IpAddr::from([192, 0, 2, 99]));
+    let bound = resolver.bound_to(&elsewhere).unwrap();
+    assert!(bound.query_a("example.net.").is_err());
+    assert!(resolver.remove_binding(&elsewhere));
+    assert!(!resolver.remove_binding(&elsewhere));
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn bound_resolvers_are_limited() {
+    let mut options = Options::new();
+    options.set_max_bindings(1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let first = LocalBinding::from(std::net::IpAddr::from([127, 0, 0, 1]));
+    let second = LocalBinding::from(std::net::IpAddr::from([127, 0, 0, 2]));
+
+    resolver.bound_to(&first).unwrap();
+    resolver.bound_to(&first).unwrap();
+    assert!(matches!(
+        resolver.bound_to(&second),
+        Err(Error::TooManyBindings)
+    ));
+
+    // Removing a binding makes room for another.
+    assert!(resolver.remove_binding(&first));
+    resolver.bound_to(&second).unwrap();
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn bound_resolvers_share_limits() {
+    let mut options = Options::new();
+    options
+        .set_transport(SlowTransport(
+            std::net::Ipv4Addr::new(192, 0, 2, 74),
+            std::time::Duration::from_millis(200),
+        ))
+        .set_max_outstanding_queries(1)
+        .set_max_queued_queries(0);
+    let resolver = Resolver::with_options(options).unwrap();
+    let loopback = LocalBinding::from(std::net::IpAddr::from([127, 0, 0, 1]));
+    let bound = resolver.bound_to(&loopback).unwrap();
+
+    // While the resolver has a query outstanding, the bound resolver can't make another.
+    let (tx, rx) = std::sync::mpsc::channel();
+    resolver.query_a("one.example.com", move |result| {
+        tx.send(result.is_ok()).unwrap();
+    });
+    let result = bound.try_query(|bound| bound.query_a("two.example.com", |_| {}));
+    assert!(matches!(result, Err(Error::Overloaded)));
+    let timeout = std::time::Duration::from_secs(5);
+    assert!(rx.recv_timeout(timeout).unwrap());
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn servers_are_reported() {
+    let resolver = BlockingResolver::new().unwrap();
+    resolver
+        .set_servers(&["192.0.2.1:5353", "2001:db8::1"])
+        .unwrap();
+    let servers = resolver.servers();
+    assert_eq!(
+        servers,
+        vec![
+            ServerInfo {
+                addr: "192.0.2.1".parse().unwrap(),
+                udp_port: 5353,
+                tcp_port: 5353,
+                interface: None,
+            },
+            ServerInfo {
+                addr: "2001:db8::1".parse().unwrap(),
+                udp_port: 53,
+                tcp_port: 53,
+                interface: None,
+            },
+        ]
+    );
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn servers_can_be_added_and_removed() {
+    let resolver = Resolver::new().unwrap();
+    resolver.set_servers(&["192.0.2.1", "192.0.2.2"]).unwrap();
+    let extra = "[2001:db8::1]:5353".parse().unwrap();
+    resolver.add_server(extra).unwrap();
+    resolver.add_server(extra).unwrap();
+    resolver
+        .remove_server("192.0.2.1:53".parse().unwrap())
+        .unwrap();
+    assert_eq!(&*resolver.get_servers(), "192.0.2.2:53,[2001:db8::1]:5353");
+}
+
+#[cfg(cares1_34)]
+#[test]
+fn servers_with_separate_ports() {
+    let resolver = Resolver::new().unwrap();
+    let servers = [
+        Server {
+            addr: "192.0.2.1".parse().unwrap(),
+            udp_port: 5353,
+            tcp_port: 5354,
+        },
+        Server::new("2001:db8::1".parse().unwrap()),
+    ];
+    resolver.set_servers_ports(&servers).unwrap();
+    let ports: Vec<_> = resolver
+        .servers()
+        .iter()
+        .map(|server| (server.udp_port, server.tcp_port))
+        .collect();
+    assert_eq!(ports, vec![(5353, 5354), (53, 53)]);
+}
+
+#[test]
+fn server_info_parses_uris() {
+    let server = ServerInfo::parse("dns://[fe80::1%eth0]:5353?tcpport=5354", 53).unwrap();
+    assert_eq!(server.addr, "fe80::1".parse::<std::net::IpAddr>().unwrap());
+    assert_eq!(server.udp_port, 5353);
+    assert_eq!(server.tcp_port, 5354);
+    assert_eq!(server.interface.as_deref(), Some("eth0"));
+
+    let server = ServerInfo::parse("192.0.2.1%eth1", 53).unwrap();
+    assert_eq!(server.udp_port, 53);
+    assert_eq!(server.interface.as_deref(), Some("eth1"));
+    assert!(ServerInfo::parse("not a server", 53).is_none());
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn builder_presets() {
+    let resolver = ResolverBuilder::with_quad9().build().unwrap();
+    assert_eq!(
+        &*resolver.get_servers(),
+        "9.9.9.9:53,149.112.112.112:53,[2620:fe::fe]:53,[2620:fe::9]:53"
+    );
+}
+
+#[test]
+fn sortlist_on_all_resolvers() {
+    let sortlist = ["130.155.160.0/255.255.240.0", "130.155.0.0"];
+    assert!(Resolver::new().unwrap().set_sortlist(&sortlist).is_ok());
+    assert!(FutureResolver::new()
+        .unwrap()
+        .set_sortlist(&sortlist)
+        .is_ok());
+    assert!(BlockingResolver::new()
+        .unwrap()
+        .set_sortlist(&sortlist)
+        .is_ok());
+    assert!(BlockingResolver::new()
+        .unwrap()
+        .set_sortlist(&["not a sortlist"])
+        .is_err());
+}
+
+#[test]
+fn options_record_settings() {
+    let mut options = Options::new();
+    options.set_timeout(1000).set_domains(&["example.com"]);
+    assert_eq!(options.timeout(), Some(1000));
+    assert_eq!(options.tries(), None);
+    assert_eq!(options.domains(), Some(&["example.com".to_owned()][..]));
+    assert_eq!(
+        format!("{:?}", options),
+        r#"Options { timeout: 1000, domains: ["example.com"] }"#
+    );
+}
+
+#[test]
+fn options_clone() {
+    let mut options = Options::new();
+    options.set_tries(2).set_lookups("fb").set_rotate();
+    let mut cloned = options.clone();
+    cloned.set_tries(3);
+    assert_eq!(options.tries(), Some(2));
+    assert_eq!(cloned.tries(), Some(3));
+    assert_eq!(cloned.lookups(), Some("fb"));
+    assert_eq!(cloned.rotate(), Some(true));
+}
+
+#[test]
+fn options_clone_keeps_every_setting() {
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 1)))
+        .set_relay_limits(4, 8, 2)
+        .set_max_bindings(8)
+        .set_query_policy(|_| Decision::Allow)
+        .set_allowed_domains(&["example.com"])
+        .set_host_aliases(&[("www", "www.example.com")])
+        .set_flags(c_ares::Flags::STAYOPEN)
+        .set_timeout(1500)
+        .set_tries(2)
+        .set_ndots(3)
+        .set_udp_port(5353)
+        .set_tcp_port(5354)
+        .set_domains(&["example.com", "example.org"])
+        .set_lookups("fb")
+        .set_sock_send_buffer_size(4096)
+        .set_sock_receive_buffer_size(8192)
+        .set_no_rotate()
+        .set_ednspsz(1232)
+        .set_max_in_flight(10)
+        .set_max_outstanding_queries(20)
+        .set_max_queued_queries(30)
+        .set_rate_limit(40, 50)
+        .set_memory_budget(1 << 20)
+        .set_thread_name("resolver")
+        .set_thread_stack_size(1 << 16);
+    #[cfg(cares1_15)]
+    options.set_resolvconf_path("/etc/resolv.conf");
+    #[cfg(cares1_19)]
+    options.set_hosts_path("/etc/hosts");
+    #[cfg(cares1_20)]
+    options.set_udp_max_queries(100);
+    #[cfg(cares1_22)]
+    options
+        .set_watch_hosts_file(std::time::Duration::from_secs(5))
+        .set_max_timeout(4000);
+    #[cfg(all(feature = "net-watch", cares1_22))]
+    options.set_watch_network(true);
+    #[cfg(cares1_23)]
+    options.set_query_cache_max_ttl(60);
+    #[cfg(cares1_24)]
+    options
+        .set_edns_client_subnet(std::net::IpAddr::from([192, 0, 2, 0]), 24)
+        .set_local_names(LocalNames::Reject)
+        .set_sanitize_responses(true)
+        .set_packet_capture(|_| {})
+        .set_recording("responses.json", RecordingMode::Replay);
+    #[cfg(all(feature = "tls", cares1_24))]
+    options.set_tls_client_config(crate::tls::default_client_config().unwrap());
+    #[cfg(cares1_29)]
+    options
+        .set_server_failover(5, std::time::Duration::from_secs(2))
+        .set_circuit_breaker(3, std::time::Duration::from_secs(10));
+    #[cfg(cares1_34)]
+    options.set_dns_cookies(false).set_dns0x20(true);
+    #[cfg(feature = "idna")]
+    options.set_idna_mode(IdnaMode::Strict);
+    #[cfg(feature = "thread-tuning")]
+    options.set_thread_priority(5).set_thread_affinity(&[0, 1]);
+
+    // The `Debug` representation shows every value that has been set.
+    let cloned = options.clone();
+    assert_eq!(format!("{cloned:?}"), format!("{options:?}"));
+    #[cfg(cares1_29)]
+    {
+        assert!(cloned.has_server_failover_options());
+        assert_eq!(
+            cloned.server_failover(),
+            Some((5, std::time::Duration::from_secs(2)))
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn resolver_config_from_json() {
+    let json = r#"{"timeout": 2000, "domains": ["example.com"], "servers": ["8.8.8.8"]}"#;
+    let config: ResolverConfig = serde_json::from_str(json).unwrap();
+    let options = config.to_options();
+    assert_eq!(options.timeout(), Some(2000));
+    assert_eq!(options.domains(), Some(&["example.com".to_owned()][..]));
+    assert_eq!(ResolverConfig::from(&options).servers, None);
+    assert!(serde_json::from_str::<ResolverConfig>(r#"{"bogus": 1}"#).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn results_round_trip_through_json() {
+    let results = HostResults {
+        hostname: "example.com".to_owned(),
+        addresses: vec![std::net::IpAddr::from([192, 0, 2, 1])],
+        aliases: vec!["www.example.com".to_owned()],
+    };
+    let json = serde_json::to_string(&results).unwrap();
+    assert_eq!(
+        json,
+        r#"{"hostname":"example.com","addresses":["192.0.2.1"],"aliases":["www.example.com"]}"#
+    );
+    assert_eq!(serde_json::from_str::<HostResults>(&json).unwrap(), results);
+
+    let result = NameInfoResult {
+        node: Some("localhost".to_owned()),
+        service: None,
+    };
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(
+        serde_json::from_str::<NameInfoResult>(&json).unwrap(),
+        result
+    );
+
+    let exchange = MailExchange {
+        preference: 10,
+        host: "mail.example.com".to_owned(),
+        addresses: Vec::new(),
+    };
+    let json = serde_json::to_string(&exchange).unwrap();
+    assert_eq!(
+        serde_json::from_str::<MailExchange>(&json).unwrap(),
+        exchange
+    );
+}
+
+#[test]
+fn host_results_give_socket_addrs() {
+    let v4 = std::net::Ipv4Addr::new(192, 0, 2, 1);
+    let v6 = std::net::Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+    let results = HostResults {
+        hostname: "example.com".to_owned(),
+        addresses: vec![v6.into(), v4.into()],
+        aliases: Vec::new(),
+    };
+    assert_eq!(
+        results.to_socket_addrs(443),
+        vec![
+            std::net::SocketAddr::new(v6.into(), 443),
+            std::net::SocketAddr::new(v4.into(), 443),
+        ]
+    );
+    assert_eq!(results.partition_by_family(), (vec![v4], vec![v6]));
+}
+
+#[test]
+fn results_are_displayed() {
+    let results = HostResults {
+        hostname: "example.com".to_owned(),
+        addresses: vec![
+            std::net::IpAddr::from([192, 0, 2, 1]),
+            std::net::IpAddr::from([192, 0, 2, 2]),
+        ],
+        aliases: Vec::new(),
+    };
+    assert_eq!(
+        results.to_string(),
+        "Hostname: example.com, Addresses: [192.0.2.1, 192.0.2.2], Aliases: []"
+    );
+
+    let result = NameInfoResult {
+        node: Some("localhost".to_owned()),
+        service: None,
+    };
+    assert_eq!(result.to_string(), "Node: localhost, Service: <None>");
+
+    let exchange = MailExchange {
+        preference: 10,
+        host: "mail.example.com".to_owned(),
+        addresses: vec![std::net::IpAddr::from([192, 0, 2, 25])],
+    };
+    assert_eq!(
+        exchange.to_string(),
+        "Host: mail.example.com, Preference: 10, Addresses: [192.0.2.25]"
+    );
+
+    let target = SrvTarget {
+        host: "sip.example.com".to_owned(),
+        port: 5060,
+        priority: 10,
+        weight: 5,
+    };
+    assert_eq!(
+        target.to_string(),
+        "Host: sip.example.com, Port: 5060, Priority: 10, Weight: 5"
+    );
+    let endpoint = SrvEndpoint {
+        target,
+        addresses: vec!["192.0.2.5:5060".parse().unwrap()],
+    };
+    assert!(endpoint
+        .to_string()
+        .ends_with(", Addresses: [192.0.2.5:5060]"));
+}
+
+#[test]
+fn options_from_env_values() {
+    let (options, servers) = Options::from_env_values(
+        Some("ndots:2 timeout:3 attempts:4 rotate use-vc debug"),
+        Some("example.com example.org"),
+        Some("8.8.8.8, [2001:4860:4860::8888]:53,"),
+    );
+    assert_eq!(options.ndots(), Some(2));
+    assert_eq!(options.timeout(), Some(3000));
+    assert_eq!(options.tries(), Some(4));
+    assert_eq!(options.rotate(), Some(true));
+    let mut flags = c_ares::Flags::USEVC;
+    #[cfg(cares1_22)]
+    flags.insert(c_ares::Flags::EDNS);
+    assert_eq!(options.flags(), Some(flags));
+    assert_eq!(
+        options.domains(),
+        Some(&["example.com".to_owned(), "example.org".to_owned()][..])
+    );
+    assert_eq!(servers, vec!["8.8.8.8", "[2001:4860:4860::8888]:53"]);
+}
+
+#[test]
+fn options_from_empty_env_values() {
+    let (options, servers) = Options::from_env_values(None, None, None);
+    assert_eq!(format!("{:?}", options), "Options");
+    assert!(servers.is_empty());
+}
+
+#[test]
+fn lookups_from_nsswitch() {
+    let lookups = |contents| {
+        let mut options = Options::new();
+        options.set_lookups_from_nsswitch_contents(contents);
+        options.lookups().map(str::to_owned)
+    };
+    let contents = "# comment\npasswd: files\nhosts: files mdns4_minimal [NOTFOUND = return] dns\n";
+    assert_eq!(lookups(contents).as_deref(), Some("fb"));
+    assert_eq!(
+        lookups("hosts: dns [!UNAVAIL=return] files myhostname").as_deref(),
+        Some("bf")
+    );
+    assert_eq!(lookups("passwd: files").as_deref(), Some("bf"));
+    assert_eq!(lookups("hosts: myhostname"), None);
+    assert!(Options::new()
+        .set_lookups_from_nsswitch("/nonexistent/nsswitch.conf")
+        .is_err());
+}
+
+#[test]
+fn options_flag_helpers_accumulate() {
+    let mut options = Options::new();
+    options.use_tcp().no_search().edns();
+    assert_eq!(
+        options.flags(),
+        Some(c_ares::Flags::USEVC | c_ares::Flags::NOSEARCH | c_ares::Flags::EDNS)
+    );
+}
+
+#[test]
+fn options_flag_helpers_keep_default_flags() {
+    let mut options = Options::new();
+    options.use_tcp();
+    let flags = options.flags().unwrap();
+    assert!(flags.contains(c_ares::Flags::USEVC));
+    #[cfg(cares1_22)]
+    assert!(flags.contains(c_ares::Flags::EDNS));
+
+    // Flags that are set explicitly are respected.
+    let mut options = Options::new();
+    options.set_flags(c_ares::Flags::NOCHECKRESP).no_aliases();
+    assert_eq!(
+        options.flags(),
+        Some(c_ares::Flags::NOCHECKRESP | c_ares::Flags::NOALIASES)
+    );
+}
+
+// Answer an A query with a single record.
+fn answer_a(query: &[u8], address: std::net::Ipv4Addr) -> Vec<u8> {
+    answer(query, 1, &address.octets())
+}
+
+fn answer(query: &[u8], rr_type: u16, rdata: &[u8]) -> Vec<u8> {
+    // Find the end of the question: the name, followed by type and class.
+    let mut end = 12;
+    while query[end] != 0 {
+        end += usize::from(query[end]) + 1;
+    }
+    end += 5;
+
+    let mut response = query[..end].to_vec();
+    response[2] |= 0x80;
+    response[3] = 0x80;
+    response[6..12].copy_from_slice(&[0, 1, 0, 0, 0, 0]);
+    response.extend_from_slice(&[0xc0, 0x0c]);
+    response.extend_from_slice(&rr_type.to_be_bytes());
+    response.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+    response.extend_from_slice(&u16::try_from(rdata.len()).unwrap().to_be_bytes());
+    response.extend_from_slice(rdata);
+    response
+}
+
+// Answers every query with a single record of the given type and data.
+struct RecordTransport(u16, Vec<u8>);
+
+impl Transport for RecordTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        Ok(answer(query, self.0, &self.1))
+    }
+}
+
+struct FixedTransport(std::net::Ipv4Addr);
+
+impl Transport for FixedTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        Ok(answer_a(query, self.0))
+    }
+}
+
+// Answers A queries with the given address, after a delay.
+#[cfg(cares1_24)]
+struct SlowTransport(std::net::Ipv4Addr, std::time::Duration);
+
+#[cfg(cares1_24)]
+impl Transport for SlowTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        std::thread::sleep(self.1);
+        Ok(answer_a(query, self.0))
+    }
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn blocking_queries_can_time_out() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 65);
+    let mut options = Options::new();
+    options.set_transport(SlowTransport(
+        address,
+        std::time::Duration::from_millis(500),
+    ));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    let start = std::time::Instant::now();
+    let timeout = std::time::Duration::from_millis(50);
+    let result = resolver.query_a_timeout("slow.example.com", timeout);
+    assert_eq!(result.err(), Some(c_ares::Error::ETIMEOUT));
+    let result =
+        resolver.get_host_by_name_timeout("slow.example.com", c_ares::AddressFamily::INET, timeout);
+    assert_eq!(result.err(), Some(c_ares::Error::ETIMEOUT));
+    assert!(start.elapsed() < std::time::Duration::from_millis(400));
+
+    // Given long enough, the query completes - and the abandoned queries do no harm.
+    let results = resolver
+        .query_a_timeout("slow.example.com", std::time::Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(results.iter().next().unwrap().ipv4(), address);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn outstanding_queries_are_limited() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 69);
+    let mut options = Options::new();
+    options
+        .set_transport(SlowTransport(
+            address,
+            std::time::Duration::from_millis(200),
+        ))
+        .set_max_outstanding_queries(1)
+        .set_max_queued_queries(1);
+    let resolver = Resolver::with_options(options).unwrap();
+    assert!(resolver.check_capacity().is_ok());
+
+    // The first query is made, the second is queued, and the third is rejected - failing on the
+    // event loop thread, as any other query would.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut rejections = Vec::new();
+    for name in ["one.example.com", "two.example.com", "three.example.com"] {
+        let tx = tx.clone();
+        let thread_id = resolver.thread_id();
+        let rejection = resolver.try_query(|resolver| {
+            resolver.query_a(name, move |result| {
+                assert_eq!(std::thread::current().id(), thread_id);
+                let result = result.map(|results| results.iter().next().unwrap().ipv4());
+                tx.send((name, result)).unwrap();
+            })
+        });
+        rejections.push(rejection.err());
+    }
+    assert!(matches!(
+        rejections[..],
+        [None, None, Some(Error::Overloaded)]
+    ));
+    assert!(matches!(resolver.check_capacity(), Err(Error::Overloaded)));
+    let timeout = std::time::Duration::from_secs(5);
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap(),
+        ("three.example.com", Err(c_ares::Error::ENOMEM))
+    );
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap(),
+        ("one.example.com", Ok(address))
+    );
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap(),
+        ("two.example.com", Ok(address))
+    );
+    assert!(resolver.check_capacity().is_ok());
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn queued_queries_are_made_by_priority() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 71);
+    let mut options = Options::new();
+    options
+        .set_transport(SlowTransport(address, std::time::Duration::from_millis(50)))
+        .set_max_outstanding_queries(1);
+    let resolver = Resolver::with_options(options).unwrap();
+
+    // The first query is made at once, and the rest are queued.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let queries = [
+        ("first.example.com", Priority::Low),
+        ("low.example.com", Priority::Low),
+        ("normal.example.com", Priority::Normal),
+        ("high.example.com", Priority::High),
+    ];
+    for (name, priority) in queries {
+        let tx = tx.clone();
+        priority.scope(|| resolver.query_a(name, move |_| tx.send(name).unwrap()));
+    }
+    assert_eq!(Priority::current(), Priority::Normal);
+
+    let timeout = std::time::Duration::from_secs(5);
+    let order: Vec<_> = (0..4).map(|_| rx.recv_timeout(timeout).unwrap()).collect();
+    assert_eq!(
+        order,
+        vec![
+            "first.example.com",
+            "high.example.com",
+            "normal.example.com",
+            "low.example.com"
+        ]
+    );
+}
+
+#[test]
+fn memory_budget_limits_buffered_results() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 72);
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(address))
+        .set_memory_budget(1);
+    let resolver = FutureResolver::with_options(options).unwrap();
+
+    // Results that are not consumed use up the budget.
+    let names = ["one.example.com", "two.example.com"];
+    let mut many = resolver.resolve_many(names, c_ares::AddressFamily::INET);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while resolver.load().memory_used == 0 && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(resolver.load().memory_used > 0);
+    assert!(matches!(
+        resolver.check_capacity(),
+        Err(Error::MemoryBudgetExceeded)
+    ));
+    let result = futures_executor::block_on(resolver.query_a("three.example.com"));
+    assert_eq!(result.err(), Some(c_ares::Error::ENOMEM));
+    let result = futures_executor::block_on(
+        resolver.try_query(|resolver| resolver.query_a("three.example.com")),
+    );
+    assert!(matches!(result, Err(Error::MemoryBudgetExceeded)));
+
+    // Consuming them frees it again.
+    assert_eq!(collect_stream(&mut many).len(), 2);
+    assert_eq!(resolver.load().memory_used, 0);
+    assert!(resolver.check_capacity().is_ok());
+    let results = futures_executor::block_on(resolver.query_a("three.example.com")).unwrap();
+    assert_eq!(results.iter().next().unwrap().ipv4(), address);
+}
+
+#[test]
+fn event_loop_thread_is_configured() {
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 73)))
+        .set_thread_name("dns-event-loop")
+        .set_thread_stack_size(256 * 1024);
+    assert_eq!(options.thread_name(), Some("dns-event-loop"));
+    assert_eq!(options.thread_stack_size(), Some(256 * 1024));
+    let resolver = Resolver::with_options(options).unwrap();
+
+    // Handlers are called on the event loop thread.
+    let (tx, rx) = std::sync::mpsc::channel();
+    resolver.query_a("example.com", move |result| {
+        let thread = std::thread::current();
+        tx.send((
+            result.is_ok(),
+            thread.id(),
+            thread.name().map(str::to_owned),
+        ))
+        .unwrap();
+    });
+    let (ok, id, name) = rx.recv().unwrap();
+    assert!(ok);
+    assert_eq!(id, resolver.thread_id());
+    assert_ne!(id, std::thread::current().id());
+    assert_eq!(name.as_deref(), Some("dns-event-loop"));
+}
+
+#[cfg(all(feature = "thread-tuning", target_os = "linux"))]
+#[test]
+fn event_loop_thread_is_tuned() {
+    // The CPUs that the calling thread may run on.
+    fn affinity() -> Vec<usize> {
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        assert_eq!(unsafe { libc::sched_getaffinity(0, size, &mut set) }, 0);
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+            .collect()
+    }
+
+    // Lowering the priority needs no privileges.
+    let cpu = affinity()[0];
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 74)))
+        .set_thread_priority(19)
+        .set_thread_affinity(&[cpu]);
+    assert_eq!(options.thread_priority(), Some(19));
+    assert_eq!(options.thread_affinity(), Some(&[cpu][..]));
+    let resolver = Resolver::with_options(options).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    resolver.query_a("example.com", move |result| {
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t) };
+        tx.send((result.is_ok(), nice, affinity())).unwrap();
+    });
+    assert_eq!(rx.recv().unwrap(), (true, 19, vec![cpu]));
+
+    // Settings that can't be applied are reported.
+    let mut options = Options::new();
+    options.set_thread_affinity(&[libc::CPU_SETSIZE as usize]);
+    assert!(matches!(
+        Resolver::with_options(options),
+        Err(Error::EventLoopFailed(_))
+    ));
+}
+
+#[test]
+fn queries_are_rate_limited() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 70);
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(address))
+        .set_rate_limit(20, 2);
+    let resolver = Resolver::with_options(options).unwrap();
+
+    // Two queries are made at once, and the rest at twenty per second.
+    let start = std::time::Instant::now();
+    let (tx, rx) = std::sync::mpsc::channel();
+    for index in 0..6 {
+        let tx = tx.clone();
+        let name = format!("host{index}.example.com");
+        resolver.query_a(&name, move |result| tx.send(result.is_ok()).unwrap());
+    }
+    let load = resolver.load();
+    assert_eq!(load.queued, 4);
+    assert!(load.rate_limit_tokens.unwrap() < 1.0);
+
+    let timeout = std::time::Duration::from_secs(5);
+    for _ in 0..6 {
+        assert!(rx.recv_timeout(timeout).unwrap());
+    }
+    assert!(start.elapsed() >= std::time::Duration::from_millis(150));
+    assert_eq!(resolver.load().queued, 0);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn blocking_lookups_are_made_in_parallel() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 66);
+    let mut options = Options::new();
+    options.set_transport(SlowTransport(
+        address,
+        std::time::Duration::from_millis(200),
+    ));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let pinned = std::net::IpAddr::from([192, 0, 2, 67]);
+    resolver.add_host_override("pinned.example.com", &[pinned]);
+
+    let names = [
+        "a.example.com",
+        "pinned.example.com",
+        "b.example.com",
+        "bad..example.com",
+        "c.example.com",
+    ];
+    let start = std::time::Instant::now();
+    let results = resolver.resolve_all(&names, c_ares::AddressFamily::INET);
+    assert!(start.elapsed() < std::time::Duration::from_millis(600));
+    assert_eq!(results.len(), 5);
+    for index in [0, 2, 4] {
+        let addresses = &results[index].as_ref().unwrap().addresses;
+        assert_eq!(addresses, &vec![std::net::IpAddr::V4(address)]);
+    }
+    assert_eq!(results[1].as_ref().unwrap().addresses, vec![pinned]);
+    assert_eq!(results[3].as_ref().err(), Some(&c_ares::Error::EBADNAME));
+}
+
+#[test]
+fn blocking_resolver_gives_socket_addresses() {
+    use std::net::{SocketAddr, ToSocketAddrs};
+
+    let resolver = BlockingResolver::new().unwrap();
+    let address = std::net::IpAddr::from([192, 0, 2, 68]);
+    resolver.add_host_override("web.example.com", &[address]);
+
+    let addresses = CAresToSocketAddrs::new(&resolver, "web.example.com:8080").unwrap();
+    assert_eq!(addresses.host(), "web.example.com");
+    let addresses: Vec<_> = addresses.to_socket_addrs().unwrap().collect();
+    assert_eq!(addresses, vec![SocketAddr::new(address, 8080)]);
+
+    let addresses = CAresToSocketAddrs::new(&resolver, "[2001:db8::1]:443").unwrap();
+    let addresses: Vec<_> = addresses.to_socket_addrs().unwrap().collect();
+    assert_eq!(addresses, vec!["[2001:db8::1]:443".parse().unwrap()]);
+
+    assert!(CAresToSocketAddrs::new(&resolver, "web.example.com").is_err());
+    assert!(CAresToSocketAddrs::new(&resolver, "2001:db8::1:443").is_err());
+    let invalid = CAresToSocketAddrs::with_port(&resolver, "bad..example.com", 80);
+    assert!(invalid.to_socket_addrs().is_err());
+}
+
+#[test]
+fn permits_limit_queries_in_flight() {
+    use std::task::{Context, Poll, Waker};
+
+    let mut options = Options::new();
+    options.set_max_in_flight(2);
+    let resolver = FutureResolver::with_options(options).unwrap();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let first = futures_executor::block_on(resolver.acquire_permit());
+    let second = futures_executor::block_on(resolver.acquire_permit());
+    assert_eq!(resolver.poll_ready(&mut cx), Poll::Pending);
+
+    // A waiting caller gets a permit once one is released.
+    let waiter = resolver.clone();
+    let handle = std::thread::spawn(move || futures_executor::block_on(waiter.acquire_permit()));
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(!handle.is_finished());
+    drop(first);
+    let third = handle.join().unwrap();
+    assert_eq!(resolver.poll_ready(&mut cx), Poll::Pending);
+
+    drop(second);
+    drop(third);
+    assert_eq!(resolver.poll_ready(&mut cx), Poll::Ready(()));
+
+    // Without a limit, permits are always available.
+    let resolver = FutureResolver::new().unwrap();
+    let _permits: Vec<_> = (0..10)
+        .map(|_| futures_executor::block_on(resolver.acquire_permit()))
+        .collect();
+    assert_eq!(resolver.poll_ready(&mut cx), Poll::Ready(()));
+}
+
+#[test]
+fn routing_resolver_routes_by_name() {
+    let resolver = |address| {
+        let mut options = Options::new();
+        options.set_transport(FixedTransport(address));
+        BlockingResolver::with_options(options).unwrap()
+    };
+    let default = std::net::Ipv4Addr::new(192, 0, 2, 10);
+    let corp = std::net::Ipv4Addr::new(192, 0, 2, 11);
+    let lab = std::net::Ipv4Addr::new(192, 0, 2, 12);
+    let mut routing = RoutingResolver::new(resolver(default));
+    routing
+        .add_route("corp.example", resolver(corp))
+        .add_route("*.lab.corp.example", resolver(lab));
+
+    let lookup = |name| {
+        let results = routing.query_a(name).unwrap();
+        results.iter().next().unwrap().ipv4()
+    };
+    assert_eq!(lookup("example.com"), default);
+    assert_eq!(lookup("corp.example"), corp);
+    assert_eq!(lookup("WWW.Corp.Example."), corp);
+    assert_eq!(lookup("lab.corp.example"), corp);
+    assert_eq!(lookup("host.lab.corp.example"), lab);
+    assert_eq!(lookup("notcorp.example"), default);
+}
+
+// A transport whose server always fails.
+struct FailingTransport;
+
+impl Transport for FailingTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut response = query.to_vec();
+        response[2] |= 0x80;
+        response[3] = 0x82;
+        Ok(response)
+    }
+}
+
+#[test]
+fn fallback_resolver_falls_back() {
+    let mut options = Options::new();
+    options.set_transport(FailingTransport);
+    let primary = FutureResolver::with_options(options).unwrap();
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 13);
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(address));
+    let fallback = FutureResolver::with_options(options).unwrap();
+
+    let mut resolver = FallbackResolver::new(primary);
+    resolver.add_fallback(fallback);
+    let result = futures_executor::block_on(resolver.query_a("example.com")).unwrap();
+    assert_eq!(result.resolver, 1);
+    let addresses: Vec<_> = result.answer.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+// A transport that can't reach anything.
+struct BrokenTransport;
+
+impl Transport for BrokenTransport {
+    fn exchange(&self, _query: &[u8]) -> std::io::Result<Vec<u8>> {
+        Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+    }
+}
+
+#[test]
+fn transport_errors_fail_queries_at_once() {
+    for flags in [c_ares::Flags::empty(), c_ares::Flags::USEVC] {
+        let mut options = Options::new();
+        options
+            .set_transport(BrokenTransport)
+            .set_flags(flags)
+            .set_timeout(5000)
+            .set_tries(1);
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        let start = std::time::Instant::now();
+        let result = resolver.query_a("example.com");
+        assert_eq!(result.err(), Some(c_ares::Error::ESERVFAIL));
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn relay_limits_hold_queries_back() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 66);
+    let mut options = Options::new();
+    options
+        .set_transport(SlowTransport(address, std::time::Duration::from_millis(50)))
+        .set_relay_limits(1, 0, 1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let resolver = &resolver;
+    std::thread::scope(|scope| {
+        let lookups: Vec<_> = (0..4)
+            .map(|n| scope.spawn(move || resolver.query_a(&format!("host{n}.example.com"))))
+            .collect();
+        for lookup in lookups {
+            let results = lookup.join().unwrap().unwrap();
+            assert_eq!(results.iter().next().unwrap().ipv4(), address);
+        }
+    });
+}
+
+#[test]
+fn retry_policies() {
+    use std::time::Duration;
+
+    let policy = RetryPolicy::fixed(Duration::from_millis(10)).with_max_retries(2);
+    assert_eq!(policy.delay(1), Some(Duration::from_millis(10)));
+    assert_eq!(policy.delay(2), Some(Duration::from_millis(10)));
+    assert_eq!(policy.delay(3), None);
+
+    let policy = RetryPolicy::exponential(Duration::from_millis(100), Duration::from_millis(300));
+    for (retry, max) in [(1, 100), (2, 200), (3, 300), (10, 300)] {
+        let delay = policy.delay(retry).unwrap();
+        assert!(delay >= Duration::from_millis(max / 2));
+        assert!(delay <= Duration::from_millis(max));
+    }
+
+    let policy = RetryPolicy::custom(|retry| (retry < 2).then_some(Duration::ZERO));
+    assert_eq!(policy.delay(1), Some(Duration::ZERO));
+    assert_eq!(policy.delay(2), None);
+}
+
+#[test]
+fn retry_delays_share_a_timer() {
+    use std::time::Duration;
+
+    // Many delays at once are all served by the one timer thread, each in its turn.
+    let start = std::time::Instant::now();
+    let (tx, rx) = std::sync::mpsc::channel();
+    for delay in [60, 20, 40] {
+        let tx = tx.clone();
+        let sleep = retry::sleep(Duration::from_millis(delay));
+        std::thread::spawn(move || {
+            futures_executor::block_on(sleep);
+            tx.send(delay).unwrap();
+        });
+    }
+    let order: Vec<_> = (0..3).map(|_| rx.recv().unwrap()).collect();
+    assert_eq!(order, vec![20, 40, 60]);
+    assert!(start.elapsed() >= Duration::from_millis(60));
+}
+
+#[test]
+fn retry_budget_limits_retries() {
+    let budget = RetryBudget::new(0.5, std::time::Duration::from_secs(60)).with_min_retries(1);
+    let policy = RetryPolicy::immediate().with_budget(budget.clone());
+    assert!(policy.next_retry(1).is_some());
+    assert!(!budget.can_retry());
+    for _ in 0..4 {
+        policy.record_request();
+    }
+    assert!(policy.next_retry(1).is_some());
+    assert!(policy.next_retry(1).is_some());
+    assert!(policy.next_retry(1).is_none());
+}
+
+#[test]
+fn fallback_resolver_follows_retry_policy() {
+    let mut options = Options::new();
+    options.set_transport(FailingTransport);
+    let primary = BlockingResolver::with_options(options).unwrap();
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 14)));
+    let fallback = BlockingResolver::with_options(options).unwrap();
+
+    let mut resolver = FallbackResolver::new(primary);
+    resolver
+        .add_fallback(fallback)
+        .set_retry_policy(RetryPolicy::immediate().with_max_retries(0));
+    assert!(resolver.query_a("example.com").is_err());
+}
+
+#[cfg(cares1_29)]
+#[test]
+fn server_stats_are_gathered() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut builder = ResolverBuilder::new();
+    builder
+        .options()
+        .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 15)));
+    builder.set_server_state_callback(move |_, success, _| tx.send(success).unwrap());
+    let resolver = builder.build_blocking().unwrap();
+    resolver.query_a("example.com").unwrap();
+    resolver.query_a("example.org").unwrap();
+    assert!(rx.recv().unwrap());
+
+    let stats = resolver.server_stats();
+    assert_eq!(stats.len(), 1);
+    let stats = stats.values().next().unwrap();
+    assert_eq!(stats.successes, 2);
+    assert_eq!(stats.failures, 0);
+    assert!(stats.last_success.is_some());
+}
+
+// Run a UDP DNS server on the loopback interface, answering queries using the given transport.
+fn udp_server<T: Transport>(transport: T) -> String {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        let mut buffer = [0; 512];
+        loop {
+            let (length, peer) = socket.recv_from(&mut buffer).unwrap();
+            let response = transport.exchange(&buffer[..length]).unwrap();
+            socket.send_to(&response, peer).unwrap();
+        }
+    });
+    server
+}
+
+#[cfg(cares1_29)]
+#[test]
+fn circuit_breaker_takes_out_failing_servers() {
+    let failing = udp_server(FailingTransport);
+    let working = udp_server(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 16)));
+    let mut options = Options::new();
+    options.set_circuit_breaker(1, std::time::Duration::from_millis(200));
+    let mut builder = ResolverBuilder::with_options(options);
+    builder.set_servers(&[&failing, &working]);
+    let resolver = builder.build_blocking().unwrap();
+
+    resolver.query_a("example.com").unwrap();
+    assert_eq!(&*resolver.get_servers(), working);
+    assert!(resolver.server_stats()[&failing].circuit_open);
+
+    // After the probe interval, the failing server is put back in its place.
+    std::thread::sleep(std::time::Duration::from_millis(400));
+    assert_eq!(&*resolver.get_servers(), format!("{failing},{working}"));
+    assert!(!resolver.server_stats()[&failing].circuit_open);
+}
+
+#[test]
+fn circuits_are_closed_when_servers_are_set() {
+    let failing = udp_server(FailingTransport);
+    let working = udp_server(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 17)));
+    let other = udp_server(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 18)));
+    let mut options = Options::new();
+    options.set_circuit_breaker(1, std::time::Duration::from_millis(200));
+    let mut builder = ResolverBuilder::with_options(options);
+    builder.set_servers(&[&failing, &working]);
+    let resolver = builder.build_blocking().unwrap();
+    let tcp = resolver.tcp().unwrap();
+
+    // Derived resolvers follow the circuit breaker.
+    resolver.query_a("example.com").unwrap();
+    assert_eq!(&*resolver.get_servers(), working);
+    assert_eq!(&*tcp.get_servers(), working);
+
+    // Setting the servers closes the circuit, and the probe timer leaves them be.
+    resolver.set_servers(&[&failing, &other]).unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while resolver.server_stats()[&failing].circuit_open && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(!resolver.server_stats()[&failing].circuit_open);
+    std::thread::sleep(std::time::Duration::from_millis(400));
+    let servers = format!("{failing},{other}");
+    assert_eq!(&*resolver.get_servers(), servers);
+    assert_eq!(&*tcp.get_servers(), servers);
+}
+
+#[test]
+fn blocking_lookups_can_be_cancelled() {
+    // A server that never answers.
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap().to_string();
+
+    let mut options = Options::new();
+    options.set_timeout(10_000).set_tries(1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver.set_servers(&[&server]).unwrap();
+    std::thread::scope(|scope| {
+        // Cancel only once the query has reached the server, and so is certainly outstanding.
+        scope.spawn(|| {
+            let mut buffer = [0; 512];
+            socket.recv_from(&mut buffer).unwrap();
+            resolver.cancel();
+        });
+        let result = resolver.query_a("example.com");
+        assert!(matches!(result, Err(c_ares::Error::ECANCELLED)));
+    });
+    drop(socket);
+}
+
+#[test]
+fn dropping_resolver_fails_queued_queries() {
+    // A server that never answers.
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap().to_string();
+    let mut options = Options::new();
+    options.set_timeout(10_000).set_tries(1);
+    let resolver = Resolver::with_options(options).unwrap();
+    resolver.set_servers(&[&server]).unwrap();
+
+    // Drop the resolver while most of these are still waiting for the event loop.
+    let (tx, rx) = std::sync::mpsc::channel();
+    for n in 0..100 {
+        let tx = tx.clone();
+        resolver.query_a(&format!("host{n}.example.com"), move |result| {
+            let _ = tx.send(result.err());
+        });
+    }
+    drop(tx);
+    drop(resolver);
+    let errors: Vec<_> = rx.iter().collect();
+    assert_eq!(errors.len(), 100);
+    assert!(errors
+        .iter()
+        .all(|&error| error == Some(c_ares::Error::EDESTRUCTION)));
+    drop(socket);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn handlers_can_call_resolver_methods() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 67)));
+    let resolver = std::sync::Arc::new(Resolver::with_options(options).unwrap());
+    let sortlist = ["130.155.160.0/255.255.240.0"];
+
+    // Methods that would wait for the event loop fail, rather than deadlocking; others work.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let inner = std::sync::Arc::clone(&resolver);
+    resolver.query_a("example.com", move |result| {
+        let sortlist = inner.set_sortlist(&sortlist).err();
+        let servers = inner.get_servers();
+        inner.set_local_ipv4(std::net::Ipv4Addr::LOCALHOST).cancel();
+        let _ = tx.send((result.is_ok(), sortlist, servers));
+    });
+    let (ok, sortlist_error, servers) = rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+    assert!(ok);
+    assert_eq!(sortlist_error, Some(c_ares::Error::ENOTIMP));
+    assert!(servers.is_empty());
+
+    // Away from the event loop, they work as usual.
+    resolver.set_sortlist(&sortlist).unwrap();
+    assert!(!resolver.get_servers().is_empty());
+}
+
+#[test]
+fn queries_go_to_transport() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 1);
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(address));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+#[test]
+fn tcp_queries_go_to_transport() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 2);
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(address)).use_tcp();
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn relay_only_answers_the_resolver() {
+    use std::io::{Read, Write};
+
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 3);
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(address));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let relay: std::net::SocketAddr = resolver.get_servers().parse().unwrap();
+
+    // The resolver, and the TCP resolver derived from it, are answered.
+    let results = resolver.query_a("example.com").unwrap();
+    assert_eq!(results.iter().next().unwrap().ipv4(), address);
+    let results = resolver.tcp().unwrap().query_a("example.com").unwrap();
+    assert_eq!(results.iter().next().unwrap().ipv4(), address);
+
+    // Anyone else is not.
+    let query = [
+        0x12, 0x34, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3,
+        b'c', b'o', b'm', 0, 0, 1, 0, 1,
+    ];
+    let timeout = Some(std::time::Duration::from_millis(500));
+    let socket = std::net::UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0)).unwrap();
+    socket.set_read_timeout(timeout).unwrap();
+    socket.send_to(&query, relay).unwrap();
+    let mut buffer = [0; 512];
+    assert!(socket.recv_from(&mut buffer).is_err());
+
+    let mut stream = std::net::TcpStream::connect(relay).unwrap();
+    stream.set_read_timeout(timeout).unwrap();
+    let mut message = u16::try_from(query.len()).unwrap().to_be_bytes().to_vec();
+    message.extend_from_slice(&query);
+    let _ = stream.write_all(&message);
+    assert!(!matches!(stream.read(&mut buffer), Ok(length) if length > 0));
+}
+
+#[test]
+fn servers_cannot_be_set_with_a_transport() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 4)));
+    let resolver = Resolver::with_options(options).unwrap();
+    assert_eq!(
+        resolver.set_servers(&["192.0.2.53"]).err(),
+        Some(c_ares::Error::ENOTIMP)
+    );
+
+    #[cfg(cares1_24)]
+    {
+        let relay = resolver.get_servers_csv();
+        let server: std::net::SocketAddr = "192.0.2.53:53".parse().unwrap();
+        assert_eq!(
+            resolver.add_server(server).err(),
+            Some(c_ares::Error::ENOTIMP)
+        );
+        let relay_address: std::net::SocketAddr = relay.parse().unwrap();
+        assert_eq!(
+            resolver.remove_server(relay_address).err(),
+            Some(c_ares::Error::ENOTIMP)
+        );
+        assert_eq!(resolver.get_servers_csv(), relay);
+    }
+}
+
+// A transport whose responses don't preserve the case of the query name.
+#[cfg(cares1_34)]
+struct LowercaseTransport(std::net::Ipv4Addr);
+
+#[cfg(cares1_34)]
+impl Transport for LowercaseTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut response = answer_a(query, self.0);
+        let mut index = 12;
+        while response[index] != 0 {
+            let end = index + 1 + usize::from(response[index]);
+            response[index + 1..end].make_ascii_lowercase();
+            index = end;
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(cares1_34)]
+#[test]
+fn dns0x20_rejects_mismatched_case() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 6);
+    for (enable, accepted) in [(false, true), (true, false)] {
+        let mut options = Options::new();
+        options
+            .set_transport(LowercaseTransport(address))
+            .set_timeout(200)
+            .set_tries(1)
+            .set_dns0x20(enable);
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        let result = resolver.query_a("example.com");
+        assert_eq!(result.is_ok(), accepted);
+    }
+
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(address))
+        .set_dns0x20(true);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    assert!(resolver.query_a("example.com").is_ok());
+}
+
+// A transport that answers only queries that carry an EDNS OPT record.
+#[cfg(cares1_34)]
+struct RequireEdnsTransport;
+
+#[cfg(cares1_34)]
+impl Transport for RequireEdnsTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let additional = u16::from_be_bytes([query[10], query[11]]);
+        if additional > 0 {
+            Ok(answer_a(query, std::net::Ipv4Addr::new(192, 0, 2, 8)))
+        } else {
+            Err(std::io::Error::other("no OPT record"))
+        }
+    }
+}
+
+#[cfg(cares1_34)]
+#[test]
+fn dns0x20_keeps_edns() {
+    for enable in [true, false] {
+        let mut options = Options::new();
+        options.set_dns0x20(enable);
+        assert!(options.flags().unwrap().contains(c_ares::Flags::EDNS));
+        options
+            .set_transport(RequireEdnsTransport)
+            .set_timeout(200)
+            .set_tries(1);
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        assert!(resolver.query_a("example.com").is_ok());
+    }
+}
+
+// A transport that answers only queries that do, or don't, carry a DNS cookie.
+#[cfg(cares1_34)]
+struct CookieTransport(bool);
+
+#[cfg(cares1_34)]
+impl Transport for CookieTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let cookie = query.windows(4).any(|window| window == [0, 10, 0, 8]);
+        if cookie == self.0 {
+            Ok(answer_a(query, std::net::Ipv4Addr::new(192, 0, 2, 7)))
+        } else {
+            Err(std::io::Error::other("unexpected cookie"))
+        }
+    }
+}
+
+#[cfg(cares1_34)]
+#[test]
+fn dns_cookies_can_be_disabled() {
+    for enable in [true, false] {
+        let mut options = Options::new();
+        options
+            .set_transport(CookieTransport(enable))
+            .set_timeout(200)
+            .set_tries(1)
+            .set_dns_cookies(enable);
+        assert_eq!(options.dns_cookies(), Some(enable));
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        assert!(resolver.query_a("example.com").is_ok());
+    }
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn tcp_resolver_queries_over_tcp() {
+    use std::io::{Read, Write};
+
+    // A DNS server that only listens on TCP.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap().to_string();
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 4);
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut length = [0; 2];
+        stream.read_exact(&mut length).unwrap();
+        let mut query = vec![0; usize::from(u16::from_be_bytes(length))];
+        stream.read_exact(&mut query).unwrap();
+        let response = answer_a(&query, address);
+        let length = u16::try_from(response.len()).unwrap().to_be_bytes();
+        stream.write_all(&length).unwrap();
+        stream.write_all(&response).unwrap();
+    });
+
+    let mut options = Options::new();
+    options.set_timeout(500).set_tries(1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver.set_servers(&[&server]).unwrap();
+    let results = resolver.tcp().unwrap().query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn queries_carry_client_subnet() {
+    // A DNS server that answers only queries carrying the expected client subnet.
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap().to_string();
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 5);
+    std::thread::spawn(move || {
+        let mut buffer = [0; 512];
+        loop {
+            let (length, peer) = socket.recv_from(&mut buffer).unwrap();
+            let query = &buffer[..length];
+            let subnet = [0, 8, 0, 7, 0, 1, 20, 0, 198, 51, 96];
+            if query.ends_with(&subnet) {
+                socket.send_to(&answer_a(query, address), peer).unwrap();
+            }
+        }
+    });
+
+    let mut options = Options::new();
+    options
+        .set_timeout(500)
+        .set_tries(1)
+        .set_edns_client_subnet("198.51.100.1".parse().unwrap(), 20);
+    let mut builder = ResolverBuilder::with_options(options);
+    builder.set_servers(&[&server]);
+    let resolver = builder.build_blocking().unwrap();
+    let results = resolver.query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+
+    // The servers are reported as set, not as the relay.
+    assert_eq!(resolver.get_servers_csv(), server);
+    let servers = resolver.servers();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(
+        std::net::SocketAddr::new(servers[0].addr, servers[0].udp_port).to_string(),
+        server
+    );
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn relayed_tcp_queries_stay_on_tcp() {
+    use std::io::{Read, Write};
+
+    // A DNS server that answers only over TCP.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap().to_string();
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 7);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut prefix = [0; 2];
+            while stream.read_exact(&mut prefix).is_ok() {
+                let mut query = vec![0; usize::from(u16::from_be_bytes(prefix))];
+                stream.read_exact(&mut query).unwrap();
+                let response = answer_a(&query, address);
+                let length = u16::try_from(response.len()).unwrap();
+                stream.write_all(&length.to_be_bytes()).unwrap();
+                stream.write_all(&response).unwrap();
+            }
+        }
+    });
+
+    let mut options = Options::new();
+    options
+        .set_timeout(500)
+        .set_tries(1)
+        .set_edns_client_subnet("198.51.100.1".parse().unwrap(), 20);
+    let mut builder = ResolverBuilder::with_options(options);
+    builder.set_servers(&[&server]);
+    let resolver = builder.build_blocking().unwrap();
+    let results = resolver.tcp().unwrap().query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn queries_without_client_subnet_are_not_sent() {
+    // A query with two questions has nowhere for the option to go.
+    let transport = transport::EdnsTransport::new(
+        std::sync::Arc::new(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 6))),
+        Some(("198.51.100.1".parse().unwrap(), 20)),
+        false,
+    );
+    let question = [7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0, 0, 1, 0, 1];
+    let mut query = vec![0x12, 0x34, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    query.extend_from_slice(&question);
+    assert!(transport.exchange(&query).is_ok());
+    query[5] = 2;
+    query.extend_from_slice(&question);
+    assert!(transport.exchange(&query).is_err());
+}
+
+#[cfg(feature = "socks5")]
+#[test]
+fn queries_go_through_socks5_proxy() {
+    use std::io::{Read, Write};
+
+    // A fake proxy, which is also the DNS server.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = listener.local_addr().unwrap();
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 3);
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut greeting = [0; 3];
+        stream.read_exact(&mut greeting).unwrap();
+        assert_eq!(greeting, [5, 1, 0]);
+        stream.write_all(&[5, 0]).unwrap();
+
+        let mut request = [0; 10];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request, [5, 1, 0, 1, 198, 51, 100, 1, 0, 53]);
+        stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+
+        let mut prefix = [0; 2];
+        stream.read_exact(&mut prefix).unwrap();
+        let mut query = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        stream.read_exact(&mut query).unwrap();
+        let response = answer_a(&query, address);
+        let length = u16::try_from(response.len()).unwrap();
+        stream.write_all(&length.to_be_bytes()).unwrap();
+        stream.write_all(&response).unwrap();
+    });
+
+    let server = "198.51.100.1:53".parse().unwrap();
+    let mut options = Options::new();
+    options.set_transport(Socks5Transport::new(proxy, server));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn tls_transport_parses_servers() {
+    assert!(TlsTransport::new(&[
+        "tls://dns.google@8.8.8.8:853",
+        "tls://[2606:4700:4700::1111]"
+    ])
+    .is_ok());
+    assert!(TlsTransport::new(&["8.8.8.8"]).is_err());
+    assert!(TlsTransport::new(&["tls://dns.google@nowhere"]).is_err());
+}
+
+// A DNS-over-TLS server answering A queries with `address` - or, if `address` is `None`, not
+// answering at all.  Returns its address, and a client configuration that trusts it.
+#[cfg(feature = "tls")]
+fn dot_server(
+    address: Option<std::net::Ipv4Addr>,
+) -> (std::net::SocketAddr, std::sync::Arc<rustls::ClientConfig>) {
+    use std::io::{Read, Write};
+
+    tls_server(move |stream| {
+        let mut prefix = [0; 2];
+        if stream.read_exact(&mut prefix).is_err() {
+            return;
+        }
+        let mut query = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        stream.read_exact(&mut query).unwrap();
+        let Some(address) = address else {
+            let _ = stream.read(&mut prefix);
+            return;
+        };
+        let answer = answer_a(&query, address);
+        let mut response = (answer.len() as u16).to_be_bytes().to_vec();
+        response.extend_from_slice(&answer);
+        stream.write_all(&response).unwrap();
+        stream.flush().unwrap();
+    })
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn queries_go_over_tls() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 9);
+    let (server, config) = dot_server(Some(address));
+    let (silent, _) = dot_server(None);
+    let query = [
+        0x12, 0x34, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0,
+        0, 1, 0, 1,
+    ];
+    let transport = |servers: &[&str]| {
+        let mut transport =
+            TlsTransport::with_client_config(servers, std::sync::Arc::clone(&config)).unwrap();
+        transport.set_timeout(std::time::Duration::from_millis(500));
+        transport
+    };
+
+    // Certificates are verified against the name given, or else the address.
+    let answer = answer_a(&query, address);
+    let by_name = format!("tls://localhost@{server}");
+    assert_eq!(transport(&[&by_name]).exchange(&query).unwrap(), answer);
+    let by_address = format!("tls://{server}");
+    assert_eq!(transport(&[&by_address]).exchange(&query).unwrap(), answer);
+    let wrong_name = format!("tls://dns.example.com@{server}");
+    assert!(transport(&[&wrong_name]).exchange(&query).is_err());
+
+    // A server that doesn't answer times out, and the next is tried.
+    let silent = format!("tls://localhost@{silent}");
+    let start = std::time::Instant::now();
+    assert!(transport(&[&silent]).exchange(&query).is_err());
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(
+        transport(&[&silent, &by_name]).exchange(&query).unwrap(),
+        answer
+    );
+}
+
+#[cfg(all(feature = "tls", cares1_24))]
+#[test]
+fn tls_servers_can_be_set() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 10);
+    let (server, config) = dot_server(Some(address));
+    let mut options = Options::new();
+    options.set_tls_client_config(config).set_tries(1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    let tls_server = format!("tls://localhost@{server}");
+    assert_eq!(
+        resolver.set_servers(&[&tls_server, "192.0.2.53"]).err(),
+        Some(c_ares::Error::EBADSTR)
+    );
+    resolver.set_servers(&[&tls_server]).unwrap();
+    let results = resolver.query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+    let servers: Vec<_> = resolver
+        .servers()
+        .iter()
+        .map(|server| std::net::SocketAddr::new(server.addr, server.udp_port))
+        .collect();
+    assert_eq!(servers, vec![server]);
+
+    // Servers given as usual are then reached through the same relay.
+    resolver.set_servers(&["192.0.2.53"]).unwrap();
+    let servers: Vec<_> = resolver
+        .servers()
+        .iter()
+        .map(|server| server.addr)
+        .collect();
+    assert_eq!(servers, vec![std::net::IpAddr::from([192, 0, 2, 53])]);
+}
+
+#[cfg(feature = "https")]
+#[test]
+fn https_transport_parses_urls() {
+    let address: std::net::SocketAddr = "8.8.8.8:443".parse().unwrap();
+    assert!(HttpsTransport::with_addresses("https://dns.google/dns-query", &[address]).is_ok());
+    assert!(HttpsTransport::with_addresses("https://localhost:8443", &[address]).is_ok());
+    assert!(HttpsTransport::with_addresses("https://[2001:db8::1]/dns-query", &[address]).is_ok());
+    assert!(HttpsTransport::with_addresses("https://[2001:db8::1]:8443", &[address]).is_ok());
+    assert!(HttpsTransport::with_addresses("http://dns.google/dns-query", &[address]).is_err());
+    assert!(HttpsTransport::with_addresses("https://[2001:db8::1", &[address]).is_err());
+    assert!(HttpsTransport::with_addresses("https://2001:db8::1/", &[address]).is_err());
+    assert!(HttpsTransport::with_addresses("https://localhost:/", &[address]).is_err());
+}
+
+#[cfg(feature = "https")]
+#[test]
+fn https_responses_are_decoded() {
+    use crate::https::read_response;
+
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabcdef";
+    assert_eq!(read_response(response).unwrap().unwrap(), b"abc");
+
+    let response =
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nab\r\n1\r\nc\r\n0\r\n\r\n";
+    assert_eq!(read_response(response).unwrap().unwrap(), b"abc");
+
+    let response = b"HTTP/1.1 400 Bad Request\r\n\r\n";
+    assert!(read_response(response).is_err());
+
+    // Incomplete responses need more reading.
+    assert!(read_response(b"HTTP/1.1 200 OK\r\nContent-Le")
+        .unwrap()
+        .is_none());
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nab";
+    assert!(read_response(response).unwrap().is_none());
+    let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nab\r\n1\r";
+    assert!(read_response(response).unwrap().is_none());
+
+    // A body without a length can't be told from one that's cut short.
+    let response = b"HTTP/1.1 200 OK\r\n\r\nabc";
+    assert!(read_response(response).is_err());
+}
+
+// A TLS server on the loopback interface, with a certificate for `localhost` and `127.0.0.1` from
+// the test CA, which calls `serve` for each connection.  Returns its address, and a client
+// configuration that trusts the test CA.
+#[cfg(feature = "tls")]
+fn tls_server<F>(serve: F) -> (std::net::SocketAddr, std::sync::Arc<rustls::ClientConfig>)
+where
+    F: Fn(&mut rustls::StreamOwned<rustls::ServerConnection, std::net::TcpStream>)
+        + Send
+        + Sync
+        + 'static,
+{
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use std::sync::Arc;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certificate = CertificateDer::from_pem_slice(include_bytes!("../testdata/server.pem"));
+    let key = PrivateKeyDer::from_pem_slice(include_bytes!("../testdata/server.key"));
+    let config = rustls::ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate.unwrap()], key.unwrap())
+        .unwrap();
+    let config = Arc::new(config);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let serve = Arc::new(serve);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let config = Arc::clone(&config);
+            let serve = Arc::clone(&serve);
+            std::thread::spawn(move || {
+                let connection = rustls::ServerConnection::new(config).unwrap();
+                serve(&mut rustls::StreamOwned::new(connection, stream));
+            });
+        }
+    });
+
+    let mut roots = rustls::RootCertStore::empty();
+    let ca = CertificateDer::from_pem_slice(include_bytes!("../testdata/ca.pem"));
+    roots.add(ca.unwrap()).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    (address, Arc::new(config))
+}
+
+#[cfg(feature = "https")]
+#[test]
+fn queries_go_over_https() {
+    use std::io::{Read, Write};
+
+    // A DNS-over-HTTPS endpoint, which frames its answers as the path of the request says.
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 8);
+    let (server, config) = tls_server(move |stream| {
+        let mut request = Vec::new();
+        let mut byte = [0];
+        while !request.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+        let request = String::from_utf8(request).unwrap();
+        let path = request.split_whitespace().nth(1).unwrap().to_owned();
+        let length = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .unwrap();
+        let mut query = vec![0; length.parse().unwrap()];
+        stream.read_exact(&mut query).unwrap();
+
+        let answer = answer_a(&query, address);
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\n";
+        let response = match path.as_str() {
+            "/length" => format!("{head}Content-Length: {}\r\n\r\n", answer.len()),
+            "/chunked" => format!(
+                "{head}Transfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+                answer.len()
+            ),
+            "/truncated" => format!("{head}Content-Length: {}\r\n\r\n", answer.len() + 1),
+            _ => format!("{head}\r\n"),
+        };
+        let mut response = response.into_bytes();
+        response.extend_from_slice(&answer);
+        if path == "/chunked" {
+            response.extend_from_slice(b"\r\n0\r\n\r\n");
+        }
+        stream.write_all(&response).unwrap();
+        stream.flush().unwrap();
+
+        // Keep complete responses open, as a server might: the client shouldn't wait for the end.
+        if path == "/length" || path == "/chunked" {
+            let _ = stream.read(&mut byte);
+        }
+    });
+
+    let exchange = |url: &str| {
+        let url = url.replace("PORT", &server.port().to_string());
+        let mut transport =
+            HttpsTransport::with_client_config(&url, &[server], std::sync::Arc::clone(&config))
+                .unwrap();
+        transport.set_timeout(std::time::Duration::from_secs(5));
+        transport
+    };
+    let query = [
+        0x12, 0x34, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0,
+        0, 1, 0, 1,
+    ];
+    let answer = answer_a(&query, address);
+    for url in [
+        "https://localhost:PORT/length",
+        "https://127.0.0.1:PORT/chunked",
+    ] {
+        assert_eq!(exchange(url).exchange(&query).unwrap(), answer, "{url}");
+    }
+    for url in [
+        "https://localhost:PORT/truncated",
+        "https://localhost:PORT/unframed",
+    ] {
+        assert!(exchange(url).exchange(&query).is_err(), "{url}");
+    }
+
+    // And so to a resolver.
+    let mut options = Options::new();
+    options.set_transport(exchange("https://localhost:PORT/length"));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_a("example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![address]);
+}
+
+#[cfg(cares1_22)]
+#[test]
+fn https_records() {
+    let rdata = [
+        0, 1, 0, // priority 1, target "."
+        0, 1, 0, 6, 2, b'h', b'2', 2, b'h', b'3', // alpn
+        0, 3, 0, 2, 0x01, 0xbb, // port 443
+        0, 4, 0, 4, 192, 0, 2, 1, // ipv4hint
+    ];
+    let mut options = Options::new();
+    options.set_transport(RecordTransport(65, rdata.to_vec()));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_https("example.com").unwrap();
+    let records: Vec<&HTTPSResult> = results.iter().collect();
+    assert_eq!(records.len(), 1);
+    let record = records[0];
+    assert_eq!(record.priority, 1);
+    assert_eq!(record.target, "");
+    assert_eq!(record.alpn, vec!["h2", "h3"]);
+    assert!(!record.no_default_alpn);
+    assert_eq!(record.port, Some(443));
+    assert_eq!(record.ipv4hint, vec![std::net::Ipv4Addr::new(192, 0, 2, 1)]);
+    assert!(record.ipv6hint.is_empty());
+    assert_eq!(record.params.len(), 3);
+}
+
+#[cfg(cares1_22)]
+#[test]
+fn tlsa_records() {
+    let rdata = [3, 1, 1, 0xde, 0xad, 0xbe, 0xef];
+    let mut options = Options::new();
+    options.set_transport(RecordTransport(52, rdata.to_vec()));
+    let resolver = FutureResolver::with_options(options).unwrap();
+    let results = futures_executor::block_on(resolver.query_tlsa("_443._tcp.example.com")).unwrap();
+    let records: Vec<TLSAResult> = results.into_iter().collect();
+    assert_eq!(
+        records,
+        vec![TLSAResult {
+            cert_usage: 3,
+            selector: 1,
+            matching_type: 1,
+            cert_data: vec![0xde, 0xad, 0xbe, 0xef],
+        }]
+    );
+}
+
+// Answers every query with an A record, an MX record and a record of a type unknown to c-ares.
+struct AnyTransport;
+
+impl Transport for AnyTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut response = answer_a(query, std::net::Ipv4Addr::new(192, 0, 2, 17));
+        response[7] = 3;
+        response.extend_from_slice(&[
+            0xc0, 0x0c, 0, 15, 0, 1, 0, 0, 0, 60, 0, 4, 0, 10, 0xc0, 0x0c,
+        ]);
+        response.extend_from_slice(&[0xc0, 0x0c, 0xff, 0x00, 0, 1, 0, 0, 0, 60, 0, 2, 1, 2]);
+        Ok(response)
+    }
+}
+
+#[cfg(cares1_22)]
+#[test]
+fn any_records() {
+    let mut options = Options::new();
+    options.set_transport(AnyTransport);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_any("example.com").unwrap();
+    let records: Vec<AnyRecord> = results.into_iter().map(|result| result.record).collect();
+    assert_eq!(
+        records,
+        vec![
+            AnyRecord::A(std::net::Ipv4Addr::new(192, 0, 2, 17)),
+            AnyRecord::MX {
+                preference: 10,
+                exchange: "example.com".to_owned()
+            },
+            AnyRecord::Other {
+                rr_type: 0xff00,
+                data: vec![1, 2]
+            },
+        ]
+    );
+}
+
+#[cfg(cares1_22)]
+#[test]
+fn sshfp_records() {
+    let rdata = [4, 2, 0x12, 0x34, 0x56];
+    let mut options = Options::new();
+    options.set_transport(RecordTransport(44, rdata.to_vec()));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_sshfp("host.example.com").unwrap();
+    let records: Vec<&SSHFPResult> = results.iter().collect();
+    assert_eq!(
+        records,
+        vec![&SSHFPResult {
+            algorithm: 4,
+            fingerprint_type: 2,
+            fingerprint: vec![0x12, 0x34, 0x56],
+        }]
+    );
+
+    let mut options = Options::new();
+    options.set_transport(RecordTransport(44, vec![4]));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let result = resolver.query_sshfp("host.example.com");
+    assert!(matches!(result, Err(c_ares::Error::EBADRESP)));
+}
+
+#[cfg(cares1_22)]
+#[test]
+fn dnssec_records() {
+    let resolver = |rr_type, rdata: &[u8]| {
+        let mut options = Options::new();
+        options.set_transport(RecordTransport(rr_type, rdata.to_vec()));
+        BlockingResolver::with_options(options).unwrap()
+    };
+
+    let dnskey = resolver(48, &[1, 1, 3, 13, 0xaa, 0xbb])
+        .query_dnskey("example.com")
+        .unwrap();
+    let dnskey: Vec<DNSKEYResult> = dnskey.into_iter().collect();
+    assert_eq!(
+        dnskey,
+        vec![DNSKEYResult {
+            flags: 257,
+            protocol: 3,
+            algorithm: 13,
+            public_key: vec![0xaa, 0xbb],
+        }]
+    );
+
+    let ds = resolver(43, &[0x30, 0x39, 13, 2, 0xcc])
+        .query_ds("example.com")
+        .unwrap();
+    let ds: Vec<DSResult> = ds.into_iter().collect();
+    assert_eq!(
+        ds,
+        vec![DSResult {
+            key_tag: 12345,
+            algorithm: 13,
+            digest_type: 2,
+            digest: vec![0xcc],
+        }]
+    );
+
+    let mut rrsig = vec![0, 1, 13, 2, 0, 0, 0x0e, 0x10];
+    rrsig.extend_from_slice(&[0x65, 0, 0, 0, 0x64, 0, 0, 0, 0x30, 0x39]);
+    rrsig.extend_from_slice(b"\x07example\x03com\x00");
+    rrsig.extend_from_slice(&[0xdd, 0xee]);
+    let rrsig = resolver(46, &rrsig).query_rrsig("example.com").unwrap();
+    let rrsig: Vec<RRSIGResult> = rrsig.into_iter().collect();
+    assert_eq!(
+        rrsig,
+        vec![RRSIGResult {
+            type_covered: 1,
+            algorithm: 13,
+            labels: 2,
+            original_ttl: 3600,
+            expiration: 0x6500_0000,
+            inception: 0x6400_0000,
+            key_tag: 12345,
+            signer_name: "example.com".to_owned(),
+            signature: vec![0xdd, 0xee],
+        }]
+    );
+}
+
+#[test]
+fn email_records() {
+    let resolver = |strings: &[&str]| {
+        let mut rdata = Vec::new();
+        for string in strings {
+            rdata.push(u8::try_from(string.len()).unwrap());
+            rdata.extend_from_slice(string.as_bytes());
+        }
+        let mut options = Options::new();
+        options.set_transport(RecordTransport(16, rdata));
+        BlockingResolver::with_options(options).unwrap()
+    };
+
+    let spf = resolver(&["v=spf1 ip4:192.0.2.0/24 include:_spf.example.com ~all"])
+        .lookup_spf("example.com")
+        .unwrap();
+    assert_eq!(
+        spf.mechanisms,
+        vec![
+            email::SpfMechanism {
+                qualifier: email::SpfQualifier::Pass,
+                name: "ip4".to_owned(),
+                value: Some("192.0.2.0/24".to_owned()),
+            },
+            email::SpfMechanism {
+                qualifier: email::SpfQualifier::Pass,
+                name: "include".to_owned(),
+                value: Some("_spf.example.com".to_owned()),
+            },
+            email::SpfMechanism {
+                qualifier: email::SpfQualifier::SoftFail,
+                name: "all".to_owned(),
+                value: None,
+            },
+        ]
+    );
+
+    let dmarc = resolver(&["v=DMARC1; p=reject; rua=mailto:dmarc@example.com; adkim=s"])
+        .lookup_dmarc("example.com")
+        .unwrap();
+    assert_eq!(dmarc.policy, email::DmarcPolicy::Reject);
+    assert_eq!(dmarc.subdomain_policy, None);
+    assert_eq!(dmarc.percentage, 100);
+    assert_eq!(dmarc.dkim_alignment, email::DmarcAlignment::Strict);
+    assert_eq!(dmarc.spf_alignment, email::DmarcAlignment::Relaxed);
+    assert_eq!(dmarc.aggregate_reports, vec!["mailto:dmarc@example.com"]);
+
+    // A long key is split across several strings.
+    let dkim = resolver(&["v=DKIM1; k=ed25519; p=MCow", "BQYDK2Vw"])
+        .lookup_dkim("mail", "example.com")
+        .unwrap();
+    assert_eq!(dkim.key_type, "ed25519");
+    assert_eq!(dkim.public_key, "MCowBQYDK2Vw");
+
+    let result = resolver(&["not spf"]).lookup_spf("example.org");
+    assert!(matches!(result, Err(c_ares::Error::ENODATA)));
+}
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for label in name.split('.') {
+        encoded.push(u8::try_from(label.len()).unwrap());
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    encoded
+}
+
+// Serves two instances of `_http._tcp.example.com`, of which only the first has an SRV record.
+struct DiscoveryTransport;
+
+impl Transport for DiscoveryTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut end = 12;
+        while query[end] != 0 {
+            end += usize::from(query[end]) + 1;
+        }
+        let name = &query[12..=end];
+        let rr_type = u16::from_be_bytes([query[end + 1], query[end + 2]]);
+        let response = if rr_type == 12 {
+            let mut response = answer(query, 12, &encode_name("Web._http._tcp.example.com"));
+            response[7] = 2;
+            let second = encode_name("Gone._http._tcp.example.com");
+            response.extend_from_slice(&[0xc0, 0x0c, 0, 12, 0, 1, 0, 0, 0, 60, 0]);
+            response.push(u8::try_from(second.len()).unwrap());
+            response.extend_from_slice(&second);
+            response
+        } else if rr_type == 33 && name == encode_name("Web._http._tcp.example.com") {
+            let mut rdata = vec![0, 10, 0, 5, 0x1f, 0x90];
+            rdata.extend_from_slice(&encode_name("web.example.com"));
+            answer(query, 33, &rdata)
+        } else if rr_type == 16 && name == encode_name("Web._http._tcp.example.com") {
+            answer(query, 16, b"\x07path=/x\x03tls")
+        } else if rr_type == 1 && name == encode_name("web.example.com") {
+            answer_a(query, std::net::Ipv4Addr::new(192, 0, 2, 18))
+        } else {
+            // No records.
+            let mut response = answer(query, 1, &[]);
+            response.truncate(end + 5);
+            response[7] = 0;
+            response
+        };
+        Ok(response)
+    }
+}
+
+#[test]
+fn services_are_discovered() {
+    let expected = vec![discovery::ServiceInstance {
+        name: "Web._http._tcp.example.com".to_owned(),
+        instance: "Web".to_owned(),
+        host: "web.example.com".to_owned(),
+        port: 8080,
+        priority: 10,
+        weight: 5,
+        txt: vec![
+            ("path".to_owned(), Some(b"/x".to_vec())),
+            ("tls".to_owned(), None),
+        ],
+        addresses: vec![std::net::IpAddr::from([192, 0, 2, 18])],
+    }];
+
+    let mut options = Options::new();
+    options.set_transport(DiscoveryTransport);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let instances = resolver
+        .discover_services("_http._tcp.example.com")
+        .unwrap();
+    assert_eq!(instances, expected);
+
+    let mut options = Options::new();
+    options.set_transport(DiscoveryTransport);
+    let resolver = FutureResolver::with_options(options).unwrap();
+    let instances =
+        futures_executor::block_on(resolver.discover_services("_http._tcp.example.com")).unwrap();
+    assert_eq!(instances, expected);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn local_names_can_be_rejected() {
+    let server = udp_server(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 19)));
+    let mut options = Options::new();
+    options.set_local_names(LocalNames::Reject);
+    let mut builder = ResolverBuilder::with_options(options);
+    builder.set_servers(&[&server]);
+    let resolver = builder.build_blocking().unwrap();
+
+    let result = resolver.query_a("printer.LOCAL");
+    assert!(matches!(result, Err(c_ares::Error::ENOTFOUND)));
+    let result = resolver.try_query(|resolver| resolver.search_a("printer.local."));
+    assert!(matches!(result, Err(Error::LocalName)));
+    assert_eq!(resolver.metrics().snapshot().queries, 0);
+
+    let result = resolver.query_a("printer.example.com").unwrap();
+    let addresses: Vec<_> = result.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 19)]);
+}
+
+#[test]
+fn enum_lookup() {
+    assert_eq!(
+        e164::enum_domain("+1 (415) 555-1234").as_deref(),
+        Some("4.3.2.1.5.5.5.5.1.4.1.e164.arpa")
+    );
+    assert_eq!(e164::enum_domain("4155551234"), None);
+
+    let naptr = |flags: &str, regexp: &str| {
+        let mut rdata = vec![0, 100, 0, 10];
+        for string in [flags, "E2U+sip", regexp] {
+            rdata.push(u8::try_from(string.len()).unwrap());
+            rdata.extend_from_slice(string.as_bytes());
+        }
+        rdata.push(0);
+        let mut options = Options::new();
+        options.set_transport(RecordTransport(35, rdata));
+        BlockingResolver::with_options(options).unwrap()
+    };
+
+    let uris = naptr("u", r"!^\+1([0-9]+)$!sip:\1@example.com!")
+        .enum_lookup("+14155551234")
+        .unwrap();
+    assert_eq!(
+        uris,
+        vec![e164::EnumUri {
+            order: 100,
+            preference: 10,
+            services: "E2U+sip".to_owned(),
+            uri: "sip:4155551234@example.com".to_owned(),
+        }]
+    );
+
+    // Non-terminal rules aren't followed.
+    let result = naptr("", "").enum_lookup("+14155551235");
+    assert!(matches!(result, Err(c_ares::Error::ENODATA)));
+
+    let result = naptr("u", "!^.*$!sip:x!").enum_lookup("14155551236");
+    assert!(matches!(result, Err(c_ares::Error::EBADNAME)));
+}
+
+#[test]
+fn srv_targets_are_selected() {
+    let target = |host: &str, priority, weight| SrvTarget {
+        host: host.to_owned(),
+        port: 5060,
+        priority,
+        weight,
+    };
+    let selector: SrvSelector = vec![
+        target("c.example.com", 20, 0),
+        target("a.example.com", 10, 60),
+        target("b.example.com", 10, 40),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(selector.len(), 3);
+    let mut hosts: Vec<_> = selector.map(|target| target.host).collect();
+    assert_eq!(hosts.pop().unwrap(), "c.example.com");
+    hosts.sort();
+    assert_eq!(hosts, vec!["a.example.com", "b.example.com"]);
+
+    // A single target of "." means that the service is unavailable.
+    let mut selector: SrvSelector = std::iter::once(target(".", 0, 0)).collect();
+    assert!(selector.next().is_none());
+
+    let expected = SrvEndpoint {
+        target: SrvTarget {
+            host: "web.example.com".to_owned(),
+            port: 8080,
+            priority: 10,
+            weight: 5,
+        },
+        addresses: vec!["192.0.2.18:8080".parse().unwrap()],
+    };
+
+    let mut options = Options::new();
+    options.set_transport(DiscoveryTransport);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let endpoint = resolver.resolve_srv("Web._http._tcp.example.com").unwrap();
+    assert_eq!(endpoint, expected);
+
+    let mut options = Options::new();
+    options.set_transport(DiscoveryTransport);
+    let resolver = FutureResolver::with_options(options).unwrap();
+    let endpoint =
+        futures_executor::block_on(resolver.resolve_srv("Web._http._tcp.example.com")).unwrap();
+    assert_eq!(endpoint, expected);
+}
+
+// Answers PTR queries by pointing each name at itself.
+struct EchoPtrTransport;
+
+impl Transport for EchoPtrTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut end = 12;
+        while query[end] != 0 {
+            end += usize::from(query[end]) + 1;
+        }
+        Ok(answer(query, 12, &query[12..=end]))
+    }
+}
+
+#[test]
+fn ptr_queries_for_addresses() {
+    let mut options = Options::new();
+    options.set_transport(EchoPtrTransport);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    let ip = std::net::IpAddr::from([192, 0, 2, 1]);
+    let result = resolver.query_ptr_for(&ip).unwrap();
+    assert_eq!(result.hostname(), "1.2.0.192.in-addr.arpa");
+
+    let ip: std::net::IpAddr = "2001:db8::567:89ab".parse().unwrap();
+    let result = resolver.query_ptr_for(&ip).unwrap();
+    assert_eq!(
+        result.hostname(),
+        "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+    );
+}
+
+#[test]
+fn invalid_names_are_rejected() {
+    assert!(validate_name("example.com.").is_ok());
+    assert!(validate_name(".").is_ok());
+    assert!(validate_name(r"a\.b\065.example.com").is_ok());
+
+    let long_label = "a".repeat(64);
+    let long_name = ["a".repeat(63).as_str(); 4].join(".");
+    let cases = [
+        ("exa\0mple.com", InvalidName::ContainsNul),
+        ("example..com", InvalidName::EmptyLabel),
+        (".example.com", InvalidName::EmptyLabel),
+        ("example.com\\", InvalidName::BadEscape),
+        (r"\06.example.com", InvalidName::BadEscape),
+        (&long_label, InvalidName::LabelTooLong(long_label.clone())),
+        (&long_name, InvalidName::TooLong(257)),
+    ];
+    for (name, expected) in cases {
+        match validate_name(name) {
+            Err(Error::InvalidName(invalid)) => assert_eq!(invalid, expected),
+            other => panic!("unexpected result {other:?} for {name}"),
+        }
+    }
+
+    // Queries for invalid names fail without reaching the server.
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 20)));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let result = resolver.query_a("example..com");
+    assert!(matches!(result, Err(c_ares::Error::EBADNAME)));
+    let result = resolver.try_query(|resolver| resolver.query_a("example..com"));
+    assert!(matches!(
+        result,
+        Err(Error::InvalidName(InvalidName::EmptyLabel))
+    ));
+    assert!(resolver.query_a("example.com").is_ok());
+
+    // As with any other query, the handler is called on the event loop thread.
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 20)));
+    let resolver = Resolver::with_options(options).unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    resolver.query_a("example..com", move |result| {
+        tx.send((std::thread::current().id(), result.err()))
+            .unwrap();
+    });
+    let timeout = std::time::Duration::from_secs(5);
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap(),
+        (resolver.thread_id(), Some(c_ares::Error::EBADNAME))
+    );
+}
+
+#[cfg(feature = "idna")]
+#[test]
+fn unicode_names_are_converted() {
+    let resolver = |mode| {
+        let mut options = Options::new();
+        options.set_transport(EchoPtrTransport).set_idna_mode(mode);
+        BlockingResolver::with_options(options).unwrap()
+    };
+
+    let result = resolver(IdnaMode::Lenient)
+        .query_ptr("Bücher.example")
+        .unwrap();
+    assert_eq!(result.hostname(), "xn--bcher-kva.example");
+    assert_eq!(domain_to_unicode(result.hostname()), "bücher.example");
+
+    let result = resolver(IdnaMode::Lenient).query_ptr("_bücher.example");
+    assert!(result.is_ok());
+    let result = resolver(IdnaMode::Strict).query_ptr("_bücher.example");
+    assert!(matches!(result, Err(c_ares::Error::EBADNAME)));
+}
+
+// Answers TXT queries with a record for the name asked about, and one for another name.
+struct UnrelatedTxtTransport;
+
+impl Transport for UnrelatedTxtTransport {
+    fn exchange(&self, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut response = answer(query, 16, b"\x04good");
+        response[7] = 2;
+        response.extend_from_slice(&encode_name("other.example.com"));
+        response.extend_from_slice(&[0, 16, 0, 1, 0, 0, 0, 60, 0, 4, 3]);
+        response.extend_from_slice(b"bad");
+        Ok(response)
+    }
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn responses_can_be_sanitized() {
+    let texts = |sanitize, name| {
+        let mut options = Options::new();
+        options
+            .set_transport(UnrelatedTxtTransport)
+            .set_sanitize_responses(sanitize);
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        let results = resolver.query_txt(name).unwrap();
+        results
+            .iter()
+            .map(|result| result.text().to_vec())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        texts(false, "one.example.com"),
+        vec![b"good".to_vec(), b"bad".to_vec()]
+    );
+    assert_eq!(texts(true, "two.example.com"), vec![b"good".to_vec()]);
+}
+
+#[test]
+fn query_policy_is_applied() {
+    let mut options = Options::new();
+    options
+        .set_transport(EchoPtrTransport)
+        .set_query_policy(|info| {
+            if info.name.ends_with(".blocked.example") {
+                Decision::Deny(c_ares::Error::EREFUSED)
+            } else if info.query_type == Some(12) && info.name == "old.example" {
+                Decision::Rewrite("new.example".to_owned())
+            } else {
+                Decision::Allow
+            }
+        });
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    let result = resolver.query_ptr("host.blocked.example");
+    assert!(matches!(result, Err(c_ares::Error::EREFUSED)));
+
+    let result = resolver.query_ptr("old.example").unwrap();
+    assert_eq!(result.hostname(), "new.example");
+
+    let result = resolver.search_ptr("other.example").unwrap();
+    assert_eq!(result.hostname(), "other.example");
+}
+
+#[test]
+fn queries_can_be_restricted_to_allowed_domains() {
+    let mut options = Options::new();
+    options
+        .set_transport(EchoPtrTransport)
+        .set_allowed_domains(&["Example.COM.", "2.0.192.in-addr.arpa"]);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    assert!(resolver.query_ptr("example.com").is_ok());
+    assert!(resolver.query_ptr("www.EXAMPLE.com.").is_ok());
+    for name in ["example.org", "badexample.com", "example.com.evil.org"] {
+        let result = resolver.query_ptr(name);
+        assert!(matches!(result, Err(c_ares::Error::ENOSERVER)), "{name}");
+        let result = resolver.try_query(|resolver| resolver.query_ptr(name));
+        assert!(matches!(result, Err(Error::DomainNotAllowed)), "{name}");
+    }
+
+    let result = resolver.query_ptr_for(&std::net::IpAddr::from([192, 0, 2, 1]));
+    assert!(result.is_ok());
+    let address = std::net::IpAddr::from([198, 51, 100, 1]);
+    let result = resolver.get_host_by_address(&address);
+    assert!(matches!(result, Err(c_ares::Error::ENOSERVER)));
+    let result = resolver.try_query(|resolver| resolver.get_host_by_address(&address));
+    assert!(matches!(result, Err(Error::DomainNotAllowed)));
+}
+
+#[test]
+fn host_aliases_are_applied_to_searches() {
+    let path = std::env::temp_dir().join(format!("c-ares-resolver-aliases-{}", std::process::id()));
+    std::fs::write(
+        &path,
+        "# aliases\nWeb www.example.com\nmail mx.example.com.\n",
+    )
+    .unwrap();
+    let mut options = Options::new();
+    options
+        .set_transport(EchoPtrTransport)
+        .set_domains(&["search.example"])
+        .set_host_aliases_file(path.to_str().unwrap())
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(options.host_aliases().unwrap().len(), 2);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    let hostname =
+        |result: c_ares::Result<c_ares::PTRResults>| result.unwrap().hostname().to_owned();
+    assert_eq!(hostname(resolver.search_ptr("web")), "www.example.com");
+    assert_eq!(hostname(resolver.search_ptr("MAIL")), "mx.example.com");
+    assert_eq!(
+        hostname(resolver.search_ptr("other")),
+        "other.search.example"
+    );
+    assert_eq!(hostname(resolver.query_ptr("web")), "web");
+}
+
+#[test]
+fn host_overrides_are_used() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 21)));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let v4 = std::net::IpAddr::from([192, 0, 2, 22]);
+    let v6: std::net::IpAddr = "2001:db8::22".parse().unwrap();
+    resolver.add_host_override("Pinned.example.com.", &[v4, v6]);
+
+    let result = resolver.query_a("pinned.example.com").unwrap();
+    let addresses: Vec<_> = result.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 22)]);
+
+    let result = resolver.search_aaaa("pinned.example.com").unwrap();
+    let addresses: Vec<_> = result.iter().map(|result| result.ipv6()).collect();
+    assert_eq!(
+        addresses,
+        vec!["2001:db8::22".parse::<std::net::Ipv6Addr>().unwrap()]
+    );
+
+    let result = resolver
+        .get_host_by_name("pinned.example.com", c_ares::AddressFamily::UNSPEC)
+        .unwrap();
+    assert_eq!(result.addresses, vec![v4, v6]);
+
+    assert!(resolver.remove_host_override("pinned.example.com"));
+    let result = resolver.query_a("pinned.example.com").unwrap();
+    let addresses: Vec<_> = result.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 21)]);
+}
+
+#[cfg(cares1_19)]
+#[test]
+fn host_overrides_are_used_by_resolver_host_lookups() {
+    let resolver = Resolver::new().unwrap();
+    let v4 = std::net::IpAddr::from([192, 0, 2, 23]);
+    let v6: std::net::IpAddr = "2001:db8::23".parse().unwrap();
+    resolver.add_host_override("Pinned.example.com.", &[v4, v6]);
+
+    let lookup = |family| {
+        let (tx, rx) = std::sync::mpsc::channel();
+        resolver.get_host_by_name("pinned.example.com", family, move |result| {
+            let result = result.map(|results| {
+                let addresses: Vec<_> = results.addresses().collect();
+                (results.hostname().to_owned(), addresses)
+            });
+            tx.send(result).unwrap();
+        });
+        rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap()
+    };
+    assert_eq!(
+        lookup(c_ares::AddressFamily::INET),
+        Ok(("pinned.example.com".to_owned(), vec![v4]))
+    );
+    assert_eq!(
+        lookup(c_ares::AddressFamily::INET6),
+        Ok(("pinned.example.com".to_owned(), vec![v6]))
+    );
+
+    resolver.add_host_override("pinned.example.com", &[v6]);
+    assert_eq!(
+        lookup(c_ares::AddressFamily::INET),
+        Err(c_ares::Error::ENODATA)
+    );
+}
+
+#[test]
+fn raw_queries_take_typed_class_and_type() {
+    assert_eq!(RecordType::from(28), RecordType::Aaaa);
+    assert_eq!(RecordType::Other(28), RecordType::from(28));
+    assert_eq!(u16::from(RecordType::Other(65280)), 65280);
+    assert_eq!(RecordType::Https.to_string(), "HTTPS");
+    assert_eq!(RecordType::from(65280).to_string(), "TYPE65280");
+    assert_eq!(DnsClass::from(3), DnsClass::Ch);
+    assert_eq!(DnsClass::Other(9).to_string(), "CLASS9");
+
+    let server = testing::DnsServer::start().unwrap();
+    server
+        .add_txt("example.com", "hello")
+        .add_txt("example.net", "hello");
+    let resolver = BlockingResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let response = resolver
+        .query("example.com", DnsClass::In, RecordType::Txt)
+        .unwrap();
+    let txt = c_ares::TXTResults::parse_from(&response).unwrap();
+    assert_eq!(txt.iter().next().unwrap().text(), b"hello");
+
+    resolver.query("example.net", 1, 16).unwrap();
+    let questions = server.questions();
+    assert_eq!(questions.len(), 2);
+    assert!(questions
+        .iter()
+        .all(|question| question.query_type == 16 && question.dns_class == 1));
+}
+
+#[cfg(cares1_29)]
+#[test]
+fn raw_responses_have_headers() {
+    let header = MessageHeader::parse(&[0x12, 0x34, 0x82, 0x82, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(header.id, 0x1234);
+    assert!(header.truncated && header.recursion_available);
+    assert!(!header.authoritative);
+    assert_eq!(header.rcode, 2);
+    assert_eq!(header.question_count, 1);
+    assert_eq!(MessageHeader::parse(&[0; 11]), None);
+
+    let server = testing::DnsServer::start().unwrap();
+    server
+        .add_txt("example.com", "one")
+        .add_txt("example.com", "two")
+        .set_rcode("broken.example.com", 2);
+    let resolver = BlockingResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let response = resolver.query_raw_ext("example.com", DnsClass::In, RecordType::Txt);
+    let raw = response.result.unwrap();
+    assert_eq!(raw.header.rcode, 0);
+    assert_eq!(raw.header.answer_count, 2);
+    assert!(!raw.header.truncated);
+    assert_eq!(MessageHeader::parse(&raw.data), Some(raw.header));
+
+    let resolver = FutureResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+    let lookup = resolver.query_raw_ext("broken.example.com", DnsClass::In, RecordType::Txt);
+    let response = futures_executor::block_on(lookup).unwrap();
+    assert_eq!(response.result.unwrap_err(), c_ares::Error::ESERVFAIL);
+}
+
+#[test]
+fn effective_options_merge_system_configuration() {
+    let path = std::env::temp_dir().join(format!(
+        "c-ares-resolver-resolv-conf-{}",
+        std::process::id()
+    ));
+    let conf = "nameserver 127.0.0.1\nsearch c.example d.example\noptions attempts:5 ndots:3\n";
+    std::fs::write(&path, conf).unwrap();
+
+    let mut options = Options::new();
+    options
+        .set_resolvconf_path(path.to_str().unwrap())
+        .set_timeout(1234)
+        .set_ndots(2)
+        .set_lookups("fb");
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let config = resolver.effective_options();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.timeout, 1234);
+    assert_eq!(config.ndots, 2);
+    assert_eq!(config.lookups, "fb");
+    assert_eq!(config.tries, 5);
+    assert_eq!(config.domains, vec!["c.example", "d.example"]);
+}
+
+#[test]
+fn search_domains_can_be_set_at_runtime() {
+    let server = testing::DnsServer::start().unwrap();
+    server
+        .add_a("host.b.example", std::net::Ipv4Addr::new(10, 0, 0, 1))
+        .add_txt("host.a.example", "found");
+    let mut options = Options::new();
+    options.set_ndots(1).set_domains(&["c.example"]);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+    assert_eq!(resolver.ndots(), 1);
+    assert_eq!(resolver.search_domains(), vec!["c.example"]);
+
+    resolver.set_search_domains(&["a.example", "b.example"]);
+    assert_eq!(resolver.search_domains(), vec!["a.example", "b.example"]);
+    let results = resolver.search_a("host").unwrap();
+    let addresses: Vec<std::net::Ipv4Addr> = results.iter().map(|result| result.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(10, 0, 0, 1)]);
+    let names: Vec<String> = server
+        .questions()
+        .into_iter()
+        .map(|question| question.name)
+        .collect();
+    assert_eq!(names, vec!["host.a.example", "host.b.example"]);
+
+    // A name that exists, but has no records of the type asked for, is reported as such.
+    let error = resolver.search_aaaa("host").err();
+    assert_eq!(error, Some(c_ares::Error::ENODATA));
+
+    // Names with enough dots are tried as they are first.
+    server.clear_questions();
+    let error = resolver.search_mx("missing.example").err();
+    assert_eq!(error, Some(c_ares::Error::ENOTFOUND));
+    let names: Vec<String> = server
+        .questions()
+        .into_iter()
+        .map(|question| question.name)
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "missing.example",
+            "missing.example.a.example",
+            "missing.example.b.example"
+        ]
+    );
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn query_flags_apply_to_single_queries() {
+    let server = testing::DnsServer::start().unwrap();
+    server
+        .add_a("host.a.example", std::net::Ipv4Addr::new(192, 0, 2, 1))
+        .add_a("other.example", std::net::Ipv4Addr::new(192, 0, 2, 2));
+    let mut options = Options::new();
+    options
+        .set_ndots(1)
+        .set_domains(&["a.example"])
+        .set_host_aliases(&[("web", "other.example")]);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    assert!(resolver.search_a_opts("host", QueryFlags::empty()).is_ok());
+    let error = resolver.search_a_opts("host", QueryFlags::NO_SEARCH).err();
+    assert_eq!(error, Some(c_ares::Error::ENOTFOUND));
+
+    assert!(resolver.search_a_opts("web", QueryFlags::empty()).is_ok());
+    let error = resolver.search_a_opts("web", QueryFlags::NO_ALIASES).err();
+    assert_eq!(error, Some(c_ares::Error::ENOTFOUND));
+
+    // Without the cache, every query goes to the server.
+    let asked = || {
+        server
+            .questions()
+            .iter()
+            .filter(|question| question.name == "host.a.example")
+            .count()
+    };
+    let before = asked();
+    let flags = QueryFlags::NO_CACHE | QueryFlags::NO_ALIASES;
+    assert!(flags.contains(QueryFlags::NO_CACHE));
+    assert!(!flags.contains(QueryFlags::NO_SEARCH));
+    for _ in 0..2 {
+        assert!(resolver.search_a_opts("host", flags).is_ok());
+    }
+    assert_eq!(asked(), before + 2);
+}
+
+#[test]
+fn name_info_options_build_flags() {
+    let mut options = NameInfoOptions::new();
+    options
+        .set_lookup_host(false)
+        .set_numeric_service(true)
+        .set_dgram(true);
+    assert_eq!(
+        options.flags(),
+        c_ares::NIFlags::LOOKUPSERVICE | c_ares::NIFlags::NUMERICSERV | c_ares::NIFlags::UDP
+    );
+
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], 53));
+    let mut options = NameInfoOptions::new();
+    options.set_numeric_host(true).set_numeric_service(true);
+
+    let resolver = BlockingResolver::new().unwrap();
+    let result = resolver.get_name_info(&address, options).unwrap();
+    assert_eq!(result.node.as_deref(), Some("127.0.0.1"));
+    assert_eq!(result.service.as_deref(), Some("53"));
+
+    let resolver = FutureResolver::new().unwrap();
+    let lookup = resolver.get_name_info(&address, options.flags());
+    let result = futures_executor::block_on(lookup).unwrap();
+    assert_eq!(result.node.as_deref(), Some("127.0.0.1"));
+}
+
+#[test]
+fn host_lookups_merge_both_families() {
+    let v4 = std::net::IpAddr::from([192, 0, 2, 23]);
+    let v6: std::net::IpAddr = "2001:db8::23".parse().unwrap();
+
+    let resolver = BlockingResolver::new().unwrap();
+    resolver.add_host_override("both.example.com", &[v6, v4]);
+    let result = resolver.get_host_by_name_any("both.example.com").unwrap();
+    assert_eq!(result.addresses, vec![v4, v6]);
+
+    let resolver = FutureResolver::new().unwrap();
+    resolver.add_host_override("both.example.com", &[v6]);
+    let lookup = resolver.get_host_by_name_any("both.example.com");
+    let result = futures_executor::block_on(lookup).unwrap();
+    assert_eq!(result.addresses, vec![v6]);
+
+    let result = host::merge(Err(c_ares::Error::ENODATA), Err(c_ares::Error::ETIMEOUT));
+    assert_eq!(result, Err(c_ares::Error::ETIMEOUT));
+    let result = host::merge(Err(c_ares::Error::ENOTFOUND), Err(c_ares::Error::ENODATA));
+    assert_eq!(result, Err(c_ares::Error::ENOTFOUND));
+}
+
+#[cfg(all(cares1_22, unix))]
+#[test]
+fn hosts_file_is_watched() {
+    let path = std::env::temp_dir().join(format!("c-ares-resolver-hosts-{}", std::process::id()));
+    std::fs::write(&path, "192.0.2.30 watched.example\n").unwrap();
+
+    let mut options = Options::new();
+    options
+        .set_lookups("f")
+        .set_hosts_path(path.to_str().unwrap())
+        .set_watch_hosts_file(std::time::Duration::from_millis(20));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let lookup = || {
+        resolver
+            .get_host_by_name("watched.example", c_ares::AddressFamily::INET)
+            .unwrap()
+            .addresses
+    };
+    assert_eq!(lookup(), vec![std::net::IpAddr::from([192, 0, 2, 30])]);
+
+    std::fs::write(&path, "192.0.2.31 watched.example\n").unwrap();
+    let modified = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert_eq!(lookup(), vec![std::net::IpAddr::from([192, 0, 2, 31])]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(all(feature = "net-watch", cares1_22, target_os = "linux"))]
+#[test]
+fn network_can_be_watched() {
+    let mut options = Options::new();
+    options.set_lookups("f").set_watch_network(true);
+    assert!(options.watch_network());
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let result = resolver.get_host_by_name("localhost", c_ares::AddressFamily::INET);
+    assert!(result.is_ok());
+    drop(resolver);
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[test]
+fn uring_poller_reports_events() {
+    use std::os::fd::AsRawFd;
+    use std::time::Duration;
+
+    // Kernels without io_uring, or with it disabled, fall back to `polling`.
+    let Ok(poller) = crate::uring::Poller::new() else {
+        return;
+    };
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let fd = socket.as_raw_fd();
+    poller.add(fd, true, false).unwrap();
+
+    // A notification wakes the poller, but is no event.
+    let mut events = Vec::new();
+    poller.wait(&mut events, Duration::from_millis(50)).unwrap();
+    poller.notify().unwrap();
+    poller.wait(&mut events, Duration::from_secs(5)).unwrap();
+    assert!(events.is_empty());
+
+    socket
+        .send_to(b"ping", socket.local_addr().unwrap())
+        .unwrap();
+    poller.wait(&mut events, Duration::from_secs(5)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].socket, fd);
+    assert!(events[0].readable);
+    assert!(!events[0].writable);
+
+    // Interest is oneshot, until renewed.
+    events.clear();
+    poller.wait(&mut events, Duration::from_millis(50)).unwrap();
+    assert!(events.is_empty());
+    poller.modify(fd, true, true).unwrap();
+    poller.wait(&mut events, Duration::from_secs(5)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].readable && events[0].writable);
+
+    events.clear();
+    poller.modify(fd, true, false).unwrap();
+    poller.delete(fd).unwrap();
+    poller.wait(&mut events, Duration::from_millis(50)).unwrap();
+    assert!(events.is_empty());
+}
+
+#[cfg(cares1_29)]
+#[test]
+fn query_responses_report_servers() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 41)));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    let response = resolver.query_a_ext("example.com");
+    let addresses: Vec<_> = response.result.unwrap().iter().map(|a| a.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 41)]);
+    let server = response.server.unwrap();
+    assert!(resolver.server_stats().contains_key(&server), "{server}");
+    assert_eq!(response.transport, Some(Protocol::Udp));
+
+    resolver.add_host_override(
+        "pinned.example.com",
+        &[std::net::IpAddr::from([192, 0, 2, 42])],
+    );
+    let first = response.id;
+    let response = resolver.query_a_ext("pinned.example.com");
+    assert!(response.result.is_ok());
+    assert_eq!(response.server, None);
+    assert!(response.id > first);
+
+    // A query that no server answers has no server, even if others are answered meanwhile.
+    let server = testing::DnsServer::start().unwrap();
+    server.add_a("example.com", std::net::Ipv4Addr::new(192, 0, 2, 43));
+    server.set_rcode("failing.example.com", 2);
+    let mut options = Options::new();
+    options.set_tries(1);
+    let resolver = FutureResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+    let failing = resolver.query_a_ext("failing.example.com");
+    let working = resolver.query_a_ext("example.com");
+    let (failing, working) =
+        futures_executor::block_on(async { (failing.await.unwrap(), working.await.unwrap()) });
+    assert_eq!(failing.result.err(), Some(c_ares::Error::ESERVFAIL));
+    assert_eq!(failing.server, None);
+    assert!(working.result.is_ok());
+    assert_eq!(working.server, Some(server.address().to_string()));
+}
+
+#[test]
+fn queries_are_logged() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 51)));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let entries = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = std::sync::Arc::clone(&entries);
+    resolver.set_query_log(move |entry| {
+        assert_ne!(entry.id, 0);
+        let entry = (
+            entry.name.to_owned(),
+            entry.query_type,
+            entry.error,
+            entry.rcode,
+        );
+        log.lock().unwrap().push(entry);
+    });
+
+    resolver.query_a("example.com").unwrap();
+    let v6: std::net::IpAddr = "2001:db8::51".parse().unwrap();
+    resolver.add_host_override("v6only.example.com", &[v6]);
+    assert!(resolver.query_a("v6only.example.com").is_err());
+    assert!(resolver.query_a("bad..name").is_err());
+
+    let entries = entries.lock().unwrap();
+    assert_eq!(
+        *entries,
+        vec![
+            ("example.com".to_owned(), Some(1), None, Some(0)),
+            (
+                "v6only.example.com".to_owned(),
+                Some(1),
+                Some(c_ares::Error::ENODATA),
+                Some(0)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn query_response_ids_match_the_query_log() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 53)));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let ids = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = std::sync::Arc::clone(&ids);
+    resolver.set_query_log(move |entry| {
+        log.lock().unwrap().push((entry.name.to_owned(), entry.id));
+    });
+
+    let first = resolver.query_a_ext("example.com");
+    let second = resolver.query_a_ext("www.example.com");
+    assert!(first.result.is_ok() && second.result.is_ok());
+    assert_eq!(
+        *ids.lock().unwrap(),
+        vec![
+            ("example.com".to_owned(), first.id),
+            ("www.example.com".to_owned(), second.id),
+        ]
+    );
+
+    // A query that is never logged still gets an ID of its own.
+    let rejected = resolver.query_a_ext("bad..name");
+    assert!(rejected.result.is_err());
+    assert_eq!(ids.lock().unwrap().len(), 2);
+    assert_ne!(rejected.id, 0);
+    assert_ne!(rejected.id, first.id);
+    assert_ne!(rejected.id, second.id);
+}
+
+#[cfg(cares1_29)]
+#[test]
+fn metrics_are_counted() {
+    let mut options = Options::new();
+    options.set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 52)));
+    let resolver = BlockingResolver::with_options(options).unwrap();
+
+    resolver.query_a("example.com").unwrap();
+    let v6: std::net::IpAddr = "2001:db8::52".parse().unwrap();
+    resolver.add_host_override("v6only.example.com", &[v6]);
+    assert!(resolver.query_a("v6only.example.com").is_err());
+    resolver.query_aaaa("v6only.example.com").unwrap();
+
+    let snapshot = resolver.metrics().snapshot();
+    assert_eq!(snapshot.queries, 3);
+    assert_eq!(snapshot.queries_by_type.get(&Some(1)), Some(&2));
+    assert_eq!(snapshot.queries_by_type.get(&Some(28)), Some(&1));
+    assert_eq!(
+        snapshot.errors.into_iter().collect::<Vec<_>>(),
+        vec![(c_ares::Error::ENODATA, 1)]
+    );
+    assert_eq!(snapshot.cache_hits, 2);
+    assert_eq!(snapshot.cache_misses, 1);
+    assert_eq!(snapshot.latency.count, 3);
+    assert_eq!(snapshot.latency.counts.iter().sum::<u64>(), 3);
+}
+
+#[test]
+fn load_is_measured() {
+    let resolver = BlockingResolver::new().unwrap();
+    assert_eq!(resolver.load(), LoadStats::default());
+
+    let address: std::net::IpAddr = "192.0.2.54".parse().unwrap();
+    resolver.add_host_override("pinned.example.com", &[address]);
+    resolver.query_a("pinned.example.com").unwrap();
+    resolver.query_a("pinned.example.com").unwrap();
+    resolver.query_a("pinned.example.com").unwrap();
+
+    let load = resolver.load();
+    assert_eq!(load.in_flight, 0);
+    assert_eq!(load.qps_1m, 3.0 / 60.0);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn responses_can_be_recorded_and_replayed() {
+    let path =
+        std::env::temp_dir().join(format!("c-ares-resolver-recording-{}", std::process::id()));
+    {
+        let mut options = Options::new();
+        options
+            .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 91)))
+            .set_recording(&path, RecordingMode::Record);
+        let resolver = BlockingResolver::with_options(options).unwrap();
+        resolver.query_a("example.com").unwrap();
+    }
+
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 92)))
+        .set_recording(&path, RecordingMode::Replay);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    let results = resolver.query_a("Example.COM").unwrap();
+    let addresses: Vec<_> = results.iter().map(|a| a.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 91)]);
+    assert_eq!(
+        resolver.query_a("other.example.com").err(),
+        Some(c_ares::Error::EREFUSED)
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    let mut options = Options::new();
+    options.set_recording(&path, RecordingMode::Replay);
+    assert!(BlockingResolver::with_options(options).is_err());
+}
+
+#[cfg(feature = "otel")]
+#[test]
+fn metrics_can_be_registered_with_opentelemetry() {
+    let resolver = BlockingResolver::new().unwrap();
+    let meter = opentelemetry::global::meter("c-ares-resolver");
+    resolver.metrics().register(&meter);
+
+    let address: std::net::IpAddr = "192.0.2.53".parse().unwrap();
+    resolver.add_host_override("pinned.example.com", &[address]);
+    resolver.query_a("pinned.example.com").unwrap();
+    assert!(resolver.query_aaaa("pinned.example.com").is_err());
+    assert_eq!(resolver.metrics().snapshot().queries, 2);
+}
+
+#[cfg(feature = "hyper")]
+#[test]
+fn future_resolver_resolves_for_hyper() {
+    use hyper_util::client::legacy::connect::dns::Name;
+    use tower_service::Service;
+
+    let mut resolver = FutureResolver::new().unwrap();
+    let address: std::net::IpAddr = "192.0.2.80".parse().unwrap();
+    resolver.add_host_override("web.example.com", &[address]);
+    let name: Name = "web.example.com".parse().unwrap();
+    let addresses: Vec<std::net::SocketAddr> = futures_executor::block_on(resolver.call(name))
+        .unwrap()
+        .collect();
+    assert_eq!(addresses, vec![std::net::SocketAddr::new(address, 0)]);
+}
+
+#[cfg(feature = "reqwest")]
+#[test]
+fn future_resolver_resolves_for_reqwest() {
+    use ::reqwest::dns::{Name, Resolve};
+
+    let resolver = FutureResolver::new().unwrap();
+    let address: std::net::IpAddr = "192.0.2.81".parse().unwrap();
+    resolver.add_host_override("web.example.com", &[address]);
+    let name: Name = "web.example.com".parse().unwrap();
+    let addresses: Vec<std::net::SocketAddr> = futures_executor::block_on(resolver.resolve(name))
+        .unwrap()
+        .collect();
+    assert_eq!(addresses, vec![std::net::SocketAddr::new(address, 0)]);
+}
+
+#[cfg(feature = "tower")]
+#[test]
+fn future_resolver_is_a_tower_service() {
+    use tower_service::Service;
+
+    let mut resolver = FutureResolver::new().unwrap();
+    let address: std::net::IpAddr = "192.0.2.82".parse().unwrap();
+    resolver.add_host_override("web.example.com", &[address]);
+    let name: Name = "web.example.com".parse().unwrap();
+    assert_eq!(name.as_str(), "web.example.com");
+    let results = futures_executor::block_on(resolver.call(name)).unwrap();
+    assert_eq!(results.addresses, vec![address]);
+    assert_eq!(Name::new("example..com"), Err(InvalidName::EmptyLabel));
+}
+
+#[cfg(all(feature = "hickory", cares1_22))]
+#[test]
+fn results_convert_to_and_from_hickory() {
+    use hickory_proto::rr::{RData, Record, RecordType};
+
+    let records = vec![
+        AnyRecord::A("192.0.2.1".parse().unwrap()),
+        AnyRecord::MX {
+            preference: 10,
+            exchange: "mail.example.com".to_owned(),
+        },
+        AnyRecord::TXT(b"v=spf1 -all".to_vec()),
+        AnyRecord::CAA {
+            critical: false,
+            tag: "issue".to_owned(),
+            value: b"ca.example.net".to_vec(),
+        },
+        AnyRecord::DS(DSResult {
+            key_tag: 12345,
+            algorithm: 13,
+            digest_type: 2,
+            digest: vec![0xab; 32],
+        }),
+        AnyRecord::TLSA(TLSAResult {
+            cert_usage: 3,
+            selector: 1,
+            matching_type: 1,
+            cert_data: vec![0xcd; 32],
+        }),
+        AnyRecord::Other {
+            rr_type: 65280,
+            data: vec![1, 2, 3],
+        },
+    ];
+    for record in records {
+        let result = AnyResult {
+            name: "example.com".to_owned(),
+            ttl: 300,
+            record,
+        };
+        let converted: Record = (&result).try_into().unwrap();
+        assert_eq!(converted.name().to_ascii(), "example.com.");
+        assert_eq!(converted.ttl(), 300);
+        assert_eq!(AnyResult::try_from(&converted).unwrap(), result);
+    }
+
+    let result = AnyResult {
+        name: "example.com".to_owned(),
+        ttl: 60,
+        record: AnyRecord::MX {
+            preference: 10,
+            exchange: "mail.example.com".to_owned(),
+        },
+    };
+    let converted: Record = (&result).try_into().unwrap();
+    assert_eq!(converted.record_type(), RecordType::MX);
+    assert!(matches!(converted.data(), RData::MX(mx) if mx.preference() == 10));
+
+    let svcb = AnyResult {
+        name: "example.com".to_owned(),
+        ttl: 60,
+        record: AnyRecord::Other {
+            rr_type: 99,
+            data: Vec::new(),
+        },
+    };
+    assert!(<Record as TryFrom<_>>::try_from(&svcb).is_err());
+}
+
+#[cfg(feature = "actix")]
+#[test]
+fn future_resolver_resolves_for_actix() {
+    use actix_tls::connect::Resolve;
+
+    let resolver = FutureResolver::new().unwrap();
+    let address: std::net::IpAddr = "192.0.2.83".parse().unwrap();
+    resolver.add_host_override("web.example.com", &[address]);
+    let addresses = futures_executor::block_on(resolver.lookup("web.example.com", 443)).unwrap();
+    assert_eq!(addresses, vec![std::net::SocketAddr::new(address, 443)]);
+}
+
+#[cfg(cares1_24)]
+#[test]
+fn packets_can_be_captured() {
+    let packets = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let captured = std::sync::Arc::clone(&packets);
+    let mut options = Options::new();
+    options
+        .set_transport(FixedTransport(std::net::Ipv4Addr::new(192, 0, 2, 61)))
+        .set_packet_capture(move |packet| {
+            let packet = (packet.direction, packet.data.to_vec(), packet.peer);
+            captured.lock().unwrap().push(packet);
+        });
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver.query_a("example.com").unwrap();
+
+    let packets = packets.lock().unwrap();
+    let directions: Vec<_> = packets.iter().map(|packet| packet.0).collect();
+    assert_eq!(directions, vec![Direction::Outgoing, Direction::Incoming]);
+    assert_eq!(packets[0].1[..2], packets[1].1[..2]);
+    assert!(c_ares::AResults::parse_from(&packets[1].1).is_ok());
+    assert!(packets.iter().all(|packet| packet.2.is_none()));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn captured_packets_are_written_as_pcap() {
+    let query = [
+        0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1,
+    ];
+    let mut writer = pcap::PcapWriter::new(Vec::new()).unwrap();
+    let udp = Packet {
+        direction: Direction::Outgoing,
+        data: &query,
+        peer: Some("192.0.2.53:53".parse().unwrap()),
+        protocol: Some(Protocol::Udp),
+    };
+    let tcp = Packet {
+        direction: Direction::Incoming,
+        peer: Some("[2001:db8::53]:53".parse().unwrap()),
+        protocol: Some(Protocol::Tcp),
+        ..udp
+    };
+    writer.write_packet(&udp).unwrap();
+    writer.write_packet(&tcp).unwrap();
+    let file = writer.into_inner();
+
+    assert_eq!(file[..4], [0xd4, 0xc3, 0xb2, 0xa1]);
+    assert_eq!(file[20..24], [101, 0, 0, 0]);
+
+    // An IPv4 header and a UDP header, then the message.
+    let record = &file[24..];
+    let length = u32::from_le_bytes(record[8..12].try_into().unwrap()) as usize;
+    assert_eq!(length, 20 + 8 + query.len());
+    let frame = &record[16..16 + length];
+    assert_eq!(frame[0], 0x45);
+    assert_eq!(frame[9], 17);
+    assert_eq!(frame[16..20], [192, 0, 2, 53]);
+    assert_eq!(frame[22..24], 53_u16.to_be_bytes());
+    assert_eq!(frame[28..], query);
+
+    // An IPv6 header and a TCP header, then the length-prefixed message.
+    let record = &record[16 + length..];
+    let length = u32::from_le_bytes(record[8..12].try_into().unwrap()) as usize;
+    assert_eq!(length, 40 + 20 + 2 + query.len());
+    let frame = &record[16..16 + length];
+    assert_eq!(frame[0] >> 4, 6);
+    assert_eq!(frame[6], 6);
+    assert_eq!(frame[40..42], 53_u16.to_be_bytes());
+    assert_eq!(frame[60..62], (query.len() as u16).to_be_bytes());
+    assert_eq!(frame[62..], query);
+}
+
+// Code written against the traits, as a user of the crate might.
+fn first_address<R: BlockingResolve>(resolver: &R, name: &str) -> c_ares::Result<std::net::IpAddr> {
+    let host = resolver.lookup_host(name, c_ares::AddressFamily::UNSPEC)?;
+    host.addresses
+        .first()
+        .copied()
+        .ok_or(c_ares::Error::ENODATA)
+}
+
+#[test]
+fn mock_resolver_answers_from_fixtures() {
+    let mut resolver = MockResolver::new();
+    resolver
+        .add_ipv4("example.com", &[std::net::Ipv4Addr::new(192, 0, 2, 71)])
+        .add_ipv6("example.com", &["2001:db8::71".parse().unwrap()])
+        .add_txt("example.com", b"v=spf1 -all")
+        .set_error("broken.example.com", c_ares::Error::ESERVFAIL);
+
+    assert_eq!(
+        first_address(&resolver, "EXAMPLE.com."),
+        Ok("192.0.2.71".parse().unwrap())
+    );
+    assert_eq!(
+        BlockingResolve::lookup_txt(&resolver, "example.com"),
+        Ok(vec![b"v=spf1 -all".to_vec()])
+    );
+    assert_eq!(
+        BlockingResolve::lookup_raw(&resolver, "example.com", 1, 15),
+        Err(c_ares::Error::ENODATA)
+    );
+    assert_eq!(
+        first_address(&resolver, "broken.example.com"),
+        Err(c_ares::Error::ESERVFAIL)
+    );
+
+    let resolver: &dyn Resolve = &resolver;
+    let addresses = futures_executor::block_on(resolver.lookup_ipv6("example.com"));
+    assert_eq!(addresses, Ok(vec!["2001:db8::71".parse().unwrap()]));
+    let missing = futures_executor::block_on(resolver.lookup_ipv4("missing.example.com"));
+    assert_eq!(missing, Err(c_ares::Error::ENOTFOUND));
+}
+
+#[test]
+fn resolvers_implement_resolve() {
+    let address: std::net::IpAddr = "192.0.2.72".parse().unwrap();
+    let resolver = BlockingResolver::new().unwrap();
+    resolver.add_host_override("pinned.example.com", &[address]);
+    assert_eq!(first_address(&resolver, "pinned.example.com"), Ok(address));
+
+    let resolver = FutureResolver::new().unwrap();
+    resolver.add_host_override("pinned.example.com", &[address]);
+    let resolver: Box<dyn Resolve> = Box::new(resolver);
+    let addresses = futures_executor::block_on(resolver.lookup_ipv4("pinned.example.com"));
+    assert_eq!(addresses, Ok(vec![std::net::Ipv4Addr::new(192, 0, 2, 72)]));
+}
+
+#[test]
+fn mail_exchanges_are_looked_up() {
+    let server = testing::DnsServer::start().unwrap();
+    server
+        .add_mx("example.com", 20, "b.example.com")
+        .add_mx("example.com", 10, "a.example.com")
+        .add_mx("example.com", 30, "A.example.com")
+        .add_a("a.example.com", std::net::Ipv4Addr::new(192, 0, 2, 25))
+        .add_a(
+            "implicit.example.com",
+            std::net::Ipv4Addr::new(192, 0, 2, 26),
+        )
+        .add_mx("null.example.com", 0, ".");
+
+    let mut options = Options::new();
+    options.set_tries(1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let exchanges = resolver.lookup_mx("example.com", false).unwrap();
+    let hosts: Vec<_> = exchanges.iter().map(|mx| mx.host.as_str()).collect();
+    assert_eq!(hosts, vec!["a.example.com", "b.example.com"]);
+    assert!(exchanges.iter().all(|mx| mx.addresses.is_empty()));
+
+    let exchanges = resolver.lookup_mx("example.com", true).unwrap();
+    assert_eq!(
+        exchanges[0].addresses,
+        vec!["192.0.2.25".parse::<std::net::IpAddr>().unwrap()]
+    );
+    assert!(exchanges[1].addresses.is_empty());
+
+    let expected = vec![MailExchange {
+        preference: 0,
+        host: "implicit.example.com".to_owned(),
+        addresses: vec!["192.0.2.26".parse().unwrap()],
+    }];
+    assert_eq!(
+        resolver.lookup_mx("implicit.example.com", false).unwrap(),
+        expected
+    );
+    assert_eq!(
+        resolver.lookup_mx("null.example.com", true).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        resolver.lookup_mx("missing.example.com", true).err(),
+        Some(c_ares::Error::ENOTFOUND)
+    );
+
+    let resolver = FutureResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+    let exchanges =
+        futures_executor::block_on(resolver.lookup_mx("implicit.example.com", true)).unwrap();
+    assert_eq!(exchanges, expected);
+}
+
+// Wait for all of the items from a stream.
+fn collect_stream<S: futures_core::Stream + Unpin>(stream: &mut S) -> Vec<S::Item> {
+    let mut items = Vec::new();
+    futures_executor::block_on(std::future::poll_fn(|cx| loop {
+        match std::pin::Pin::new(&mut *stream).poll_next(cx) {
+            std::task::Poll::Ready(Some(item)) => items.push(item),
+            std::task::Poll::Ready(None) => return std::task::Poll::Ready(()),
+            std::task::Poll::Pending => return std::task::Poll::Pending,
+        }
+    }));
+    items
+}
+
+#[test]
+fn many_names_are_resolved_as_a_stream() {
+    use futures_core::Stream;
+
+    let resolver = FutureResolver::new().unwrap();
+    let names = ["a.example.com", "b.example.com", "c.example.com"];
+    for (index, name) in names.iter().enumerate() {
+        let address = std::net::IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, index as u8));
+        resolver.add_host_override(name, &[address]);
+    }
+    let names = names
+        .iter()
+        .copied()
+        .chain(std::iter::once("bad..example.com"));
+    let mut stream = resolver.resolve_many(names, c_ares::AddressFamily::INET);
+    assert_eq!(stream.size_hint(), (4, Some(4)));
+
+    let mut results = collect_stream(&mut stream);
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "a.example.com",
+            "b.example.com",
+            "bad..example.com",
+            "c.example.com"
+        ]
+    );
+    let addresses = &results[1].1.as_ref().unwrap().addresses;
+    assert_eq!(addresses, &vec![std::net::IpAddr::from([192, 0, 2, 1])]);
+    assert_eq!(results[2].1, Err(c_ares::Error::EBADNAME));
+}
+
+#[test]
+fn many_names_are_resolved_with_a_limit() {
+    use futures_core::Stream;
+
+    let server = testing::DnsServer::start().unwrap();
+    let names: Vec<String> = (0..5).map(|i| format!("host{i}.example.com")).collect();
+    for (i, name) in names.iter().enumerate() {
+        server.add_a(name, std::net::Ipv4Addr::new(192, 0, 2, i as u8));
+    }
+    let resolver = FutureResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let mut stream = resolver.resolve_many_limited(names.clone(), c_ares::AddressFamily::INET, 2);
+    assert_eq!(stream.size_hint(), (5, Some(5)));
+    assert!(resolver.load().in_flight <= 2);
+    let mut results = collect_stream(&mut stream);
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(results.len(), 5);
+    for (i, (name, result)) in results.iter().enumerate() {
+        assert_eq!(name, &names[i]);
+        let address = std::net::IpAddr::from([192, 0, 2, i as u8]);
+        assert_eq!(result.as_ref().unwrap().addresses, vec![address]);
+    }
+    assert_eq!(stream.size_hint(), (0, Some(0)));
+}
+
+// Allow the process at least `count` open files, if the hard limit permits.
+#[cfg(unix)]
+fn raise_open_file_limit(count: u64) {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // Safety: `limit` is a valid `rlimit` for the duration of both calls.
+    unsafe {
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) == 0 && limit.rlim_cur < count {
+            limit.rlim_cur = count.min(limit.rlim_max);
+            libc::setrlimit(libc::RLIMIT_NOFILE, &limit);
+        }
+    }
+}
+
+#[cfg(cares1_20)]
+#[test]
+fn many_sockets_are_polled_at_once() {
+    // More sockets than `select()` can watch: FD_SETSIZE is 1024 on Linux, and smaller elsewhere.
+    const QUERIES: usize = 1100;
+    #[cfg(unix)]
+    raise_open_file_limit(4096);
+
+    // A server that holds its replies until every query has arrived - so that every socket is open
+    // at once - and reports how many sockets it heard from.
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(20)))
+        .unwrap();
+    let server = socket.local_addr().unwrap().to_string();
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 68);
+    let handle = std::thread::spawn(move || {
+        let mut queries = Vec::new();
+        let mut peers = std::collections::HashSet::new();
+        let mut buffer = [0; 512];
+        while queries.len() < QUERIES {
+            let Ok((length, peer)) = socket.recv_from(&mut buffer) else {
+                break;
+            };
+            queries.push((buffer[..length].to_vec(), peer));
+            peers.insert(peer);
+        }
+        for (query, peer) in &queries {
+            socket.send_to(&answer_a(query, address), peer).unwrap();
+        }
+        peers.len()
+    });
+
+    let mut options = Options::new();
+    options
+        .set_udp_max_queries(1)
+        .set_timeout(30_000)
+        .set_tries(1);
+    let resolver = FutureResolver::with_options(options).unwrap();
+    resolver.set_servers(&[&server]).unwrap();
+    let names: Vec<String> = (0..QUERIES)
+        .map(|i| format!("host{i}.example.com"))
+        .collect();
+    let mut stream = resolver.resolve_many(names, c_ares::AddressFamily::INET);
+    let results = collect_stream(&mut stream);
+    let sockets = handle.join().unwrap();
+    assert!(sockets > 1024, "only {sockets} sockets were open at once");
+    assert_eq!(results.len(), QUERIES);
+    for (name, result) in results {
+        assert!(result.is_ok(), "{name}: {result:?}");
+    }
+}
+
+#[test]
+fn addresses_are_watched() {
+    use futures_core::Stream;
+
+    // Records with TTL zero aren't cached, so that each lookup reaches the server.
+    let server = testing::DnsServer::start().unwrap();
+    server.add_record("web.example.com", 1, 0, &[192, 0, 2, 2]);
+    let resolver = FutureResolver::new().unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let interval = std::time::Duration::from_millis(20);
+    let mut watch = resolver.watch_a("web.example.com", Some(interval));
+    let mut next = || {
+        futures_executor::block_on(std::future::poll_fn(|cx| {
+            std::pin::Pin::new(&mut watch).poll_next(cx)
+        }))
+    };
+    let addresses = next().unwrap().unwrap();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 2)]);
+
+    // Unchanged lookups are not yielded, so the next item comes after the change.
+    let questions = server.questions().len();
+    server.add_record("web.example.com", 1, 0, &[192, 0, 2, 1]);
+    let addresses = next().unwrap().unwrap();
+    assert_eq!(
+        addresses,
+        vec![
+            std::net::Ipv4Addr::new(192, 0, 2, 1),
+            std::net::Ipv4Addr::new(192, 0, 2, 2)
+        ]
+    );
+    assert!(server.questions().len() > questions);
+}
+
+#[test]
+fn test_server_serves_zone() {
+    let server = testing::DnsServer::start().unwrap();
+    server
+        .add_a("example.com", std::net::Ipv4Addr::new(192, 0, 2, 81))
+        .add_cname("www.example.com", "example.com")
+        .add_txt("example.com", "hello")
+        .set_rcode("broken.example.com", 2);
+
+    let mut options = Options::new();
+    options.set_tries(1);
+    let resolver = BlockingResolver::with_options(options).unwrap();
+    resolver
+        .set_servers(&[&server.address().to_string()])
+        .unwrap();
+
+    let results = resolver.query_a("WWW.example.com").unwrap();
+    let addresses: Vec<_> = results.iter().map(|a| a.ipv4()).collect();
+    assert_eq!(addresses, vec![std::net::Ipv4Addr::new(192, 0, 2, 81)]);
+    let results = resolver.query_txt("example.com").unwrap();
+    assert_eq!(results.iter().next().unwrap().text(), b"hello");
+    assert_eq!(
+        resolver.query_aaaa("example.com").err(),
+        Some(c_ares::Error::ENODATA)
+    );
+    assert_eq!(
+        resolver.query_a("missing.example.com").err(),
+        Some(c_ares::Error::ENOTFOUND)
+    );
+    assert_eq!(
+        resolver.query_a("broken.example.com").err(),
+        Some(c_ares::Error::ESERVFAIL)
+    );
+
+    let questions = server.questions();
+    assert_eq!(questions[0].name, "www.example.com");
+    assert_eq!(questions[0].query_type, 1);
+    assert_eq!(questions[0].dns_class, 1);
+
+    let tcp = resolver.tcp().unwrap();
+    server.clear_questions();
+    assert!(tcp.query_a("example.com").is_ok());
+    assert_eq!(server.questions().len(), 1);
+}
+
+#[test]
+fn faulty_resolver_injects_faults() {
+    let mut mock = MockResolver::new();
+    let response = vec![0; 40];
+    mock.add_ipv4("example.com", &[std::net::Ipv4Addr::new(192, 0, 2, 101)])
+        .set_raw("example.com", 1, &response);
+
+    let mut resolver = FaultyResolver::new(mock);
+    resolver.set_truncations(100);
+    let truncated = BlockingResolve::lookup_raw(&resolver, "example.com", 1, 1).unwrap();
+    assert_eq!(truncated.len(), 20);
+    assert_eq!(truncated[2] & 0x02, 0x02);
+    assert_eq!(
+        BlockingResolve::lookup_ipv4(&resolver, "example.com"),
+        Err(c_ares::Error::EBADRESP)
+    );
+
+    resolver
+        .set_truncations(0)
+        .set_latency(std::time::Duration::from_millis(50), 100);
+    let start = std::time::Instant::now();
+    assert!(BlockingResolve::lookup_ipv4(&resolver, "example.com").is_ok());
+    assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+
+    resolver
+        .set_latency(std::time::Duration::ZERO, 0)
+        .set_timeouts(100);
+    let lookups = resolver.inner().lookups().len();
+    let result = futures_executor::block_on(Resolve::lookup_ipv4(&resolver, "example.com"));
+    assert_eq!(result, Err(c_ares::Error::ETIMEOUT));
+    assert_eq!(resolver.inner().lookups().len(), lookups);
+}
+
+// Code written once, for any of the resolvers.
+fn first_a<R: Queries>(resolver: &R, name: &str) -> c_ares::Result<std::net::Ipv4Addr> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    resolver.query_a(name, move |result| {
+        let address = result.and_then(|results| {
+            results
+                .iter()
+                .next()
+                .map(|result| result.ipv4())
+                .ok_or(c_ares::Error::ENODATA)
+        });
+        sender.send(address).unwrap();
+    });
+    receiver.recv().unwrap()
+}
+
+#[test]
+fn queries_are_shared_by_all_resolvers() {
+    let address = std::net::Ipv4Addr::new(192, 0, 2, 111);
+    let options = || {
+        let mut options = Options::new();
+        options.set_transport(FixedTransport(address));
+        options
+    };
+    let resolver = Resolver::with_options(options()).unwrap();
+    assert_eq!(first_a(&resolver, "example.com"), Ok(address));
+    let resolver = FutureResolver::with_options(options()).unwrap();
+    assert_eq!(first_a(&resolver, "example.com"), Ok(address));
+    let resolver = BlockingResolver::with_options(options()).unwrap();
+    assert_eq!(first_a(&resolver, "example.com"), Ok(address));
+}
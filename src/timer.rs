@@ -0,0 +1,98 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Something to do once its deadline has passed.  Entries are ordered by deadline, and then by the
+// order in which they were added.
+struct Entry {
+    deadline: Instant,
+    sequence: u64,
+    task: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.deadline, self.sequence).cmp(&(other.deadline, other.sequence))
+    }
+}
+
+struct State {
+    entries: BinaryHeap<Reverse<Entry>>,
+    sequence: u64,
+}
+
+// A single thread, shared by all resolvers, on which to run tasks after a delay - rather than
+// spawning a thread for each.  Tasks should be quick, eg handing work on to an event loop.
+struct Timer {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+static TIMER: Timer = Timer {
+    state: Mutex::new(State {
+        entries: BinaryHeap::new(),
+        sequence: 0,
+    }),
+    changed: Condvar::new(),
+};
+
+impl Timer {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            state = match state.entries.peek() {
+                None => self.changed.wait(state).unwrap(),
+                Some(Reverse(entry)) if entry.deadline > now => {
+                    let wait = entry.deadline - now;
+                    self.changed.wait_timeout(state, wait).unwrap().0
+                }
+                Some(_) => {
+                    let Reverse(entry) = state.entries.pop().unwrap();
+                    drop(state);
+                    (entry.task)();
+                    self.state.lock().unwrap()
+                }
+            };
+        }
+    }
+}
+
+// Run `task` on the timer thread once `delay` has passed.  The thread is started on first use.
+pub(crate) fn after<F>(delay: Duration, task: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    static START: Once = Once::new();
+    START.call_once(|| {
+        thread::Builder::new()
+            .name("c-ares-resolver-timer".to_owned())
+            .spawn(|| TIMER.run())
+            .expect("Failed to start timer thread");
+    });
+    let mut state = TIMER.state.lock().unwrap();
+    state.sequence += 1;
+    let entry = Entry {
+        deadline: Instant::now() + delay,
+        sequence: state.sequence,
+        task: Box::new(task),
+    };
+    state.entries.push(Reverse(entry));
+    TIMER.changed.notify_one();
+}
@@ -0,0 +1,187 @@
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+use crate::capture::Direction;
+use crate::error::Error;
+use crate::transport::Transport;
+
+const DEFAULT_PORT: u16 = 853;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Whether `server` describes a DNS-over-TLS server, rather than one that `c-ares` contacts itself.
+pub(crate) fn is_tls_server(server: &str) -> bool {
+    server.trim_start().starts_with("tls://")
+}
+
+// TLS configuration verifying certificates against the Mozilla root certificates.
+pub(crate) fn default_client_config() -> io::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+// A DNS-over-TLS server.
+#[derive(Clone, Debug)]
+struct TlsServer {
+    name: ServerName<'static>,
+    address: SocketAddr,
+}
+
+impl TlsServer {
+    // Parse a server description of the form `tls://host@ip[:port]` or `tls://ip[:port]`.
+    fn parse(server: &str) -> Result<Self, Error> {
+        let invalid =
+            || io::Error::new(io::ErrorKind::InvalidInput, format!("bad server {server}"));
+        let server = server.strip_prefix("tls://").ok_or_else(invalid)?;
+        let (host, address) = match server.split_once('@') {
+            Some((host, address)) => (Some(host), address),
+            None => (None, server),
+        };
+        let address = match address.parse::<SocketAddr>() {
+            Ok(address) => address,
+            Err(_) => {
+                let ip = address
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<IpAddr>()
+                    .map_err(|_| invalid())?;
+                SocketAddr::new(ip, DEFAULT_PORT)
+            }
+        };
+        let name = match host {
+            Some(host) => ServerName::try_from(host.to_owned()).map_err(|_| invalid())?,
+            None => ServerName::from(address.ip()),
+        };
+        let tls_server = Self { name, address };
+        Ok(tls_server)
+    }
+}
+
+/// A `Transport` that sends DNS queries over TLS, per RFC 7858.
+///
+/// Servers are tried in order until one of them gives an answer.  Each query is made on a fresh
+/// connection.
+///
+/// Servers can also be given to `Resolver::set_servers()` as `tls://` strings, in which case the
+/// resolver sends its queries over TLS without a `Transport` being set.
+#[derive(Clone, Debug)]
+pub struct TlsTransport {
+    servers: Vec<TlsServer>,
+    config: Arc<ClientConfig>,
+    timeout: Duration,
+    next: Arc<AtomicUsize>,
+}
+
+impl TlsTransport {
+    /// Returns a `TlsTransport` that sends queries to the given servers, verifying their
+    /// certificates against the Mozilla root certificates.
+    ///
+    /// Servers are described as `tls://host@ip[:port]`, where `host` is the name against which
+    /// the server's certificate is verified; eg `tls://dns.google@8.8.8.8:853` or
+    /// `tls://one.one.one.one@[2606:4700:4700::1111]`.  If `host@` is omitted then the
+    /// certificate is verified against the IP address.  The port defaults to 853.
+    pub fn new(servers: &[&str]) -> Result<Self, Error> {
+        let config = default_client_config()?;
+        Self::with_client_config(servers, config)
+    }
+
+    /// Returns a `TlsTransport` that sends queries to the given servers, using the provided TLS
+    /// configuration.
+    ///
+    /// Servers are described as for `new()`.
+    pub fn with_client_config(servers: &[&str], config: Arc<ClientConfig>) -> Result<Self, Error> {
+        let servers = servers
+            .iter()
+            .map(|server| TlsServer::parse(server))
+            .collect::<Result<Vec<_>, _>>()?;
+        let transport = Self {
+            servers,
+            config,
+            timeout: DEFAULT_TIMEOUT,
+            next: Arc::new(AtomicUsize::new(0)),
+        };
+        Ok(transport)
+    }
+
+    /// Set a timeout for connecting to a server, and for each read and write thereafter.  The
+    /// default is five seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // The addresses of the servers.
+    pub(crate) fn addresses(&self) -> Vec<SocketAddr> {
+        self.servers.iter().map(|server| server.address).collect()
+    }
+
+    // Send `query` to the servers, as `exchange()` does - telling `observe` of each message sent to
+    // or received from a server.
+    pub(crate) fn exchange_observed<F>(&self, query: &[u8], observe: F) -> io::Result<Vec<u8>>
+    where
+        F: Fn(Direction, &[u8], SocketAddr),
+    {
+        // Start with whichever server last worked.
+        let count = self.servers.len();
+        let first = self.next.load(Ordering::Relaxed);
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no servers");
+        for offset in 0..count {
+            let index = (first + offset) % count;
+            match self.exchange_with(&self.servers[index], query, &observe) {
+                Ok(response) => {
+                    self.next.store(index, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn exchange_with<F>(&self, server: &TlsServer, query: &[u8], observe: F) -> io::Result<Vec<u8>>
+    where
+        F: Fn(Direction, &[u8], SocketAddr),
+    {
+        let socket = TcpStream::connect_timeout(&server.address, self.timeout)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        socket.set_write_timeout(Some(self.timeout))?;
+        socket.set_nodelay(true)?;
+        let connection = ClientConnection::new(Arc::clone(&self.config), server.name.clone())
+            .map_err(io::Error::other)?;
+        let mut stream = StreamOwned::new(connection, socket);
+
+        let length = u16::try_from(query.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "query too long"))?;
+        let mut message = Vec::with_capacity(query.len() + 2);
+        message.extend_from_slice(&length.to_be_bytes());
+        message.extend_from_slice(query);
+        stream.write_all(&message)?;
+        stream.flush()?;
+        observe(Direction::Outgoing, query, server.address);
+
+        let mut prefix = [0; 2];
+        stream.read_exact(&mut prefix)?;
+        let mut response = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        stream.read_exact(&mut response)?;
+        observe(Direction::Incoming, &response, server.address);
+        Ok(response)
+    }
+}
+
+impl Transport for TlsTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        self.exchange_observed(query, |_, _, _| {})
+    }
+}
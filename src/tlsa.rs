@@ -0,0 +1,40 @@
+use crate::dnsrec::DnsRr;
+
+use c_ares_sys::ares_dns_rr_key_t;
+
+dns_results!(
+    /// The result of a successful TLSA lookup.
+    TLSAResults,
+    TLSAResult,
+    ARES_REC_TYPE_TLSA
+);
+
+/// The contents of a single TLSA record.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TLSAResult {
+    /// The certificate usage - eg 3 for DANE-EE, a domain-issued certificate.
+    pub cert_usage: u8,
+
+    /// The selector - 0 if `cert_data` matches the full certificate, 1 if it matches the
+    /// SubjectPublicKeyInfo.
+    pub selector: u8,
+
+    /// The matching type - 0 if `cert_data` is the exact data, 1 if it is a SHA-256 hash and 2
+    /// if it is a SHA-512 hash.
+    pub matching_type: u8,
+
+    /// The certificate association data.
+    pub cert_data: Vec<u8>,
+}
+
+impl TLSAResult {
+    pub(crate) fn from_rr(rr: &DnsRr) -> Self {
+        Self {
+            cert_usage: rr.u8(ares_dns_rr_key_t::ARES_RR_TLSA_CERT_USAGE),
+            selector: rr.u8(ares_dns_rr_key_t::ARES_RR_TLSA_SELECTOR),
+            matching_type: rr.u8(ares_dns_rr_key_t::ARES_RR_TLSA_MATCH),
+            cert_data: rr.bin(ares_dns_rr_key_t::ARES_RR_TLSA_DATA),
+        }
+    }
+}
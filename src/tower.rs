@@ -0,0 +1,71 @@
+use std::fmt;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+use tower_service::Service;
+
+use crate::futureresolver::{CAresFuture, FutureResolver};
+use crate::host::HostResults;
+use crate::name::{check_name, InvalidName};
+
+/// A name to be looked up by a `FutureResolver`, used as a `tower_service::Service`.
+///
+/// A `Name` is checked when it is created, as by `validate_name()`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Name(String);
+
+impl Name {
+    /// Create a `Name`, checking that it can be queried.
+    pub fn new(name: &str) -> Result<Self, InvalidName> {
+        check_name(name)?;
+        Ok(Self(name.to_owned()))
+    }
+
+    /// The name, as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Name {
+    type Err = InvalidName;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::new(name)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A `FutureResolver` is a `tower_service::Service`, looking up the addresses for a `Name` - so
+/// that middleware from the `tower` ecosystem, eg for timeouts, rate limiting and load shedding,
+/// can be layered over it.
+///
+/// Names are looked up by `get_host_by_name()`, for both IPv4 and IPv6 addresses.  The resolver is
+/// always ready.
+///
+/// ```no_run
+/// use c_ares_resolver::{FutureResolver, Name};
+/// use tower_service::Service;
+///
+/// let mut resolver = FutureResolver::new().unwrap();
+/// let name: Name = "example.com".parse().unwrap();
+/// let results = futures_executor::block_on(resolver.call(name)).unwrap();
+/// ```
+impl Service<Name> for FutureResolver {
+    type Response = HostResults;
+    type Error = c_ares::Error;
+    type Future = CAresFuture<HostResults>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        self.get_host_by_name(name.as_str(), c_ares::AddressFamily::UNSPEC)
+    }
+}
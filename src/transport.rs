@@ -0,0 +1,795 @@
+use std::cell::Cell;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::capture::{capture, Direction, PacketCapture, Protocol};
+use crate::eventloop::Sockets;
+#[cfg(feature = "tls")]
+use crate::tls::TlsTransport;
+
+/// A pluggable transport for DNS traffic.
+///
+/// When a `Transport` is set on the `Options` with which a resolver is created, the resolver sends
+/// every query to the transport instead of to the network.  This allows DNS traffic to be routed
+/// through user-space networking stacks, tunnels, proxies, or test harnesses.
+///
+/// The resolver achieves this by relaying: `c-ares` is pointed at a socket on the loopback
+/// interface, and queries that arrive there are handed to the transport.  Only queries from the
+/// resolver's own sockets are relayed: anything else that reaches the socket is ignored.
+///
+/// It follows that when a transport is in use, the transport - and not the resolver's list of
+/// servers - determines where queries go: `set_servers()` on such a resolver fails with
+/// `c_ares::Error::ENOTIMP`.
+pub trait Transport: Send + Sync + 'static {
+    /// Send a DNS query message, and return the response message.
+    ///
+    /// `query` is a complete DNS message, without any TCP length prefix.  The response should be
+    /// likewise.
+    ///
+    /// This is called on a thread owned by the resolver, and may block.  Queries are not
+    /// serialized: several calls may be in progress at once - though no more than the relay has
+    /// threads for, so that further queries wait while those are busy.  See
+    /// `Options::set_relay_limits()`.
+    ///
+    /// If this fails, the resolver is answered with SERVFAIL - so that the query fails with
+    /// `c_ares::Error::ESERVFAIL`, or is retried, rather than waiting to time out.
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+// The limits on the work that a relay does at once.  See `Options::set_relay_limits()`.
+#[derive(Clone, Copy, Debug)]
+pub struct RelayLimits {
+    // The number of threads on which the relay exchanges UDP queries with the transport, and the
+    // number of queries that may wait for one of them.
+    pub threads: usize,
+    pub queued: usize,
+
+    // The number of TCP connections that the relay serves at once, each on its own thread.
+    pub connections: usize,
+}
+
+impl Default for RelayLimits {
+    fn default() -> Self {
+        Self {
+            threads: 16,
+            queued: 64,
+            connections: 16,
+        }
+    }
+}
+
+// The resolvers whose queries a relay accepts: the one that started it, and any derived from it -
+// which send their queries to the same relay.
+#[derive(Clone)]
+pub struct RelayClients(Arc<Mutex<Vec<Sockets>>>);
+
+impl RelayClients {
+    pub fn new(sockets: Sockets) -> Self {
+        Self(Arc::new(Mutex::new(vec![sockets])))
+    }
+
+    // Accept queries from the clients of `other` too.
+    pub fn join(&self, other: &Self) {
+        let others = other.0.lock().unwrap().clone();
+        let mut clients = self.0.lock().unwrap();
+        clients.retain(Sockets::is_live);
+        clients.extend(others);
+    }
+
+    // Whether `peer` is one of the clients' sockets.  Only if it isn't one that they already know
+    // of is it worth waiting for their event loops to catch up.
+    fn contains(&self, peer: SocketAddr) -> bool {
+        let clients = self.0.lock().unwrap();
+        if clients.iter().any(|sockets| sockets.knows(peer)) {
+            return true;
+        }
+        let clients = clients.clone();
+        clients.iter().any(|sockets| sockets.contains(peer))
+    }
+}
+
+// Relays DNS messages between c-ares and a `Transport`.  The relay listens for both UDP and TCP on
+// a loopback address, and stops when dropped.
+pub struct Relay {
+    address: SocketAddr,
+    quit: Arc<AtomicBool>,
+}
+
+impl Relay {
+    // Start a relay for the given transport, accepting queries only from `clients` - or from
+    // anyone, if there are none.
+    pub fn start(
+        transport: Arc<dyn Transport>,
+        clients: Option<RelayClients>,
+        limits: RelayLimits,
+    ) -> io::Result<Self> {
+        let (udp_socket, tcp_listener) = bind_loopback()?;
+        let address = udp_socket.local_addr()?;
+        let quit = Arc::new(AtomicBool::new(false));
+
+        {
+            let transport = Arc::clone(&transport);
+            let clients = clients.clone();
+            let quit = Arc::clone(&quit);
+            thread::spawn(move || relay_udp(udp_socket, transport, clients, limits, quit));
+        }
+
+        {
+            let quit = Arc::clone(&quit);
+            thread::spawn(move || relay_tcp(tcp_listener, transport, clients, limits, quit));
+        }
+
+        let relay = Self { address, quit };
+        Ok(relay)
+    }
+
+    // The address on which the relay is listening.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.quit.store(true, Ordering::Relaxed);
+
+        // Wake the relay threads, so that they notice that they should quit.  The TCP thread
+        // notices only once it has a free slot - when `c-ares` closes a connection.
+        if let Ok(socket) = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)) {
+            let _ = socket.send_to(&[], self.address);
+        }
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+// Bind a UDP socket and a TCP listener to the same port on the loopback interface.
+fn bind_loopback() -> io::Result<(UdpSocket, TcpListener)> {
+    let mut attempts = 0;
+    loop {
+        let udp_socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let port = udp_socket.local_addr()?.port();
+        match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+            Ok(tcp_listener) => return Ok((udp_socket, tcp_listener)),
+            Err(_) if attempts < 10 => attempts += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// Whether the relay accepts queries from `peer`.
+fn accepts(clients: Option<&RelayClients>, peer: SocketAddr) -> bool {
+    clients.is_none_or(|clients| clients.contains(peer))
+}
+
+// A query received over UDP, and who to send the response to.
+type Datagram = (Vec<u8>, SocketAddr);
+
+fn relay_udp(
+    socket: UdpSocket,
+    transport: Arc<dyn Transport>,
+    clients: Option<RelayClients>,
+    limits: RelayLimits,
+    quit: Arc<AtomicBool>,
+) {
+    let socket = Arc::new(socket);
+
+    // Start the workers, which stop when the queue is dropped.
+    let (queue, datagrams) = mpsc::sync_channel::<Datagram>(limits.queued);
+    let datagrams = Arc::new(Mutex::new(datagrams));
+    for _ in 0..limits.threads.max(1) {
+        let socket = Arc::clone(&socket);
+        let transport = Arc::clone(&transport);
+        let datagrams = Arc::clone(&datagrams);
+        thread::spawn(move || loop {
+            let datagram = datagrams.lock().unwrap().recv();
+            let Ok((query, peer)) = datagram else {
+                break;
+            };
+            let response = transport.exchange(&query).or_else(|_| servfail(&query));
+            if let Ok(response) = response {
+                let _ = socket.send_to(&response, peer);
+            }
+        });
+    }
+
+    let mut buffer = [0; 65535];
+    loop {
+        let result = socket.recv_from(&mut buffer);
+        if quit.load(Ordering::Relaxed) {
+            break;
+        }
+        let (length, peer) = match result {
+            Ok((length, peer)) if length > 0 => (length, peer),
+            _ => continue,
+        };
+        if !accepts(clients.as_ref(), peer) {
+            continue;
+        }
+        if queue.send((buffer[..length].to_vec(), peer)).is_err() {
+            break;
+        }
+    }
+}
+
+fn relay_tcp(
+    listener: TcpListener,
+    transport: Arc<dyn Transport>,
+    clients: Option<RelayClients>,
+    limits: RelayLimits,
+    quit: Arc<AtomicBool>,
+) {
+    // Each connection takes a slot, and gives it back when closed.
+    let connections = limits.connections.max(1);
+    let (release, slots) = mpsc::sync_channel(connections);
+    for _ in 0..connections {
+        let _ = release.send(());
+    }
+    loop {
+        // Connections beyond the limit wait to be accepted until a slot is free.
+        if slots.recv().is_err() {
+            break;
+        }
+        let stream = listener.accept();
+        if quit.load(Ordering::Relaxed) {
+            break;
+        }
+        let accepted = stream
+            .ok()
+            .filter(|(_, peer)| accepts(clients.as_ref(), *peer));
+        let Some((stream, _)) = accepted else {
+            let _ = release.send(());
+            continue;
+        };
+        let transport = Arc::clone(&transport);
+        let release = release.clone();
+        thread::spawn(move || {
+            let _ = relay_tcp_stream(stream, transport.as_ref());
+            let _ = release.send(());
+        });
+    }
+}
+
+thread_local! {
+    // Whether the queries relayed on this thread arrived over TCP - in which case `DirectTransport`
+    // sends them on over TCP too.
+    static OVER_TCP: Cell<bool> = const { Cell::new(false) };
+}
+
+// Serve queries from a single TCP connection, until it is closed.
+fn relay_tcp_stream(mut stream: TcpStream, transport: &dyn Transport) -> io::Result<()> {
+    OVER_TCP.with(|over_tcp| over_tcp.set(true));
+    loop {
+        let mut prefix = [0; 2];
+        stream.read_exact(&mut prefix)?;
+        let mut query = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        stream.read_exact(&mut query)?;
+
+        let response = transport.exchange(&query).or_else(|_| servfail(&query))?;
+        let length = u16::try_from(response.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "response too long"))?;
+        stream.write_all(&length.to_be_bytes())?;
+        stream.write_all(&response)?;
+    }
+}
+
+// A SERVFAIL response to a query, with which the relay answers if the transport fails.
+fn servfail(query: &[u8]) -> io::Result<Vec<u8>> {
+    if query.len() < 12 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "query too short",
+        ));
+    }
+
+    // Echo the question, if there's one that can be found.
+    let end = question_end(query);
+    let mut response = query[..end.unwrap_or(12)].to_vec();
+
+    // Mark it as a response - keeping the opcode and the RD bit - with the SERVFAIL rcode, and no
+    // records but the question.
+    response[2] = 0x80 | (query[2] & 0x79);
+    response[3] = 0x02;
+    let questions = u16::from(end.is_some());
+    response[4..6].copy_from_slice(&questions.to_be_bytes());
+    response[6..12].fill(0);
+    Ok(response)
+}
+
+// A transport that sends queries directly to DNS servers, over UDP - retrying over TCP if the
+// response is truncated - or over TCP, if that's how they reached the relay.  This is what c-ares
+// would have done itself: it's used when the resolver needs to see queries on their way to the
+// servers.  Or it sends them over TLS, to servers that `c-ares` could not have contacted itself.
+pub struct DirectTransport {
+    servers: RwLock<DirectServers>,
+    timeout: Duration,
+    capture: Option<PacketCapture>,
+}
+
+// The servers to which a `DirectTransport` sends queries.
+#[derive(Clone)]
+enum DirectServers {
+    Plain(Vec<SocketAddr>),
+    #[cfg(feature = "tls")]
+    Tls(TlsTransport),
+}
+
+impl DirectTransport {
+    pub fn new(
+        servers: Vec<SocketAddr>,
+        timeout: Duration,
+        capture: Option<PacketCapture>,
+    ) -> Self {
+        Self {
+            servers: RwLock::new(DirectServers::Plain(servers)),
+            timeout,
+            capture,
+        }
+    }
+
+    // Pass a message to the capture callback, if there is one.
+    fn capture(&self, direction: Direction, data: &[u8], server: SocketAddr, protocol: Protocol) {
+        capture(
+            self.capture.as_ref(),
+            direction,
+            data,
+            Some(server),
+            Some(protocol),
+        );
+    }
+
+    pub fn servers(&self) -> Vec<SocketAddr> {
+        match *self.servers.read().unwrap() {
+            DirectServers::Plain(ref servers) => servers.clone(),
+            #[cfg(feature = "tls")]
+            DirectServers::Tls(ref transport) => transport.addresses(),
+        }
+    }
+
+    pub fn set_servers(&self, servers: Vec<SocketAddr>) {
+        *self.servers.write().unwrap() = DirectServers::Plain(servers);
+    }
+
+    // Send queries over TLS, with `transport`.
+    #[cfg(feature = "tls")]
+    pub fn set_tls(&self, transport: TlsTransport) {
+        *self.servers.write().unwrap() = DirectServers::Tls(transport);
+    }
+
+    fn exchange_udp(&self, server: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+        let local: SocketAddr = match server {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        socket.connect(server)?;
+        socket.send(query)?;
+        self.capture(Direction::Outgoing, query, server, Protocol::Udp);
+        let mut buffer = [0; 65535];
+        loop {
+            let length = socket.recv(&mut buffer)?;
+            self.capture(
+                Direction::Incoming,
+                &buffer[..length],
+                server,
+                Protocol::Udp,
+            );
+            // Ignore anything that isn't a response to this query.
+            if length >= 12 && buffer[..2] == query[..2] {
+                return Ok(buffer[..length].to_vec());
+            }
+        }
+    }
+
+    fn exchange_tcp(&self, server: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect_timeout(&server, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let length = u16::try_from(query.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "query too long"))?;
+        stream.write_all(&length.to_be_bytes())?;
+        stream.write_all(query)?;
+        self.capture(Direction::Outgoing, query, server, Protocol::Tcp);
+        let mut prefix = [0; 2];
+        stream.read_exact(&mut prefix)?;
+        let mut response = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        stream.read_exact(&mut response)?;
+        self.capture(Direction::Incoming, &response, server, Protocol::Tcp);
+        Ok(response)
+    }
+}
+
+impl Transport for DirectTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        if query.len() < 12 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "query too short",
+            ));
+        }
+        let servers = self.servers.read().unwrap().clone();
+        #[cfg(feature = "tls")]
+        let servers = match servers {
+            DirectServers::Plain(servers) => servers,
+            DirectServers::Tls(transport) => {
+                return transport.exchange_observed(query, |direction, data, server| {
+                    self.capture(direction, data, server, Protocol::Tcp);
+                });
+            }
+        };
+        #[cfg(not(feature = "tls"))]
+        let DirectServers::Plain(servers) = servers;
+        let over_tcp = OVER_TCP.with(Cell::get);
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no servers");
+        for server in servers {
+            let result = if over_tcp {
+                self.exchange_tcp(server, query)
+            } else {
+                self.exchange_udp(server, query).and_then(|response| {
+                    let truncated = response[2] & 0x02 != 0;
+                    if truncated {
+                        self.exchange_tcp(server, query)
+                    } else {
+                        Ok(response)
+                    }
+                })
+            };
+            match result {
+                Ok(response) => return Ok(response),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+// Parse servers in the format accepted by c-ares: `host[:port]`, comma-separated, where IPv6
+// addresses with ports require square brackets.  Any interface suffix is ignored.
+pub fn parse_servers(servers: &[&str], default_port: u16) -> io::Result<Vec<SocketAddr>> {
+    servers
+        .iter()
+        .flat_map(|servers| servers.split(','))
+        .map(str::trim)
+        .filter(|server| !server.is_empty())
+        .map(|server| {
+            let server = server.strip_prefix("dns://").unwrap_or(server);
+            let server = match server.find('%') {
+                Some(start) => {
+                    let end = server[start..]
+                        .find(']')
+                        .map_or(server.len(), |i| start + i);
+                    format!("{}{}", &server[..start], &server[end..])
+                }
+                None => server.to_owned(),
+            };
+            if let Ok(address) = server.parse::<SocketAddr>() {
+                return Ok(address);
+            }
+            server
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, default_port))
+                .map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, format!("bad server {server}"))
+                })
+        })
+        .collect()
+}
+
+// A transport that modifies the EDNS options on queries, before passing them on to another
+// transport: adding an EDNS Client Subnet option (RFC 7871), and/or removing DNS cookies (RFC
+// 7873).
+pub struct EdnsTransport {
+    inner: Arc<dyn Transport>,
+    client_subnet: Option<Vec<u8>>,
+    strip_cookies: bool,
+}
+
+impl EdnsTransport {
+    pub fn new(
+        inner: Arc<dyn Transport>,
+        client_subnet: Option<(IpAddr, u8)>,
+        strip_cookies: bool,
+    ) -> Self {
+        let client_subnet =
+            client_subnet.map(|(address, prefix)| client_subnet_option(address, prefix));
+        Self {
+            inner,
+            client_subnet,
+            strip_cookies,
+        }
+    }
+}
+
+impl Transport for EdnsTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let mut query = query.to_vec();
+        if self.strip_cookies {
+            if let Some(modified) = remove_edns_option(&query, COOKIE) {
+                query = modified;
+            }
+        }
+        if let Some(ref option) = self.client_subnet {
+            query = add_edns_option(&query, option).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "cannot add client subnet")
+            })?;
+        }
+        self.inner.exchange(&query)
+    }
+}
+
+/// How a resolver handles queries for names under `.local`, which are reserved for multicast DNS
+/// (RFC 6762).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LocalNames {
+    /// Send them to the unicast servers, like any other name.  This is the default.
+    Unicast,
+
+    /// Fail them at once, rather than sending them to the unicast servers - which usually know
+    /// nothing of them, so that they time out.  They fail with `c_ares::Error::ENOTFOUND`, as
+    /// RFC 6762 has unicast servers answer - or, by way of `Resolver::try_query()`, with
+    /// `Error::LocalName`.
+    ///
+    /// As with `Options::set_allowed_domains()`, searches are checked against the name as given:
+    /// a search domain under `.local` is not caught.
+    Reject,
+
+    /// Send them via multicast DNS.
+    #[cfg(feature = "mdns")]
+    Multicast,
+}
+
+// Whether `name` is under `.local`.
+#[cfg(cares1_24)]
+pub(crate) fn is_local_name(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    name.rsplit('.')
+        .next()
+        .is_some_and(|label| label.eq_ignore_ascii_case("local"))
+}
+
+// A transport that diverts queries for names under `.local` to another transport.
+#[cfg(feature = "mdns")]
+pub struct LocalTransport {
+    inner: Arc<dyn Transport>,
+    local: Arc<dyn Transport>,
+}
+
+#[cfg(feature = "mdns")]
+impl LocalTransport {
+    pub fn new(inner: Arc<dyn Transport>, local: Arc<dyn Transport>) -> Self {
+        Self { inner, local }
+    }
+}
+
+#[cfg(feature = "mdns")]
+impl Transport for LocalTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        if question_end(query).is_some() && is_local_query(query) {
+            self.local.exchange(query)
+        } else {
+            self.inner.exchange(query)
+        }
+    }
+}
+
+// A transport that checks that the records in the answers to queries belong to the name that was
+// asked about - or to a name that it is an alias of, via a chain of CNAME records.  Records for
+// other names are dropped.
+#[cfg(cares1_24)]
+pub struct SanitizingTransport {
+    inner: Arc<dyn Transport>,
+}
+
+#[cfg(cares1_24)]
+impl SanitizingTransport {
+    pub fn new(inner: Arc<dyn Transport>) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(cares1_24)]
+impl Transport for SanitizingTransport {
+    fn exchange(&self, query: &[u8]) -> io::Result<Vec<u8>> {
+        let response = self.inner.exchange(query)?;
+        Ok(sanitize_response(&response).unwrap_or(response))
+    }
+}
+
+// Drop unrelated records from the answer section of a response - returning `None` if there are
+// none, or if the response can't be understood.  Such responses are passed on unchanged, for
+// `c-ares` to deal with.
+#[cfg(cares1_24)]
+fn sanitize_response(response: &[u8]) -> Option<Vec<u8>> {
+    use crate::dnsrec::DnsRecord;
+    use c_ares_sys::{ares_dns_rec_type_t, ares_dns_rr_key_t};
+
+    let normalize = |name: &str| name.trim_end_matches('.').to_ascii_lowercase();
+    let mut record = DnsRecord::parse(response).ok()?;
+    let mut names = vec![normalize(&record.question_name()?)];
+
+    // Follow CNAME chains, in whatever order the records appear.
+    let aliases: Vec<(String, String)> = record
+        .answers(ares_dns_rec_type_t::ARES_REC_TYPE_CNAME)
+        .iter()
+        .map(|rr| {
+            let target = rr.str(ares_dns_rr_key_t::ARES_RR_CNAME_CNAME);
+            (normalize(&rr.name()), normalize(&target))
+        })
+        .collect();
+    let mut index = 0;
+    while let Some(name) = names.get(index) {
+        let targets = aliases
+            .iter()
+            .filter(|(owner, _)| owner == name)
+            .map(|(_, target)| target);
+        let new: Vec<String> = targets.filter(|t| !names.contains(t)).cloned().collect();
+        names.extend(new);
+        index += 1;
+    }
+
+    let removed = record.retain_answers(|rr| names.contains(&normalize(&rr.name())));
+    if removed == 0 {
+        return None;
+    }
+    record.write().ok()
+}
+
+// The offset of the end of the question in a query with a single question.
+fn question_end(query: &[u8]) -> Option<usize> {
+    if query.len() < 12 || query[4..6] != [0, 1] {
+        return None;
+    }
+    let mut end = 12;
+    while *query.get(end)? != 0 {
+        end += 1 + usize::from(query[end]);
+    }
+    end += 5;
+    (end <= query.len()).then_some(end)
+}
+
+// Parse the question from a message with a single question: returning the name - in lower case,
+// without a trailing dot - the type, the class, and the offset of the end of the question.
+pub(crate) fn parse_question(message: &[u8]) -> Option<(String, u16, u16, usize)> {
+    let end = question_end(message)?;
+    let mut labels = Vec::new();
+    let mut offset = 12;
+    while offset < end - 5 {
+        let length = usize::from(message[offset]);
+        if length > 63 {
+            return None;
+        }
+        let label = message.get(offset + 1..offset + 1 + length)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        offset += 1 + length;
+    }
+    let query_type = u16::from_be_bytes([message[end - 4], message[end - 3]]);
+    let dns_class = u16::from_be_bytes([message[end - 2], message[end - 1]]);
+    Some((labels.join("."), query_type, dns_class, end))
+}
+
+// Whether a query is for a name under `.local`.
+#[cfg(feature = "mdns")]
+fn is_local_query(query: &[u8]) -> bool {
+    let mut offset = 12;
+    let mut last_label: &[u8] = &[];
+    while let Some(&length) = query.get(offset) {
+        if length == 0 {
+            break;
+        }
+        let start = offset + 1;
+        let end = start + usize::from(length);
+        let Some(label) = query.get(start..end) else {
+            return false;
+        };
+        last_label = label;
+        offset = end;
+    }
+    last_label.eq_ignore_ascii_case(b"local")
+}
+
+const CLIENT_SUBNET: u16 = 8;
+const COOKIE: u16 = 10;
+
+// Encode an EDNS Client Subnet option, with the address truncated to the prefix length.
+fn client_subnet_option(address: IpAddr, prefix: u8) -> Vec<u8> {
+    let (family, octets, prefix) = match address {
+        IpAddr::V4(address) => (1u16, address.octets().to_vec(), prefix.min(32)),
+        IpAddr::V6(address) => (2u16, address.octets().to_vec(), prefix.min(128)),
+    };
+    let length = usize::from(prefix).div_ceil(8);
+    let mut octets = octets[..length].to_vec();
+    if prefix % 8 != 0 {
+        if let Some(last) = octets.last_mut() {
+            *last &= 0xff << (8 - prefix % 8);
+        }
+    }
+
+    let mut option = Vec::with_capacity(8 + length);
+    option.extend_from_slice(&CLIENT_SUBNET.to_be_bytes());
+    option.extend_from_slice(&u16::try_from(4 + length).unwrap().to_be_bytes());
+    option.extend_from_slice(&family.to_be_bytes());
+    option.push(prefix);
+    option.push(0);
+    option.extend_from_slice(&octets);
+    option
+}
+
+// Find the OPT record in a query.
+//
+// Queries from c-ares have a single question, and at most an OPT record in the additional section.
+// Returns `None` if the query doesn't look like that; otherwise the offset at which the OPT record
+// starts - or would start, if there were one - and whether there is one.
+fn find_opt_record(query: &[u8]) -> Option<(usize, bool)> {
+    let count = |offset: usize| u16::from_be_bytes([query[offset], query[offset + 1]]);
+    if query.len() < 12 || count(4) != 1 || count(6) != 0 || count(8) != 0 || count(10) > 1 {
+        return None;
+    }
+
+    // Skip the question: the name, followed by type and class.
+    let mut end = 12;
+    while *query.get(end)? != 0 {
+        end += 1 + usize::from(query[end]);
+    }
+    end += 5;
+
+    if count(10) == 0 {
+        return Some((end, false));
+    }
+    if query.len() < end + 11 || query[end] != 0 || query[end + 1..end + 3] != [0, 41] {
+        return None;
+    }
+    Some((end, true))
+}
+
+// Add an EDNS option to a query, adding an OPT record if there isn't one already.
+fn add_edns_option(query: &[u8], option: &[u8]) -> Option<Vec<u8>> {
+    let (start, has_opt) = find_opt_record(query)?;
+    let mut modified = query.to_vec();
+    if has_opt {
+        // Extend the existing OPT record.
+        let rdlength = start + 9;
+        let length = usize::from(u16::from_be_bytes([query[rdlength], query[rdlength + 1]]));
+        let length = u16::try_from(length + option.len()).ok()?;
+        modified[rdlength..rdlength + 2].copy_from_slice(&length.to_be_bytes());
+    } else {
+        // Add an OPT record: root name, type 41, UDP payload size, no extended flags.
+        modified.truncate(start);
+        modified.push(0);
+        modified.extend_from_slice(&41u16.to_be_bytes());
+        modified.extend_from_slice(&1232u16.to_be_bytes());
+        modified.extend_from_slice(&[0; 4]);
+        modified.extend_from_slice(&u16::try_from(option.len()).ok()?.to_be_bytes());
+        modified[11] = 1;
+    }
+    modified.extend_from_slice(option);
+    Some(modified)
+}
+
+// Remove any EDNS options with the given code from a query.
+fn remove_edns_option(query: &[u8], code: u16) -> Option<Vec<u8>> {
+    let (start, has_opt) = find_opt_record(query)?;
+    if !has_opt {
+        return None;
+    }
+    let rdata = start + 11;
+    let mut modified = query[..rdata].to_vec();
+    let mut options = &query[rdata..];
+    while options.len() >= 4 {
+        let this_code = u16::from_be_bytes([options[0], options[1]]);
+        let length = 4 + usize::from(u16::from_be_bytes([options[2], options[3]]));
+        let option = options.get(..length)?;
+        if this_code != code {
+            modified.extend_from_slice(option);
+        }
+        options = &options[length..];
+    }
+    let length = u16::try_from(modified.len() - rdata).ok()?;
+    modified[start + 9..rdata].copy_from_slice(&length.to_be_bytes());
+    Some(modified)
+}
@@ -0,0 +1,55 @@
+use std::io;
+
+// Set the priority and CPU affinity of the current thread - as set by
+// `Options::set_thread_priority()` and `Options::set_thread_affinity()`.  These are supported only
+// on Linux: elsewhere, asking for either fails.
+pub(crate) fn tune_thread(priority: Option<i32>, cpus: Option<&[usize]>) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(cpus) = cpus {
+            set_affinity(cpus)?;
+        }
+        if let Some(priority) = priority {
+            set_priority(priority)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    if priority.is_some() || cpus.is_some() {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[usize]) -> io::Result<()> {
+    // Safety: an all-zero `cpu_set_t` is a valid, empty, set.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            let message = format!("no such CPU: {cpu}");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+
+    // A thread ID of zero means the calling thread.
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    if unsafe { libc::sched_setaffinity(0, size, &set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_priority(nice: i32) -> io::Result<()> {
+    // On Linux, nice values belong to threads rather than processes: given a thread ID,
+    // `setpriority()` sets the value for that thread alone.
+    let tid = unsafe { libc::gettid() };
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
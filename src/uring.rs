@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use io_uring::types::{Fd, SubmitArgs, Timespec};
+use io_uring::{opcode, squeue, IoUring};
+
+use crate::eventloop::SocketEvent;
+
+// The size of the submission queue.
+const ENTRIES: u32 = 256;
+
+// The user data of the poll on the eventfd by which the event loop is woken, and of requests whose
+// completions are of no interest.  Poll requests on sockets are numbered from `FIRST_POLL`.
+const NOTIFY: u64 = 0;
+const IGNORED: u64 = 1;
+const FIRST_POLL: u64 = 2;
+
+// The ring, and the poll requests made on it.
+struct State {
+    ring: IoUring,
+    next: u64,
+
+    // The latest poll request for each socket, and the socket for each request that has not yet
+    // completed.
+    sockets: HashMap<RawFd, u64>,
+    pending: HashMap<u64, RawFd>,
+
+    notify_armed: bool,
+}
+
+impl State {
+    // Queue a request, first submitting those already queued if there is no room for it.
+    fn push(&mut self, entry: &squeue::Entry) -> io::Result<()> {
+        loop {
+            // Safety: none of our requests refer to buffers, so there is nothing that must outlive
+            // them.
+            if unsafe { self.ring.submission().push(entry) }.is_ok() {
+                return Ok(());
+            }
+            self.ring.submit()?;
+        }
+    }
+
+    // Poll `fd` for the given events, once.
+    fn poll(&mut self, fd: RawFd, readable: bool, writable: bool) -> io::Result<()> {
+        let mut flags = 0;
+        if readable {
+            flags |= libc::POLLIN;
+        }
+        if writable {
+            flags |= libc::POLLOUT;
+        }
+        let user_data = self.next;
+        self.next += 1;
+        let entry = opcode::PollAdd::new(Fd(fd), flags as u32)
+            .build()
+            .user_data(user_data);
+        self.push(&entry)?;
+        self.sockets.insert(fd, user_data);
+        self.pending.insert(user_data, fd);
+        Ok(())
+    }
+
+    // Cancel the poll request for `fd`, if it has not yet completed.
+    fn cancel(&mut self, fd: RawFd) -> io::Result<()> {
+        let Some(user_data) = self.sockets.remove(&fd) else {
+            return Ok(());
+        };
+        if self.pending.remove(&user_data).is_some() {
+            let entry = opcode::PollRemove::new(user_data)
+                .build()
+                .user_data(IGNORED);
+            self.push(&entry)?;
+        }
+        Ok(())
+    }
+}
+
+// Waits for events on sockets using io_uring, rather than epoll.  Like `polling::Poller`, interest
+// in a socket is oneshot: it must be renewed after each event.
+pub(crate) struct Poller {
+    state: Mutex<State>,
+    notifier: File,
+}
+
+impl Poller {
+    // Create a poller - failing if the kernel doesn't support io_uring, or supports too old a
+    // version of it.
+    pub(crate) fn new() -> io::Result<Self> {
+        let ring = IoUring::new(ENTRIES)?;
+
+        // Waiting with a timeout needs IORING_FEAT_EXT_ARG, from Linux 5.11.
+        if !ring.params().is_feature_ext_arg() {
+            return Err(io::Error::from(io::ErrorKind::Unsupported));
+        }
+
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Safety: we have just created this file descriptor, and nothing else owns it.
+        let notifier = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        let state = State {
+            ring,
+            next: FIRST_POLL,
+            sockets: HashMap::new(),
+            pending: HashMap::new(),
+            notify_armed: false,
+        };
+        let poller = Self {
+            state: Mutex::new(state),
+            notifier,
+        };
+        Ok(poller)
+    }
+
+    // Start waiting for events on `fd`.
+    pub(crate) fn add(&self, fd: RawFd, readable: bool, writable: bool) -> io::Result<()> {
+        self.state.lock().unwrap().poll(fd, readable, writable)
+    }
+
+    // Change, or renew, the events that we are waiting for on `fd`.
+    pub(crate) fn modify(&self, fd: RawFd, readable: bool, writable: bool) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.cancel(fd)?;
+        state.poll(fd, readable, writable)
+    }
+
+    // Stop waiting for events on `fd`.
+    pub(crate) fn delete(&self, fd: RawFd) -> io::Result<()> {
+        self.state.lock().unwrap().cancel(fd)
+    }
+
+    // Wake the thread that is waiting for events.
+    pub(crate) fn notify(&self) -> io::Result<()> {
+        match (&self.notifier).write(&1u64.to_ne_bytes()) {
+            // If the counter is full, the waiting thread is going to wake anyway.
+            Err(err) if err.kind() != io::ErrorKind::WouldBlock => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    // Submit queued requests, and wait for events - or for a notification, or the timeout.
+    pub(crate) fn wait(&self, events: &mut Vec<SocketEvent>, timeout: Duration) -> io::Result<()> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if !state.notify_armed {
+            let entry = opcode::PollAdd::new(Fd(self.notifier.as_raw_fd()), libc::POLLIN as u32)
+                .build()
+                .user_data(NOTIFY);
+            state.push(&entry)?;
+            state.notify_armed = true;
+        }
+
+        let timespec = Timespec::from(timeout);
+        let args = SubmitArgs::new().timespec(&timespec);
+        match state.ring.submitter().submit_with_args(1, &args) {
+            Err(err) if err.raw_os_error() != Some(libc::ETIME) => return Err(err),
+            _ => {}
+        }
+
+        for entry in state.ring.completion() {
+            match entry.user_data() {
+                NOTIFY => {
+                    let _ = (&self.notifier).read(&mut [0; 8]);
+                    state.notify_armed = false;
+                }
+                IGNORED => {}
+                user_data => {
+                    // Completions of cancelled requests are no longer pending, and are dropped.
+                    let Some(fd) = state.pending.remove(&user_data) else {
+                        continue;
+                    };
+                    // A failed poll - eg on a socket that has been closed - reports nothing.
+                    let result = entry.result();
+                    if result < 0 {
+                        continue;
+                    }
+                    let flags = result as i16;
+                    let event = SocketEvent {
+                        socket: fd,
+                        readable: flags & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0,
+                        writable: flags & (libc::POLLOUT | libc::POLLHUP | libc::POLLERR) != 0,
+                    };
+                    events.push(event);
+                }
+            }
+        }
+        Ok(())
+    }
+}
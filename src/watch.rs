@@ -0,0 +1,94 @@
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use crate::futureresolver::FutureResolver;
+use crate::retry::sleep;
+
+// The shortest wait between lookups, however short the TTL.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long to wait after a failed lookup, when re-resolving on TTL expiry.
+const ERROR_INTERVAL: Duration = Duration::from_secs(5);
+
+type Lookup = c_ares::Result<Vec<Ipv4Addr>>;
+type Step = Pin<Box<dyn Future<Output = (Lookup, Duration)> + Send>>;
+
+/// The type of stream returned by `FutureResolver::watch_a()`.
+///
+/// The stream yields the IPv4 addresses of a name - sorted and without duplicates - when it is
+/// first polled, and again each time that they change.  A failed lookup is yielded as an error,
+/// unless the previous lookup failed in the same way.  The stream never ends.
+#[must_use = "streams do nothing unless polled"]
+pub struct WatchA {
+    resolver: FutureResolver,
+    name: String,
+    interval: Option<Duration>,
+    last: Option<Lookup>,
+    step: Step,
+}
+
+// Wait for `delay`, then look up the A records for `name`, returning the addresses and how long
+// to wait before the next lookup.
+fn step(
+    resolver: &FutureResolver,
+    name: &str,
+    interval: Option<Duration>,
+    delay: Duration,
+) -> Step {
+    let resolver = resolver.clone();
+    let name = name.to_owned();
+    Box::pin(async move {
+        sleep(delay).await;
+        let results = resolver.query_a(&name).await;
+        let lookup = results.as_ref().map_err(|&error| error).map(|results| {
+            let mut addresses: Vec<Ipv4Addr> = results.iter().map(|a| a.ipv4()).collect();
+            addresses.sort_unstable();
+            addresses.dedup();
+            addresses
+        });
+        let next = interval.unwrap_or_else(|| match results {
+            Ok(results) => {
+                let ttl = results.iter().map(|a| a.ttl()).min().unwrap_or(0);
+                Duration::from_secs(u64::try_from(ttl).unwrap_or(0)).max(MIN_INTERVAL)
+            }
+            Err(_) => ERROR_INTERVAL,
+        });
+        (lookup, next)
+    })
+}
+
+impl WatchA {
+    pub(crate) fn new(resolver: FutureResolver, name: &str, interval: Option<Duration>) -> Self {
+        let step = step(&resolver, name, interval, Duration::ZERO);
+        Self {
+            resolver,
+            name: name.to_owned(),
+            interval,
+            last: None,
+            step,
+        }
+    }
+}
+
+impl Stream for WatchA {
+    type Item = Lookup;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Lookup>> {
+        loop {
+            let (lookup, next) = match self.step.as_mut().poll(cx) {
+                Poll::Ready(ready) => ready,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.step = step(&self.resolver, &self.name, self.interval, next);
+            if self.last.as_ref() != Some(&lookup) {
+                self.last = Some(lookup.clone());
+                return Poll::Ready(Some(lookup));
+            }
+        }
+    }
+}